@@ -1,9 +1,23 @@
 use crate::App;
 use crate::app::AppLLMSettingsManager;
-use crate::core::clipboard_monitor::start_clipboard_monitor;
+use crate::core::clipboard_monitor::{
+    ClipboardConfig, ClipboardHashes, ClipboardState, PasteFileGuard, start_clipboard_monitor,
+    sweep_stale_paste_files,
+};
+use crate::core::history::QuestionHistory;
+use crate::core::llm_backend::{BackendHealth, LLMManager};
+use crate::core::logger;
+use crate::core::question_type::{Question, QuestionType};
+use crate::core::request_log::RequestLog;
+use crate::core::request_queue::RequestQueue;
+use crate::core::stats;
+use crate::core::response_cache::{DEFAULT_CACHE_CAPACITY, ResponseCache};
 use slint::ComponentHandle;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use once_cell::sync::Lazy;
 
 // 全局共享的 tokio runtime，避免重复创建
@@ -11,11 +25,69 @@ static TOKIO_RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
     tokio::runtime::Runtime::new().expect("Failed to create tokio runtime")
 });
 
+/// 请求代次：每次发起请求时递增，切换提供商时作废进行中的请求
+///
+/// 请求开始时记下自己的代次，回复到达时代次已经变化说明请求已被取代，回复会被丢弃。
+#[derive(Clone, Debug, Default)]
+pub struct RequestGeneration(Arc<AtomicU64>);
+
+impl RequestGeneration {
+    /// 开始一个新请求，返回它的代次
+    pub fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// 作废所有进行中的请求
+    pub fn invalidate(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// 指定代次的请求是否仍是最新的请求
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.0.load(Ordering::SeqCst) == generation
+    }
+}
+
+/// 题型在侧边栏中的页面序号，与 main.slint 中 `question_type` 的对应关系一致；没有页面的题型返回 None
+fn sidebar_index(question_type: QuestionType) -> Option<i32> {
+    match question_type {
+        QuestionType::ListeningSingle => Some(0),
+        QuestionType::SingleChoice => Some(1),
+        QuestionType::ClozeTest => Some(2),
+        QuestionType::Reading => Some(3),
+        QuestionType::MutiTiankong => Some(4),
+        QuestionType::GeneralFill => Some(5),
+        QuestionType::ClozeTestNote => Some(8),
+        QuestionType::CompleteQuestion => Some(9),
+        QuestionType::Matching => Some(10),
+        QuestionType::ShortAnswer => Some(11),
+        QuestionType::Ordering => Some(12),
+        QuestionType::ListeningCompound => None,
+    }
+}
+
 /// 应用全局状态管理
 pub struct AppState {
     pub current_image_path: Arc<Mutex<Option<PathBuf>>>,
     pub clipboard_path: Arc<Mutex<Option<PathBuf>>>,
+    /// 监控线程检测到的新剪贴板文本，等待填入输入框
+    pub clipboard_text: Arc<Mutex<Option<String>>>,
+    /// 最近一次发送的题目，用于换题型重新生成
+    pub last_question: Arc<Mutex<Option<Question>>>,
     pub llm_settings: Arc<Mutex<AppLLMSettingsManager>>,
+    /// 模型回复缓存，重复发送相同的题干和图片时直接使用缓存的回复
+    pub response_cache: Arc<Mutex<ResponseCache>>,
+    pub clipboard_config: ClipboardConfig,
+    /// 最近一次后端健康检查的结果
+    pub backend_health: Arc<Mutex<BackendHealth>>,
+    /// 本次运行创建的剪贴板临时图片，AppState 释放时删除
+    pub paste_files: PasteFileGuard,
+    /// 最近处理的剪贴板内容哈希，监控线程、立即粘贴和复制共享，避免同一内容被处理两次
+    pub clipboard_hashes: Arc<ClipboardHashes>,
+    /// 停止当前回复的信号
+    pub stop_signal: Arc<AtomicBool>,
+    /// 当前请求的代次，过期请求的回复不会更新界面
+    pub request_generation: RequestGeneration,
 }
 
 impl AppState {
@@ -23,32 +95,74 @@ impl AppState {
         tracing::info!("[app_state] Initializing application state");
 
         // 创建 LLM 设置管理器
-        let llm_settings = match AppLLMSettingsManager::new() {
-            Ok(settings) => {
-                tracing::info!("[app_state] LLM settings manager initialized");
-                settings
-            }
-            Err(e) => {
-                tracing::error!("[app_state] Failed to initialize LLM settings: {}", e);
-                return Err(e);
-            }
+        let llm_settings = AppLLMSettingsManager::new();
+        tracing::info!("[app_state] LLM settings manager initialized");
+
+        let response_cache = if llm_settings.get_config().response_cache_on_disk {
+            ResponseCache::open_default(DEFAULT_CACHE_CAPACITY).unwrap_or_else(|e| {
+                tracing::error!("[app_state] Failed to open response cache file, using memory cache: {}", e);
+                ResponseCache::default()
+            })
+        } else {
+            ResponseCache::default()
         };
 
+        // 请求日志追加时不裁剪，启动时删除超出条数上限的旧记录
+        if let Err(e) = RequestLog::open_default().and_then(|log| log.trim()) {
+            tracing::warn!("[app_state] Failed to trim request log: {}", e);
+        }
 
         Ok(Self {
             current_image_path: Arc::new(Mutex::new(None)),
             clipboard_path: Arc::new(Mutex::new(None)),
+            clipboard_text: Arc::new(Mutex::new(None)),
+            last_question: Arc::new(Mutex::new(None)),
             llm_settings: Arc::new(Mutex::new(llm_settings)),
+            response_cache: Arc::new(Mutex::new(response_cache)),
+            clipboard_config: ClipboardConfig::load(),
+            backend_health: Arc::new(Mutex::new(BackendHealth::default())),
+            paste_files: PasteFileGuard::new(),
+            clipboard_hashes: Arc::new(ClipboardHashes::new(ClipboardState::load().last_image_hash)),
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            request_generation: RequestGeneration::default(),
         })
     }
 
     pub fn setup_clipboard_monitor(&mut self) -> Arc<Mutex<Option<PathBuf>>> {
         tracing::info!("[app_state] Setting up clipboard monitor");
-        let clipboard_path = start_clipboard_monitor();
+        // 清理之前运行遗留的超过一天的临时图片
+        sweep_stale_paste_files(&std::env::temp_dir(), Duration::from_secs(24 * 60 * 60));
+        let current_image_path = self.current_image_path.clone();
+        let last_question = self.last_question.clone();
+        let clipboard_path = start_clipboard_monitor(
+            self.clipboard_config.monitor_interval(),
+            self.clipboard_config.max_paste_files,
+            self.clipboard_config.image_limits(),
+            self.paste_files.tracker(),
+            self.clipboard_text.clone(),
+            self.clipboard_hashes.clone(),
+            move || Self::paste_files_in_use(&current_image_path, &last_question),
+        );
         self.clipboard_path = clipboard_path.clone();
         clipboard_path
     }
 
+    /// 正在使用的图片：当前显示的图片和最近发送的题目，清理剪贴板临时图片时保留
+    pub fn paste_files_in_use(
+        current_image_path: &Mutex<Option<PathBuf>>,
+        last_question: &Mutex<Option<Question>>,
+    ) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = current_image_path.lock().ok().and_then(|path| path.clone()).into_iter().collect();
+        if let Some(path) = last_question
+            .lock()
+            .ok()
+            .and_then(|question| question.as_ref().and_then(|question| question.img_path.clone()))
+        {
+            paths.push(path);
+        }
+        paths
+    }
+
     /// 设置所有 LLM 相关的回调函数
     pub fn setup_llm_callbacks(&self, app: &App) -> &AppState {
         self.setup_llm_provider_callback(app);
@@ -60,20 +174,28 @@ impl AppState {
         self.setup_llm_test_callback(app);
         self.setup_llm_save_callback(app);
         self.setup_llm_load_callback(app);
+        self.setup_llm_usage_stats_callback(app);
+        self.setup_llm_usage_summary_callback(app);
+        self.setup_llm_log_level_callback(app);
+        self.setup_llm_profile_callbacks(app);
+        self.setup_question_type_selected_callback(app);
         &self
     }
 
     /// 初始化 UI 的 LLM 设置显示
     pub fn init_llm_ui_state(&self, app: &App) {
         if let Ok(settings) = self.llm_settings.lock() {
-            let config = settings.get_config();
-
-            app.set_llm_provider(config.provider.clone().into());
-            app.set_llm_model(config.model.clone().into());
-            app.set_llm_api_key(config.api_key.clone().unwrap_or_default().into());
-            app.set_llm_base_url(config.base_url.clone().unwrap_or_default().into());
-            app.set_llm_github_token(config.github_token.clone().unwrap_or_default().into());
-            app.set_llm_enable_streaming(config.enable_streaming);
+            Self::show_llm_config(app, &settings);
+            // 恢复上次选择的题型页面
+            if let Some(index) = settings
+                .get_config()
+                .default_question_type
+                .as_deref()
+                .and_then(|name| QuestionType::from_str(name).ok())
+                .and_then(sidebar_index)
+            {
+                app.set_current_page(index);
+            }
 
             tracing::info!(
                 "[app_state] 初始化 LLM UI 状态: {}",
@@ -82,16 +204,91 @@ impl AppState {
         }
     }
 
-    // LLM 提供商变更回调
+    /// 把当前配置档的设置显示到设置页
+    fn show_llm_config(app: &App, settings: &AppLLMSettingsManager) {
+        let config = settings.get_config();
+        app.set_llm_provider(config.provider.clone().into());
+        app.set_llm_model(config.model.clone().into());
+        app.set_llm_api_key(config.api_key.clone().unwrap_or_default().into());
+        app.set_llm_base_url(config.base_url.clone().unwrap_or_default().into());
+        app.set_llm_github_token(config.github_token.clone().unwrap_or_default().into());
+        app.set_llm_enable_streaming(config.enable_streaming);
+        app.set_llm_usage_stats(config.usage_stats);
+        app.set_llm_log_level(config.log_level.clone().into());
+        let profiles: Vec<slint::SharedString> = settings.list_profiles().into_iter().map(Into::into).collect();
+        app.set_llm_profiles(slint::VecModel::from_slice(&profiles));
+        app.set_llm_active_profile(settings.active_profile().into());
+    }
+
+    /// 在后台检查当前后端是否可用，不阻塞界面
+    pub fn start_health_check(&self, app: &App) {
+        Self::refresh_backend_health(&self.llm_settings, &self.backend_health, app.as_weak());
+    }
+
+    /// 按当前设置重新检查后端，结果写入缓存并更新界面指示灯
+    fn refresh_backend_health(
+        settings: &Arc<Mutex<AppLLMSettingsManager>>,
+        health: &Arc<Mutex<BackendHealth>>,
+        app_weak: slint::Weak<App>,
+    ) {
+        let Ok(manager) = settings
+            .lock()
+            .map(|settings| LLMManager::from_config(settings.get_config()))
+        else {
+            tracing::error!("[app_state] Failed to lock LLM settings for health check");
+            return;
+        };
+
+        Self::set_backend_health(health, &app_weak, BackendHealth::Checking);
+        let health = health.clone();
+        std::thread::spawn(move || {
+            let result = TOKIO_RUNTIME.block_on(BackendHealth::check(&manager));
+            tracing::info!("[app_state] Backend health: {}", result.summary());
+            Self::set_backend_health(&health, &app_weak, result);
+        });
+    }
+
+    fn set_backend_health(health: &Arc<Mutex<BackendHealth>>, app_weak: &slint::Weak<App>, value: BackendHealth) {
+        if let Ok(mut cached) = health.lock() {
+            *cached = value.clone();
+        }
+        let app_weak = app_weak.clone();
+        slint::invoke_from_event_loop(move || {
+            if let Some(app) = app_weak.upgrade() {
+                app.set_llm_health(value.status().into());
+                app.set_llm_health_detail(value.summary().into());
+            }
+        })
+        .ok();
+    }
+
+    // LLM 提供商变更回调，进行中的请求会被取消，之后到达的回复不再更新界面
     fn setup_llm_provider_callback(&self, app: &App) {
         let settings: Arc<Mutex<AppLLMSettingsManager>> = self.llm_settings.clone();
+        let stop_signal = self.stop_signal.clone();
+        let request_generation = self.request_generation.clone();
+        let app_weak = app.as_weak();
         app.on_llm_provider_changed(move |provider| {
             if let Ok(mut settings) = settings.lock() {
                 settings.set_provider(provider.to_string());
             }
+            Self::abort_requests(&stop_signal, &request_generation);
+            if let Some(app) = app_weak.upgrade()
+                && app.get_is_streaming()
+            {
+                app.set_is_streaming(false);
+                app.set_status_message("已切换提供商，之前的请求已取消".into());
+            }
         });
     }
 
+    /// 停止并作废所有进行中的请求
+    pub fn abort_requests(stop_signal: &AtomicBool, request_generation: &RequestGeneration) {
+        tracing::info!("[app_state] Aborting in-flight requests");
+        stop_signal.store(true, Ordering::Relaxed);
+        request_generation.invalidate();
+    }
+
     // LLM 模型变更回调
     fn setup_llm_model_callback(&self, app: &App) {
         let settings = self.llm_settings.clone();
@@ -142,6 +339,58 @@ impl AppState {
         });
     }
 
+    // 本地使用统计开关回调
+    fn setup_llm_usage_stats_callback(&self, app: &App) {
+        let settings = self.llm_settings.clone();
+        app.on_llm_usage_stats_changed(move |enabled| {
+            if let Ok(mut settings) = settings.lock() {
+                settings.set_usage_stats(enabled);
+            }
+        });
+    }
+
+    // 日志级别变更回调，立即生效，保存后写入配置
+    fn setup_llm_log_level_callback(&self, app: &App) {
+        let settings = self.llm_settings.clone();
+        app.on_llm_log_level_changed(move |level| {
+            logger::set_level(&level);
+            if let Ok(mut settings) = settings.lock() {
+                settings.set_log_level(level.to_string());
+            }
+            tracing::info!("[app_state] Log level changed to {}", level);
+        });
+    }
+
+    // 最近 7 天使用统计回调
+    fn setup_llm_usage_summary_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        app.on_llm_refresh_usage_summary(move || {
+            let summary = stats::weekly_summary();
+            tracing::info!("[app_state] Weekly usage: {} requests", summary.total);
+            if let Some(app) = app_weak.upgrade() {
+                app.set_llm_usage_summary(summary.to_string().into());
+            }
+        });
+    }
+
+    // 切换题型页面回调，记住选择的题型
+    fn setup_question_type_selected_callback(&self, app: &App) {
+        let settings = self.llm_settings.clone();
+        app.on_question_type_selected(move |name| {
+            let Ok(question_type) = QuestionType::from_str(&name) else {
+                return;
+            };
+            if let Ok(mut settings) = settings.lock() {
+                if settings.get_config().default_question_type.as_deref() == Some(question_type.as_str()) {
+                    return;
+                }
+                if let Err(e) = settings.set_default_question_type(question_type) {
+                    tracing::warn!("[app_state] Failed to remember question type {}: {}", name, e);
+                }
+            }
+        });
+    }
+
     // LLM 连接测试回调
     fn setup_llm_test_callback(&self, app: &App) {
         let settings = self.llm_settings.clone();
@@ -165,6 +414,7 @@ impl AppState {
                     }
                 });
 
+                let connected = result.is_ok();
                 let test_result = match result {
                     Ok(msg) => msg,
                     Err(msg) => msg,
@@ -178,21 +428,62 @@ impl AppState {
                     }
                 })
                 .ok();
+
+                // 连接恢复后重试离线队列中的请求
+                if connected {
+                    Self::drain_pending_requests(&settings);
+                }
             });
         });
     }
 
+    /// 重新发送离线队列中的请求，完成的题目写入历史记录
+    fn drain_pending_requests(settings: &Arc<Mutex<AppLLMSettingsManager>>) {
+        let queue = match RequestQueue::open_default() {
+            Ok(queue) => queue,
+            Err(e) => {
+                tracing::error!("[app_state] Failed to open request queue: {}", e);
+                return;
+            }
+        };
+        let Ok(config) = settings.lock().map(|settings| settings.get_config().clone()) else {
+            tracing::error!("[app_state] Failed to lock LLM settings for queue drain");
+            return;
+        };
+
+        // 和首次发送一样，题型配置了单独的模型时按该模型重试
+        let completed = TOKIO_RUNTIME.block_on(
+            queue.drain(|question_type| LLMManager::manager_for_question_type(&config, question_type)),
+        );
+        if completed.is_empty() {
+            return;
+        }
+        match QuestionHistory::open_default() {
+            Ok(history) => {
+                for question in &completed {
+                    if let Err(e) = history.save(question) {
+                        tracing::error!("[app_state] Failed to save drained question: {}", e);
+                    }
+                }
+            }
+            Err(e) => tracing::error!("[app_state] Failed to open question history: {}", e),
+        }
+    }
+
     // LLM 保存设置回调
     fn setup_llm_save_callback(&self, app: &App) {
-        let settings = self.llm_settings.clone();
+        let settings_handle = self.llm_settings.clone();
+        let backend_health = self.backend_health.clone();
         let app_weak = app.as_weak();
         app.on_llm_save_settings(move || {
-            if let Ok(mut settings) = settings.lock() {
-                match settings.save_config() {
+            if let Ok(mut settings) = settings_handle.lock() {
+                match settings.save_and_apply() {
                     Ok(_) => {
-                        tracing::info!("[app_state] LLM 设置已保存");
+                        tracing::info!("[app_state] LLM 设置已保存并应用");
                         if let Some(app) = app_weak.upgrade() {
-                            app.set_llm_test_result("✅ 设置已保存".into());
+                            app.set_llm_test_result(
+                                format!("✅ 设置已保存并应用\n{}", settings.get_config_summary()).into(),
+                            );
                         }
                     }
                     Err(e) => {
@@ -203,32 +494,27 @@ impl AppState {
                     }
                 }
             }
+            // 写入失败时设置仍会应用，两种情况都重新检查后端
+            Self::refresh_backend_health(&settings_handle, &backend_health, app_weak.clone());
         });
     }
 
     // LLM 加载设置回调
     fn setup_llm_load_callback(&self, app: &App) {
-        let settings = self.llm_settings.clone();
+        let settings_handle = self.llm_settings.clone();
+        let backend_health = self.backend_health.clone();
         let app_weak = app.as_weak();
         app.on_llm_load_settings(move || {
-            if let Ok(mut settings) = settings.lock() {
+            let mut reloaded = false;
+            if let Ok(mut settings) = settings_handle.lock() {
                 match settings.reload_config() {
                     Ok(_) => {
+                        reloaded = true;
                         tracing::info!("[app_state] LLM 设置已重新加载");
 
                         // 更新 UI 显示
                         if let Some(app) = app_weak.upgrade() {
-                            let config = settings.get_config();
-                            app.set_llm_provider(config.provider.clone().into());
-                            app.set_llm_model(config.model.clone().into());
-                            app.set_llm_api_key(config.api_key.clone().unwrap_or_default().into());
-                            app.set_llm_base_url(
-                                config.base_url.clone().unwrap_or_default().into(),
-                            );
-                            app.set_llm_github_token(
-                                config.github_token.clone().unwrap_or_default().into(),
-                            );
-                            app.set_llm_enable_streaming(config.enable_streaming);
+                            Self::show_llm_config(&app, &settings);
                             app.set_llm_test_result("✅ 设置已重新加载".into());
                         }
                     }
@@ -240,6 +526,56 @@ impl AppState {
                     }
                 }
             }
+            if reloaded {
+                Self::refresh_backend_health(&settings_handle, &backend_health, app_weak.clone());
+            }
         });
     }
+
+    // LLM 配置档切换和保存回调，成功后刷新设置页并重新检查后端
+    fn setup_llm_profile_callbacks(&self, app: &App) {
+        let settings_handle = self.llm_settings.clone();
+        let backend_health = self.backend_health.clone();
+        let app_weak = app.as_weak();
+        app.on_llm_profile_switched(move |name| {
+            Self::update_llm_profile(&settings_handle, &backend_health, &app_weak, |settings| {
+                settings.switch_profile(&name).map(|_| format!("✅ 已切换到配置档: {}", name))
+            });
+        });
+
+        let settings_handle = self.llm_settings.clone();
+        let backend_health = self.backend_health.clone();
+        let app_weak = app.as_weak();
+        app.on_llm_profile_saved(move |name| {
+            Self::update_llm_profile(&settings_handle, &backend_health, &app_weak, |settings| {
+                settings.save_profile(&name).map(|_| format!("✅ 已保存配置档: {}", name.trim()))
+            });
+        });
+    }
+
+    fn update_llm_profile(
+        settings_handle: &Arc<Mutex<AppLLMSettingsManager>>,
+        backend_health: &Arc<Mutex<BackendHealth>>,
+        app_weak: &slint::Weak<App>,
+        update: impl FnOnce(&mut AppLLMSettingsManager) -> Result<String, Box<dyn std::error::Error>>,
+    ) {
+        let Ok(mut settings) = settings_handle.lock() else {
+            tracing::error!("[app_state] Failed to lock LLM settings for profile update");
+            return;
+        };
+        let result = update(&mut settings);
+        if let Some(app) = app_weak.upgrade() {
+            // 失败时也刷新，让下拉框回到当前配置档
+            Self::show_llm_config(&app, &settings);
+            match &result {
+                Ok(message) => app.set_llm_test_result(message.clone().into()),
+                Err(e) => app.set_llm_test_result(format!("❌ 配置档操作失败: {}", e).into()),
+            }
+        }
+        drop(settings);
+        match result {
+            Ok(_) => Self::refresh_backend_health(settings_handle, backend_health, app_weak.clone()),
+            Err(e) => tracing::error!("[app_state] 配置档操作失败: {}", e),
+        }
+    }
 }
@@ -0,0 +1,254 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+
+use crate::app::AppLLMSettingsManager;
+use crate::core::llm_backend::{LLMManager, LLMResponse};
+use crate::core::question_type::{PromptOptions, Question, QuestionType};
+use crate::core::utility;
+
+/// 启用命令行模式的参数
+pub const CLI_FLAG: &str = "--cli";
+
+/// 指定输出文件的参数，没有时结果写到标准输出
+pub const OUT_FLAG: &str = "--out";
+
+/// 命令行模式的用法说明
+pub const USAGE: &str = "用法: question_tool --cli <图片路径> <题型> [--out <输出文件>]";
+
+/// 命令行模式失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    /// 参数个数不对
+    Usage,
+    /// 无法识别的题型名称
+    UnknownQuestionType(String),
+    /// 图片不存在或无法解码
+    InvalidImage(PathBuf),
+    /// 请求模型失败
+    Request(String),
+    /// 请求结束但没有可用的回复
+    NoReply,
+    /// 写出结果失败
+    Output(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage => write!(f, "{}", USAGE),
+            CliError::UnknownQuestionType(name) => write!(f, "未知题型: {}", name),
+            CliError::InvalidImage(path) => write!(f, "图片无效或无法读取: {}", path.display()),
+            CliError::Request(e) => write!(f, "请求失败: {}", e),
+            CliError::NoReply => write!(f, "模型没有返回可用的回复"),
+            CliError::Output(e) => write!(f, "输出结果失败: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// 命令行模式的参数：`--cli <图片路径> <题型> [--out <输出文件>]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub image_path: PathBuf,
+    pub question_type: QuestionType,
+    /// 输出文件，None 时写到标准输出
+    pub out_path: Option<PathBuf>,
+}
+
+impl CliArgs {
+    /// 从命令行参数（不含程序名）中解析，没有 `--cli` 时返回 None
+    pub fn parse(args: &[String]) -> Option<Result<Self, CliError>> {
+        if !args.iter().any(|arg| arg == CLI_FLAG) {
+            return None;
+        }
+
+        let mut positional = Vec::new();
+        let mut out_path = None;
+        let mut args = args.iter().filter(|arg| *arg != CLI_FLAG);
+        while let Some(arg) = args.next() {
+            if arg == OUT_FLAG {
+                let Some(path) = args.next() else {
+                    return Some(Err(CliError::Usage));
+                };
+                out_path = Some(PathBuf::from(path));
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        let [image_path, question_type] = positional.as_slice() else {
+            return Some(Err(CliError::Usage));
+        };
+        Some(
+            QuestionType::from_str(question_type)
+                .map(|question_type| Self {
+                    image_path: PathBuf::from(image_path.as_str()),
+                    question_type,
+                    out_path,
+                })
+                .map_err(|_| CliError::UnknownQuestionType(question_type.to_string())),
+        )
+    }
+}
+
+/// 把图片按 `options` 作为一道题目发送给模型，返回带有完整回复的题目
+pub async fn convert(args: &CliArgs, options: &PromptOptions, manager: &LLMManager) -> Result<Question, CliError> {
+    if !utility::is_valid_image(&args.image_path) {
+        return Err(CliError::InvalidImage(args.image_path.clone()));
+    }
+    let mut question = Question::new_with_params(
+        args.question_type,
+        String::new(),
+        Some(args.image_path.clone()),
+        Default::default(),
+        options,
+    );
+
+    let (sender, receiver) = mpsc::channel::<LLMResponse>();
+    manager
+        .send_message(question.prompt_stem(), Some(&args.image_path), sender)
+        .await
+        .map_err(|e| CliError::Request(e.to_string()))?;
+    let response = receiver
+        .try_iter()
+        .filter(|response| response.is_complete)
+        .last()
+        .filter(|response| !response.is_error())
+        .ok_or(CliError::NoReply)?;
+    question.set_model_reply(response.content);
+    Ok(question)
+}
+
+/// 转换图片并把模型输出和附加代码写到输出文件，没有指定输出文件时写到 `out`
+pub async fn run_with_manager(
+    args: &CliArgs,
+    options: &PromptOptions,
+    manager: &LLMManager,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let question = convert(args, options, manager).await?;
+    let result = match &args.out_path {
+        Some(path) => question.write_output_to(path).inspect(|_| {
+            tracing::info!("[cli] Output written to {}", path.display());
+        }),
+        None => writeln!(out, "{}", question.get_final_output()),
+    };
+    result.map_err(|e| CliError::Output(e.to_string()))
+}
+
+/// 按保存的模型设置执行命令行模式，结果写到标准输出，返回进程退出码
+pub async fn run(args: Result<CliArgs, CliError>) -> i32 {
+    let result = match args {
+        Ok(args) => {
+            let settings = AppLLMSettingsManager::new();
+            let manager = LLMManager::manager_for_question_type(settings.get_config(), args.question_type);
+            tracing::info!(
+                "[cli] Converting {} as {}",
+                args.image_path.display(),
+                args.question_type.as_str()
+            );
+            run_with_manager(&args, &settings.prompt_options(), &manager, &mut std::io::stdout().lock()).await
+        }
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            if matches!(e, CliError::UnknownQuestionType(_)) {
+                eprintln!("{}", USAGE);
+            }
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::llm_backend::MockBackend;
+    use uuid::Uuid;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_cli_args() {
+        assert_eq!(CliArgs::parse(&args(&["a.png", "单选题"])), None);
+        assert_eq!(
+            CliArgs::parse(&args(&["--cli", "a.png", "单选题"])),
+            Some(Ok(CliArgs {
+                image_path: PathBuf::from("a.png"),
+                question_type: QuestionType::SingleChoice,
+                out_path: None,
+            }))
+        );
+        assert_eq!(
+            CliArgs::parse(&args(&["--cli", "--out", "out.js", "a.png", "单选题"])),
+            Some(Ok(CliArgs {
+                image_path: PathBuf::from("a.png"),
+                question_type: QuestionType::SingleChoice,
+                out_path: Some(PathBuf::from("out.js")),
+            }))
+        );
+        assert_eq!(CliArgs::parse(&args(&["--cli", "a.png", "单选题", "--out"])), Some(Err(CliError::Usage)));
+        assert_eq!(CliArgs::parse(&args(&["--cli", "a.png"])), Some(Err(CliError::Usage)));
+        assert_eq!(
+            CliArgs::parse(&args(&["a.png", "选择题", "--cli"])),
+            Some(Err(CliError::UnknownQuestionType("选择题".to_string())))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cli_converts_image_with_mock_backend() {
+        let dir = std::env::temp_dir().join(format!("question_tool_cli_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image_path = dir.join("question.png");
+        image::RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 0]))
+            .save_with_format(&image_path, image::ImageFormat::Png)
+            .unwrap();
+
+        let mut manager = LLMManager::new();
+        manager.add_backend(Box::new(MockBackend::fixed(
+            r#"var Questions = [{ "stem": "1 + 1 = ?", "options": ["1", "2"], "answer": 1, "analysis": "故答案为：B" }];"#,
+        )));
+        let cli_args = CliArgs::parse(&args(&["--cli", image_path.to_str().unwrap(), "单选题"]))
+            .unwrap()
+            .unwrap();
+
+        let options = PromptOptions::default();
+        let mut out = Vec::new();
+        run_with_manager(&cli_args, &options, &manager, &mut out).await.unwrap();
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.starts_with("var Questions = [{ \"stem\": \"1 + 1 = ?\""));
+        // 输出与界面复制的内容一致，包含附加代码
+        assert!(output.contains("clickSaveWithRetry"));
+
+        // 指定输出文件时写入文件而不是标准输出
+        let out_path = dir.join("out.js");
+        let file_args = CliArgs {
+            out_path: Some(out_path.clone()),
+            ..cli_args.clone()
+        };
+        let mut out = Vec::new();
+        run_with_manager(&file_args, &options, &manager, &mut out).await.unwrap();
+        assert!(out.is_empty());
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap().trim_end(), output.trim_end());
+
+        let missing = CliArgs {
+            image_path: dir.join("missing.png"),
+            question_type: QuestionType::SingleChoice,
+            out_path: None,
+        };
+        assert_eq!(
+            run_with_manager(&missing, &options, &manager, &mut Vec::new()).await,
+            Err(CliError::InvalidImage(dir.join("missing.png")))
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,10 @@
 use super::AppState;
 use crate::App;
+use crate::core::utility;
 use slint::{Image, Timer, Weak};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// 剪贴板定时器管理
 pub struct ClipboardTimer {
@@ -19,20 +21,23 @@ impl ClipboardTimer {
         }
     }
 
-    /// 启动定时器监控剪贴板变化
-    pub fn start(&mut self, app_weak: Weak<App>) {
-        tracing::info!("[clipboard_timer] Starting UI timer for clipboard polling");
+    /// 启动定时器，每隔 `interval` 检查监控线程是否保存了新图片
+    ///
+    /// 间隔越短，新截图显示得越快，但 UI 线程会更频繁地被唤醒；默认 1000ms。
+    pub fn start(&mut self, app_weak: Weak<App>, interval: Duration) {
+        tracing::info!("[clipboard_timer] Starting UI timer for clipboard polling, interval: {:?}", interval);
 
         let path_monitor = self.app_state.clipboard_path.clone();
+        let text_monitor = self.app_state.clipboard_text.clone();
         let current_image_path = self.app_state.current_image_path.clone();
 
         let timer = Timer::default();
         tracing::info!("[clipboard_timer] Starting clipboard check(before function)");
         timer.start(
             slint::TimerMode::Repeated,
-            std::time::Duration::from_millis(1000),
+            interval,
             move || {
-                Self::handle_clipboard_check(&path_monitor, &current_image_path, &app_weak);
+                Self::handle_clipboard_check(&path_monitor, &text_monitor, &current_image_path, &app_weak);
             },
         );
         
@@ -40,12 +45,22 @@ impl ClipboardTimer {
         self.timer = Some(timer);
     }
 
-    /// 处理剪贴板检查逻辑
+    /// 处理剪贴板检查逻辑：有新文本时填入题目输入框，否则检查新图片
     fn handle_clipboard_check(
         path_monitor: &Arc<Mutex<Option<PathBuf>>>,
+        text_monitor: &Arc<Mutex<Option<String>>>,
         current_image_path: &Arc<Mutex<Option<PathBuf>>>,
         app_weak: &Weak<App>,
     ) {
+        let new_text = text_monitor.lock().ok().and_then(|mut text| text.take());
+        if let Some(text) = new_text {
+            if let Some(app) = app_weak.upgrade() {
+                tracing::info!("[clipboard_timer] Prefilling clipboard text, {} chars", text.chars().count());
+                app.set_prefill_text(text.into());
+            }
+            return;
+        }
+
         if let Ok(path_lock) = path_monitor.lock() {
             if let Some(ref path) = *path_lock {
                 tracing::debug!(
@@ -54,7 +69,8 @@ impl ClipboardTimer {
                 );
 
                 if let Some(app) = app_weak.upgrade() {
-                    match Image::load_from_path(path) {
+                    // 显示缩略图，避免在界面线程解码全尺寸截图；发送时仍使用原图
+                    match Image::load_from_path(&utility::thumbnail_for_display(path)) {
                         Ok(image) => {
                             tracing::info!(
                                 "[clipboard_timer] Successfully loaded and displayed image"
@@ -1,23 +1,86 @@
 use super::AppState;
+use super::app_state::RequestGeneration;
 use crate::App;
-use crate::core::llm_backend::LLMResponse;
-use crate::core::question_type::{AdditionalCodeGenerator, Question, QuestionType};
+use crate::core::batch;
+use crate::core::clipboard_monitor;
+use crate::core::export;
+use crate::core::fixture;
+use crate::core::history::QuestionHistory;
+use crate::core::llm_backend::{LLMManager, LLMResponse, RateLimited};
+use crate::core::parser::{self, ParsedQuestion};
+use crate::core::question_type::{
+    self, DEFAULT_PARAGRAPH_STYLE, PromptOptions, PromptParams, Question, QuestionType, ScriptOptions,
+};
+use crate::core::request_log::{RequestLog, RequestLogEntry};
+use crate::core::request_queue::{self, RequestQueue};
+use crate::core::response_cache::ResponseCache;
+use crate::core::stats::{UsageRecord, UsageStats};
+use crate::core::translate;
+use crate::core::utility::{self, DuplicateOptionMode, QuestionsJsIssue};
 use slint::ComponentHandle;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, mpsc, atomic::{AtomicBool, Ordering}};
+use std::time::{Duration, Instant};
+
+/// 复制成功后在复制按钮旁显示的提示
+const COPY_SUCCESS_STATUS: &str = "✅ 已复制";
+
+/// 流式回复刷新界面的最短间隔，避免每个 token 都重新渲染整段文本
+const STREAM_UI_INTERVAL: Duration = Duration::from_millis(30);
+
+/// 限制流式回复刷新界面的频率
+struct StreamThrottle {
+    interval: Duration,
+    last_update: Option<Instant>,
+}
+
+impl StreamThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_update: None,
+        }
+    }
+
+    /// 距离下一次允许刷新还需要等待的时间，已经可以刷新时返回 None
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.last_update?);
+        (elapsed < self.interval).then(|| self.interval - elapsed)
+    }
+
+    fn mark(&mut self, now: Instant) {
+        self.last_update = Some(now);
+    }
+}
+
+/// 取出通道中已经到达的后续回复，返回最新的一条，遇到完成响应时停止
+///
+/// 流式回复每次都带有到目前为止的完整内容，跳过中间结果不会丢失文本。
+fn latest_response(mut response: LLMResponse, receiver: &mpsc::Receiver<LLMResponse>) -> LLMResponse {
+    while !response.is_complete {
+        match receiver.try_recv() {
+            Ok(next) => response = next,
+            Err(_) => break,
+        }
+    }
+    response
+}
 
 /// UI 事件处理器
 pub struct EventHandlers {
     app_state: Arc<AppState>,
     stop_signal: Arc<AtomicBool>,
+    request_generation: RequestGeneration,
 }
 
 impl EventHandlers {
     /// 创建新的事件处理器
     pub fn new(app_state: Arc<AppState>) -> Self {
-        Self { 
+        Self {
+            stop_signal: app_state.stop_signal.clone(),
+            request_generation: app_state.request_generation.clone(),
             app_state,
-            stop_signal: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -27,6 +90,22 @@ impl EventHandlers {
         self.setup_copy_callback(app);
         self.setup_stop_response_callback(app);
         self.setup_clear_image_callback(app);
+        self.setup_load_image_callback(app);
+        self.setup_paste_clipboard_callback(app);
+        self.setup_batch_process_callback(app);
+        self.setup_regenerate_callback(app);
+        self.setup_regenerate_last_callback(app);
+        self.setup_export_fixture_callback(app);
+        self.setup_preview_prompt_callback(app);
+        self.setup_clear_cache_callback(app);
+        self.setup_clear_history_callback(app);
+        self.setup_export_gift_callback(app);
+        self.setup_export_anki_callback(app);
+        self.setup_export_csv_callback(app);
+        self.setup_save_output_callback(app);
+        self.setup_copy_answer_key_callback(app);
+        self.setup_renumber_blank_ids_callback(app);
+        self.setup_insert_example_callback(app);
     }
 
     /// 设置发送消息回调
@@ -34,7 +113,10 @@ impl EventHandlers {
         let app_weak = app.as_weak();
         let current_image_path = self.app_state.current_image_path.clone();
         let llm_settings = self.app_state.llm_settings.clone();
+        let last_question = self.app_state.last_question.clone();
+        let response_cache = self.app_state.response_cache.clone();
         let stop_signal = self.stop_signal.clone();
+        let request_generation = self.request_generation.clone();
 
         app.on_send_message(move || {
             let app_handle = app_weak.clone();
@@ -60,10 +142,6 @@ impl EventHandlers {
                     text.len()
                 );
 
-                // 设置流式状态
-                app.set_is_streaming(true);
-                app.set_model_reply("".into());
-
                 // 获取当前图片路径
                 let image_path = if let Ok(path_lock) = image_path_handle.lock() {
                     path_lock.as_ref().map(|p| p.clone())
@@ -72,28 +150,630 @@ impl EventHandlers {
                     None
                 };
 
+                // 截断或损坏的截图会被 API 拒绝，发送前清除并提示重新截图
+                if let Some(path) = image_path.as_ref().filter(|path| !utility::is_valid_image(path)) {
+                    tracing::error!("[event_handlers] Invalid image, request skipped: {}", path.display());
+                    if let Ok(mut current) = image_path_handle.lock() {
+                        *current = None;
+                    }
+                    app.set_current_image(slint::Image::default());
+                    app.set_status_message("❌ 图片已损坏或不完整，已清除，请重新截图或选择图片后再发送".into());
+                    return;
+                }
+
+                // 设置流式状态
+                app.set_is_streaming(true);
+                app.set_model_reply("".into());
+                app.set_model_reasoning("".into());
+                app.set_generated_count(0);
+                app.set_passage_preview("".into());
+                app.set_status_message("".into());
+
                 if image_path.is_some() {
                     tracing::debug!("[event_handlers] Including image in LLM request");
                 }
                 tracing::info!("Question type: {}", app.get_question_type().as_str());
-                let question = Question::new(
+                let options = llm_settings_handle
+                    .lock()
+                    .map(|settings| settings.prompt_options())
+                    .unwrap_or_default();
+                let mut question = Question::new_with_params(
                     QuestionType::from_str(app.get_question_type().as_str())
                         .expect("wrong question type, please check again!{}"),
                     text,
                     image_path,
+                    PromptParams::new(&app.get_grade(), &app.get_difficulty()),
+                    &options,
+                );
+                question.set_subject(Some(app.get_subject().to_string()));
+                question.set_grade(Some(app.get_grade().to_string()));
+
+                // 纯文本模型会忽略图片，发送前提示
+                if question.get_img_path().is_some() {
+                    let text_only_model = llm_settings_handle
+                        .lock()
+                        .ok()
+                        .map(|settings| settings.get_config().clone())
+                        .filter(|config| !config.supports_vision(question.get_type()))
+                        .map(|config| config.model_for(question.get_type()).to_string());
+                    if let Some(model) = text_only_model {
+                        tracing::warn!("[event_handlers] Model {} may not accept images", model);
+                        app.set_status_message(
+                            format!("⚠️ 模型 {} 可能不支持图片输入，图片内容可能会被忽略", model).into(),
+                        );
+                    }
+                }
+
+                // 题干过长时可能超出上下文窗口，发送前提示
+                let stem_limit = llm_settings_handle
+                    .lock()
+                    .ok()
+                    .map(|settings| settings.get_config().stem_warning_limit());
+                if let Some(warning) = stem_limit.and_then(|limit| question.stem_length_warning(limit)) {
+                    tracing::warn!("[event_handlers] {}", warning);
+                    let status = app.get_status_message();
+                    let status = if status.is_empty() {
+                        warning
+                    } else {
+                        format!("{}\n{}", status, warning)
+                    };
+                    app.set_status_message(status.into());
+                }
+
+                let estimated_tokens = question.estimated_prompt_tokens();
+                tracing::info!("[event_handlers] Estimated prompt tokens: {}", estimated_tokens);
+                app.set_estimated_tokens(estimated_tokens as i32);
+
+                Self::remember_last_question(&last_question, &question);
+
+                Self::handle_llm_request(
+                    app_handle,
+                    question,
+                    llm_settings_handle,
+                    response_cache.clone(),
+                    stop_signal_handle,
+                    request_generation.clone(),
+                    true,
                 );
-                Self::handle_llm_request(app_handle, question, llm_settings_handle, stop_signal_handle);
             }
         });
     }
 
+    /// 设置提示词预览回调：按发送时相同的方式构建题目，只显示完整提示词，不请求模型
+    fn setup_preview_prompt_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let current_image_path = self.app_state.current_image_path.clone();
+        let llm_settings = self.app_state.llm_settings.clone();
+
+        app.on_preview_prompt(move || {
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let text = app.get_prefill_text().to_string();
+            if text.trim().is_empty() {
+                tracing::debug!("[event_handlers] No text to preview");
+                return;
+            }
+            let Ok(question_type) = QuestionType::from_str(app.get_question_type().as_str()) else {
+                tracing::warn!("[event_handlers] Unknown question type for preview: {}", app.get_question_type());
+                return;
+            };
+            let image_path = current_image_path.lock().ok().and_then(|path| path.clone());
+            let has_image = image_path.is_some();
+
+            let params = PromptParams::new(&app.get_grade(), &app.get_difficulty());
+            let options = llm_settings.lock().map(|settings| settings.prompt_options()).unwrap_or_default();
+            let question = Question::new_with_params(question_type, text, image_path, params, &options);
+            tracing::info!("[event_handlers] Previewing prompt for {}", question_type.as_str());
+            app.set_model_reply(question.prompt_stem().into());
+            app.set_model_reasoning("".into());
+            app.set_generated_count(0);
+            app.set_passage_preview("".into());
+            app.set_estimated_tokens(question.estimated_prompt_tokens() as i32);
+            app.set_status_message(
+                if has_image {
+                    "提示词预览（未发送，另附一张图片）"
+                } else {
+                    "提示词预览（未发送）"
+                }
+                .into(),
+            );
+        });
+    }
+
+    /// 设置清空回复缓存回调
+    fn setup_clear_cache_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let response_cache = self.app_state.response_cache.clone();
+
+        app.on_clear_cache(move || {
+            tracing::info!("[event_handlers] Clear response cache triggered");
+            let cleared = match response_cache.lock() {
+                Ok(mut cache) => {
+                    let count = cache.len();
+                    cache.clear();
+                    count
+                }
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to lock response cache: {}", e);
+                    return;
+                }
+            };
+            if let Some(app) = app_weak.upgrade() {
+                app.set_status_message(format!("已清空 {} 条缓存的回复", cleared).into());
+            }
+        });
+    }
+
+    /// 设置清空题目历史回调：清空 `history.jsonl` 的内容，保留文件以便继续记录
+    fn setup_clear_history_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+
+        app.on_clear_history(move || {
+            tracing::info!("[event_handlers] Clear question history triggered");
+            let message = match QuestionHistory::open_default().and_then(|history| history.clear()) {
+                Ok(()) => "已清空题目历史记录".to_string(),
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to clear question history: {}", e);
+                    format!("清空历史记录失败: {}", e)
+                }
+            };
+            if let Some(app) = app_weak.upgrade() {
+                app.set_status_message(message.into());
+            }
+        });
+    }
+
+    /// 设置导出 GIFT 回调：把当前回复中的单选题和判断题导出为 Moodle 可导入的文件
+    fn setup_export_gift_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+
+        app.on_export_gift(move |path| {
+            tracing::info!("[event_handlers] Export GIFT triggered");
+            if let Some(app) = app_weak.upgrade() {
+                Self::export_parsed_reply(&app, &path, "GIFT", "gift", export::export_gift);
+            }
+        });
+    }
+
+    /// 设置导出 Anki 回调：题干作为正面，正确选项和解析作为背面
+    fn setup_export_anki_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+
+        app.on_export_anki(move |path| {
+            tracing::info!("[event_handlers] Export Anki TSV triggered");
+            if let Some(app) = app_weak.upgrade() {
+                Self::export_parsed_reply(&app, &path, "Anki", "tsv", export::export_anki_tsv);
+            }
+        });
+    }
+
+    /// 设置导出 CSV 回调：每道小题一行，带上最近发送题目的科目和年级
+    fn setup_export_csv_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let last_question = self.app_state.last_question.clone();
+
+        app.on_export_csv(move |path| {
+            tracing::info!("[event_handlers] Export CSV triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let mut question = match last_question.lock().ok().and_then(|last| last.clone()) {
+                Some(question) => question,
+                None => {
+                    let Ok(question_type) = QuestionType::from_str(app.get_question_type().as_str()) else {
+                        tracing::warn!("[event_handlers] Unknown question type for CSV export: {}", app.get_question_type());
+                        return;
+                    };
+                    Question::new(question_type, String::new(), None)
+                }
+            };
+            question.set_model_reply(app.get_model_reply().to_string());
+            match question.parsed_output() {
+                Ok(parsed) if !parsed.is_empty() => {}
+                Ok(_) => {
+                    app.set_status_message("当前回复中没有题目，无法导出".into());
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("[event_handlers] Failed to parse reply for CSV export: {}", e);
+                    app.set_status_message(format!("无法解析当前回复: {}", e).into());
+                    return;
+                }
+            }
+
+            if let Some(path) = Self::export_path(&path, "CSV", "csv") {
+                Self::write_export(&app, &path, "CSV", &export::export_csv(&[question]));
+            }
+        });
+    }
+
+    /// 设置保存到文件回调：把当前回复和附加代码写入文件，路径为空时弹出保存文件对话框
+    fn setup_save_output_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let llm_settings = self.app_state.llm_settings.clone();
+
+        app.on_save_output(move |path| {
+            tracing::info!("[event_handlers] Save output triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let reply = app.get_model_reply().to_string();
+            if reply.trim().is_empty() {
+                app.set_status_message("当前没有回复，无法保存".into());
+                return;
+            }
+            let Ok(question_type) = QuestionType::from_str(app.get_question_type().as_str()) else {
+                tracing::warn!("[event_handlers] Unknown question type for save: {}", app.get_question_type());
+                return;
+            };
+
+            let path = if path.trim().is_empty() {
+                match rfd::FileDialog::new()
+                    .set_title("保存到文件")
+                    .add_filter("JavaScript", &["js"])
+                    .set_file_name(format!("{}.js", question_type.as_str()))
+                    .save_file()
+                {
+                    Some(path) => path,
+                    None => {
+                        tracing::debug!("[event_handlers] Save output cancelled");
+                        return;
+                    }
+                }
+            } else {
+                PathBuf::from(path.as_str())
+            };
+
+            let options = llm_settings.lock().map(|settings| settings.prompt_options()).unwrap_or_default();
+            let mut question =
+                Question::new_with_params(question_type, String::new(), None, PromptParams::default(), &options);
+            question.set_model_reply(reply);
+            match question.write_output_to(&path) {
+                Ok(()) => {
+                    tracing::info!("[event_handlers] Output saved to {}", path.display());
+                    app.set_status_message(format!("已保存到: {}", path.display()).into());
+                }
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to save output: {}", e);
+                    app.set_status_message(format!("保存失败: {}", e).into());
+                }
+            }
+        });
+    }
+
+    /// 解析当前回复并用 `render` 导出到文件，路径为空时弹出保存文件对话框
+    fn export_parsed_reply(
+        app: &App,
+        path: &str,
+        format_name: &str,
+        extension: &str,
+        render: fn(&[ParsedQuestion]) -> String,
+    ) {
+        let questions = match parser::parse_questions(&app.get_model_reply()) {
+            Ok(questions) if !questions.is_empty() => questions,
+            Ok(_) => {
+                app.set_status_message("当前回复中没有题目，无法导出".into());
+                return;
+            }
+            Err(e) => {
+                tracing::warn!("[event_handlers] Failed to parse reply for {} export: {}", format_name, e);
+                app.set_status_message(format!("无法解析当前回复: {}", e).into());
+                return;
+            }
+        };
+
+        if let Some(path) = Self::export_path(path, format_name, extension) {
+            Self::write_export(app, &path, format_name, &render(&questions));
+        }
+    }
+
+    /// 导出文件的路径，为空时弹出保存文件对话框，取消时返回 None
+    fn export_path(path: &str, format_name: &str, extension: &str) -> Option<PathBuf> {
+        if !path.trim().is_empty() {
+            return Some(PathBuf::from(path));
+        }
+        let path = rfd::FileDialog::new()
+            .set_title(format!("导出 {}", format_name))
+            .add_filter(format_name, &[extension])
+            .set_file_name(format!("questions.{}", extension))
+            .save_file();
+        if path.is_none() {
+            tracing::debug!("[event_handlers] {} export cancelled", format_name);
+        }
+        path
+    }
+
+    /// 写入导出内容并在状态栏显示结果
+    fn write_export(app: &App, path: &Path, format_name: &str, content: &str) {
+        match std::fs::write(path, content) {
+            Ok(()) => {
+                tracing::info!("[event_handlers] Exported {} to {}", format_name, path.display());
+                app.set_status_message(format!("已导出 {} 到: {}", format_name, path.display()).into());
+            }
+            Err(e) => {
+                tracing::error!("[event_handlers] Failed to write {} export: {}", format_name, e);
+                app.set_status_message(format!("导出 {} 失败: {}", format_name, e).into());
+            }
+        }
+    }
+
+    /// 把完成的请求写入请求日志
+    fn record_request(
+        log: &RequestLog,
+        question_type: QuestionType,
+        model: &str,
+        prompt_chars: usize,
+        latency: Duration,
+        response: &LLMResponse,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = RequestLogEntry::now(
+            question_type,
+            model,
+            prompt_chars,
+            latency,
+            !response.is_error(),
+            &response.content,
+        );
+        log.append(&entry)
+    }
+
+    /// 设置换题型重新生成回调：复用上一题的题干和图片，按新题型重新请求
+    fn setup_regenerate_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let llm_settings = self.app_state.llm_settings.clone();
+        let last_question = self.app_state.last_question.clone();
+        let response_cache = self.app_state.response_cache.clone();
+        let stop_signal = self.stop_signal.clone();
+        let request_generation = self.request_generation.clone();
+
+        app.on_regenerate_as(move |question_type| {
+            let Ok(question_type) = QuestionType::from_str(question_type.as_str()) else {
+                tracing::warn!("[event_handlers] Unknown question type for regenerate: {}", question_type);
+                return;
+            };
+            let options = llm_settings.lock().map(|settings| settings.prompt_options()).unwrap_or_default();
+            let Some(question) = Self::next_regeneration(&last_question, Some(question_type), &options) else {
+                tracing::debug!("[event_handlers] No previous question to regenerate");
+                return;
+            };
+
+            tracing::info!("[event_handlers] Regenerating last question as {}", question_type.as_str());
+            Self::start_regeneration(
+                app_weak.clone(),
+                question,
+                llm_settings.clone(),
+                response_cache.clone(),
+                stop_signal.clone(),
+                request_generation.clone(),
+                true,
+            );
+        });
+    }
+
+    /// 设置重新生成回调：按原题型重新发送上一题，不使用缓存的回复
+    fn setup_regenerate_last_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let llm_settings = self.app_state.llm_settings.clone();
+        let last_question = self.app_state.last_question.clone();
+        let response_cache = self.app_state.response_cache.clone();
+        let stop_signal = self.stop_signal.clone();
+        let request_generation = self.request_generation.clone();
+
+        app.on_regenerate(move || {
+            let options = llm_settings.lock().map(|settings| settings.prompt_options()).unwrap_or_default();
+            let Some(question) = Self::next_regeneration(&last_question, None, &options) else {
+                tracing::debug!("[event_handlers] No previous question to regenerate");
+                if let Some(app) = app_weak.upgrade() {
+                    app.set_status_message("没有可重新生成的题目，请先发送一次".into());
+                }
+                return;
+            };
+
+            tracing::info!("[event_handlers] Regenerating last question");
+            Self::start_regeneration(
+                app_weak.clone(),
+                question,
+                llm_settings.clone(),
+                response_cache.clone(),
+                stop_signal.clone(),
+                request_generation.clone(),
+                false,
+            );
+        });
+    }
+
+    /// 记录最近一次发送的题目，供重新生成和导出用例使用
+    fn remember_last_question(last_question: &std::sync::Mutex<Option<Question>>, question: &Question) {
+        if let Ok(mut last) = last_question.lock() {
+            *last = Some(question.clone());
+        }
+    }
+
+    /// 基于上一题按当前设置创建新的题目并记为最近发送的题目，未指定题型时沿用原题型
+    fn next_regeneration(
+        last_question: &std::sync::Mutex<Option<Question>>,
+        question_type: Option<QuestionType>,
+        options: &PromptOptions,
+    ) -> Option<Question> {
+        let mut last = last_question.lock().ok()?;
+        let previous = last.as_ref()?;
+        let question = previous.regenerate_as(question_type.unwrap_or(previous.get_type()), options);
+        *last = Some(question.clone());
+        Some(question)
+    }
+
+    /// 清空上一次的回复并发送重新生成的题目
+    fn start_regeneration(
+        app_weak: slint::Weak<App>,
+        question: Question,
+        llm_settings: Arc<std::sync::Mutex<crate::app::AppLLMSettingsManager>>,
+        response_cache: Arc<std::sync::Mutex<ResponseCache>>,
+        stop_signal: Arc<AtomicBool>,
+        request_generation: RequestGeneration,
+        use_cache: bool,
+    ) {
+        stop_signal.store(false, Ordering::Relaxed);
+        if let Some(app) = app_weak.upgrade() {
+            app.set_is_streaming(true);
+            app.set_model_reply("".into());
+            app.set_model_reasoning("".into());
+            app.set_generated_count(0);
+            app.set_passage_preview("".into());
+            app.set_status_message("".into());
+            app.set_estimated_tokens(question.estimated_prompt_tokens() as i32);
+        }
+
+        Self::handle_llm_request(
+            app_weak,
+            question,
+            llm_settings,
+            response_cache,
+            stop_signal,
+            request_generation,
+            use_cache,
+        );
+    }
+
+    /// 设置导出复现用例回调：把上一题的题干、题型、脱敏配置和图片写入用例目录
+    fn setup_export_fixture_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let llm_settings = self.app_state.llm_settings.clone();
+        let last_question = self.app_state.last_question.clone();
+
+        app.on_export_fixture(move |dir| {
+            tracing::info!("[event_handlers] Export fixture triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let Some(mut question) = last_question.lock().ok().and_then(|last| last.clone()) else {
+                app.set_status_message("没有可导出的题目，请先发送一次".into());
+                return;
+            };
+
+            let reply = app.get_model_reply().to_string();
+            if !reply.trim().is_empty() {
+                question.set_model_reply(reply);
+            }
+            let Ok(config) = llm_settings.lock().map(|settings| settings.get_config().clone()) else {
+                tracing::error!("[event_handlers] Failed to lock LLM settings for fixture export");
+                return;
+            };
+
+            let dir = if dir.trim().is_empty() {
+                fixture::default_fixture_dir()
+            } else {
+                Ok(std::path::PathBuf::from(dir.as_str()))
+            };
+            match dir.and_then(|dir| fixture::export_fixture(&dir, &question, &config)) {
+                Ok(path) => {
+                    app.set_status_message(format!("用例已导出到: {}", path.display()).into());
+                }
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to export fixture: {}", e);
+                    app.set_status_message(format!("导出用例失败: {}", e).into());
+                }
+            }
+        });
+    }
+
+    /// 设置批量处理回调：目录为空时弹出文件夹选择框，逐张图片生成题目并写入历史记录
+    fn setup_batch_process_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let llm_settings = self.app_state.llm_settings.clone();
+
+        app.on_batch_process_directory(move |dir| {
+            tracing::info!("[event_handlers] Batch process triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let dir = if dir.trim().is_empty() {
+                match rfd::FileDialog::new().set_title("选择截图文件夹").pick_folder() {
+                    Some(dir) => dir,
+                    None => {
+                        tracing::debug!("[event_handlers] Batch folder selection cancelled");
+                        return;
+                    }
+                }
+            } else {
+                PathBuf::from(dir.as_str())
+            };
+            let Ok(question_type) = QuestionType::from_str(app.get_question_type().as_str()) else {
+                tracing::warn!("[event_handlers] Unknown question type for batch: {}", app.get_question_type());
+                return;
+            };
+            let Ok((manager, max_concurrent, options)) = llm_settings.lock().map(|settings| {
+                let config = settings.get_config();
+                (
+                    LLMManager::manager_for_question_type(config, question_type),
+                    config.max_concurrent,
+                    settings.prompt_options(),
+                )
+            }) else {
+                tracing::error!("[event_handlers] Failed to lock LLM settings for batch");
+                return;
+            };
+
+            app.set_status_message(format!("批量处理中: {}", dir.display()).into());
+            let app_weak = app_weak.clone();
+            tokio::spawn(async move {
+                // slint::Weak 不是 Sync，进度回调在多个请求间共享，需要包一层 Mutex
+                let progress_app = std::sync::Mutex::new(app_weak.clone());
+                let questions = batch::process_directory_with_progress(&dir, question_type, &options, &manager, max_concurrent, |done, total| {
+                    let Ok(app_weak) = progress_app.lock().map(|app_weak| app_weak.clone()) else {
+                        return;
+                    };
+                    app_weak
+                        .upgrade_in_event_loop(move |app| {
+                            app.set_status_message(format!("批量处理中: {}/{}", done, total).into());
+                        })
+                        .ok();
+                })
+                .await;
+
+                // 批量题目没有题干，只要有模型输出就算完成
+                let completed: Vec<&Question> =
+                    questions.iter().filter(|question| question.get_output().is_some()).collect();
+                match QuestionHistory::open_default() {
+                    Ok(history) => {
+                        for question in &completed {
+                            if let Err(e) = history.save(question) {
+                                tracing::error!("[event_handlers] Failed to save batch question: {}", e);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::error!("[event_handlers] Failed to open question history: {}", e),
+                }
+
+                let message = format!(
+                    "批量处理完成: 成功 {} / 共 {} 张图片，结果已写入历史记录",
+                    completed.len(),
+                    questions.len()
+                );
+                tracing::info!("[event_handlers] {}", message);
+                slint::invoke_from_event_loop(move || {
+                    if let Some(app) = app_weak.upgrade() {
+                        app.set_status_message(message.into());
+                    }
+                })
+                .ok();
+            });
+        });
+    }
+
     /// 处理 LLM 请求
     fn handle_llm_request(
         app_handle: slint::Weak<App>,
         mut question: Question,
         llm_settings: Arc<std::sync::Mutex<crate::app::AppLLMSettingsManager>>,
+        response_cache: Arc<std::sync::Mutex<ResponseCache>>,
         stop_signal: Arc<AtomicBool>,
+        request_generation: RequestGeneration,
+        use_cache: bool,
     ) {
+        // 记下本次请求的代次，切换提供商或发起新请求后旧请求的回复会被丢弃
+        let generation = request_generation.next();
+
         // 创建响应通道
         tracing::info!("[event_handlers] Preparing to send LLM request");
         let (response_sender, response_receiver) = mpsc::channel::<LLMResponse>();
@@ -101,53 +781,264 @@ impl EventHandlers {
         // 在后台线程中处理 LLM 请求
         let text_for_llm = question.prompt_stem();
         let image_path = question.img_path.clone();
-        tokio::spawn(async move {
-            // 从设置中获取当前的 LLM manager
-            let manager = if let Ok(settings) = llm_settings.lock() {
-                crate::core::llm_backend::LLMManager::from_config(settings.get_config())
-            } else {
-                tracing::error!("[event_handlers] Failed to lock LLM settings, using default");
-                crate::core::llm_backend::LLMManager::default()
-            };
-
-            let result = manager
-                .send_message(text_for_llm, image_path.as_deref(), response_sender)
-                .await;
+        let pending_question = question.clone();
+        let question_type = question.get_type();
+        let (fill_missing_analysis, analysis_markdown, duplicate_options, merge_questions_arrays, usage_stats, provider, model, preprocess) =
+            llm_settings
+                .lock()
+                .map(|settings| {
+                    let config = settings.get_config();
+                    (
+                        config.fill_missing_analysis,
+                        config.analysis_markdown_to_html,
+                        config.duplicate_options,
+                        config.merge_questions_arrays,
+                        config.usage_stats,
+                        config.provider.clone(),
+                        config.model_for(question_type).to_string(),
+                        config.preprocess_images.then_some((config.preprocess_options, config.send_both_images)),
+                    )
+                })
+                .unwrap_or((false, false, DuplicateOptionMode::default(), false, false, String::new(), String::new(), None));
+        // 回复完成后按发送时的设置替换段落样式
+        let options = llm_settings.lock().map(|settings| settings.prompt_options()).unwrap_or_default();
+        let prompt_chars = text_for_llm.chars().count();
+        let started_at = Instant::now();
+        // 开启英文解析时在回复完成后单独请求翻译，需要在响应线程中驱动异步请求
+        let translate_analysis = app_handle.upgrade().is_some_and(|app| app.get_translate_analysis());
+        let runtime = tokio::runtime::Handle::current();
+        let settings_for_translation = llm_settings.clone();
 
-            if let Err(e) = result {
-                tracing::error!("[event_handlers] LLM request failed: {}", e);
+        // 同一模型对相同的题干和图片已有缓存的回复时直接使用，不再请求模型；重新生成时跳过缓存
+        let cache_key = ResponseCache::key(&provider, &model, &text_for_llm, image_path.as_deref());
+        let cached = use_cache
+            .then(|| response_cache.lock().ok().and_then(|mut cache| cache.get(&cache_key)))
+            .flatten();
+        let from_cache = cached.is_some();
+        if let Some(output) = cached {
+            tracing::info!("[event_handlers] Response cache hit, request skipped");
+            if let Some(app) = app_handle.upgrade() {
+                app.set_status_message("已使用缓存的回复（清空缓存后可重新请求）".into());
             }
-        });
+            let _ = response_sender.send(LLMResponse {
+                content: output,
+                is_complete: true,
+                reasoning: None,
+            });
+        } else {
+            tokio::spawn(async move {
+                // 从设置中获取当前的 LLM manager，题型配置了单独的模型时使用该模型
+                let manager = if let Ok(settings) = llm_settings.lock() {
+                    crate::core::llm_backend::LLMManager::manager_for_question_type(
+                        settings.get_config(),
+                        question_type,
+                    )
+                } else {
+                    tracing::error!("[event_handlers] Failed to lock LLM settings, using default");
+                    crate::core::llm_backend::LLMManager::default()
+                };
+
+                // 开启预处理时发送处理后的临时图片，失败时使用原图
+                let processed = preprocess.zip(image_path.as_deref()).and_then(|((opts, _), path)| {
+                    utility::preprocess_for_ocr(path, opts)
+                        .map_err(|e| tracing::warn!("[event_handlers] Image preprocessing failed, using original: {}", e))
+                        .ok()
+                });
+                // 设置了同时发送两张图片时先发原图再发处理后的图片，并在提示词中说明
+                let send_both = preprocess.is_some_and(|(_, send_both)| send_both);
+                let images: Vec<&Path> = match (image_path.as_deref(), processed.as_deref()) {
+                    (Some(original), Some(processed)) if send_both => vec![original, processed],
+                    (original, processed) => processed.or(original).into_iter().collect(),
+                };
+                let text_for_llm = if images.len() > 1 {
+                    format!("{}{}", question_type::BOTH_IMAGES_NOTE, text_for_llm)
+                } else {
+                    text_for_llm
+                };
+                let result = manager
+                    .send_message_with_images(text_for_llm, &images, response_sender)
+                    .await;
+                if let Some(processed) = processed {
+                    let _ = std::fs::remove_file(processed);
+                }
+
+                if let Err(e) = result {
+                    tracing::error!("[event_handlers] LLM request failed: {}", e);
+
+                    // 网络不可用时放入离线队列，连接测试成功后自动重试
+                    if request_queue::is_network_error(&e) {
+                        match RequestQueue::open_default().and_then(|queue| queue.enqueue(&pending_question)) {
+                            Ok(()) => tracing::info!("[event_handlers] Request queued for retry"),
+                            Err(e) => tracing::error!("[event_handlers] Failed to queue request: {}", e),
+                        }
+                    }
+                }
+            });
+        }
 
         // 在主线程中处理响应
         let app_for_response = app_handle.clone();
         let stop_signal_for_response = stop_signal.clone();
+        let generation_for_ui = request_generation.clone();
         std::thread::spawn(move || {
-            while let Ok(response) = response_receiver.recv() {
-                // 检查停止信号
-                if stop_signal_for_response.load(Ordering::Relaxed) {
-                    tracing::info!("[event_handlers] Stop signal received, stopping response");
-                    // UI 状态已在按钮点击时更新，这里只需退出循环
+            let mut throttle = StreamThrottle::new(STREAM_UI_INTERVAL);
+            while let Ok(mut response) = response_receiver.recv() {
+                // 检查停止信号和请求是否已被取代
+                if !Self::accepts_response(&stop_signal_for_response, &request_generation, generation) {
+                    tracing::info!("[event_handlers] Request stopped or superseded, discarding response");
+                    // UI 状态已在按钮点击或切换提供商时更新，这里只需退出循环
                     break;
                 }
+
+                // 限制中间结果的刷新频率，等待期间到达的回复只显示最新的一条
+                if !response.is_complete {
+                    if let Some(wait) = throttle.remaining(Instant::now()) {
+                        std::thread::sleep(wait);
+                    }
+                    response = latest_response(response, &response_receiver);
+                    throttle.mark(Instant::now());
+                }
                 
                 tracing::trace!(
                     "[event_handlers] Received LLM response chunk, length: {}",
                     response.content.len()
                 );
 
+                // 缓存模型的原始回复，后处理按当前设置重新进行
+                if response.is_complete && !response.is_error() && !from_cache {
+                    match response_cache.lock() {
+                        Ok(mut cache) => cache.insert(cache_key.clone(), response.content.clone()),
+                        Err(e) => tracing::error!("[event_handlers] Failed to lock response cache: {}", e),
+                    }
+                }
+
+                // 平台只读取第一个 Questions 数组，出现多个时按设置合并或提示
+                let multiple_questions = (response.is_complete && !response.is_error())
+                    .then(|| utility::validate_questions_js(&response.content).err())
+                    .flatten()
+                    .filter(|issue| matches!(issue, QuestionsJsIssue::Multiple(_)));
+                if let Some(issue) = multiple_questions {
+                    tracing::warn!("[event_handlers] {}", issue);
+                    let message = if merge_questions_arrays {
+                        response.content = utility::merge_questions_arrays(&response.content);
+                        format!("{}，已合并为一个", issue)
+                    } else {
+                        issue.to_string()
+                    };
+                    let app_weak = app_for_response.clone();
+                    slint::invoke_from_event_loop(move || {
+                        if let Some(app) = app_weak.upgrade() {
+                            app.set_status_message(message.into());
+                        }
+                    })
+                    .ok();
+                }
+
+                // 模型未给出解析时补充占位解析
+                if response.is_complete && fill_missing_analysis && !response.is_error() {
+                    response.content = utility::fill_missing_analysis(&response.content);
+                }
+
+                // 解析中的 Markdown 在平台上会原样显示，转换为 HTML 标签
+                if response.is_complete && analysis_markdown && !response.is_error() {
+                    response.content = utility::convert_analysis_markdown(&response.content);
+                }
+
+                // 检查同一题目中重复的选项，按设置提示或替换
+                if response.is_complete && duplicate_options != DuplicateOptionMode::Off && !response.is_error() {
+                    let duplicates = utility::find_duplicate_options(&response.content);
+                    if !duplicates.is_empty() {
+                        let summary = duplicates.iter().map(ToString::to_string).collect::<Vec<_>>().join("；");
+                        tracing::warn!("[event_handlers] Duplicate options found: {}", summary);
+                        let message = if duplicate_options == DuplicateOptionMode::Replace {
+                            response.content = utility::replace_duplicate_options(&response.content);
+                            format!("已替换重复选项：{}", summary)
+                        } else {
+                            format!("发现重复选项：{}", summary)
+                        };
+                        let app_weak = app_for_response.clone();
+                        slint::invoke_from_event_loop(move || {
+                            if let Some(app) = app_weak.upgrade() {
+                                app.set_status_message(message.into());
+                            }
+                        })
+                        .ok();
+                    }
+                }
+
+                // 平台要求每个空的 data-blank-id 不同，重复时提示
+                let duplicate_blank_ids = (response.is_complete && !response.is_error())
+                    .then(|| utility::check_unique_blank_ids(&response.content).err())
+                    .flatten();
+                if let Some(duplicates) = duplicate_blank_ids {
+                    tracing::warn!("[event_handlers] Duplicate data-blank-id values: {:?}", duplicates);
+                    let message = format!("发现重复的 data-blank-id：{}", duplicates.join("、"));
+                    let app_weak = app_for_response.clone();
+                    slint::invoke_from_event_loop(move || {
+                        if let Some(app) = app_weak.upgrade() {
+                            app.set_status_message(message.into());
+                        }
+                    })
+                    .ok();
+                }
+
+                // 使用自定义段落样式时统一替换文章中的段落样式
+                if response.is_complete && !response.is_error() {
+                    let style = options.paragraph_style();
+                    if style != DEFAULT_PARAGRAPH_STYLE {
+                        response.content = utility::apply_paragraph_style(&response.content, style);
+                    }
+                }
+
+                // 开启英文解析时只把解析发给模型翻译，翻译失败时保留中文解析
+                if response.is_complete && translate_analysis && !response.is_error() {
+                    let manager = match settings_for_translation.lock() {
+                        Ok(settings) => LLMManager::manager_for_question_type(settings.get_config(), question_type),
+                        Err(e) => {
+                            tracing::error!("[event_handlers] Failed to lock LLM settings for translation: {}", e);
+                            LLMManager::default()
+                        }
+                    };
+                    let message = match runtime.block_on(translate::translate_analysis(&manager, &response.content)) {
+                        Ok(translated) => {
+                            response.content = translated;
+                            "已将解析翻译为英文".to_string()
+                        }
+                        Err(e) => {
+                            tracing::warn!("[event_handlers] Failed to translate analysis: {}", e);
+                            format!("解析翻译失败，保留原解析：{}", e)
+                        }
+                    };
+                    let app_weak = app_for_response.clone();
+                    slint::invoke_from_event_loop(move || {
+                        if let Some(app) = app_weak.upgrade() {
+                            app.set_status_message(message.into());
+                        }
+                    })
+                    .ok();
+                }
+
                 // 使用slint的invoke_from_event_loop来确保UI更新在主线程中执行
                 let content = response.content.clone();
+                let reasoning = response.reasoning.clone();
                 let is_complete = response.is_complete;
                 let app_weak = app_for_response.clone();
+                let generation_for_ui = generation_for_ui.clone();
 
                 slint::invoke_from_event_loop(move || {
+                    // 等待主线程期间请求可能已被取代
+                    if !generation_for_ui.is_current(generation) {
+                        return;
+                    }
                     if let Some(app) = app_weak.upgrade() {
                         tracing::debug!(
                             "[event_handlers] Updating UI with response length: {}",
                             content.len()
                         );
                         app.set_model_reply(content.into());
+                        if let Some(reasoning) = reasoning {
+                            app.set_model_reasoning(reasoning.into());
+                        }
 
                         if is_complete {
                             app.set_is_streaming(false);
@@ -159,7 +1050,92 @@ impl EventHandlers {
                 .ok();
 
                 if is_complete {
-                    question.set_model_reply(response.content.into());
+                    let is_error = response.is_error();
+
+                    // 被限流时提示需要等待的时间
+                    if let Some(rate_limited) = is_error.then(|| RateLimited::from_message(&response.content)).flatten() {
+                        let hint = format!("⚠️ {}", rate_limited.hint());
+                        let app_weak = app_for_response.clone();
+                        slint::invoke_from_event_loop(move || {
+                            if let Some(app) = app_weak.upgrade() {
+                                app.set_status_message(hint.into());
+                            }
+                        })
+                        .ok();
+                    }
+
+                    // 开启本地统计时记录本次请求，命中缓存的回复没有实际请求，不计入
+                    if usage_stats && !from_cache {
+                        let reply_tokens = if is_error { 0 } else { utility::estimate_tokens(&response.content) };
+                        let record = UsageRecord::now(
+                            question.get_type(),
+                            question.estimated_prompt_tokens(),
+                            reply_tokens,
+                            !is_error,
+                        );
+                        if let Err(e) = UsageStats::open_default().and_then(|stats| stats.record(&record)) {
+                            tracing::error!("[event_handlers] Failed to record usage stats: {}", e);
+                        }
+                    }
+
+                    // 请求日志只记录长度、模型和截断后的回复，命中缓存时不记录
+                    if !from_cache {
+                        let logged = RequestLog::open_default().and_then(|log| {
+                            Self::record_request(&log, question_type, &model, prompt_chars, started_at.elapsed(), &response)
+                        });
+                        if let Err(e) = logged {
+                            tracing::error!("[event_handlers] Failed to write request log: {}", e);
+                        }
+                    }
+
+                    question.set_model_reply(response.content);
+
+                    // 按题型检查输出结构，缺少字段或类型不对时提示
+                    if let Some(Err(errors)) = (!is_error).then(|| question.validate_output()) {
+                        let summary = errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("；");
+                        tracing::warn!("[event_handlers] Output does not match schema: {}", summary);
+                        let message = format!("输出结构不完整：{}", summary);
+                        let app_weak = app_for_response.clone();
+                        slint::invoke_from_event_loop(move || {
+                            if let Some(app) = app_weak.upgrade() {
+                                app.set_status_message(message.into());
+                            }
+                        })
+                        .ok();
+                    }
+
+                    // 成功完成的题目写入历史记录
+                    if !is_error {
+                        match QuestionHistory::open_default() {
+                            Ok(history) => {
+                                if let Err(e) = history.save(&question) {
+                                    tracing::error!("[event_handlers] Failed to save question history: {}", e);
+                                }
+                            }
+                            Err(e) => {
+                                tracing::error!("[event_handlers] Failed to open question history: {}", e);
+                            }
+                        }
+                    }
+
+                    // 成功生成后统计题目数量、提取文章预览并更新 UI，出错时保持发送前清零的状态
+                    if !is_error {
+                        let generated_count = question.count_generated().unwrap_or(0);
+                        tracing::info!("[event_handlers] Generated {} questions", generated_count);
+                        let passage_preview = question
+                            .get_output()
+                            .and_then(utility::extract_new_content)
+                            .map(|html| utility::html_to_preview_text(&html))
+                            .unwrap_or_default();
+                        let app_weak = app_for_response.clone();
+                        slint::invoke_from_event_loop(move || {
+                            if let Some(app) = app_weak.upgrade() {
+                                app.set_generated_count(generated_count as i32);
+                                app.set_passage_preview(passage_preview.into());
+                            }
+                        })
+                        .ok();
+                    }
                     break;
                 }
             }
@@ -168,26 +1144,138 @@ impl EventHandlers {
 
     /// 设置复制回调
     fn setup_copy_callback(&self, app: &App) {
-        let app_weak = app.as_weak();
+        let status_duration = self.app_state.clipboard_config.copy_status_duration();
+        let llm_settings = self.app_state.llm_settings.clone();
+        let clipboard_hashes = self.app_state.clipboard_hashes.clone();
+        // 重复复制时重新计时，避免上一次的定时器提前清除新的提示
+        let clear_timer = slint::Timer::default();
+
+        let copy_reply = std::rc::Rc::new(move |app: &App, with_additional_code: bool| {
+            let reply = app.get_model_reply().to_string();
+            if reply.trim().is_empty() {
+                tracing::debug!("[event_handlers] No reply to copy");
+                return;
+            }
+            let question_type = QuestionType::from_str(app.get_question_type().as_str())
+                .expect("wrong question type, please check again!");
+            let scripts = llm_settings
+                .lock()
+                .map(|settings| settings.prompt_options().scripts)
+                .unwrap_or_default();
+            let text = Self::copy_text(reply, question_type, &scripts, with_additional_code);
+            let status = Self::copy_with_status(&text, |text| Self::copy_to_clipboard(text, &clipboard_hashes));
+            app.set_copy_status(status.into());
+            if !status.is_empty() {
+                let app_weak = app.as_weak();
+                clear_timer.start(slint::TimerMode::SingleShot, status_duration, move || {
+                    if let Some(app) = app_weak.upgrade() {
+                        app.set_copy_status("".into());
+                    }
+                });
+            }
+        });
 
+        let app_weak = app.as_weak();
+        let copy = copy_reply.clone();
         app.on_copy_reply_and_addcode(move || {
             tracing::info!("[event_handlers] Copy reply triggered");
             if let Some(app) = app_weak.upgrade() {
-                let reply = app.get_model_reply().to_string();
-                if !reply.trim().is_empty() {
-                    let additional_code = AdditionalCodeGenerator::new(
-                        QuestionType::from_str(app.get_question_type().as_str())
-                            .expect("wrong question type, please check again!"),
-                    )
-                    .get_code();
-                    Self::copy_to_clipboard(&(reply + &additional_code));
-                } else {
-                    tracing::debug!("[event_handlers] No reply to copy");
-                }
+                copy(&app, true);
+            }
+        });
+
+        let app_weak = app.as_weak();
+        app.on_copy_reply_only(move || {
+            tracing::info!("[event_handlers] Copy reply only triggered");
+            if let Some(app) = app_weak.upgrade() {
+                copy_reply(&app, false);
             }
         });
     }
 
+    /// 回复是否仍应更新界面：没有按下停止，且请求没有被之后的请求或提供商切换取代
+    fn accepts_response(stop_signal: &AtomicBool, request_generation: &RequestGeneration, generation: u64) -> bool {
+        !stop_signal.load(Ordering::Relaxed) && request_generation.is_current(generation)
+    }
+
+    /// 复制到剪贴板的内容：模型回复，需要时在末尾附加对应题型的自动化代码
+    fn copy_text(reply: String, question_type: QuestionType, scripts: &ScriptOptions, with_additional_code: bool) -> String {
+        if with_additional_code {
+            reply + &scripts.generator(question_type).get_code()
+        } else {
+            reply
+        }
+    }
+
+    /// 设置复制答案回调：解析当前回复，把答案速查复制到剪贴板
+    fn setup_copy_answer_key_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let clipboard_hashes = self.app_state.clipboard_hashes.clone();
+
+        app.on_copy_answer_key(move || {
+            tracing::info!("[event_handlers] Copy answer key triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let questions = match parser::parse_questions(&app.get_model_reply()) {
+                Ok(questions) if !questions.is_empty() => questions,
+                Ok(_) => {
+                    app.set_status_message("当前回复中没有题目，无法生成答案".into());
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("[event_handlers] Failed to parse reply for answer key: {}", e);
+                    app.set_status_message(format!("无法解析当前回复: {}", e).into());
+                    return;
+                }
+            };
+            let answer_key = export::export_answer_key(&questions);
+            let status = match Self::copy_to_clipboard(&answer_key, &clipboard_hashes) {
+                Ok(()) => format!("已复制答案: {}", answer_key),
+                Err(e) => format!("复制答案失败: {}", e),
+            };
+            app.set_status_message(status.into());
+        });
+    }
+
+    /// 设置修复空格 ID 回调：为当前回复中重复的 data-blank-id 重新编号
+    fn setup_renumber_blank_ids_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+
+        app.on_renumber_blank_ids(move || {
+            tracing::info!("[event_handlers] Renumber blank ids triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let reply = app.get_model_reply().to_string();
+            let message = match utility::check_unique_blank_ids(&reply) {
+                Ok(()) => "没有重复的 data-blank-id".to_string(),
+                Err(duplicates) => {
+                    app.set_model_reply(utility::renumber_blank_ids(&reply).into());
+                    format!("已为重复的 data-blank-id 重新编号：{}", duplicates.join("、"))
+                }
+            };
+            app.set_status_message(message.into());
+        });
+    }
+
+    /// 设置插入示例回调：按当前题型填入示例题干，方便新用户了解应粘贴的内容
+    fn setup_insert_example_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+
+        app.on_insert_example(move || {
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let Ok(question_type) = QuestionType::from_str(app.get_question_type().as_str()) else {
+                tracing::warn!("[event_handlers] Unknown question type for example: {}", app.get_question_type());
+                return;
+            };
+            tracing::info!("[event_handlers] Inserting example stem for {}", question_type.as_str());
+            app.set_prefill_text(question_type.example_stem().into());
+        });
+    }
+
     /// 设置停止响应回调
     fn setup_stop_response_callback(&self, app: &App) {
         let stop_signal = self.stop_signal.clone();
@@ -226,14 +1314,292 @@ impl EventHandlers {
         });
     }
 
-    /// 复制文本到剪贴板
-    fn copy_to_clipboard(text: &str) {
-        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            if let Err(e) = clipboard.set_text(text) {
-                tracing::error!("[event_handlers] Failed to copy to clipboard: {}", e);
+    /// 设置从文件加载图片回调：路径为空时弹出文件选择框，校验能解码后按剪贴板图片的方式使用
+    fn setup_load_image_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let current_image_path = self.app_state.current_image_path.clone();
+
+        app.on_load_image_from_path(move |path| {
+            tracing::info!("[event_handlers] Load image from file triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+
+            let path = if path.is_empty() {
+                match rfd::FileDialog::new()
+                    .set_title("选择题目图片")
+                    .add_filter("图片", &utility::SUPPORTED_IMAGE_EXTENSIONS)
+                    .pick_file()
+                {
+                    Some(path) => path,
+                    None => {
+                        tracing::debug!("[event_handlers] Image selection cancelled");
+                        return;
+                    }
+                }
             } else {
+                PathBuf::from(path.as_str())
+            };
+
+            let loaded = utility::decode_image_file(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|_| {
+                    slint::Image::load_from_path(&utility::thumbnail_for_display(&path))
+                        .map_err(|_| "无法显示图片".to_string())
+                });
+            match loaded {
+                Ok(image) => {
+                    tracing::info!("[event_handlers] Loaded image from file: {}", path.display());
+                    app.set_current_image(image);
+                    app.set_status_message(format!("已加载图片: {}", path.display()).into());
+                    if let Ok(mut current) = current_image_path.lock() {
+                        *current = Some(path);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to load image {}: {}", path.display(), e);
+                    app.set_status_message(
+                        format!("无法加载图片（仅支持可解码的 PNG/JPEG）: {}", e).into(),
+                    );
+                }
+            }
+        });
+    }
+
+    /// 设置立即粘贴回调：直接读取一次剪贴板图片并显示，不等待后台轮询
+    fn setup_paste_clipboard_callback(&self, app: &App) {
+        let app_weak = app.as_weak();
+        let current_image_path = self.app_state.current_image_path.clone();
+        let image_limits = self.app_state.clipboard_config.image_limits();
+        let max_paste_files = self.app_state.clipboard_config.max_paste_files;
+        let paste_files = self.app_state.paste_files.tracker();
+        let clipboard_hashes = self.app_state.clipboard_hashes.clone();
+        let last_question = self.app_state.last_question.clone();
+
+        app.on_paste_clipboard(move || {
+            tracing::info!("[event_handlers] Paste clipboard triggered");
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = clipboard_monitor::save_clipboard_image(
+                image_limits,
+                max_paste_files,
+                &paste_files,
+                &clipboard_hashes.image,
+                &AppState::paste_files_in_use(&current_image_path, &last_question),
+            ) else {
+                app.set_status_message("剪贴板中没有可用的图片".into());
+                return;
+            };
+            match slint::Image::load_from_path(&utility::thumbnail_for_display(&path)) {
+                Ok(image) => {
+                    tracing::info!("[event_handlers] Pasted clipboard image: {}", path.display());
+                    app.set_current_image(image);
+                    app.set_status_message("已粘贴剪贴板图片".into());
+                    if let Ok(mut current) = current_image_path.lock() {
+                        *current = Some(path);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("[event_handlers] Failed to display pasted image {}: {:?}", path.display(), e);
+                    app.set_status_message("无法显示剪贴板图片".into());
+                }
+            }
+        });
+    }
+
+    /// 复制文本到剪贴板，并记录到 `hashes` 中，避免监控线程把它填入题目输入框
+    fn copy_to_clipboard(text: &str, hashes: &clipboard_monitor::ClipboardHashes) -> Result<(), String> {
+        hashes.remember_own_text(text);
+        let mut clipboard = arboard::Clipboard::new().map_err(|e| {
+            tracing::error!("[event_handlers] Failed to open clipboard: {}", e);
+            e.to_string()
+        })?;
+        match clipboard.set_text(text) {
+            Ok(()) => {
                 tracing::info!("[event_handlers] Reply copied to clipboard successfully");
+                Ok(())
             }
+            Err(e) => {
+                tracing::error!("[event_handlers] Failed to copy to clipboard: {}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// 复制文本并返回 UI 上显示的复制状态，复制失败时返回空字符串
+    fn copy_with_status(text: &str, copy: impl FnOnce(&str) -> Result<(), String>) -> &'static str {
+        match copy(text) {
+            Ok(()) => COPY_SUCCESS_STATUS,
+            Err(_) => "",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superseded_response_does_not_update_reply() {
+        let stop_signal = AtomicBool::new(false);
+        let request_generation = RequestGeneration::default();
+        let mut model_reply = String::new();
+        let deliver = |generation: u64, content: &str, model_reply: &mut String| {
+            if EventHandlers::accepts_response(&stop_signal, &request_generation, generation) {
+                *model_reply = content.to_string();
+            }
+        };
+
+        let first = request_generation.next();
+        deliver(first, "old partial", &mut model_reply);
+        assert_eq!(model_reply, "old partial");
+
+        // 切换提供商后发起新请求，停止信号已被新请求重置，旧请求迟到的回复仍被丢弃
+        AppState::abort_requests(&stop_signal, &request_generation);
+        stop_signal.store(false, Ordering::Relaxed);
+        let second = request_generation.next();
+        deliver(first, "old complete", &mut model_reply);
+        assert_eq!(model_reply, "old partial");
+        deliver(second, "new reply", &mut model_reply);
+        assert_eq!(model_reply, "new reply");
+
+        // 只切换提供商、没有新请求时也不再更新
+        AppState::abort_requests(&stop_signal, &request_generation);
+        stop_signal.store(false, Ordering::Relaxed);
+        deliver(second, "late reply", &mut model_reply);
+        assert_eq!(model_reply, "new reply");
+    }
+
+    #[test]
+    fn test_copy_reply_only_omits_additional_code() {
+        let reply = "var Questions = [];".to_string();
+        let scripts = ScriptOptions::default();
+        let with_code = EventHandlers::copy_text(reply.clone(), QuestionType::SingleChoice, &scripts, true);
+        let reply_only = EventHandlers::copy_text(reply.clone(), QuestionType::SingleChoice, &scripts, false);
+        let additional_code = scripts.generator(QuestionType::SingleChoice).get_code();
+        assert!(!additional_code.is_empty());
+        assert_eq!(reply_only, reply);
+        assert_eq!(with_code.strip_prefix(reply_only.as_str()), Some(additional_code.as_str()));
+    }
+
+    #[test]
+    fn test_stream_updates_are_throttled_and_coalesced() {
+        let start = Instant::now();
+        let mut throttle = StreamThrottle::new(STREAM_UI_INTERVAL);
+        assert_eq!(throttle.remaining(start), None);
+        throttle.mark(start);
+        assert_eq!(throttle.remaining(start + Duration::from_millis(10)), Some(Duration::from_millis(20)));
+        assert_eq!(throttle.remaining(start + STREAM_UI_INTERVAL), None);
+
+        let partial = |content: &str| LLMResponse {
+            content: content.to_string(),
+            is_complete: false,
+            reasoning: None,
+        };
+        let (sender, receiver) = mpsc::channel();
+        for content in ["var Q", "var Questions", "var Questions = ["] {
+            sender.send(partial(content)).unwrap();
+        }
+        sender
+            .send(LLMResponse {
+                content: "var Questions = [];".to_string(),
+                is_complete: true,
+                reasoning: None,
+            })
+            .unwrap();
+        sender.send(partial("next request")).unwrap();
+
+        // 等待期间累积的中间结果合并为最新的一条，完成响应之后的回复留在通道中
+        let latest = latest_response(partial("var"), &receiver);
+        assert!(latest.is_complete);
+        assert_eq!(latest.content, "var Questions = [];");
+        assert_eq!(receiver.try_recv().unwrap().content, "next request");
+
+        let latest = latest_response(partial("only"), &receiver);
+        assert_eq!(latest.content, "only");
+        assert!(!latest.is_complete);
+    }
+
+    #[test]
+    fn test_copy_status_set_after_successful_copy() {
+        let mut copied = String::new();
+        let status = EventHandlers::copy_with_status("var Questions = [];", |text| {
+            copied = text.to_string();
+            Ok(())
+        });
+        assert_eq!(status, COPY_SUCCESS_STATUS);
+        assert_eq!(copied, "var Questions = [];");
+
+        let status = EventHandlers::copy_with_status("x", |_| Err("clipboard unavailable".to_string()));
+        assert_eq!(status, "");
+    }
+
+    #[test]
+    fn test_regenerate_uses_last_sent_question() {
+        let last_question = std::sync::Mutex::new(None);
+        let options = PromptOptions::default();
+        assert!(EventHandlers::next_regeneration(&last_question, None, &options).is_none());
+
+        let sent = Question::new(
+            QuestionType::Reading,
+            "阅读题干".to_string(),
+            Some(PathBuf::from("question.png")),
+        );
+        EventHandlers::remember_last_question(&last_question, &sent);
+        let stored = last_question.lock().unwrap().clone().unwrap();
+        assert_eq!(stored.get_id(), sent.get_id());
+
+        // 重新生成复用题干、图片和题型，但是一次新的请求
+        let regenerated = EventHandlers::next_regeneration(&last_question, None, &options).unwrap();
+        assert_ne!(regenerated.get_id(), sent.get_id());
+        assert_eq!(regenerated.get_type(), QuestionType::Reading);
+        assert_eq!(regenerated.get_stem(), sent.get_stem());
+        assert_eq!(regenerated.get_img_path(), sent.get_img_path());
+        assert_eq!(regenerated.prompt_stem(), sent.prompt_stem());
+        assert_eq!(last_question.lock().unwrap().as_ref().unwrap().get_id(), regenerated.get_id());
+
+        let converted =
+            EventHandlers::next_regeneration(&last_question, Some(QuestionType::SingleChoice), &options).unwrap();
+        assert_eq!(converted.get_type(), QuestionType::SingleChoice);
+        assert_eq!(converted.get_stem(), sent.get_stem());
+    }
+
+    #[tokio::test]
+    async fn test_sent_request_is_logged() {
+        use crate::core::llm_backend::MockBackend;
+
+        let mut manager = LLMManager::new();
+        manager.add_backend(Box::new(MockBackend::fixed("var Questions = [];")));
+        let question = Question::new(QuestionType::SingleChoice, "题干".to_string(), None);
+        let text = question.prompt_stem();
+        let prompt_chars = text.chars().count();
+
+        let (sender, receiver) = mpsc::channel();
+        let started_at = Instant::now();
+        manager.send_message(text, None, sender).await.unwrap();
+        let response = receiver.try_iter().find(|response| response.is_complete).unwrap();
+
+        let path = std::env::temp_dir().join(format!("question_tool_request_log_{}.jsonl", uuid::Uuid::new_v4()));
+        let log = RequestLog::new(&path);
+        EventHandlers::record_request(
+            &log,
+            question.get_type(),
+            "mock-model",
+            prompt_chars,
+            started_at.elapsed(),
+            &response,
+        )
+        .unwrap();
+
+        let entries = log.last(5);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].question_type, "单选题");
+        assert_eq!(entries[0].model, "mock-model");
+        assert_eq!(entries[0].prompt_chars, prompt_chars);
+        assert!(entries[0].success);
+        assert_eq!(entries[0].response, "var Questions = [];");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,7 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
-use crate::core::llm_backend::LLMManager;
+use std::time::{Duration, Instant};
+use crate::core::batch::DEFAULT_MAX_CONCURRENT;
+use crate::core::logger;
+use crate::core::llm_backend::{
+    DEFAULT_MAX_RETRIES, DEFAULT_MAX_TOKENS, DEFAULT_TEMPERATURE, DEFAULT_TIMEOUT_SECS, LLMManager, LLMProvider,
+    TokenUsage,
+};
+use crate::core::question_type::{
+    AnalysisOrder, DEFAULT_MAX_STEM_CHARS, DEFAULT_PARAGRAPH_STYLE, DEFAULT_SAVE_RETRY_ATTEMPTS,
+    DEFAULT_SAVE_RETRY_DELAY_MS, PromptLanguage, PromptOptions, QuestionType, ScriptOptions,
+};
+use crate::core::utility::{self, DEFAULT_MAX_IMAGE_DIM, DuplicateOptionMode, ImageEncodeFormat, PreprocessOpts};
 
 /// LLM 设置配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,8 +22,199 @@ pub struct LLMConfig {
     pub model: String,
     pub api_key: Option<String>,
     pub base_url: Option<String>,
+    /// OpenAI 组织 ID（组织级账号需要，作为 `OpenAI-Organization` 请求头发送）
+    #[serde(default)]
+    pub organization: Option<String>,
     pub github_token: Option<String>,
+    /// Google Gemini API Key（provider 为 "Gemini" 时使用，未配置时读取 GEMINI_API_KEY）
+    #[serde(default)]
+    pub gemini_api_key: Option<String>,
     pub enable_streaming: bool,
+    /// HTTP/SOCKS 代理地址（如 `http://127.0.0.1:7890`、`socks5://127.0.0.1:1080`），未配置时直连
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// 请求失败时把脱敏后的请求体写入 `<config_dir>/logs/failed_requests`
+    #[serde(default)]
+    pub log_failed_requests: bool,
+    /// 发送前题干的最大字符数，0 表示不限制
+    #[serde(default = "default_max_stem_chars")]
+    pub max_stem_chars: usize,
+    /// 发送前图片最长边的像素上限，0 表示不缩放
+    #[serde(default = "default_max_image_dim")]
+    pub max_image_dim: u32,
+    /// 发送前图片的编码格式（PNG 或指定质量的 JPEG）
+    #[serde(default)]
+    pub image_format: ImageEncodeFormat,
+    /// 发送前是否对图片做灰度、对比度等预处理，便于识别较淡的扫描件
+    #[serde(default)]
+    pub preprocess_images: bool,
+    /// 开启预处理时使用的选项
+    #[serde(default)]
+    pub preprocess_options: PreprocessOpts,
+    /// 开启预处理时同时发送原图和预处理后的图片，适合难以识别的扫描件
+    #[serde(default)]
+    pub send_both_images: bool,
+    /// 发送前合并题干中 OCR 行尾连字符断开的单词
+    #[serde(default)]
+    pub dehyphenate_stem: bool,
+    /// 模型未给出解析时，根据答案生成占位解析
+    #[serde(default)]
+    pub fill_missing_analysis: bool,
+    /// 把解析中的 Markdown（粗体、斜体、换行）转换为 HTML，避免平台上显示星号
+    #[serde(default)]
+    pub analysis_markdown_to_html: bool,
+    /// 同一题目出现重复选项时的处理方式（默认只提示）
+    #[serde(default)]
+    pub duplicate_options: DuplicateOptionMode,
+    /// 提示词要求的解析顺序（考点在前或答案在前）
+    #[serde(default)]
+    pub analysis_order: AnalysisOrder,
+    /// 阅读/完形等文章段落 `<p>` 的样式
+    #[serde(default = "default_paragraph_style")]
+    pub paragraph_style: String,
+    /// 提示词说明文字的语言（默认中文）
+    #[serde(default)]
+    pub prompt_language: PromptLanguage,
+    /// 每个提示词预先生成并要求模型使用的 data-blank-id 数量，0 表示由模型自行编写
+    #[serde(default)]
+    pub blank_id_pool_size: usize,
+    /// 生成代码最多点击保存按钮的次数
+    #[serde(default = "default_save_retry_attempts")]
+    pub save_retry_attempts: usize,
+    /// 生成代码两次点击保存按钮之间的等待时间（毫秒）
+    #[serde(default = "default_save_retry_delay_ms")]
+    pub save_retry_delay_ms: u64,
+    /// 全局请求超时时间（秒）
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// 单次回复的最大 token 数
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    /// 采样温度
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    /// 按提供商覆盖的请求超时时间（秒），键为 "GPT" / "GitHub" / "Gemini" / "Custom"
+    #[serde(default)]
+    pub provider_timeouts: HashMap<String, u64>,
+    /// 回复少于该字符数时视为失败并重试，0 表示不检查
+    #[serde(default)]
+    pub min_reply_chars: usize,
+    /// 回复过短或请求返回可重试状态码时的最大重试次数
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// 除默认状态码（429/500/502/503/504）外额外视为可重试的 HTTP 状态码，如 Cloudflare 的 520
+    #[serde(default)]
+    pub retryable_status: Vec<u16>,
+    /// 输出中有多个 `Questions` 数组时是否合并为一个，关闭时只提示
+    #[serde(default)]
+    pub merge_questions_arrays: bool,
+    /// 是否在本地记录使用统计（题型、token 估算、成功与否），不会上传
+    #[serde(default)]
+    pub usage_stats: bool,
+    /// 是否把回复缓存保存到配置目录，关闭时只缓存在内存中
+    #[serde(default)]
+    pub response_cache_on_disk: bool,
+    /// 批量处理时最多同时进行的请求数
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// 按题型覆盖的模型，键为 `QuestionType::as_str()`
+    #[serde(default)]
+    pub model_overrides: HashMap<String, String>,
+    /// 题干超过该字符数时发送前提示，未配置时按提供商使用默认值
+    #[serde(default)]
+    pub stem_warning_chars: Option<usize>,
+    /// 日志级别（`RUST_LOG` 的写法，如 `debug`），设置了 `RUST_LOG` 环境变量时以环境变量为准
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// 上次选择的题型（`QuestionType::as_str()`），启动时恢复，未记录时使用侧边栏的第一项
+    #[serde(default)]
+    pub default_question_type: Option<String>,
+}
+
+impl LLMConfig {
+    /// 获取指定提供商的请求超时时间，未单独配置时使用全局超时
+    pub fn timeout_for(&self, provider: &LLMProvider) -> Duration {
+        let secs = self
+            .provider_timeouts
+            .get(&provider.to_string())
+            .copied()
+            .unwrap_or(self.timeout_secs);
+        Duration::from_secs(secs)
+    }
+
+    /// 获取配置的代理地址，空字符串视为未配置
+    pub fn proxy_url(&self) -> Option<&str> {
+        self.proxy_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|proxy_url| !proxy_url.is_empty())
+    }
+
+    /// 获取指定题型使用的模型，未单独配置时使用 `model`
+    pub fn model_for(&self, question_type: QuestionType) -> &str {
+        self.model_overrides
+            .get(question_type.as_str())
+            .map(String::as_str)
+            .filter(|model| !model.trim().is_empty())
+            .unwrap_or(&self.model)
+    }
+
+    /// 题干长度提示的字符数上限，未配置时按当前提供商取默认值
+    pub fn stem_warning_limit(&self) -> usize {
+        self.stem_warning_chars
+            .unwrap_or_else(|| LLMProvider::from_name(&self.provider).default_stem_warning_chars())
+    }
+
+    /// 题型实际使用的模型是否支持图片输入
+    pub fn supports_vision(&self, question_type: QuestionType) -> bool {
+        LLMManager::manager_for_question_type(self, question_type)
+            .current_backend()
+            .is_none_or(|backend| backend.supports_vision())
+    }
+}
+
+fn default_max_concurrent() -> usize {
+    DEFAULT_MAX_CONCURRENT
+}
+
+fn default_max_retries() -> u32 {
+    DEFAULT_MAX_RETRIES
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_max_tokens() -> u32 {
+    DEFAULT_MAX_TOKENS
+}
+
+fn default_temperature() -> f32 {
+    DEFAULT_TEMPERATURE
+}
+
+fn default_save_retry_attempts() -> usize {
+    DEFAULT_SAVE_RETRY_ATTEMPTS
+}
+
+fn default_save_retry_delay_ms() -> u64 {
+    DEFAULT_SAVE_RETRY_DELAY_MS
+}
+
+fn default_log_level() -> String {
+    logger::DEFAULT_LOG_LEVEL.to_string()
+}
+
+fn default_paragraph_style() -> String {
+    DEFAULT_PARAGRAPH_STYLE.to_string()
+}
+
+fn default_max_stem_chars() -> usize {
+    DEFAULT_MAX_STEM_CHARS
+}
+
+fn default_max_image_dim() -> u32 {
+    DEFAULT_MAX_IMAGE_DIM
 }
 
 impl Default for LLMConfig {
@@ -21,61 +224,236 @@ impl Default for LLMConfig {
             model: "gpt-4o".to_string(),
             api_key: None,
             base_url: None,
+            organization: None,
             github_token: std::env::var("GITHUB_TOKEN").ok(),
+            gemini_api_key: None,
             enable_streaming: true,
+            proxy_url: None,
+            log_failed_requests: false,
+            max_stem_chars: DEFAULT_MAX_STEM_CHARS,
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            preprocess_images: false,
+            preprocess_options: PreprocessOpts::default(),
+            send_both_images: false,
+            dehyphenate_stem: false,
+            fill_missing_analysis: false,
+            analysis_markdown_to_html: false,
+            duplicate_options: DuplicateOptionMode::default(),
+            analysis_order: AnalysisOrder::default(),
+            paragraph_style: default_paragraph_style(),
+            prompt_language: PromptLanguage::default(),
+            blank_id_pool_size: 0,
+            max_tokens: default_max_tokens(),
+            temperature: default_temperature(),
+            save_retry_attempts: default_save_retry_attempts(),
+            save_retry_delay_ms: default_save_retry_delay_ms(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            provider_timeouts: HashMap::new(),
+            min_reply_chars: 0,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retryable_status: Vec::new(),
+            merge_questions_arrays: false,
+            usage_stats: false,
+            response_cache_on_disk: false,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            model_overrides: HashMap::new(),
+            stem_warning_chars: None,
+            log_level: default_log_level(),
+            default_question_type: None,
+        }
+    }
+}
+
+/// 默认配置档名称，旧版单配置文件迁移到该配置档
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// 配置文件内容：多个命名配置档及当前使用的配置档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LLMProfiles {
+    active_profile: String,
+    profiles: BTreeMap<String, LLMConfig>,
+}
+
+impl Default for LLMProfiles {
+    fn default() -> Self {
+        Self {
+            active_profile: DEFAULT_PROFILE_NAME.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE_NAME.to_string(), LLMConfig::default())]),
         }
     }
 }
 
 /// LLM 设置管理器
 pub struct AppLLMSettingsManager {
+    /// 当前配置档的配置（包含尚未保存的修改）
     config: LLMConfig,
+    profiles: BTreeMap<String, LLMConfig>,
+    active_profile: String,
     manager: LLMManager,
-    config_path: PathBuf,
+    /// 配置文件路径，配置目录不可用时为 None，配置只保存在内存中
+    config_path: Option<PathBuf>,
+    /// 启动时从配置目录读取的附加代码设置
+    scripts: ScriptOptions,
 }
 
 impl AppLLMSettingsManager {
     /// 创建新的设置管理器
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_path = Self::get_config_path()?;
-        let config = Self::load_config_from_file(&config_path)?;
+    ///
+    /// 配置目录不可用或配置文件无法读取时记录警告，回退到内存中的默认配置，
+    /// 界面仍可启动，本次运行中填写的设置照常生效，只是无法写入文件。
+    /// 附加代码的选择器只在这里读取一次，修改 selectors.json 后需要重新启动。
+    pub fn new() -> Self {
+        let settings = match Self::get_config_path() {
+            Ok(config_path) => Self::load_or_default(config_path),
+            Err(e) => {
+                tracing::warn!("[llm_settings] 配置目录不可用，本次使用内存中的默认配置: {}", e);
+                Self::from_profiles(None, LLMProfiles::default())
+            }
+        };
+        Self {
+            scripts: ScriptOptions::load(),
+            ..settings
+        }
+    }
+
+    /// 从指定的配置文件加载，失败时回退到内存中的默认配置（不会覆盖原文件）
+    pub fn load_or_default(config_path: PathBuf) -> Self {
+        Self::with_config_path(config_path.clone()).unwrap_or_else(|e| {
+            tracing::warn!(
+                "[llm_settings] 无法加载配置文件 {:?}，本次使用内存中的默认配置: {}",
+                config_path,
+                e
+            );
+            Self::from_profiles(None, LLMProfiles::default())
+        })
+    }
+
+    /// 使用指定的配置文件路径创建设置管理器
+    pub fn with_config_path(config_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let profiles = Self::load_profiles_from_file(&config_path)?;
+        Ok(Self::from_profiles(Some(config_path), profiles))
+    }
+
+    fn from_profiles(config_path: Option<PathBuf>, profiles: LLMProfiles) -> Self {
+        let LLMProfiles { active_profile, profiles } = profiles;
+        let config = profiles.get(&active_profile).cloned().unwrap_or_default();
         let manager = LLMManager::from_config(&config);
+        logger::set_level(&config.log_level);
 
-        Ok(Self {
+        Self {
             config,
+            profiles,
+            active_profile,
             manager,
             config_path,
-        })
+            scripts: ScriptOptions::default(),
+        }
+    }
+
+    /// 配置文件路径，配置只保存在内存中时返回错误
+    fn require_config_path(&self) -> Result<&PathBuf, Box<dyn std::error::Error>> {
+        self.config_path
+            .as_ref()
+            .ok_or_else(|| "配置目录不可写，设置只在本次运行中生效".into())
     }
 
     /// 获取配置文件路径
     fn get_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let mut config_dir = dirs::config_dir()
-            .or_else(|| dirs::home_dir())
-            .ok_or("无法找到配置目录")?;
-        
-        config_dir.push("question_tool");
-        
-        // 确保目录存在
-        if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)?;
-        }
-        
-        config_dir.push("llm_config.json");
-        Ok(config_dir)
-    }
-
-    /// 从文件加载配置
-    fn load_config_from_file(path: &PathBuf) -> Result<LLMConfig, Box<dyn std::error::Error>> {
-        if path.exists() {
-            let content = fs::read_to_string(path)?;
-            let config: LLMConfig = serde_json::from_str(&content)?;
-            tracing::info!("[llm_settings] 已加载配置: {:?}", config.provider);
-            Ok(config)
-        } else {
+        Ok(utility::app_config_dir()?.join("llm_config.json"))
+    }
+
+    /// 从文件加载配置档，旧版单配置文件会迁移为默认配置档并写回
+    fn load_profiles_from_file(path: &PathBuf) -> Result<LLMProfiles, Box<dyn std::error::Error>> {
+        if !path.exists() {
             tracing::info!("[llm_settings] 配置文件不存在，使用默认配置");
-            Ok(LLMConfig::default())
+            return Ok(LLMProfiles::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&content)?;
+        if value.get("profiles").is_some() {
+            let mut profiles: LLMProfiles = serde_json::from_value(value)?;
+            if !profiles.profiles.contains_key(&profiles.active_profile) {
+                tracing::warn!(
+                    "[llm_settings] 配置档 {} 不存在，使用默认配置",
+                    profiles.active_profile
+                );
+                profiles
+                    .profiles
+                    .insert(profiles.active_profile.clone(), LLMConfig::default());
+            }
+            tracing::info!(
+                "[llm_settings] 已加载配置档: {} ({} 个)",
+                profiles.active_profile,
+                profiles.profiles.len()
+            );
+            return Ok(profiles);
+        }
+
+        let config: LLMConfig = serde_json::from_value(value)?;
+        tracing::info!("[llm_settings] 已加载旧版配置: {:?}，迁移为默认配置档", config.provider);
+        let profiles = LLMProfiles {
+            active_profile: DEFAULT_PROFILE_NAME.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE_NAME.to_string(), config)]),
+        };
+        if let Err(e) = Self::write_profiles(path, &profiles) {
+            tracing::error!("[llm_settings] 迁移配置文件失败: {}", e);
+        }
+        Ok(profiles)
+    }
+
+    /// 将配置档写入文件
+    fn write_profiles(path: &PathBuf, profiles: &LLMProfiles) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(profiles)?)?;
+        Ok(())
+    }
+
+    /// 列出所有配置档名称（按名称排序）
+    pub fn list_profiles(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// 获取当前配置档名称
+    pub fn active_profile(&self) -> &str {
+        &self.active_profile
+    }
+
+    /// 切换到指定配置档并立即应用，未保存的修改会被丢弃
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let config = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("配置档不存在: {}", name))?;
+        self.config = config;
+        self.active_profile = name.to_string();
+        self.write_to_file()?;
+        self.update_manager()?;
+        tracing::info!("[llm_settings] 已切换到配置档: {}", name);
+        Ok(())
+    }
+
+    /// 将当前配置保存为指定名称的配置档（已存在时覆盖），并设为当前配置档
+    pub fn save_profile(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("配置档名称不能为空".into());
         }
+        self.active_profile = name.to_string();
+        self.save_config()?;
+        self.update_manager()?;
+        tracing::info!("[llm_settings] 已保存配置档: {}", name);
+        Ok(())
+    }
+
+    /// 把当前的配置档集合写入文件
+    fn write_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let profiles = LLMProfiles {
+            active_profile: self.active_profile.clone(),
+            profiles: self.profiles.clone(),
+        };
+        Self::write_profiles(self.require_config_path()?, &profiles)
     }
 
     /// 获取当前配置
@@ -83,6 +461,23 @@ impl AppLLMSettingsManager {
         &self.config
     }
 
+    /// 按当前配置生成题目使用的设置
+    pub fn prompt_options(&self) -> PromptOptions {
+        PromptOptions {
+            max_stem_chars: self.config.max_stem_chars,
+            dehyphenate_stem: self.config.dehyphenate_stem,
+            analysis_order: self.config.analysis_order,
+            paragraph_style: self.config.paragraph_style.clone(),
+            blank_id_pool_size: self.config.blank_id_pool_size,
+            language: self.config.prompt_language,
+            scripts: ScriptOptions {
+                save_retry_attempts: self.config.save_retry_attempts,
+                save_retry_delay_ms: self.config.save_retry_delay_ms,
+                ..self.scripts.clone()
+            },
+        }
+    }
+
     /// 更新提供商
     pub fn set_provider(&mut self, provider: String) {
         self.config.provider = provider;
@@ -113,9 +508,33 @@ impl AppLLMSettingsManager {
         self.config.enable_streaming = enable;
     }
 
+    /// 更新日志级别，保存后下次启动沿用
+    pub fn set_log_level(&mut self, level: String) {
+        self.config.log_level = level;
+    }
+
+    /// 设置是否在本地记录使用统计
+    pub fn set_usage_stats(&mut self, enabled: bool) {
+        self.config.usage_stats = enabled;
+    }
+
+    /// 记住最后选择的题型，下次启动时恢复
+    ///
+    /// 立即写入文件，但只写入这一项，设置页中尚未保存的其他修改不会一并保存。
+    pub fn set_default_question_type(&mut self, question_type: QuestionType) -> Result<(), Box<dyn std::error::Error>> {
+        let value = Some(question_type.as_str().to_string());
+        self.config.default_question_type = value.clone();
+        self.profiles
+            .entry(self.active_profile.clone())
+            .or_insert_with(|| self.config.clone())
+            .default_question_type = value;
+        self.write_to_file()
+    }
+
     /// 更新管理器配置（内部使用）
     fn update_manager(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.manager = LLMManager::from_config(&self.config);
+        logger::set_level(&self.config.log_level);
         Ok(())
     }
 
@@ -128,14 +547,27 @@ impl AppLLMSettingsManager {
             return Err(format!("更新管理器失败: {}", e));
         }
         
+        let started = Instant::now();
         match self.manager.test_current_backend().await {
-            Ok(response) => {
-                let success_msg = format!("✅ 连接成功!\n提供商: {}\n模型: {}\n响应: {}", 
-                    self.config.provider, 
-                    self.config.model, 
-                    response.chars().take(100).collect::<String>()
+            Ok((response, usage)) => {
+                let mut success_msg = connection_success_message(
+                    &self.config.provider,
+                    &self.config.model,
+                    started.elapsed(),
+                    usage,
+                    &response,
                 );
-                tracing::info!("[llm_settings] 连接测试成功");
+                // 提供商能列出模型时检查模型名称，避免手动填写的模型名拼错
+                match self.manager.list_current_backend_models().await {
+                    Ok(models) => {
+                        if let Some(hint) = unknown_model_hint(&self.config.model, &models) {
+                            success_msg.push('\n');
+                            success_msg.push_str(&hint);
+                        }
+                    }
+                    Err(e) => tracing::debug!("[llm_settings] 获取模型列表失败: {}", e),
+                }
+                tracing::info!("[llm_settings] 连接测试成功，耗时 {}ms", started.elapsed().as_millis());
                 Ok(success_msg)
             }
             Err(e) => {
@@ -146,20 +578,35 @@ impl AppLLMSettingsManager {
         }
     }
 
-    /// 保存配置到文件
+    /// 保存当前配置到当前配置档并写入文件
     pub fn save_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // 保存前确保管理器配置是最新的
+        self.profiles.insert(self.active_profile.clone(), self.config.clone());
+        self.write_to_file()?;
+        tracing::info!(
+            "[llm_settings] 配置档 {} 已保存到: {:?}",
+            self.active_profile,
+            self.config_path
+        );
+        Ok(())
+    }
+
+    /// 保存配置并立即应用到当前管理器
+    ///
+    /// 写入文件失败时仍会应用到当前管理器，再返回写入错误。
+    pub fn save_and_apply(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let saved = self.save_config();
         self.update_manager()?;
-        
-        let content = serde_json::to_string_pretty(&self.config)?;
-        fs::write(&self.config_path, content)?;
-        tracing::info!("[llm_settings] 配置已保存到: {:?}", self.config_path);
+        saved?;
+        tracing::info!("[llm_settings] 配置已应用: {}", self.get_config_summary());
         Ok(())
     }
 
     /// 重新加载配置
     pub fn reload_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.config = Self::load_config_from_file(&self.config_path)?;
+        let LLMProfiles { active_profile, profiles } = Self::load_profiles_from_file(self.require_config_path()?)?;
+        self.config = profiles.get(&active_profile).cloned().unwrap_or_default();
+        self.profiles = profiles;
+        self.active_profile = active_profile;
         self.update_manager()?;
         tracing::info!("[llm_settings] 配置已重新加载");
         Ok(())
@@ -178,22 +625,74 @@ impl AppLLMSettingsManager {
     }
 }
 
+/// 连接测试成功时显示的信息：提供商、模型、往返耗时和 token 用量
+fn connection_success_message(
+    provider: &str,
+    model: &str,
+    latency: Duration,
+    usage: Option<TokenUsage>,
+    response: &str,
+) -> String {
+    let usage = match usage {
+        Some(usage) => format!(
+            "输入 {} / 输出 {} tokens",
+            usage.prompt_tokens, usage.completion_tokens
+        ),
+        None => "提供商未返回".to_string(),
+    };
+    format!(
+        "✅ 连接成功!\n提供商: {}\n模型: {}\n延迟: {}ms\n用量: {}\n响应: {}",
+        provider,
+        model,
+        latency.as_millis(),
+        usage,
+        response.chars().take(100).collect::<String>()
+    )
+}
+
+/// 提供商的模型列表中没有 `model` 时返回提示；列表为空（提供商不支持列出模型）时不提示
+fn unknown_model_hint(model: &str, models: &[String]) -> Option<String> {
+    (!models.is_empty() && !models.iter().any(|available| available == model))
+        .then(|| format!("⚠️ 提供商的模型列表中没有 {}，请检查模型名称", model))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_connection_success_message_reports_latency() {
+        let usage = TokenUsage {
+            prompt_tokens: 12,
+            completion_tokens: 5,
+        };
+        let message =
+            connection_success_message("Custom", "qwen2.5", Duration::from_millis(1234), Some(usage), "Hello");
+        assert!(message.starts_with("✅ 连接成功!"));
+        assert!(message.contains("模型: qwen2.5"));
+        assert!(message.contains("延迟: 1234ms"));
+        assert!(message.contains("输入 12 / 输出 5 tokens"));
+
+        let message = connection_success_message("GPT", "gpt-4o", Duration::from_micros(800), None, &"x".repeat(300));
+        assert!(message.contains("延迟: 0ms"));
+        assert!(message.contains("用量: 提供商未返回"));
+        assert!(message.ends_with(&format!("响应: {}", "x".repeat(100))));
+    }
+
+    #[test]
+    fn test_unknown_model_hint() {
+        let models = vec!["gpt-4o".to_string(), "gpt-4o-mini".to_string()];
+        assert_eq!(unknown_model_hint("gpt-4o", &models), None);
+        assert!(unknown_model_hint("gpt-4o-mni", &models).unwrap().contains("gpt-4o-mni"));
+        assert_eq!(unknown_model_hint("anything", &[]), None);
+    }
+
     #[tokio::test]
     async fn test_settings_manager() {
         let _ = tracing_subscriber::fmt::try_init();
         
         // 创建设置管理器
-        let mut manager = match AppLLMSettingsManager::new() {
-            Ok(m) => m,
-            Err(e) => {
-                println!("创建设置管理器失败: {}", e);
-                return;
-            }
-        };
+        let mut manager = AppLLMSettingsManager::new();
 
         // 测试配置更新
         manager.set_provider("GitHub".to_string());
@@ -225,4 +724,188 @@ mod tests {
 
         println!("✅ 设置管理器测试完成");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_unreadable_config_falls_back_to_memory() {
+        // 配置文件路径是一个目录：读取和写入都会失败
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        fs::create_dir_all(&config_path).unwrap();
+        assert!(AppLLMSettingsManager::with_config_path(config_path.clone()).is_err());
+
+        let mut manager = AppLLMSettingsManager::load_or_default(config_path.clone());
+        assert!(manager.config_path.is_none());
+        assert_eq!(manager.get_config().provider, LLMConfig::default().provider);
+
+        // 本次运行中填写的 Key 仍会应用，只是无法写入文件
+        manager.set_provider("Custom".to_string());
+        manager.set_base_url("http://localhost:8000/v1".to_string());
+        manager.set_api_key("sk-session".to_string());
+        assert!(manager.save_and_apply().is_err());
+        assert_eq!(
+            manager.manager.current_backend().unwrap().provider(),
+            crate::core::llm_backend::LLMProvider::Custom
+        );
+        assert_eq!(manager.get_config().api_key.as_deref(), Some("sk-session"));
+        assert!(manager.reload_config().is_err());
+        assert!(config_path.is_dir());
+
+        let _ = fs::remove_dir_all(&config_path);
+    }
+
+    #[test]
+    fn test_stem_warning_limit_by_provider() {
+        let mut config = LLMConfig::default();
+        assert_eq!(config.stem_warning_limit(), 6000);
+        config.provider = "GitHub".to_string();
+        assert_eq!(config.stem_warning_limit(), 3000);
+        config.provider = "Gemini".to_string();
+        assert_eq!(config.stem_warning_limit(), 20000);
+        config.stem_warning_chars = Some(500);
+        assert_eq!(config.stem_warning_limit(), 500);
+    }
+
+    #[test]
+    fn test_prompt_options_carry_save_retry() {
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut manager = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        manager.config.save_retry_attempts = 5;
+        manager.config.save_retry_delay_ms = 1500;
+
+        let options = manager.prompt_options();
+        assert_eq!(options.scripts.save_retry_attempts, 5);
+        assert_eq!(options.scripts.save_retry_delay_ms, 1500);
+        let code = options.scripts.generator(QuestionType::SingleChoice).get_code();
+        assert!(code.contains("1500"));
+
+        let _ = fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_save_and_apply_updates_manager() {
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut manager = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(
+            manager.manager.current_backend().unwrap().provider(),
+            crate::core::llm_backend::LLMProvider::GPT
+        );
+
+        manager.set_provider("GitHub".to_string());
+        manager.set_model("Meta-Llama-3.1-8B-Instruct".to_string());
+        manager.save_and_apply().unwrap();
+
+        let backend = manager.manager.current_backend().unwrap();
+        assert_eq!(backend.provider(), crate::core::llm_backend::LLMProvider::GitHub);
+        assert_eq!(backend.model_name(), "Meta-Llama-3.1-8B-Instruct");
+
+        // 保存的文件重新加载后保持一致
+        let reloaded = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(reloaded.get_config().provider, "GitHub");
+        assert_eq!(reloaded.get_config().model, "Meta-Llama-3.1-8B-Instruct");
+
+        let _ = fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_default_question_type_round_trip() {
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut manager = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(manager.get_config().default_question_type, None);
+
+        // 未保存的其他修改不随题型一起写入
+        manager.set_model("unsaved-model".to_string());
+        manager.set_default_question_type(QuestionType::Reading).unwrap();
+
+        let reloaded = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        let saved = reloaded.get_config().default_question_type.as_deref();
+        assert_eq!(saved, Some(QuestionType::Reading.as_str()));
+        assert_eq!(saved.and_then(|name| name.parse::<QuestionType>().ok()), Some(QuestionType::Reading));
+        assert_ne!(reloaded.get_config().model, "unsaved-model");
+
+        let _ = fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_legacy_config_migrates_to_default_profile() {
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let legacy = LLMConfig {
+            provider: "GitHub".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            ..LLMConfig::default()
+        };
+        fs::write(&config_path, serde_json::to_string_pretty(&legacy).unwrap()).unwrap();
+
+        let manager = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(manager.list_profiles(), vec![DEFAULT_PROFILE_NAME.to_string()]);
+        assert_eq!(manager.active_profile(), DEFAULT_PROFILE_NAME);
+        assert_eq!(manager.get_config().provider, "GitHub");
+        assert_eq!(manager.get_config().model, "gpt-4o-mini");
+
+        // 迁移后的文件已经是配置档格式
+        let content: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        assert_eq!(content["active_profile"], DEFAULT_PROFILE_NAME);
+        assert_eq!(content["profiles"][DEFAULT_PROFILE_NAME]["model"], "gpt-4o-mini");
+
+        let _ = fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_profiles_create_switch_and_persist() {
+        let config_path = std::env::temp_dir().join(format!(
+            "question_tool_llm_config_{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let mut manager = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(manager.list_profiles(), vec![DEFAULT_PROFILE_NAME.to_string()]);
+
+        // 基于当前配置创建新的配置档
+        manager.set_provider("GitHub".to_string());
+        manager.set_model("gpt-4o".to_string());
+        manager.save_profile("reading").unwrap();
+        assert_eq!(manager.active_profile(), "reading");
+        assert_eq!(
+            manager.list_profiles(),
+            vec![DEFAULT_PROFILE_NAME.to_string(), "reading".to_string()]
+        );
+        assert_eq!(
+            manager.manager.current_backend().unwrap().provider(),
+            crate::core::llm_backend::LLMProvider::GitHub
+        );
+
+        // 切换回默认配置档，未保存的修改被丢弃
+        manager.set_model("unsaved-model".to_string());
+        manager.switch_profile(DEFAULT_PROFILE_NAME).unwrap();
+        assert_eq!(manager.get_config().provider, "GPT");
+        assert_eq!(
+            manager.manager.current_backend().unwrap().provider(),
+            crate::core::llm_backend::LLMProvider::GPT
+        );
+        assert!(manager.switch_profile("missing").is_err());
+        assert_eq!(manager.active_profile(), DEFAULT_PROFILE_NAME);
+
+        // 重新加载后保留配置档和当前选择
+        let mut reloaded = AppLLMSettingsManager::with_config_path(config_path.clone()).unwrap();
+        assert_eq!(reloaded.active_profile(), DEFAULT_PROFILE_NAME);
+        assert_eq!(reloaded.list_profiles().len(), 2);
+        reloaded.switch_profile("reading").unwrap();
+        assert_eq!(reloaded.get_config().provider, "GitHub");
+        assert_eq!(reloaded.get_config().model, "gpt-4o");
+
+        let _ = fs::remove_file(&config_path);
+    }
+}
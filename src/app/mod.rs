@@ -1,4 +1,5 @@
 pub mod app_state;
+pub mod cli;
 pub mod event_handlers;
 pub mod clipboard_timer;
 pub mod llm_settings;
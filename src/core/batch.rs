@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures_util::StreamExt;
+use tokio::sync::Semaphore;
+
+use super::llm_backend::{LLMManager, LLMResponse};
+use super::question_type::{PromptOptions, Question, QuestionType};
+use super::utility;
+
+/// 批量处理时默认同时进行的请求数
+pub const DEFAULT_MAX_CONCURRENT: usize = 3;
+
+/// 列出目录中支持的图片文件，按文件名排序
+pub fn list_image_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    utility::SUPPORTED_IMAGE_EXTENSIONS
+                        .iter()
+                        .any(|supported| ext.eq_ignore_ascii_case(supported))
+                })
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+/// 把目录中的每张图片按 `options` 作为一道题目发送给模型，返回按文件名排序的题目
+///
+/// 最多同时进行 `max_concurrent` 个请求，避免触发接口限流；每完成一张图片调用一次
+/// `on_progress(已完成数, 总数)`。成功的题目带有模型输出，失败或图片损坏的题目没有输出。
+pub async fn process_directory_with_progress(
+    dir: &Path,
+    question_type: QuestionType,
+    options: &PromptOptions,
+    manager: &LLMManager,
+    max_concurrent: usize,
+    on_progress: impl Fn(usize, usize),
+) -> Vec<Question> {
+    let images = match list_image_files(dir) {
+        Ok(images) => images,
+        Err(e) => {
+            tracing::error!("[batch] Failed to read directory {}: {}", dir.display(), e);
+            return Vec::new();
+        }
+    };
+    let total = images.len();
+    let max_concurrent = max_concurrent.max(1);
+    tracing::info!(
+        "[batch] Processing {} images in {}, max {} concurrent requests",
+        total,
+        dir.display(),
+        max_concurrent
+    );
+
+    let semaphore = Semaphore::new(max_concurrent);
+    let finished = AtomicUsize::new(0);
+    let mut results: Vec<(usize, Question)> = futures_util::stream::iter(images.into_iter().enumerate())
+        .map(|(index, path)| {
+            let semaphore = &semaphore;
+            let finished = &finished;
+            let on_progress = &on_progress;
+            async move {
+                let mut question =
+                    Question::new_with_params(question_type, String::new(), Some(path), Default::default(), options);
+                // 信号量不会被关闭，获取失败时直接发送
+                let _permit = semaphore.acquire().await.ok();
+                send_question(&mut question, manager).await;
+                on_progress(finished.fetch_add(1, Ordering::Relaxed) + 1, total);
+                (index, question)
+            }
+        })
+        .buffer_unordered(total.max(1))
+        .collect()
+        .await;
+
+    // 请求完成顺序不确定，按图片序号恢复顺序
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, question)| question).collect()
+}
+
+/// 发送一道题目，成功时把完整回复写入题目
+async fn send_question(question: &mut Question, manager: &LLMManager) {
+    let Some(path) = question.get_img_path().cloned() else {
+        return;
+    };
+    if !utility::is_valid_image(&path) {
+        tracing::warn!("[batch] Skipping invalid image {}", path.display());
+        return;
+    }
+
+    let (sender, receiver) = mpsc::channel::<LLMResponse>();
+    if let Err(e) = manager
+        .send_message(question.prompt_stem(), Some(&path), sender)
+        .await
+    {
+        tracing::warn!("[batch] Request for {} failed: {}", path.display(), e);
+        return;
+    }
+
+    match receiver
+        .try_iter()
+        .filter(|response| response.is_complete)
+        .last()
+        .filter(|response| !response.is_error())
+    {
+        Some(response) => question.set_model_reply(response.content),
+        None => tracing::warn!("[batch] No usable reply for {}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::llm_backend::MockBackend;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_process_directory() {
+        let dir = std::env::temp_dir().join(format!("question_tool_batch_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["b.png", "a.PNG"] {
+            image::RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 0]))
+                .save_with_format(dir.join(name), image::ImageFormat::Png)
+                .unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "not an image").unwrap();
+        fs::write(dir.join("broken.png"), "not an image").unwrap();
+
+        let mut manager = LLMManager::new();
+        // 按图片文件名回复
+        manager.add_backend(Box::new(MockBackend::new(|_, image_path| {
+            let name = image_path
+                .and_then(|path| path.file_stem())
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            Ok(format!("var Questions = [/* {} */];", name))
+        })));
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let questions = process_directory_with_progress(
+            &dir,
+            QuestionType::SingleChoice,
+            &PromptOptions::default(),
+            &manager,
+            DEFAULT_MAX_CONCURRENT,
+            |done, total| progress.lock().unwrap().push((done, total)),
+        )
+        .await;
+
+        let names: Vec<String> = questions
+            .iter()
+            .map(|question| question.get_img_path().unwrap().file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.PNG", "b.png", "broken.png"]);
+        assert_eq!(questions[0].get_output(), Some("var Questions = [/* a */];"));
+        assert_eq!(questions[1].get_output(), Some("var Questions = [/* b */];"));
+        assert_eq!(questions[2].get_output(), None);
+        assert!(questions.iter().all(|question| question.get_type() == QuestionType::SingleChoice));
+        assert_eq!(progress.lock().unwrap().last(), Some(&(3, 3)));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_process_directory_limits_concurrency() {
+        let dir = std::env::temp_dir().join(format!("question_tool_batch_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for index in 0..6 {
+            image::RgbImage::from_pixel(4, 4, image::Rgb([0, 0, 0]))
+                .save(dir.join(format!("{}.png", index)))
+                .unwrap();
+        }
+
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let mut manager = LLMManager::new();
+        // 记录同时进行的请求数，文件名越靠前的图片回复越慢
+        let running = Arc::new(AtomicUsize::new(0));
+        let backend_max_running = max_running.clone();
+        manager.add_backend(Box::new(MockBackend::new_async(move |_, image_path| {
+            let running = running.clone();
+            let max_running = backend_max_running.clone();
+            async move {
+                let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(count, Ordering::SeqCst);
+
+                let index: u64 = image_path
+                    .as_deref()
+                    .and_then(|path| path.file_stem())
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok())
+                    .unwrap_or_default();
+                tokio::time::sleep(std::time::Duration::from_millis(10 * (6 - index))).await;
+
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(format!("var Questions = [/* {} */];", index))
+            }
+        })));
+
+        let options = PromptOptions::default();
+        let questions =
+            process_directory_with_progress(&dir, QuestionType::SingleChoice, &options, &manager, 2, |_, _| {}).await;
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let outputs: Vec<&str> = questions.iter().filter_map(|question| question.get_output()).collect();
+        assert_eq!(
+            outputs,
+            (0..6).map(|index| format!("var Questions = [/* {} */];", index)).collect::<Vec<_>>()
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+use async_llm::Error;
+use serde_json::Value;
+use tokio_stream::{Stream, StreamExt};
+
+use super::llm_backend;
+
+/// 流式响应解析出的增量流
+pub type DeltaStream = Pin<Box<dyn Stream<Item = Result<StreamDelta, Error>> + Send>>;
+
+/// OpenAI 兼容的 `{base_url}/chat/completions` 接口，GPT、GitHub 和自定义后端共用
+///
+/// 请求使用后端自己构建的 HTTP 客户端发送，代理、鉴权和组织 ID 等请求头都只作用于该后端，
+/// 不经过进程级的环境变量。
+#[derive(Clone, Debug)]
+pub struct ChatCompletions {
+    client: reqwest::Client,
+    url: String,
+    /// 日志和错误信息中使用的后端名称
+    label: &'static str,
+}
+
+impl ChatCompletions {
+    /// `base_url` 为包含版本号的接口地址（如 `https://api.openai.com/v1`）
+    pub fn new(client: reqwest::Client, base_url: &str, label: &'static str) -> Self {
+        Self {
+            client,
+            url: chat_completions_url(base_url),
+            label,
+        }
+    }
+
+    /// 发送请求，成功时返回响应；429 响应带有等待时间时返回限流错误
+    pub async fn post(&self, body: &Value) -> Result<reqwest::Response, Error> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| llm_backend::request_error(self.label, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let detail = response.text().await.unwrap_or_default();
+            // 429 响应带有等待时间时返回限流错误，重试逻辑会按建议时间等待
+            let rate_limited = (status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+                .then(|| llm_backend::RateLimited::from_response(retry_after.as_deref(), &detail))
+                .flatten();
+            if let Some(rate_limited) = rate_limited {
+                return Err(rate_limited.into());
+            }
+            return Err(Error::Stream(format!("{} API returned {}: {}", self.label, status, detail)));
+        }
+        Ok(response)
+    }
+
+    /// 发送非流式请求，返回响应 JSON
+    pub async fn complete(&self, body: &Value) -> Result<Value, Error> {
+        self.post(body)
+            .await?
+            .json::<Value>()
+            .await
+            .map_err(|e| Error::Stream(format!("Invalid {} response: {}", self.label, e)))
+    }
+
+    /// 发送流式请求，返回按 SSE 事件解析出的增量流
+    pub async fn stream(&self, body: &Value) -> Result<DeltaStream, Error> {
+        let response = self.post(body).await?;
+        Ok(delta_stream(response, self.label))
+    }
+}
+
+/// chat completions 接口地址
+pub fn chat_completions_url(base_url: &str) -> String {
+    format!("{}/chat/completions", base_url.trim_end_matches('/'))
+}
+
+/// 把流式响应的字节流解析为增量流，读取或解析出错时产生一个错误
+fn delta_stream(response: reqwest::Response, label: &'static str) -> DeltaStream {
+    let bytes = Box::pin(response.bytes_stream());
+    let state = (bytes, SseDeltaParser::new(label), VecDeque::new());
+    Box::pin(futures_util::stream::unfold(state, move |(mut bytes, mut parser, mut pending)| async move {
+        loop {
+            if let Some(delta) = pending.pop_front() {
+                return Some((Ok(delta), (bytes, parser, pending)));
+            }
+            let result = match bytes.next().await? {
+                Ok(chunk) => parser.push(&chunk).map(|deltas| pending.extend(deltas)),
+                Err(e) => Err(Error::Stream(format!("{} stream error: {}", label, e))),
+            };
+            if let Err(e) = result {
+                return Some((Err(e), (bytes, parser, pending)));
+            }
+        }
+    }))
+}
+
+/// 流式事件中的一段增量，推理模型的思考过程和最终回答分开返回
+#[derive(Debug, Default, PartialEq)]
+pub struct StreamDelta {
+    pub content: String,
+    /// 思考过程，读取 DeepSeek 等的 `reasoning_content` 或 OpenRouter 的 `reasoning` 字段
+    pub reasoning: String,
+}
+
+impl StreamDelta {
+    /// 从流式事件的 `delta` 或非流式响应的 `message` 中读取回答和思考过程
+    pub fn from_message(message: &Value) -> Self {
+        let text = |key: &str| message[key].as_str().unwrap_or_default().to_string();
+        let reasoning = text("reasoning_content");
+        Self {
+            content: text("content"),
+            reasoning: if reasoning.is_empty() { text("reasoning") } else { reasoning },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty() && self.reasoning.is_empty()
+    }
+
+    /// 思考过程，没有时为 None，与 [`LLMResponse::reasoning`](super::llm_backend::LLMResponse::reasoning) 一致
+    pub fn reasoning_text(&self) -> Option<String> {
+        (!self.reasoning.is_empty()).then(|| self.reasoning.clone())
+    }
+}
+
+/// 按行解析 OpenAI 格式的 SSE 数据，从每个 `data:` 事件中提取 `delta` 的回答和思考过程
+struct SseDeltaParser {
+    buffer: Vec<u8>,
+    label: &'static str,
+}
+
+impl SseDeltaParser {
+    fn new(label: &'static str) -> Self {
+        Self { buffer: Vec::new(), label }
+    }
+
+    /// 追加一段字节，返回其中完整事件包含的文本片段
+    fn push(&mut self, bytes: &[u8]) -> Result<Vec<StreamDelta>, Error> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut deltas = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                continue;
+            }
+            let event: Value = serde_json::from_str(data)
+                .map_err(|e| Error::Stream(format!("Invalid {} stream event: {}", self.label, e)))?;
+            if let Some(error) = event.get("error") {
+                return Err(Error::Stream(format!("{} API error: {}", self.label, error)));
+            }
+            let delta = StreamDelta::from_message(&event["choices"][0]["delta"]);
+            if !delta.is_empty() {
+                deltas.push(delta);
+            }
+        }
+        Ok(deltas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sse_delta_parser() {
+        let mut parser = SseDeltaParser::new("Custom");
+        let event = r#"data: {"choices":[{"index":0,"delta":{"content":"var Questions"}}]}"#;
+        let (head, tail) = event.split_at(20);
+
+        assert!(parser.push(head.as_bytes()).unwrap().is_empty());
+        assert_eq!(
+            parser.push(format!("{}\n\n", tail).as_bytes()).unwrap(),
+            vec![StreamDelta { content: "var Questions".to_string(), reasoning: String::new() }]
+        );
+        assert!(parser.push(b"data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n").unwrap().is_empty());
+        assert!(parser.push(b"data: [DONE]\n").unwrap().is_empty());
+        assert!(parser.push(b"data: {\"error\":{\"message\":\"bad\"}}\n").is_err());
+    }
+
+    #[test]
+    fn test_sse_delta_parser_reads_reasoning() {
+        let mut parser = SseDeltaParser::new("Custom");
+        let events = concat!(
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":null,"reasoning_content":"先看题干"}}]}"#,
+            "\n",
+            r#"data: {"choices":[{"delta":{"content":"var Questions","reasoning_content":"，再写答案"}}]}"#,
+            "\n",
+            r#"data: {"choices":[{"delta":{"reasoning":"OpenRouter 的字段"}}]}"#,
+            "\n",
+        );
+        assert_eq!(
+            parser.push(events.as_bytes()).unwrap(),
+            vec![
+                StreamDelta { content: String::new(), reasoning: "先看题干".to_string() },
+                StreamDelta { content: "var Questions".to_string(), reasoning: "，再写答案".to_string() },
+                StreamDelta { content: String::new(), reasoning: "OpenRouter 的字段".to_string() },
+            ]
+        );
+
+        let message = json!({ "role": "assistant", "content": "var Questions = [];" });
+        assert_eq!(StreamDelta::from_message(&message).reasoning, "");
+    }
+}
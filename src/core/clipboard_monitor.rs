@@ -1,11 +1,637 @@
-use arboard::Clipboard;
+use arboard::{Clipboard, ImageData};
 use image::ImageEncoder;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
-pub fn start_clipboard_monitor() -> Arc<Mutex<Option<PathBuf>>> {
-    tracing::info!("[clipboard_monitor] Monitor thread starting");
+use super::utility;
+
+/// 轮询间隔允许的最小值（毫秒）
+pub const MIN_POLL_INTERVAL_MS: u64 = 200;
+/// 轮询间隔允许的最大值（毫秒）
+pub const MAX_POLL_INTERVAL_MS: u64 = 5000;
+/// 默认最多同时保留的剪贴板临时图片数
+pub const DEFAULT_MAX_PASTE_FILES: usize = 20;
+/// 默认允许保存的剪贴板图片最大像素数（约 4K 分辨率的两倍）
+pub const DEFAULT_MAX_IMAGE_PIXELS: u64 = 3840 * 2160 * 2;
+/// 默认允许保存的剪贴板图片最大字节数（未压缩）
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 128 * 1024 * 1024;
+/// 支持保存的每像素字节数：灰度、RGB 和 RGBA
+const SUPPORTED_BYTES_PER_PIXEL: [usize; 3] = [1, 3, 4];
+/// 按行对齐的缓冲区每行末尾允许的最大填充字节数
+const MAX_ROW_PADDING: usize = 64;
+
+/// 剪贴板轮询配置，启动时从 `<config_dir>/clipboard_config.json` 读取
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// 后台监控线程读取剪贴板的间隔（毫秒）
+    pub monitor_interval_ms: u64,
+    /// UI 定时器检查新图片的间隔（毫秒）
+    pub timer_interval_ms: u64,
+    /// 复制成功提示的显示时长（毫秒）
+    pub copy_status_ms: u64,
+    /// 本次运行最多同时保留的 `slint_paste_*.png` 文件数，超出时删除最旧的
+    pub max_paste_files: usize,
+    /// 剪贴板图片的最大像素数，超出时跳过不保存
+    pub max_image_pixels: u64,
+    /// 剪贴板图片未压缩数据的最大字节数，超出时跳过不保存
+    pub max_image_bytes: u64,
+    /// 保存前是否把预乘 alpha 的 RGBA 还原为直通 alpha，默认只在 macOS 上开启
+    pub unpremultiply_alpha: bool,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            monitor_interval_ms: 2000,
+            timer_interval_ms: 1000,
+            copy_status_ms: 3000,
+            max_paste_files: DEFAULT_MAX_PASTE_FILES,
+            max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            unpremultiply_alpha: cfg!(target_os = "macos"),
+        }
+    }
+}
+
+impl ClipboardConfig {
+    /// 从默认位置加载配置，文件不存在或无法解析时使用默认值
+    pub fn load() -> Self {
+        match utility::app_config_dir() {
+            Ok(dir) => Self::load_from(&dir.join("clipboard_config.json")),
+            Err(e) => {
+                tracing::warn!("[clipboard_monitor] No config dir, using default intervals: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 从指定文件加载配置，文件不存在或无法解析时使用默认值
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("[clipboard_monitor] Invalid clipboard config {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// 监控线程的轮询间隔（限制在 200ms–5000ms）
+    pub fn monitor_interval(&self) -> Duration {
+        Duration::from_millis(self.monitor_interval_ms.clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS))
+    }
+
+    /// UI 定时器的轮询间隔（限制在 200ms–5000ms）
+    pub fn timer_interval(&self) -> Duration {
+        Duration::from_millis(self.timer_interval_ms.clamp(MIN_POLL_INTERVAL_MS, MAX_POLL_INTERVAL_MS))
+    }
+
+    /// 复制成功提示的显示时长
+    pub fn copy_status_duration(&self) -> Duration {
+        Duration::from_millis(self.copy_status_ms)
+    }
+
+    /// 剪贴板图片的大小限制和保存选项
+    pub fn image_limits(&self) -> ImageLimits {
+        ImageLimits {
+            max_pixels: self.max_image_pixels,
+            max_bytes: self.max_image_bytes,
+            unpremultiply_alpha: self.unpremultiply_alpha,
+        }
+    }
+}
+
+/// 监控线程和界面共享的剪贴板内容哈希，用来识别已经处理过的内容
+#[derive(Debug, Default)]
+pub struct ClipboardHashes {
+    /// 最近处理的剪贴板图片哈希，0 表示剪贴板中没有图片
+    pub image: AtomicU64,
+    /// 最近处理的剪贴板文本哈希，包括程序自己复制到剪贴板的文本
+    pub text: AtomicU64,
+}
+
+impl ClipboardHashes {
+    /// 使用上次运行保存的图片哈希创建
+    pub fn new(last_image_hash: u64) -> Self {
+        Self { image: AtomicU64::new(last_image_hash), text: AtomicU64::new(0) }
+    }
+
+    /// 记录程序自己复制到剪贴板的文本，监控线程不会把它当作新题目填入输入框
+    pub fn remember_own_text(&self, text: &str) {
+        self.text.store(text_hash(text.trim()), Ordering::Relaxed);
+    }
+}
+
+/// 剪贴板监控在多次运行之间保留的状态，保存在 `<config_dir>/clipboard_state.json`
+///
+/// 记录上次处理过的图片哈希，重启后剪贴板中仍是同一张图片时不会重复保存和处理。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardState {
+    /// 上次处理的剪贴板图片哈希，0 表示剪贴板中没有图片
+    pub last_image_hash: u64,
+}
+
+impl ClipboardState {
+    /// 默认的状态文件路径，没有配置目录时返回 None
+    pub fn default_path() -> Option<PathBuf> {
+        utility::app_config_dir()
+            .inspect_err(|e| tracing::warn!("[clipboard_monitor] No config dir, clipboard state not persisted: {}", e))
+            .ok()
+            .map(|dir| dir.join("clipboard_state.json"))
+    }
+
+    /// 从默认位置加载状态，没有配置目录时返回空状态
+    pub fn load() -> Self {
+        Self::default_path()
+            .as_deref()
+            .map(Self::load_from)
+            .unwrap_or_default()
+    }
+
+    /// 从指定文件加载状态，文件不存在或无法解析时返回空状态
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("[clipboard_monitor] Invalid clipboard state {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// 把状态写入指定文件
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// 剪贴板图片的大小限制和保存选项，避免整屏的超大截图占满内存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    pub max_pixels: u64,
+    pub max_bytes: u64,
+    /// 保存前是否把预乘 alpha 的 RGBA 还原为直通 alpha
+    pub unpremultiply_alpha: bool,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        ClipboardConfig::default().image_limits()
+    }
+}
+
+/// 剪贴板图片被跳过的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageRejection {
+    /// 宽或高为 0
+    EmptyArea { width: usize, height: usize },
+    /// 像素数或字节数超出限制
+    TooLarge { pixels: u64, bytes: u64 },
+    /// 数据长度不是支持的每像素字节数的整数倍
+    UnsupportedFormat { bytes: u64, pixels: u64 },
+}
+
+impl std::fmt::Display for ImageRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageRejection::EmptyArea { width, height } => write!(f, "图片尺寸为 {}x{}，没有像素", width, height),
+            ImageRejection::TooLarge { pixels, bytes } => {
+                write!(f, "图片过大（{} 像素，{} 字节）", pixels, bytes)
+            }
+            ImageRejection::UnsupportedFormat { bytes, pixels } => {
+                write!(f, "不支持的像素格式（{} 字节 / {} 像素）", bytes, pixels)
+            }
+        }
+    }
+}
+
+impl ImageLimits {
+    /// 检查剪贴板图片是否可以保存，返回每像素字节数
+    pub fn check(&self, width: usize, height: usize, byte_len: usize) -> Result<usize, ImageRejection> {
+        let pixels = (width as u64).saturating_mul(height as u64);
+        if pixels == 0 {
+            return Err(ImageRejection::EmptyArea { width, height });
+        }
+        let bytes = byte_len as u64;
+        if pixels > self.max_pixels || bytes > self.max_bytes {
+            return Err(ImageRejection::TooLarge { pixels, bytes });
+        }
+        match candidate_layouts(width, height, byte_len).first() {
+            Some(layout) => Ok(layout.bytes_per_pixel),
+            None => Err(ImageRejection::UnsupportedFormat { bytes, pixels }),
+        }
+    }
+}
+
+/// 对剪贴板图片缓冲区的一种解读：每像素字节数和每行字节数（含行尾填充）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PixelLayout {
+    bytes_per_pixel: usize,
+    stride: usize,
+}
+
+impl PixelLayout {
+    fn color_type(&self) -> image::ColorType {
+        match self.bytes_per_pixel {
+            4 => image::ColorType::Rgba8,
+            3 => image::ColorType::Rgb8,
+            _ => image::ColorType::L8,
+        }
+    }
+
+    /// 去掉行尾填充，得到紧密排列的像素数据
+    fn pack(&self, bytes: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let row = width * self.bytes_per_pixel;
+        if row == self.stride {
+            return bytes[..row * height].to_vec();
+        }
+        bytes.chunks(self.stride).take(height).flat_map(|line| &line[..row]).copied().collect()
+    }
+}
+
+/// 按可能性从高到低列出缓冲区可能的像素布局，没有合理的解读时返回空
+///
+/// 数据长度正好是像素数的 1、3、4 倍时优先按紧密排列解读；长度不是像素数的整数倍时，
+/// 再按每行末尾有少量对齐填充的 RGBA、RGB、灰度依次尝试。每像素 2 字节等不支持的
+/// 紧密布局直接放弃，不再猜测。
+fn candidate_layouts(width: usize, height: usize, byte_len: usize) -> Vec<PixelLayout> {
+    let pixels = width.saturating_mul(height);
+    let mut layouts = Vec::new();
+    if pixels == 0 {
+        return layouts;
+    }
+    if byte_len % pixels == 0 {
+        let bytes_per_pixel = byte_len / pixels;
+        if !SUPPORTED_BYTES_PER_PIXEL.contains(&bytes_per_pixel) {
+            return layouts;
+        }
+        layouts.push(PixelLayout { bytes_per_pixel, stride: width * bytes_per_pixel });
+    }
+    if byte_len % height == 0 {
+        let stride = byte_len / height;
+        for bytes_per_pixel in SUPPORTED_BYTES_PER_PIXEL.into_iter().rev() {
+            let row = width * bytes_per_pixel;
+            if row < stride && stride - row <= MAX_ROW_PADDING {
+                layouts.push(PixelLayout { bytes_per_pixel, stride });
+            }
+        }
+    }
+    layouts
+}
+
+/// 把预乘 alpha 的 RGBA8 像素还原为直通 alpha，避免半透明边缘出现暗色光晕
+///
+/// 完全透明和完全不透明的像素保持不变。
+pub fn unpremultiply_alpha(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
+/// 剪贴板临时图片的文件名前缀
+pub const PASTE_FILE_PREFIX: &str = "slint_paste_";
+
+/// 记录本次运行创建的剪贴板临时图片，退出时（或 drop 时）统一删除
+#[derive(Debug, Default)]
+pub struct PasteFileGuard {
+    files: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl PasteFileGuard {
+    /// 创建空的临时文件记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 共享给监控线程的文件列表
+    pub fn tracker(&self) -> Arc<Mutex<Vec<PathBuf>>> {
+        self.files.clone()
+    }
+
+    /// 删除所有记录的临时文件，可重复调用
+    pub fn cleanup(&self) {
+        let files = match self.files.lock() {
+            Ok(mut files) => std::mem::take(&mut *files),
+            Err(e) => std::mem::take(&mut *e.into_inner()),
+        };
+        for path in files.iter().filter(|path| path.exists()) {
+            match std::fs::remove_file(path) {
+                Ok(_) => tracing::debug!("[clipboard_monitor] Removed temp file on exit: {}", path.display()),
+                Err(e) => tracing::warn!("[clipboard_monitor] Failed to remove temp file {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+impl Drop for PasteFileGuard {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+/// 删除 `dir` 中修改时间早于 `max_age` 的 `slint_paste_*.png` 临时图片和预览缩略图，返回删除的数量
+pub fn sweep_stale_paste_files(dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let now = std::time::SystemTime::now();
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let is_temp_file = name.starts_with(PASTE_FILE_PREFIX) || name.starts_with(utility::THUMBNAIL_FILE_PREFIX);
+        if !is_temp_file || !name.ends_with(".png") {
+            continue;
+        }
+        let is_stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > max_age);
+        if is_stale && std::fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+
+    if removed > 0 {
+        tracing::info!("[clipboard_monitor] Swept {} stale temp files from {}", removed, dir.display());
+    }
+    removed
+}
+
+/// 只保留 `created_files` 中最新的 `max_files` 个临时图片（至少 1 个），返回删除的数量
+///
+/// 只删除本次运行记录的文件，其他实例的临时图片不受影响；`in_use` 中的图片（当前显示或
+/// 等待发送的图片）即使超出上限也会保留。
+pub fn prune_paste_files(created_files: &Mutex<Vec<PathBuf>>, max_files: usize, in_use: &[PathBuf]) -> usize {
+    let Ok(mut files) = created_files.lock() else {
+        return 0;
+    };
+    files.retain(|path| path.exists());
+    let max_files = max_files.max(1);
+    if files.len() <= max_files {
+        return 0;
+    }
+
+    // 记录按创建顺序排列，最新的 max_files 个之前的都可以删除
+    let excess = files.len() - max_files;
+    let mut removed = 0;
+    let mut index = 0;
+    files.retain(|path| {
+        index += 1;
+        if index > excess || in_use.contains(path) {
+            return true;
+        }
+        match std::fs::remove_file(path) {
+            Ok(_) => {
+                removed += 1;
+                false
+            }
+            Err(e) => {
+                tracing::warn!("[clipboard_monitor] Failed to remove temp file {}: {}", path.display(), e);
+                true
+            }
+        }
+    });
+    if removed > 0 {
+        tracing::info!("[clipboard_monitor] Pruned {} temp files beyond the limit of {}", removed, max_files);
+    }
+    removed
+}
+
+/// 文本非空且与上次不同时返回去掉首尾空白的文本，并更新 `last_hash`
+fn take_new_text(last_hash: &AtomicU64, text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let hash = text_hash(text);
+    if last_hash.swap(hash, Ordering::Relaxed) == hash {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+/// 计算剪贴板文本的哈希
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 根据图片尺寸、数据长度和前 16 个字节计算剪贴板图片的哈希
+fn image_hash(image: &ImageData<'_>) -> u64 {
+    let mut hash = (image.bytes.len() as u64)
+        .wrapping_mul(image.width as u64)
+        .wrapping_mul(image.height as u64);
+    if image.bytes.len() >= 16 {
+        for i in 0..16 {
+            hash = hash.wrapping_add(image.bytes[i] as u64 * (i as u64 + 1));
+        }
+    }
+    hash
+}
+
+/// 图片哈希与上次不同时更新 `last_hash` 并返回 true
+fn take_new_image(last_hash: &AtomicU64, hash: u64) -> bool {
+    last_hash.swap(hash, Ordering::Relaxed) != hash
+}
+
+/// 更新后的图片哈希写入状态文件，写入失败只记录日志
+fn persist_image_hash(state_path: Option<&Path>, hash: u64) {
+    let Some(path) = state_path else {
+        return;
+    };
+    if let Err(e) = (ClipboardState { last_image_hash: hash }).save_to(path) {
+        tracing::warn!("[clipboard_monitor] Failed to save clipboard state {}: {}", path.display(), e);
+    }
+}
+
+/// 把剪贴板图片编码为 PNG，保存为临时目录中的 `slint_paste_<毫秒时间戳>.png`
+///
+/// 按每像素字节数选择 RGBA8、RGB8 或 L8，保存后重新解码校验；校验失败或数据长度
+/// 不是像素数的整数倍时，再按带行尾填充的其他布局依次尝试。
+/// 调用前应先用 [`ImageLimits::check`] 检查图片大小。`unpremultiply_alpha` 为 true 时，
+/// RGBA 图片在编码前还原预乘 alpha。
+pub fn save_image_to_temp(image: &ImageData<'_>, unpremultiply_alpha: bool) -> std::io::Result<PathBuf> {
+    save_image_in(image, &std::env::temp_dir(), unpremultiply_alpha)
+}
+
+fn save_image_in(image: &ImageData<'_>, dir: &Path, unpremultiply_alpha: bool) -> std::io::Result<PathBuf> {
+    let layouts = candidate_layouts(image.width, image.height, image.bytes.len());
+    if layouts.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "unsupported clipboard image layout: {} bytes for {} pixels",
+                image.bytes.len(),
+                image.width.saturating_mul(image.height)
+            ),
+        ));
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let file_path = dir.join(format!("{}{}.png", PASTE_FILE_PREFIX, timestamp));
+    tracing::debug!("[clipboard_monitor] Saving image to: {}", file_path.to_string_lossy());
+
+    // 依次尝试各种解读，保存后重新解码校验，失败时换下一种
+    let mut last_error = None;
+    for (index, layout) in layouts.iter().enumerate() {
+        match write_verified_png(image, *layout, &file_path, unpremultiply_alpha) {
+            Ok(()) => {
+                if index == 0 {
+                    tracing::info!("[clipboard_monitor] Image saved successfully as {:?}", layout.color_type());
+                } else {
+                    tracing::info!(
+                        "[clipboard_monitor] Image saved with fallback layout {:?} ({} bytes/pixel, stride {})",
+                        layout.color_type(),
+                        layout.bytes_per_pixel,
+                        layout.stride
+                    );
+                }
+                return Ok(file_path);
+            }
+            Err(e) => {
+                tracing::debug!("[clipboard_monitor] Layout {:?} failed verification: {}", layout, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    let _ = std::fs::remove_file(&file_path);
+    Err(last_error.unwrap_or_else(|| std::io::Error::other("no usable pixel layout")))
+}
+
+/// 按指定布局编码为 PNG，再重新解码检查尺寸和颜色类型与预期一致
+fn write_verified_png(
+    image: &ImageData<'_>,
+    layout: PixelLayout,
+    file_path: &Path,
+    unpremultiply: bool,
+) -> std::io::Result<()> {
+    let (width, height) = (image.width as u32, image.height as u32);
+    let color_type = layout.color_type();
+    let mut buffer = layout.pack(&image.bytes, image.width, image.height);
+    if layout.bytes_per_pixel == 4 && unpremultiply {
+        tracing::debug!("[clipboard_monitor] Un-premultiplying alpha before encoding");
+        unpremultiply_alpha(&mut buffer);
+    }
+    tracing::debug!(
+        "[clipboard_monitor] Image details: {}x{}, {} bytes, {} bytes/pixel, stride {}",
+        width,
+        height,
+        image.bytes.len(),
+        layout.bytes_per_pixel,
+        layout.stride
+    );
+
+    let mut file = File::create(file_path)?;
+    image::codecs::png::PngEncoder::new(&mut file)
+        .write_image(&buffer, width, height, color_type.into())
+        .map_err(std::io::Error::other)?;
+    drop(file);
+
+    let saved = image::open(file_path).map_err(std::io::Error::other)?;
+    if (saved.width(), saved.height()) != (width, height) || saved.color() != color_type {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "saved PNG is {}x{} {:?}, expected {}x{} {:?}",
+                saved.width(),
+                saved.height(),
+                saved.color(),
+                width,
+                height,
+                color_type
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// 检查图片大小后保存为临时 PNG，被跳过或保存失败时记录日志并返回 None
+fn save_checked_image(image: &ImageData<'_>, image_limits: ImageLimits) -> Option<PathBuf> {
+    if let Err(e) = image_limits.check(image.width, image.height, image.bytes.len()) {
+        tracing::warn!("[clipboard_monitor] Skipping clipboard image: {}", e);
+        return None;
+    }
+    save_image_to_temp(image, image_limits.unpremultiply_alpha)
+        .inspect_err(|e| tracing::error!("[clipboard_monitor] Failed to save image: {}", e))
+        .ok()
+}
+
+/// 立即读取一次剪贴板图片并保存为临时 PNG，不经过轮询线程
+///
+/// 保存的文件记录到 `created_files` 中，退出时清理；剪贴板中没有图片或图片被跳过时返回 None。
+/// 图片哈希写入与监控线程共享的 `last_image_hash`，监控线程不会再次处理同一张图片。
+/// 超出 `max_paste_files` 时删除较旧的临时图片，`in_use` 中的图片除外。
+pub fn save_clipboard_image(
+    image_limits: ImageLimits,
+    max_paste_files: usize,
+    created_files: &Mutex<Vec<PathBuf>>,
+    last_image_hash: &AtomicU64,
+    in_use: &[PathBuf],
+) -> Option<PathBuf> {
+    let image = match Clipboard::new().and_then(|mut clipboard| clipboard.get_image()) {
+        Ok(image) => image,
+        Err(e) => {
+            tracing::info!("[clipboard_monitor] No image in clipboard: {}", e);
+            return None;
+        }
+    };
+    let hash = image_hash(&image);
+    if take_new_image(last_image_hash, hash) {
+        persist_image_hash(ClipboardState::default_path().as_deref(), hash);
+    }
+    let file_path = save_checked_image(&image, image_limits)?;
+    if let Ok(mut files) = created_files.lock() {
+        files.push(file_path.clone());
+    }
+    prune_paste_files(created_files, max_paste_files, in_use);
+    Some(file_path)
+}
+
+/// 启动后台线程，每隔 `poll_interval` 读取一次剪贴板，发现新图片时保存为临时 PNG
+///
+/// 间隔越短，连续粘贴截图时响应越快，但每次轮询都要读取并哈希整张剪贴板图片，
+/// 会带来更多 CPU 占用；默认 2000ms。
+///
+/// 保存的临时文件会记录到 `created_files` 中，便于退出时清理；每次保存后最多保留
+/// `max_paste_files` 个本次运行创建的临时图片，`in_use` 返回的图片（当前显示或等待发送的
+/// 图片）不会被删除。
+/// 剪贴板中没有图片而有新的文本时，文本会写入 `text_handle`。
+/// 超出 `image_limits` 或像素格式不受支持的图片会被跳过并记录警告。
+/// 上次处理的图片哈希保存在 [`ClipboardState`] 中，重启后剪贴板未变时不会重复保存；
+/// `hashes` 与 [`save_clipboard_image`] 和界面共享，手动粘贴过的图片不会再被检测为新图片，
+/// 程序自己复制到剪贴板的文本也不会被填入输入框。
+pub fn start_clipboard_monitor(
+    poll_interval: Duration,
+    max_paste_files: usize,
+    image_limits: ImageLimits,
+    created_files: Arc<Mutex<Vec<PathBuf>>>,
+    text_handle: Arc<Mutex<Option<String>>>,
+    hashes: Arc<ClipboardHashes>,
+    in_use: impl Fn() -> Vec<PathBuf> + Send + 'static,
+) -> Arc<Mutex<Option<PathBuf>>> {
+    tracing::info!("[clipboard_monitor] Monitor thread starting, interval: {:?}", poll_interval);
     
     let current_path_handle = std::sync::Arc::new(std::sync::Mutex::new(None));
     let handle_clone = current_path_handle.clone();
@@ -13,18 +639,18 @@ pub fn start_clipboard_monitor() -> Arc<Mutex<Option<PathBuf>>> {
     
     std::thread::spawn(move || {
         tracing::debug!("[clipboard_monitor] Thread spawned");
-        let mut last_clipboard_hash = 0u64;
+        let state_path = ClipboardState::default_path();
         let mut check_count = 0u32;
         let last_file_clone = last_saved_file.clone();
         
         loop {
-            std::thread::sleep(std::time::Duration::from_millis(2000));
+            std::thread::sleep(poll_interval);
             check_count += 1;
             // 只在前几次检查时打印提示信息
             if check_count <= 5 {
                 tracing::debug!("[clipboard_monitor] Checking clipboard... (check #{})", check_count);
             } else if check_count % 30 == 0 {
-                // 每60秒打印一次心跳信息
+                // 每 30 次检查打印一次心跳信息
                 tracing::trace!("[clipboard_monitor] Still monitoring... (check #{})", check_count);
             }
             
@@ -56,37 +682,55 @@ pub fn start_clipboard_monitor() -> Arc<Mutex<Option<PathBuf>>> {
                     if !error_msg.contains("ContentNotAvailable") && !error_msg.contains("GetClipboardData") && check_count <= 5 {
                         tracing::error!("[clipboard_monitor] Clipboard error: {:?}", e);
                     }
-                    if last_clipboard_hash != 0 {
+                    if take_new_image(&hashes.image, 0) {
                         tracing::debug!("[clipboard_monitor] No image in clipboard anymore");
-                        last_clipboard_hash = 0;
+                        persist_image_hash(state_path.as_deref(), 0);
+                    }
+
+                    // 没有图片时检查是否复制了题目文本
+                    if let Ok(text) = clipboard.get_text() {
+                        let new_text = take_new_text(&hashes.text, &text);
+                        // 启动时剪贴板里已有的文本不自动填入
+                        if let (Some(text), false) = (new_text, check_count == 1) {
+                            tracing::info!("[clipboard_monitor] New text detected, {} chars", text.chars().count());
+                            if let Ok(mut handle_text) = text_handle.lock() {
+                                *handle_text = Some(text);
+                            }
+                        }
                     }
                     continue;
                 }
             };
             
             // 计算图片哈希值
-            let mut image_hash = (image.bytes.len() as u64)
-                .wrapping_mul(image.width as u64)
-                .wrapping_mul(image.height as u64);
-            
-            if image.bytes.len() >= 16 {
-                for i in 0..16 {
-                    image_hash = image_hash.wrapping_add(image.bytes[i] as u64 * (i as u64 + 1));
-                }
-            }
+            let image_hash = image_hash(&image);
             
         tracing::debug!("[clipboard_monitor] Found image in clipboard: {}x{}, {} bytes, hash: {}, last_hash: {}", 
-            image.width, image.height, image.bytes.len(), image_hash, last_clipboard_hash);
+            image.width, image.height, image.bytes.len(), image_hash, hashes.image.load(Ordering::Relaxed));
             
             // 检查是否是新图片
-            if image_hash != last_clipboard_hash {
+            if take_new_image(&hashes.image, image_hash) {
                 tracing::info!("[clipboard_monitor] New image detected!");
-                last_clipboard_hash = image_hash;
-                
-                // 删除旧的临时文件以释放磁盘空间和内存
+                persist_image_hash(state_path.as_deref(), image_hash);
+
+                // 保存图片，被跳过时保留上一张图片
+                let Some(file_path) = save_checked_image(&image, image_limits) else {
+                    continue;
+                };
+
+                // 记录临时文件，退出时统一删除
+                if let Ok(mut files) = created_files.lock() {
+                    files.push(file_path.clone());
+                }
+
+                // 在后台生成预览缩略图，界面线程显示时直接使用缓存
+                utility::thumbnail_for_display(&file_path);
+
+                // 删除旧的临时文件以释放磁盘空间和内存，并记录新文件路径；仍在使用的图片保留
+                let in_use_paths = in_use();
                 if let Ok(mut last_file) = last_file_clone.lock() {
-                    if let Some(old_path) = last_file.take() {
-                        if old_path.exists() {
+                    if let Some(old_path) = last_file.replace(file_path.clone()) {
+                        if old_path.exists() && !in_use_paths.contains(&old_path) {
                             match std::fs::remove_file(&old_path) {
                                 Ok(_) => tracing::debug!("[clipboard_monitor] Deleted old temp file: {}", old_path.display()),
                                 Err(e) => tracing::warn!("[clipboard_monitor] Failed to delete old temp file: {}", e),
@@ -94,21 +738,7 @@ pub fn start_clipboard_monitor() -> Arc<Mutex<Option<PathBuf>>> {
                         }
                     }
                 }
-                
-                // 保存图片
-                let temp_dir = std::env::temp_dir();
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let file_path = temp_dir.join(format!("slint_paste_{}.png", timestamp));
-                tracing::debug!("[clipboard_monitor] Saving image to: {}", file_path.to_string_lossy());
-                
-                // 保存新文件路径到 last_saved_file
-                if let Ok(mut last_file) = last_file_clone.lock() {
-                    *last_file = Some(file_path.clone());
-                }
-                
+
                 // 更新共享路径句柄
                 if let Ok(mut handle_path) = handle_clone.lock() {
                     *handle_path = Some(file_path.clone());
@@ -116,39 +746,331 @@ pub fn start_clipboard_monitor() -> Arc<Mutex<Option<PathBuf>>> {
                 }else{
                     tracing::info!("[clipboard_monitor] Failed to lock shared path handle");
                 }
-                // 保存图片文件
-                if let Ok(mut file) = File::create(&file_path) {
-                    let buffer = &image.bytes;
-                    let (width, height) = (image.width as u32, image.height as u32);
-                    let encoder = image::codecs::png::PngEncoder::new(&mut file);
-                    let bytes_per_pixel = buffer.len() / (width * height) as usize;
-                    let color_type = match bytes_per_pixel {
-                        4 => image::ColorType::Rgba8,
-                        3 => image::ColorType::Rgb8,
-                        1 => image::ColorType::L8,
-                        _ => image::ColorType::Rgba8,
-                    };
-                    tracing::debug!("[clipboard_monitor] Image details: {}x{}, {} bytes, {} bytes/pixel", width, height, buffer.len(), bytes_per_pixel);
-                    if encoder.write_image(buffer, width, height, color_type.into()).is_ok() {
-                        tracing::info!("[clipboard_monitor] Image saved successfully");
-                    } else if color_type != image::ColorType::Rgba8 {
-                        if let Ok(mut file) = File::create(&file_path) {
-                            let encoder = image::codecs::png::PngEncoder::new(&mut file);
-                            if encoder.write_image(buffer, width, height, image::ColorType::Rgba8.into()).is_ok() {
-                                tracing::info!("[clipboard_monitor] Image saved with fallback RGBA8 format");
-                            } else {
-                                tracing::error!("[clipboard_monitor] Failed to save image (fallback RGBA8)");
-                            }
-                        }
-                    } else {
-                        tracing::error!("[clipboard_monitor] Failed to save image");
-                    }
-                } else {
-                    tracing::error!("[clipboard_monitor] Failed to create file for image");
-                }
+
+                // 限制同时存在的临时图片数，避免占用过多磁盘空间
+                prune_paste_files(&created_files, max_paste_files, &in_use_paths);
             }
         }
     });
     
     current_path_handle
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpremultiply_alpha() {
+        let mut pixels = vec![
+            100, 50, 0, 128, // 半透明：还原为 (199, 100, 0)
+            10, 20, 30, 255, // 不透明：保持不变
+            0, 0, 0, 0, // 全透明：保持不变
+            200, 200, 200, 100, // 溢出的预乘值截断到 255
+        ];
+        unpremultiply_alpha(&mut pixels);
+        assert_eq!(
+            pixels,
+            vec![199, 100, 0, 128, 10, 20, 30, 255, 0, 0, 0, 0, 255, 255, 255, 100]
+        );
+    }
+
+    #[test]
+    fn test_clipboard_config_intervals() {
+        let config = ClipboardConfig::default();
+        assert_eq!(config.monitor_interval(), Duration::from_millis(2000));
+        assert_eq!(config.timer_interval(), Duration::from_millis(1000));
+        assert_eq!(config.copy_status_duration(), Duration::from_millis(3000));
+
+        let path = std::env::temp_dir().join(format!("question_tool_clipboard_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"monitor_interval_ms": 50}"#).unwrap();
+        let config = ClipboardConfig::load_from(&path);
+        assert_eq!(config.monitor_interval(), Duration::from_millis(MIN_POLL_INTERVAL_MS));
+        assert_eq!(config.timer_interval(), Duration::from_millis(1000));
+        assert_eq!(config.unpremultiply_alpha, cfg!(target_os = "macos"));
+
+        std::fs::write(&path, r#"{"unpremultiply_alpha": true}"#).unwrap();
+        assert!(ClipboardConfig::load_from(&path).unpremultiply_alpha);
+
+        let config = ClipboardConfig {
+            monitor_interval_ms: 60_000,
+            timer_interval_ms: 500,
+            ..ClipboardConfig::default()
+        };
+        assert_eq!(config.monitor_interval(), Duration::from_millis(MAX_POLL_INTERVAL_MS));
+        assert_eq!(config.timer_interval(), Duration::from_millis(500));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_image_limits_check() {
+        let limits = ImageLimits::default();
+        assert_eq!(limits.check(100, 50, 100 * 50 * 4), Ok(4));
+        assert_eq!(limits.check(100, 50, 100 * 50 * 3), Ok(3));
+        assert_eq!(limits.check(100, 50, 100 * 50), Ok(1));
+
+        // 宽或高为 0 时不能做除法
+        assert_eq!(limits.check(0, 50, 0), Err(ImageRejection::EmptyArea { width: 0, height: 50 }));
+        assert_eq!(limits.check(100, 0, 16), Err(ImageRejection::EmptyArea { width: 100, height: 0 }));
+
+        assert_eq!(
+            limits.check(100, 50, 100 * 50 * 2),
+            Err(ImageRejection::UnsupportedFormat { bytes: 10_000, pixels: 5_000 })
+        );
+        assert_eq!(
+            limits.check(100, 50, 100 * 50 * 4 + 1),
+            Err(ImageRejection::UnsupportedFormat { bytes: 20_001, pixels: 5_000 })
+        );
+    }
+
+    #[test]
+    fn test_image_limits_skip_oversize() {
+        // 8K 整屏截图超出默认限制
+        let (width, height) = (7680, 4320);
+        assert_eq!(
+            ImageLimits::default().check(width, height, width * height * 4),
+            Err(ImageRejection::TooLarge { pixels: 33_177_600, bytes: 132_710_400 })
+        );
+
+        let path = std::env::temp_dir().join(format!("question_tool_clipboard_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"max_image_pixels": 1000, "max_image_bytes": 3000}"#).unwrap();
+        let limits = ClipboardConfig::load_from(&path).image_limits();
+        assert_eq!(
+            limits,
+            ImageLimits {
+                max_pixels: 1000,
+                max_bytes: 3000,
+                unpremultiply_alpha: cfg!(target_os = "macos"),
+            }
+        );
+        assert_eq!(limits.check(25, 40, 3000), Ok(3));
+        assert!(matches!(limits.check(25, 40, 4000), Err(ImageRejection::TooLarge { .. })));
+        assert!(matches!(limits.check(40, 40, 1600), Err(ImageRejection::TooLarge { .. })));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_save_image_to_temp_pixel_layouts() {
+        let dir = std::env::temp_dir().join(format!("question_tool_save_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (bytes_per_pixel, color) in [
+            (4, image::ColorType::Rgba8),
+            (3, image::ColorType::Rgb8),
+            (1, image::ColorType::L8),
+        ] {
+            let image = ImageData {
+                width: 3,
+                height: 2,
+                bytes: vec![200u8; 3 * 2 * bytes_per_pixel].into(),
+            };
+            let path = save_image_in(&image, &dir, false).unwrap();
+            assert!(path.file_name().unwrap().to_string_lossy().starts_with(PASTE_FILE_PREFIX));
+            let saved = image::open(&path).unwrap();
+            assert_eq!((saved.width(), saved.height()), (3, 2));
+            assert_eq!(saved.color(), color);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        // 每像素 2 字节的布局不受支持
+        let image = ImageData {
+            width: 2,
+            height: 2,
+            bytes: vec![0u8; 2 * 2 * 2].into(),
+        };
+        let error = save_image_in(&image, &dir, false).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        // 超出限制的图片不会保存
+        let limits = ImageLimits {
+            max_pixels: 4,
+            max_bytes: u64::MAX,
+            unpremultiply_alpha: false,
+        };
+        let oversize = ImageData {
+            width: 3,
+            height: 2,
+            bytes: vec![0u8; 3 * 2 * 4].into(),
+        };
+        assert_eq!(save_checked_image(&oversize, limits), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_image_unpremultiplies_when_enabled() {
+        let dir = std::env::temp_dir().join(format!("question_tool_alpha_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let image = ImageData {
+            width: 1,
+            height: 1,
+            bytes: vec![100u8, 50, 0, 128].into(),
+        };
+        for (enabled, expected) in [(false, [100, 50, 0, 128]), (true, [199, 100, 0, 128])] {
+            let path = save_image_in(&image, &dir, enabled).unwrap();
+            assert_eq!(image::open(&path).unwrap().into_rgba8().into_raw(), expected);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_image_with_padded_rows() {
+        let dir = std::env::temp_dir().join(format!("question_tool_padded_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 5x2 的 RGB 图片，每行 15 字节按 4 字节对齐补到 16 字节：
+        // 长度 32 不是像素数 10 的整数倍，直接相除会误判为每像素 3.2 字节
+        let (width, height, stride) = (5, 2, 16);
+        let pixels: Vec<u8> = (0..width * height * 3).map(|i| (i * 8) as u8).collect();
+        let mut bytes = Vec::new();
+        for row in pixels.chunks(width * 3) {
+            bytes.extend_from_slice(row);
+            bytes.resize(bytes.len() + stride - row.len(), 0xEE);
+        }
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(ImageLimits::default().check(width, height, bytes.len()), Ok(3));
+
+        let image = ImageData { width, height, bytes: bytes.into() };
+        let path = save_image_in(&image, &dir, false).unwrap();
+        let saved = image::open(&path).unwrap();
+        assert_eq!((saved.width(), saved.height()), (5, 2));
+        assert_eq!(saved.color(), image::ColorType::Rgb8);
+        assert_eq!(saved.into_rgb8().into_raw(), pixels);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_take_new_text_dedups() {
+        let last_hash = AtomicU64::new(0);
+        assert_eq!(take_new_text(&last_hash, "  \n "), None);
+        assert_eq!(take_new_text(&last_hash, " 第一题 \n"), Some("第一题".to_string()));
+        assert_eq!(take_new_text(&last_hash, "第一题"), None);
+        assert_eq!(take_new_text(&last_hash, "第二题"), Some("第二题".to_string()));
+    }
+
+    #[test]
+    fn test_own_copied_text_is_not_prefilled() {
+        let hashes = ClipboardHashes::default();
+        assert_eq!(take_new_text(&hashes.text, "题干"), Some("题干".to_string()));
+
+        // 程序复制的回复和答案不会覆盖输入框中的题干
+        hashes.remember_own_text("var Questions = [];\n");
+        assert_eq!(take_new_text(&hashes.text, "var Questions = [];"), None);
+        hashes.remember_own_text("1. A  2. B");
+        assert_eq!(take_new_text(&hashes.text, "1. A  2. B"), None);
+
+        // 之后用户复制的新题目仍会填入
+        assert_eq!(take_new_text(&hashes.text, "下一题"), Some("下一题".to_string()));
+    }
+
+    #[test]
+    fn test_persisted_image_hash_suppresses_duplicate_after_restart() {
+        let path = std::env::temp_dir().join(format!("question_tool_clipboard_state_{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(ClipboardState::load_from(&path), ClipboardState::default());
+
+        let bytes = vec![7u8; 4 * 4 * 4];
+        let image = ImageData { width: 4, height: 4, bytes: bytes.into() };
+        let hash = image_hash(&image);
+
+        // 第一次运行：新图片被处理并记录哈希
+        let last_hash = AtomicU64::new(ClipboardState::load_from(&path).last_image_hash);
+        assert!(take_new_image(&last_hash, hash));
+        persist_image_hash(Some(&path), last_hash.load(Ordering::Relaxed));
+
+        // 重启后读取到相同的哈希，同一张图片不再保存
+        let last_hash = AtomicU64::new(ClipboardState::load_from(&path).last_image_hash);
+        assert_eq!(last_hash.load(Ordering::Relaxed), hash);
+        assert!(!take_new_image(&last_hash, hash));
+        assert!(take_new_image(&last_hash, hash + 1));
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(ClipboardState::load_from(&path).last_image_hash, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_pasted_image_hash_shared_with_monitor() {
+        let image = ImageData { width: 4, height: 4, bytes: vec![9u8; 4 * 4 * 4].into() };
+        let hash = image_hash(&image);
+        let pasted_hash = Arc::new(AtomicU64::new(0));
+        let monitor_hash = pasted_hash.clone();
+
+        // 立即粘贴处理过的图片，监控线程轮询到时不再当作新图片
+        assert!(take_new_image(&pasted_hash, hash));
+        assert!(!take_new_image(&monitor_hash, hash));
+
+        // 剪贴板清空后再复制同一张图片仍会被检测到
+        assert!(take_new_image(&monitor_hash, 0));
+        assert!(take_new_image(&monitor_hash, hash));
+    }
+
+    #[test]
+    fn test_prune_paste_files_keeps_newest() {
+        let dir = std::env::temp_dir().join(format!("question_tool_prune_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let paths: Vec<PathBuf> = (0..5u64)
+            .map(|index| {
+                let path = dir.join(format!("slint_paste_{}.png", index));
+                File::create(&path).unwrap();
+                path
+            })
+            .collect();
+        let created_files = Mutex::new(paths.clone());
+        // 其他实例创建的临时图片没有记录，不会被删除
+        let other = dir.join("slint_paste_other.png");
+        File::create(&other).unwrap();
+
+        assert_eq!(prune_paste_files(&created_files, 3, &[]), 2);
+        assert!(!paths[0].exists());
+        assert!(!paths[1].exists());
+        assert!(paths[2..].iter().all(|path| path.exists()));
+        assert!(other.exists());
+        assert_eq!(*created_files.lock().unwrap(), paths[2..].to_vec());
+
+        assert_eq!(prune_paste_files(&created_files, 3, &[]), 0);
+        // 当前显示的图片即使超出上限也保留，上限为 0 时仍保留最新的一张
+        assert_eq!(prune_paste_files(&created_files, 0, &[paths[2].clone()]), 1);
+        assert!(paths[2].exists());
+        assert!(!paths[3].exists());
+        assert!(paths[4].exists());
+        assert_eq!(*created_files.lock().unwrap(), vec![paths[2].clone(), paths[4].clone()]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sweep_stale_paste_files() {
+        let dir = std::env::temp_dir().join(format!("question_tool_sweep_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let two_days_ago = std::time::SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+
+        let write_file = |name: &str, old: bool| {
+            let path = dir.join(name);
+            let file = File::create(&path).unwrap();
+            if old {
+                file.set_modified(two_days_ago).unwrap();
+            }
+            path
+        };
+        let stale = write_file("slint_paste_1.png", true);
+        let fresh = write_file("slint_paste_2.png", false);
+        let other = write_file("other_1.png", true);
+
+        assert_eq!(sweep_stale_paste_files(&dir, Duration::from_secs(24 * 60 * 60)), 1);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(other.exists());
+
+        let guard = PasteFileGuard::new();
+        guard.tracker().lock().unwrap().push(fresh.clone());
+        drop(guard);
+        assert!(!fresh.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,358 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use async_llm::Error;
+use serde_json::{Value, json};
+use tokio_stream::StreamExt;
+
+use super::chat_completions::{self, ChatCompletions, StreamDelta};
+use super::llm_backend::{
+    self, DEFAULT_MAX_TOKENS, DEFAULT_TEMPERATURE, DEFAULT_TIMEOUT_SECS, LLMBackend, LLMProvider, LLMResponse, TokenUsage,
+};
+use super::request_logger;
+use super::utility::{self, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat};
+
+/// 测试连接时发送的消息
+const TEST_PROMPT: &str = "Please respond with 'Hello from Custom!' to confirm you are available.";
+
+/// 自定义 OpenAI 兼容后端
+/// 直接调用任意服务的 `{base_url}/chat/completions`，请求和流式响应都使用 OpenAI 的格式
+#[derive(Clone, Debug)]
+pub struct CustomBackend {
+    pub model: String,
+    pub api_key: Option<String>,
+    pub base_url: String,
+    /// 图片最长边像素上限
+    pub max_image_dim: u32,
+    /// 图片编码格式
+    pub image_format: ImageEncodeFormat,
+    /// 请求超时时间
+    pub timeout: Duration,
+    /// 是否使用流式请求，关闭时一次性返回完整回复
+    pub streaming: bool,
+    /// HTTP/SOCKS 代理地址，未配置时直连
+    pub proxy_url: Option<String>,
+    /// 单次回复的最大 token 数
+    pub max_tokens: u32,
+    /// 采样温度，越低输出格式越稳定
+    pub temperature: f32,
+}
+
+impl CustomBackend {
+    /// 创建新的自定义后端实例，`base_url` 为包含版本号的接口地址（如 `http://localhost:8000/v1`）
+    pub fn new(base_url: String, model: String) -> Self {
+        Self {
+            model,
+            api_key: None,
+            base_url,
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            streaming: true,
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
+        }
+    }
+
+    /// 设置 API Key，未设置时不发送 Authorization 请求头
+    pub fn with_api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
+
+    /// 设置图片最长边像素上限
+    pub fn with_max_image_dim(mut self, max_image_dim: u32) -> Self {
+        self.max_image_dim = max_image_dim;
+        self
+    }
+
+    /// 设置图片编码格式
+    pub fn with_image_format(mut self, image_format: ImageEncodeFormat) -> Self {
+        self.image_format = image_format;
+        self
+    }
+
+    /// 设置请求超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 设置是否使用流式请求
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// 设置 HTTP/SOCKS 代理地址
+    pub fn with_proxy_url(mut self, proxy_url: String) -> Self {
+        self.proxy_url = Some(proxy_url);
+        self
+    }
+
+    /// 设置单次回复的最大 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// 设置采样温度
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// chat completions 接口地址
+    fn chat_completions_url(&self) -> String {
+        chat_completions::chat_completions_url(&self.base_url)
+    }
+
+    /// 构建 chat completions 请求体，每张图片以 data URL 形式作为单独的内容部分附加
+    fn build_body(&self, text: &str, image_paths: &[&Path], stream: bool) -> Value {
+        let data_urls: Vec<String> = image_paths
+            .iter()
+            .filter_map(|path| {
+                tracing::debug!("[custom_backend] Converting image to base64: {}", path.display());
+                utility::img_to_data_url(path, self.max_image_dim, self.image_format)
+                    .map_err(|e| tracing::error!("[custom_backend] Failed to convert image to base64: {}", e))
+                    .ok()
+            })
+            .collect();
+        let system = if !image_paths.is_empty() && data_urls.is_empty() {
+            "You are a helpful assistant for analyzing questions and images."
+        } else {
+            ""
+        };
+        let data_urls: Vec<&str> = data_urls.iter().map(String::as_str).collect();
+        let mut body = request_logger::chat_body_with_images(&self.model, system, text, &data_urls, stream);
+        body["max_tokens"] = json!(self.max_tokens);
+        body["temperature"] = json!(self.temperature);
+        body
+    }
+
+    /// 带 API Key 和代理的 chat completions 接口
+    fn chat(&self) -> Result<ChatCompletions, Error> {
+        if self.base_url.trim().is_empty() {
+            return Err(Error::Stream("Custom provider requires a base URL".into()));
+        }
+        let client = llm_backend::build_http_client(
+            self.timeout,
+            self.proxy_url.as_deref(),
+            llm_backend::bearer_headers(self.api_key.as_deref()),
+        )?;
+        Ok(ChatCompletions::new(client, &self.base_url, "Custom"))
+    }
+
+    /// 发送请求，流式时每收到一段文本就用目前为止的回答和思考过程调用一次 `on_text`，
+    /// 返回完整的回答和思考过程（模型没有返回思考过程时为 None）
+    async fn generate(
+        &self,
+        text: &str,
+        image_paths: &[&Path],
+        mut on_text: impl FnMut(&str, Option<&str>),
+    ) -> Result<(String, Option<String>), Error> {
+        let body = self.build_body(text, image_paths, self.streaming);
+        let chat = self.chat()?;
+
+        let (accumulated, reasoning) = if self.streaming {
+            let mut deltas = chat.stream(&body).await?;
+            let mut accumulated = String::new();
+            let mut reasoning = String::new();
+            while let Some(delta) = deltas.next().await {
+                let delta = delta?;
+                accumulated.push_str(&delta.content);
+                reasoning.push_str(&delta.reasoning);
+                on_text(&accumulated, Some(reasoning.as_str()).filter(|reasoning| !reasoning.is_empty()));
+            }
+            (accumulated, reasoning)
+        } else {
+            let value = chat.complete(&body).await?;
+            let message = StreamDelta::from_message(&value["choices"][0]["message"]);
+            (message.content, message.reasoning)
+        };
+
+        if accumulated.is_empty() {
+            Err(Error::Stream("No response content from custom provider".into()))
+        } else {
+            Ok((accumulated, Some(reasoning).filter(|reasoning| !reasoning.is_empty())))
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMBackend for CustomBackend {
+    fn provider(&self) -> LLMProvider {
+        LLMProvider::Custom
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn send_message(
+        &self,
+        text: String,
+        image_path: Option<&Path>,
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        self.send_message_with_images(text, image_path.as_slice(), response_sender).await
+    }
+
+    async fn send_message_with_images(
+        &self,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        tracing::info!(
+            "[custom_backend] current model: {} at {}",
+            self.model,
+            self.base_url
+        );
+
+        let request = self.generate(&text, image_paths, |accumulated, reasoning| {
+            tracing::trace!(
+                "[custom_backend] Streaming response chunk, total length: {}",
+                accumulated.len()
+            );
+            let _ = response_sender.send(LLMResponse {
+                content: accumulated.to_string(),
+                is_complete: false,
+                reasoning: reasoning.map(str::to_string),
+            });
+        });
+        let result = llm_backend::with_timeout(self.timeout, request).await;
+
+        match result {
+            Ok((content, reasoning)) => {
+                tracing::info!(
+                    "[custom_backend] Custom response completed, total length: {}",
+                    content.len()
+                );
+                let _ = response_sender.send(LLMResponse {
+                    content,
+                    is_complete: true,
+                    reasoning,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!("[custom_backend] Custom request failed: {}", e);
+                let _ = response_sender.send(LLMResponse {
+                    content: format!("Error: {}", e),
+                    is_complete: true,
+                    reasoning: None,
+                });
+                Err(e)
+            }
+        }
+    }
+
+    async fn test_availability(&self) -> Result<String, Error> {
+        tracing::info!("[custom_backend] Testing custom provider at {}", self.base_url);
+        let (content, _) =
+            llm_backend::with_timeout(self.timeout, self.generate(TEST_PROMPT, &[], |_, _| {})).await?;
+        tracing::info!("[custom_backend] Custom test successful: {}", content);
+        Ok(content)
+    }
+
+    /// 用非流式请求测试，以便从响应中读取 `usage`
+    async fn test_availability_with_usage(&self) -> Result<(String, Option<TokenUsage>), Error> {
+        tracing::info!("[custom_backend] Testing custom provider at {}", self.base_url);
+        let body = self.build_body(TEST_PROMPT, &[], false);
+        let request = async { self.chat()?.complete(&body).await };
+        let value = llm_backend::with_timeout(self.timeout, request).await?;
+        let content = value["choices"][0]["message"]["content"].as_str().unwrap_or_default().to_string();
+        if content.is_empty() {
+            return Err(Error::Stream("No response content from custom provider".into()));
+        }
+        tracing::info!("[custom_backend] Custom test successful: {}", content);
+        Ok((content, llm_backend::parse_usage(&value)))
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, Error> {
+        let url = format!("{}/models", self.base_url.trim_end_matches('/'));
+        tracing::info!("[custom_backend] Listing models from {}", url);
+
+        let client = llm_backend::build_http_client(
+            self.timeout,
+            self.proxy_url.as_deref(),
+            llm_backend::bearer_headers(self.api_key.as_deref()),
+        )?;
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| llm_backend::request_error("Models", e))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Stream(format!("Models request failed: {}", e)))?;
+        if !status.is_success() {
+            return Err(Error::Stream(format!("Models endpoint returned {}: {}", status, body)));
+        }
+        llm_backend::parse_models_response(&body)
+    }
+
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> Value {
+        json!({
+            "provider": self.provider().to_string(),
+            "base_url": self.chat_completions_url(),
+            "api_key": self.api_key,
+            "body": self.build_body(text, image_path.as_slice(), self.streaming),
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_backend_targets_base_url() {
+        let backend = CustomBackend::new("http://fake.local:8000/v1/".to_string(), "qwen2.5".to_string())
+            .with_api_key("sk-test".to_string());
+        assert_eq!(backend.provider(), LLMProvider::Custom);
+        assert_eq!(backend.model_name(), "qwen2.5");
+        assert_eq!(backend.chat_completions_url(), "http://fake.local:8000/v1/chat/completions");
+
+        let snapshot = backend.request_snapshot("hi", None);
+        assert_eq!(snapshot["base_url"], "http://fake.local:8000/v1/chat/completions");
+        assert_eq!(snapshot["body"]["model"], "qwen2.5");
+        assert_eq!(snapshot["body"]["stream"], true);
+        assert_eq!(snapshot["body"]["messages"][1]["content"], "hi");
+    }
+
+    #[test]
+    fn test_build_body_attaches_each_image_as_separate_part() {
+        let dir = std::env::temp_dir().join(format!("question_tool_both_images_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("original.png");
+        let processed = dir.join("processed.png");
+        image::RgbImage::from_pixel(4, 4, image::Rgb([200, 200, 200])).save(&original).unwrap();
+        image::GrayImage::from_pixel(4, 4, image::Luma([255])).save(&processed).unwrap();
+
+        let backend = CustomBackend::new("http://fake.local/v1".to_string(), "qwen2.5-vl".to_string());
+        let body = backend.build_body("题目", &[original.as_path(), processed.as_path()], false);
+        let parts = body["messages"][1]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["text"], "题目");
+        let urls: Vec<&str> = parts[1..].iter().map(|part| part["image_url"]["url"].as_str().unwrap()).collect();
+        assert_eq!(urls.len(), 2);
+        assert!(urls.iter().all(|url| url.starts_with("data:image/")));
+        assert_ne!(urls[0], urls[1]);
+
+        // 只有一张图片时与原来的请求体相同
+        let body = backend.build_body("题目", &[original.as_path()], false);
+        assert_eq!(body["messages"][1]["content"].as_array().unwrap().len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
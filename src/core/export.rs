@@ -0,0 +1,358 @@
+use super::parser::ParsedQuestion;
+use super::question_type::Question;
+use super::utility;
+
+/// CSV 导出的表头
+pub const CSV_HEADER: &str = "id,type,subject,grade,stem,options,answer,analysis";
+
+/// 把题目导出为 CSV，每道解析出的小题一行，带上所属题目的科目和年级标签
+///
+/// 选项之间用 ` | ` 分隔。没有输出或输出无法解析的题目会被跳过。
+pub fn export_csv(questions: &[Question]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for question in questions {
+        let parsed = match question.parsed_output() {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                tracing::warn!("[export] Skipping question {} in CSV export: {}", question.get_id(), e);
+                continue;
+            }
+        };
+        for item in parsed {
+            let row = [
+                question.get_id().to_string(),
+                question.get_type().as_str().to_string(),
+                question.get_subject().unwrap_or_default().to_string(),
+                question.get_grade().unwrap_or_default().to_string(),
+                item.stem.clone(),
+                item.options.join(" | "),
+                answer_text(&item),
+                item.analysis.clone(),
+            ];
+            csv.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// GIFT 中需要转义的特殊字符
+const GIFT_SPECIAL_CHARS: [char; 7] = ['\\', '~', '=', '{', '}', '#', ':'];
+
+/// 把题目导出为 Moodle GIFT 格式
+///
+/// 支持单选题（数字答案索引）和判断题（布尔答案，或只有对/错两个选项）。
+/// 其他题目（填空、多选等）会被跳过并记录警告。解析作为整体反馈写在 `####` 之后。
+pub fn export_gift(questions: &[ParsedQuestion]) -> String {
+    let mut gift = String::new();
+    for (index, question) in questions.iter().enumerate() {
+        let Some(body) = gift_answer_block(question) else {
+            tracing::warn!(
+                "[export] Skipping question {} in GIFT export: unsupported answer {:?}",
+                index + 1,
+                question.answer
+            );
+            continue;
+        };
+
+        let stem = if question.stem.contains('<') {
+            format!("[html]{}", gift_escape(&question.stem))
+        } else {
+            gift_escape(&question.stem)
+        };
+        gift.push_str(&format!("::Q{}:: {} {{\n{}", index + 1, stem, body));
+        if !question.analysis.is_empty() {
+            gift.push_str(&format!("\t####{}\n", gift_escape(&question.analysis)));
+        }
+        gift.push_str("}\n\n");
+    }
+    gift
+}
+
+/// 生成 `{...}` 内的答案部分，不支持的题目返回 None
+fn gift_answer_block(question: &ParsedQuestion) -> Option<String> {
+    let truth = match &question.answer {
+        Some(serde_json::Value::Bool(truth)) => Some(*truth),
+        Some(serde_json::Value::Number(n)) if is_true_false_options(&question.options) => {
+            Some(n.as_u64()? == 0)
+        }
+        _ => None,
+    };
+    if let Some(truth) = truth {
+        return Some(format!("\t{}\n", if truth { "TRUE" } else { "FALSE" }));
+    }
+
+    let correct = question.answer.as_ref()?.as_u64()? as usize;
+    if correct >= question.options.len() {
+        return None;
+    }
+    Some(
+        question
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| {
+                format!("\t{}{}\n", if index == correct { '=' } else { '~' }, gift_escape(option))
+            })
+            .collect(),
+    )
+}
+
+/// 两个选项依次为“对”和“错”时视为判断题
+fn is_true_false_options(options: &[String]) -> bool {
+    let [first, second] = options else {
+        return false;
+    };
+    let is_any = |option: &str, words: &[&str]| {
+        let option = option.trim().trim_end_matches('.');
+        words.iter().any(|word| option.eq_ignore_ascii_case(word))
+    };
+    is_any(first, &["true", "t", "正确", "对"]) && is_any(second, &["false", "f", "错误", "错"])
+}
+
+/// 转义 GIFT 特殊字符
+fn gift_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if GIFT_SPECIAL_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 把题目导出为 Anki 可导入的 TSV：正面为题干，背面为正确选项和解析
+///
+/// 保留 HTML 以便 Anki 显示空格横线。字段中的换行替换为 `<br>`，制表符替换为空格。
+pub fn export_anki_tsv(questions: &[ParsedQuestion]) -> String {
+    let mut tsv = String::from("#separator:tab\n#html:true\n");
+    for question in questions {
+        let answer = correct_option_text(question);
+        let back = match (answer.is_empty(), question.analysis.is_empty()) {
+            (false, false) => format!("{}<br><br>{}", answer, question.analysis),
+            (false, true) => answer,
+            (true, _) => question.analysis.clone(),
+        };
+        tsv.push_str(&format!("{}\t{}\n", tsv_field(&question.stem), tsv_field(&back)));
+    }
+    tsv
+}
+
+/// 正确选项的文本：数字索引取对应选项，索引越界或没有选项时退回答案原文
+fn correct_option_text(question: &ParsedQuestion) -> String {
+    let option = |value: &serde_json::Value| {
+        let index = value.as_u64()? as usize;
+        let option = question.options.get(index)?;
+        Some(format!("{}. {}", utility::option_letter(index), option))
+    };
+    match &question.answer {
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|item| option(item).unwrap_or_else(|| answer_item_text(item)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Some(value) => option(value).unwrap_or_else(|| answer_item_text(value)),
+        None => String::new(),
+    }
+}
+
+/// 生成答案速查，如 `1.A 2.C 3.B`
+///
+/// 选项索引转换为字母，多空答案用逗号连接，缺少答案的题目标记为 `-`。
+pub fn export_answer_key(questions: &[ParsedQuestion]) -> String {
+    questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| {
+            let answer = match &question.answer {
+                Some(serde_json::Value::Array(items)) => {
+                    items.iter().map(answer_key_item).collect::<Vec<_>>().join(",")
+                }
+                Some(value) => answer_key_item(value),
+                None => "-".to_string(),
+            };
+            format!("{}.{}", index + 1, answer)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 答案速查中的单个答案：索引转换为字母，判断题为对/错，其他取文本
+fn answer_key_item(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Number(n) => match n.as_u64() {
+            Some(index) => utility::option_letter(index as usize).to_string(),
+            None => n.to_string(),
+        },
+        serde_json::Value::Bool(truth) => if *truth { "对" } else { "错" }.to_string(),
+        other => answer_item_text(other).trim().to_string(),
+    }
+}
+
+/// 替换 TSV 字段中的换行和制表符
+fn tsv_field(field: &str) -> String {
+    field
+        .trim()
+        .replace("\r\n", "<br>")
+        .replace(['\r', '\n'], "<br>")
+        .replace('\t', " ")
+}
+
+/// 答案的文本形式：字符串不带引号，数组元素用逗号连接
+fn answer_text(question: &ParsedQuestion) -> String {
+    match &question.answer {
+        Some(serde_json::Value::Array(items)) => items.iter().map(answer_item_text).collect::<Vec<_>>().join(", "),
+        Some(value) => answer_item_text(value),
+        None => String::new(),
+    }
+}
+
+fn answer_item_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 按 RFC 4180 转义 CSV 字段：含逗号、引号或换行时用双引号包裹，内部引号加倍
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::question_type::QuestionType;
+    use serde_json::json;
+
+    #[test]
+    fn test_tags_round_trip_and_csv_export() {
+        let mut question = Question::new(QuestionType::SingleChoice, "题干".to_string(), None);
+        question.set_subject(Some("英语".to_string()));
+        question.set_grade(Some("初中".to_string()));
+        question.set_model_reply(
+            r#"var Questions = [{ "stem": "Pick one, please", "options": ["A \"quoted\"", "B"], "answer": 1, "analysis": "故答案为：B" }];"#
+                .to_string(),
+        );
+
+        let json = serde_json::to_string(&question).unwrap();
+        let restored: Question = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_subject(), Some("英语"));
+        assert_eq!(restored.get_grade(), Some("初中"));
+        assert_eq!(question.regenerate_as(QuestionType::Reading, &Default::default()).get_subject(), Some("英语"));
+
+        // 旧的历史记录没有标签字段
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().retain(|key, _| key != "subject" && key != "grade");
+        let legacy: Question = serde_json::from_value(value).unwrap();
+        assert_eq!(legacy.get_subject(), None);
+
+        let csv = export_csv(&[question.clone(), Question::new(QuestionType::Reading, String::new(), None)]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},单选题,英语,初中,\"Pick one, please\",\"A \"\"quoted\"\" | B\",1,故答案为：B",
+                question.get_id()
+            )
+        );
+    }
+
+    #[test]
+    fn test_export_gift() {
+        let questions = vec![
+            ParsedQuestion {
+                stem: "1 + 1 = {?}".to_string(),
+                options: vec!["1".to_string(), "2".to_string(), "3 # three".to_string()],
+                answer: Some(json!(1)),
+                analysis: "考点：加法。故答案为：B".to_string(),
+            },
+            ParsedQuestion {
+                stem: "The earth is round.".to_string(),
+                options: vec!["True".to_string(), "False".to_string()],
+                answer: Some(json!(0)),
+                analysis: String::new(),
+            },
+            // 填空题不支持，跳过
+            ParsedQuestion {
+                stem: "The capital of France is ____.".to_string(),
+                options: Vec::new(),
+                answer: Some(json!(["Paris"])),
+                analysis: String::new(),
+            },
+            ParsedQuestion {
+                stem: "Pick <b>one</b>: a~b".to_string(),
+                options: vec!["x=y".to_string(), "z".to_string()],
+                answer: Some(json!(1)),
+                analysis: String::new(),
+            },
+        ];
+
+        let expected = "::Q1:: 1 + 1 \\= \\{?\\} {\n\
+\t~1\n\
+\t=2\n\
+\t~3 \\# three\n\
+\t####考点：加法。故答案为：B\n\
+}\n\n\
+::Q2:: The earth is round. {\n\
+\tTRUE\n\
+}\n\n\
+::Q4:: [html]Pick <b>one</b>\\: a\\~b {\n\
+\t~x\\=y\n\
+\t=z\n\
+}\n\n";
+        assert_eq!(export_gift(&questions), expected);
+    }
+
+    #[test]
+    fn test_export_anki_tsv() {
+        let questions = vec![
+            ParsedQuestion {
+                stem: "I like <span class=\"underline fillblank\">   </span>, apples and pears.".to_string(),
+                options: vec!["bananas".to_string(), "an egg".to_string()],
+                answer: Some(json!(0)),
+                analysis: "考点：名词复数。\n故答案为：A".to_string(),
+            },
+            ParsedQuestion {
+                stem: "Fill\tin: <b>Paris</b> is the capital of ____.".to_string(),
+                options: Vec::new(),
+                answer: Some(json!(["France"])),
+                analysis: String::new(),
+            },
+        ];
+
+        let expected = "#separator:tab\n#html:true\n\
+I like <span class=\"underline fillblank\">   </span>, apples and pears.\tA. bananas<br><br>考点：名词复数。<br>故答案为：A\n\
+Fill in: <b>Paris</b> is the capital of ____.\tFrance\n";
+        assert_eq!(export_anki_tsv(&questions), expected);
+    }
+
+    #[test]
+    fn test_export_answer_key() {
+        let question = |answer: Option<serde_json::Value>| ParsedQuestion {
+            answer,
+            ..ParsedQuestion::default()
+        };
+
+        let single_choice = [question(Some(json!(0))), question(Some(json!(2))), question(Some(json!(1)))];
+        assert_eq!(export_answer_key(&single_choice), "1.A 2.C 3.B");
+
+        let multi_blank = [
+            question(Some(json!(["Paris", " France "]))),
+            question(Some(json!([0, 3]))),
+            question(Some(json!(true))),
+            question(None),
+            question(Some(json!("sunny"))),
+        ];
+        assert_eq!(export_answer_key(&multi_blank), "1.Paris,France 2.A,D 3.对 4.- 5.sunny");
+        assert_eq!(export_answer_key(&[]), "");
+    }
+}
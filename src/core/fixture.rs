@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::question_type::Question;
+use super::request_logger;
+use super::utility;
+use crate::app::llm_settings::LLMConfig;
+
+/// 题干文件名
+const STEM_FILE: &str = "stem.txt";
+/// 题型文件名（内容为 `QuestionType::as_str()`）
+const TYPE_FILE: &str = "question_type.txt";
+/// 脱敏后的配置文件名
+const CONFIG_FILE: &str = "config.json";
+/// 模型回复文件名
+const REPLY_FILE: &str = "reply.txt";
+/// 图片文件名（不含扩展名）
+const IMAGE_STEM: &str = "image";
+
+/// 默认的用例导出目录 `<config_dir>/fixtures`
+pub fn default_fixture_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(utility::app_config_dir()?.join("fixtures"))
+}
+
+/// 把题目导出为可复现的用例目录 `<dir>/fixture_<id>`，返回该目录
+///
+/// 目录中包含题干、题型、脱敏后的配置、图片副本以及模型回复（如有）。
+pub fn export_fixture(
+    dir: &Path,
+    question: &Question,
+    config: &LLMConfig,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let fixture_dir = dir.join(format!("fixture_{}", question.id));
+    fs::create_dir_all(&fixture_dir)?;
+
+    fs::write(fixture_dir.join(STEM_FILE), question.get_stem())?;
+    fs::write(fixture_dir.join(TYPE_FILE), question.get_type().as_str())?;
+
+    let mut config = serde_json::to_value(config)?;
+    request_logger::redact(&mut config);
+    fs::write(fixture_dir.join(CONFIG_FILE), serde_json::to_string_pretty(&config)?)?;
+
+    if let Some(image_path) = question.get_img_path() {
+        let extension = image_path.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+        fs::copy(image_path, fixture_dir.join(format!("{}.{}", IMAGE_STEM, extension)))?;
+    }
+    if let Some(reply) = question.get_output() {
+        fs::write(fixture_dir.join(REPLY_FILE), reply)?;
+    }
+
+    tracing::info!("[fixture] Exported fixture to {}", fixture_dir.display());
+    Ok(fixture_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::question_type::QuestionType;
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    /// 从用例目录重建题目，模拟维护者重放用例
+    fn load_fixture(fixture_dir: &Path) -> Result<Question, Box<dyn std::error::Error>> {
+        let stem = fs::read_to_string(fixture_dir.join(STEM_FILE))?;
+        let type_name = fs::read_to_string(fixture_dir.join(TYPE_FILE))?;
+        let question_type = QuestionType::from_str(type_name.trim())
+            .map_err(|_| format!("未知的题型: {}", type_name.trim()))?;
+
+        let image_path = fs::read_dir(fixture_dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.file_stem().is_some_and(|stem| stem == IMAGE_STEM));
+
+        Ok(Question::new(question_type, stem, image_path))
+    }
+
+    #[test]
+    fn test_export_fixture_writes_stem_type_and_image() {
+        let dir = std::env::temp_dir().join(format!("question_tool_fixture_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let image_path = dir.join("slint_paste_1.png");
+        image::RgbImage::from_pixel(8, 8, image::Rgb([0, 0, 0]))
+            .save(&image_path)
+            .unwrap();
+
+        let mut question = Question::new(
+            QuestionType::Reading,
+            "Read the passage and answer.".to_string(),
+            Some(image_path.clone()),
+        );
+        question.set_model_reply("var Questions = [];".to_string());
+        let config = LLMConfig {
+            api_key: Some("sk-secret-api-key-123456".to_string()),
+            ..LLMConfig::default()
+        };
+
+        let fixture_dir = export_fixture(&dir.join("fixtures"), &question, &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(fixture_dir.join(STEM_FILE)).unwrap(),
+            "Read the passage and answer."
+        );
+        assert_eq!(fs::read_to_string(fixture_dir.join(TYPE_FILE)).unwrap(), "阅读理解");
+        assert_eq!(
+            fs::read(fixture_dir.join("image.png")).unwrap(),
+            fs::read(&image_path).unwrap()
+        );
+        assert_eq!(fs::read_to_string(fixture_dir.join(REPLY_FILE)).unwrap(), "var Questions = [];");
+
+        let config = fs::read_to_string(fixture_dir.join(CONFIG_FILE)).unwrap();
+        assert!(!config.contains("sk-secret-api-key-123456"));
+        assert!(config.contains("\"provider\": \"GPT\""));
+
+        // 用例可以重新加载为相同的题目
+        let replayed = load_fixture(&fixture_dir).unwrap();
+        assert_eq!(replayed.get_type(), QuestionType::Reading);
+        assert_eq!(replayed.get_stem(), question.get_stem());
+        assert_eq!(replayed.get_img_path(), Some(&fixture_dir.join("image.png")));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
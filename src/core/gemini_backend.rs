@@ -0,0 +1,352 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use async_llm::Error;
+use serde_json::{Value, json};
+use tokio_stream::StreamExt;
+
+use super::llm_backend::{
+    self, DEFAULT_MAX_TOKENS, DEFAULT_TEMPERATURE, DEFAULT_TIMEOUT_SECS, LLMBackend, LLMProvider, LLMResponse,
+};
+use super::utility::{self, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat};
+
+/// Gemini 后端实现
+/// 直接调用 Google Generative Language API (https://generativelanguage.googleapis.com)
+#[derive(Clone, Debug)]
+pub struct GeminiBackend {
+    pub model: String,
+    pub api_key: Option<String>,
+    pub base_url: String,
+    /// 图片最长边像素上限
+    pub max_image_dim: u32,
+    /// 图片编码格式
+    pub image_format: ImageEncodeFormat,
+    /// 请求超时时间
+    pub timeout: Duration,
+    /// HTTP/SOCKS 代理地址，未配置时直连
+    pub proxy_url: Option<String>,
+    /// 单次回复的最大 token 数，对应 `generationConfig.maxOutputTokens`
+    pub max_tokens: u32,
+    /// 采样温度，越低输出格式越稳定
+    pub temperature: f32,
+}
+
+impl Default for GeminiBackend {
+    fn default() -> Self {
+        Self::new("gemini-2.5-flash".to_string())
+    }
+}
+
+impl GeminiBackend {
+    /// 创建新的 Gemini 后端实例，API Key 默认读取 GEMINI_API_KEY 环境变量
+    pub fn new(model: String) -> Self {
+        Self {
+            model,
+            api_key: std::env::var("GEMINI_API_KEY").ok(),
+            base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
+        }
+    }
+
+    /// 设置 API Key
+    pub fn with_api_key(mut self, api_key: String) -> Self {
+        self.api_key = Some(api_key);
+        self
+    }
+
+    /// 设置图片最长边像素上限
+    pub fn with_max_image_dim(mut self, max_image_dim: u32) -> Self {
+        self.max_image_dim = max_image_dim;
+        self
+    }
+
+    /// 设置图片编码格式
+    pub fn with_image_format(mut self, image_format: ImageEncodeFormat) -> Self {
+        self.image_format = image_format;
+        self
+    }
+
+    /// 设置请求超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 设置 HTTP/SOCKS 代理地址
+    pub fn with_proxy_url(mut self, proxy_url: String) -> Self {
+        self.proxy_url = Some(proxy_url);
+        self
+    }
+
+    /// 设置单次回复的最大 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// 设置采样温度
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 流式接口地址（不含 API Key）
+    fn stream_url(&self) -> String {
+        format!(
+            "{}/models/{}:streamGenerateContent",
+            self.base_url.trim_end_matches('/'),
+            self.model
+        )
+    }
+
+    /// 构建 generateContent 请求体，图片以 inline_data 形式附加
+    fn build_body(&self, text: &str, image_path: Option<&Path>) -> Value {
+        let mut parts = vec![json!({ "text": text })];
+        if let Some(path) = image_path {
+            tracing::debug!("[gemini_backend] Converting image to base64: {}", path.display());
+            match utility::img_to_data_url(path, self.max_image_dim, self.image_format) {
+                Ok(data_url) => match split_data_url(&data_url) {
+                    Some((mime_type, data)) => parts.push(json!({
+                        "inline_data": { "mime_type": mime_type, "data": data }
+                    })),
+                    None => tracing::error!("[gemini_backend] Malformed image data URL"),
+                },
+                Err(e) => tracing::error!("[gemini_backend] Failed to convert image to base64: {}", e),
+            }
+        }
+
+        json!({
+            "contents": [{ "role": "user", "parts": parts }],
+            "generationConfig": { "maxOutputTokens": self.max_tokens, "temperature": self.temperature }
+        })
+    }
+
+    /// 发送流式请求，每收到一段文本就调用一次 `on_text`，返回完整文本
+    async fn stream_generate(
+        &self,
+        body: &Value,
+        mut on_text: impl FnMut(&str),
+    ) -> Result<String, Error> {
+        let api_key = self
+            .api_key
+            .as_deref()
+            .ok_or_else(|| Error::Stream("Gemini API key not available. Please set GEMINI_API_KEY.".into()))?;
+
+        let client = llm_backend::build_http_client(
+            self.timeout,
+            self.proxy_url.as_deref(),
+            Default::default(),
+        )?;
+        let response = client
+            .post(self.stream_url())
+            .query(&[("alt", "sse"), ("key", api_key)])
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| llm_backend::request_error("Gemini", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let detail = response.text().await.unwrap_or_default();
+            return Err(Error::Stream(format!("Gemini API returned {}: {}", status, detail)));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut parser = SseTextParser::default();
+        let mut accumulated = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| Error::Stream(format!("Gemini stream error: {}", e)))?;
+            for text in parser.push(&chunk)? {
+                accumulated.push_str(&text);
+                on_text(&accumulated);
+            }
+        }
+
+        if accumulated.is_empty() {
+            Err(Error::Stream("No response content from Gemini".into()))
+        } else {
+            Ok(accumulated)
+        }
+    }
+}
+
+/// 拆分 `data:<mime>;base64,<data>`，返回 (mime, data)
+fn split_data_url(data_url: &str) -> Option<(&str, &str)> {
+    let rest = data_url.strip_prefix("data:")?;
+    let (mime_type, data) = rest.split_once(";base64,")?;
+    Some((mime_type, data))
+}
+
+/// 按行解析 SSE 数据，从每个 `data:` 事件中提取候选文本
+#[derive(Default)]
+struct SseTextParser {
+    buffer: Vec<u8>,
+}
+
+impl SseTextParser {
+    /// 追加一段字节，返回其中完整事件包含的文本片段
+    fn push(&mut self, bytes: &[u8]) -> Result<Vec<String>, Error> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut texts = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let event: Value = serde_json::from_str(data.trim())
+                .map_err(|e| Error::Stream(format!("Invalid Gemini stream event: {}", e)))?;
+            if let Some(error) = event.get("error") {
+                return Err(Error::Stream(format!("Gemini API error: {}", error)));
+            }
+            let text = candidate_text(&event);
+            if !text.is_empty() {
+                texts.push(text);
+            }
+        }
+        Ok(texts)
+    }
+}
+
+/// 拼接第一个候选结果中所有 text 片段
+fn candidate_text(event: &Value) -> String {
+    event["candidates"][0]["content"]["parts"]
+        .as_array()
+        .map(|parts| parts.iter().filter_map(|part| part["text"].as_str()).collect())
+        .unwrap_or_default()
+}
+
+#[async_trait::async_trait]
+impl LLMBackend for GeminiBackend {
+    fn provider(&self) -> LLMProvider {
+        LLMProvider::Gemini
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn send_message(
+        &self,
+        text: String,
+        image_path: Option<&Path>,
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        tracing::info!("[gemini_backend] current model: {}", self.model);
+        let body = self.build_body(&text, image_path);
+
+        let request = self
+            .stream_generate(&body, |accumulated| {
+                tracing::trace!(
+                    "[gemini_backend] Streaming response chunk, total length: {}",
+                    accumulated.len()
+                );
+                let _ = response_sender.send(LLMResponse {
+                    content: accumulated.to_string(),
+                    is_complete: false,
+                    reasoning: None,
+                });
+            });
+        let result = llm_backend::with_timeout(self.timeout, request).await;
+
+        match result {
+            Ok(content) => {
+                tracing::info!(
+                    "[gemini_backend] Gemini streaming response completed, total length: {}",
+                    content.len()
+                );
+                let _ = response_sender.send(LLMResponse {
+                    content,
+                    is_complete: true,
+                    reasoning: None,
+                });
+                Ok(())
+            }
+            Err(e) => {
+                tracing::error!("[gemini_backend] Gemini request failed: {}", e);
+                let _ = response_sender.send(LLMResponse {
+                    content: format!("Error: {}", e),
+                    is_complete: true,
+                    reasoning: None,
+                });
+                Err(e)
+            }
+        }
+    }
+
+    async fn test_availability(&self) -> Result<String, Error> {
+        tracing::info!("[gemini_backend] Testing Gemini API availability...");
+        let body = self.build_body(
+            "Please respond with 'Hello from Gemini!' to confirm you are available.",
+            None,
+        );
+        let content =
+            llm_backend::with_timeout(self.timeout, self.stream_generate(&body, |_| {})).await?;
+        tracing::info!("[gemini_backend] Gemini test successful: {}", content);
+        Ok(content)
+    }
+
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> Value {
+        json!({
+            "provider": self.provider().to_string(),
+            "base_url": self.stream_url(),
+            "api_key": self.api_key,
+            "body": self.build_body(text, image_path),
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gemini_backend_creation() {
+        let backend = GeminiBackend::default();
+        assert_eq!(backend.model, "gemini-2.5-flash");
+        assert_eq!(backend.base_url, "https://generativelanguage.googleapis.com/v1beta");
+        assert_eq!(backend.provider(), LLMProvider::Gemini);
+        assert_eq!(backend.model_name(), "gemini-2.5-flash");
+        assert_eq!(
+            backend.stream_url(),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:streamGenerateContent"
+        );
+
+        let mut custom_backend = GeminiBackend::new("gemini-2.5-pro".to_string()).with_api_key("test_key".to_string());
+        custom_backend.base_url = "https://custom.api.com/".to_string();
+        assert_eq!(custom_backend.model, "gemini-2.5-pro");
+        assert_eq!(custom_backend.api_key, Some("test_key".to_string()));
+        assert_eq!(
+            custom_backend.stream_url(),
+            "https://custom.api.com/models/gemini-2.5-pro:streamGenerateContent"
+        );
+    }
+
+    #[test]
+    fn test_sse_text_parser() {
+        let mut parser = SseTextParser::default();
+        let event = r#"data: {"candidates":[{"content":{"parts":[{"text":"var "},{"text":"Questions"}],"role":"model"}}]}"#;
+        let (head, tail) = event.split_at(20);
+
+        assert!(parser.push(head.as_bytes()).unwrap().is_empty());
+        assert_eq!(parser.push(format!("{}\r\n\r\n", tail).as_bytes()).unwrap(), vec!["var Questions"]);
+        assert!(parser.push(b"data: {\"error\":{\"code\":400}}\n").is_err());
+    }
+
+    #[test]
+    fn test_split_data_url() {
+        assert_eq!(split_data_url("data:image/jpeg;base64,AAAA"), Some(("image/jpeg", "AAAA")));
+        assert_eq!(split_data_url("image/png;base64,AAAA"), None);
+    }
+}
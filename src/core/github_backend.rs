@@ -1,12 +1,16 @@
 use std::path::Path;
 use std::sync::mpsc;
+use std::time::Duration;
 
-use async_llm::{ChatMessage, ChatRequest, Error};
-use base64::{Engine, engine::general_purpose};
-use image::ImageFormat;
-use tokio_stream::StreamExt;
+use async_llm::Error;
+use reqwest::header::HeaderMap;
+use serde_json::{Value, json};
 
-use super::llm_backend::{LLMResponse, LLMBackend, LLMProvider};
+use super::chat_completions::{ChatCompletions, StreamDelta};
+use super::llm_backend::{self, DEFAULT_MAX_TOKENS, DEFAULT_TEMPERATURE, DEFAULT_TIMEOUT_SECS, LLMResponse, LLMBackend, LLMProvider};
+use super::request_logger;
+use super::stream_util::accumulate_stream;
+use super::utility::{self, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat};
 
 /// GitHub Models 后端实现
 /// 支持 GitHub Models API (https://models.inference.ai.azure.com)
@@ -15,6 +19,20 @@ pub struct GitHubBackend {
     pub model: String,
     pub api_token: Option<String>,
     pub base_url: String,
+    /// 图片最长边像素上限
+    pub max_image_dim: u32,
+    /// 图片编码格式
+    pub image_format: ImageEncodeFormat,
+    /// 请求超时时间
+    pub timeout: Duration,
+    /// 是否使用流式请求，关闭时一次性返回完整回复
+    pub streaming: bool,
+    /// HTTP/SOCKS 代理地址，未配置时直连
+    pub proxy_url: Option<String>,
+    /// 单次回复的最大 token 数
+    pub max_tokens: u32,
+    /// 采样温度，越低输出格式越稳定
+    pub temperature: f32,
 }
 
 impl Default for GitHubBackend {
@@ -23,6 +41,13 @@ impl Default for GitHubBackend {
             model: "gpt-4o".to_string(),
             api_token: std::env::var("GITHUB_TOKEN").ok(),
             base_url: "https://models.inference.ai.azure.com".to_string(),
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            streaming: true,
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
         }
     }
 }
@@ -34,6 +59,13 @@ impl GitHubBackend {
             model,
             api_token: std::env::var("GITHUB_TOKEN").ok(),
             base_url: "https://models.inference.ai.azure.com".to_string(),
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            streaming: true,
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
         }
     }
 
@@ -43,6 +75,48 @@ impl GitHubBackend {
         self
     }
 
+    /// 设置图片最长边像素上限
+    pub fn with_max_image_dim(mut self, max_image_dim: u32) -> Self {
+        self.max_image_dim = max_image_dim;
+        self
+    }
+
+    /// 设置图片编码格式
+    pub fn with_image_format(mut self, image_format: ImageEncodeFormat) -> Self {
+        self.image_format = image_format;
+        self
+    }
+
+    /// 设置请求超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 设置是否使用流式请求
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// 设置单次回复的最大 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// 设置采样温度
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 设置 HTTP/SOCKS 代理地址
+    pub fn with_proxy_url(mut self, proxy_url: String) -> Self {
+        self.proxy_url = Some(proxy_url);
+        self
+    }
+
     /// 设置自定义 API 端点
      #[allow(dead_code)]
     pub fn with_base_url(mut self, base_url: String) -> Self {
@@ -50,131 +124,186 @@ impl GitHubBackend {
         self
     }
 
-    /// 从图片路径生成 base64 编码
-    fn image_to_base64(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
-        let image = image::ImageReader::open(path)?.decode()?;
-        let mut buf = Vec::new();
-        image.write_to(&mut std::io::Cursor::new(&mut buf), ImageFormat::Png)?;
-        Ok(general_purpose::STANDARD.encode(&buf))
-    }
-
-    /// 构建消息列表
-    fn build_messages(&self, text: &str, image_path: Option<&Path>) -> Vec<ChatMessage> {
-        if let Some(path) = image_path {
-            tracing::debug!("[github_backend] Converting image to base64: {}", path.display());
-            match self.image_to_base64(path) {
-                Ok(base64) => {
-                    tracing::debug!("[github_backend] Image converted to base64 successfully");
-                    // GitHub Models API 需要 data URL 格式: data:image/png;base64,<base64_string>
-                    let data_url = format!("data:image/png;base64,{}", base64);
-                    vec![
-                        ChatMessage::system("You are GitHub Copilot, a helpful AI assistant for analyzing questions and images."),
-                        ChatMessage::user_image_with_text(text, data_url.as_str()),
-                    ]
-                }
-                Err(e) => {
-                    tracing::error!("[github_backend] Failed to convert image to base64: {}", e);
-                    vec![
-                        ChatMessage::system("you have to follow the follow rules"),
-                        ChatMessage::user(text),
-                    ]
-                }
-            }
-        } else {
-            tracing::debug!("[github_backend] Text-only request");
-            tracing::info!("messages: {:?}", text);
-            vec![
-                ChatMessage::system("you have to follow the follow rules"),
-                ChatMessage::user(text),
-            ]
-        }
+    /// 从图片路径生成 data URL（过大的图片会先缩小，MIME 与编码格式一致）
+    fn image_to_data_url(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        utility::img_to_data_url(path, self.max_image_dim, self.image_format)
     }
 
-    /// 设置环境变量以使用 GitHub Models API
-    fn setup_environment(&self) {
-        if let Some(api_token) = &self.api_token {
-            unsafe {
-                std::env::set_var("GITHUB_TOKEN", api_token);
-            }
-        } else {
-            tracing::error!("[github_backend] No GitHub token available. Please set GITHUB_TOKEN environment variable or use with_api_key()");
+    /// 请求 API 时使用的默认请求头（鉴权）
+    fn default_headers(&self) -> HeaderMap {
+        llm_backend::bearer_headers(self.api_token.as_deref())
+    }
+
+    /// 构建带默认请求头、超时和代理的 HTTP 客户端，聊天请求和模型列表都使用它
+    fn build_client(&self) -> Result<reqwest::Client, Error> {
+        llm_backend::build_http_client(
+            self.timeout,
+            self.proxy_url.as_deref(),
+            self.default_headers(),
+        )
+    }
+
+    /// 使用带鉴权和代理的客户端请求 chat completions 接口
+    fn chat(&self) -> Result<ChatCompletions, Error> {
+        Ok(ChatCompletions::new(self.build_client()?, &self.base_url, "GitHub"))
+    }
+
+    /// 构建带最大 token 数和温度的流式请求体
+    fn chat_body(&self, system: &str, text: &str, image_data_urls: &[&str]) -> Value {
+        let mut body = request_logger::chat_body_with_images(&self.model, system, text, image_data_urls, true);
+        body["max_tokens"] = json!(self.max_tokens);
+        body["temperature"] = json!(self.temperature);
+        body
+    }
+
+    /// 构建题目的请求体，每张图片以 data URL 形式作为用户消息中单独的内容部分附加
+    fn build_body(&self, text: &str, image_paths: &[&Path]) -> Value {
+        if !image_paths.is_empty() && !self.supports_vision() {
+            tracing::warn!(
+                "[github_backend] Model {} is not known to accept images, the attached image may be ignored",
+                self.model
+            );
         }
+        let data_urls: Vec<String> = image_paths
+            .iter()
+            .filter_map(|path| {
+                tracing::debug!("[github_backend] Converting image to base64: {}", path.display());
+                self.image_to_data_url(path)
+                    .map_err(|e| tracing::error!("[github_backend] Failed to convert image to base64: {}", e))
+                    .ok()
+            })
+            .collect();
+        let system = if data_urls.is_empty() {
+            "you have to follow the follow rules"
+        } else {
+            "You are GitHub Copilot, a helpful AI assistant for analyzing questions and images."
+        };
+        let data_urls: Vec<&str> = data_urls.iter().map(String::as_str).collect();
+        self.chat_body(system, text, &data_urls)
     }
 
     /// 尝试流式请求
     async fn try_streaming_request(
         &self,
-        messages: Vec<ChatMessage>,
+        body: &Value,
         response_sender: &mpsc::Sender<LLMResponse>,
-    ) -> Result<String, Error> {
+    ) -> Result<StreamDelta, Error> {
         tracing::info!("[github_backend] Attempting streaming request to GitHub Models...");
-        
-        // 临时设置环境变量
-        self.setup_environment();
-        
-        let stream_request = ChatRequest::new(&self.model, messages).with_stream();
-        
-        let mut response = stream_request.send_stream().await?;
+
+        let response = self.chat()?.stream(body).await?;
         tracing::info!("[github_backend] Streaming request successful, processing response...");
-        
-        let mut accumulated_content = String::new();
 
-        while let Some(result) = response.next().await {
-            match result {
-                Ok(response) => {
-                    if let Some(choice) = response.choices.first() {
-                        if let Some(delta) = &choice.delta {
-                            if let Some(content) = &delta.content {
-                                accumulated_content.push_str(content);
-                                
-                                tracing::trace!("[github_backend] Streaming response chunk, total length: {}", accumulated_content.len());
-                                let _ = response_sender.send(LLMResponse {
-                                    content: accumulated_content.clone(),
-                                    is_complete: false,
-                                });
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("[github_backend] GitHub streaming error during processing: {}", e);
-                    return Err(e);
-                }
-            }
-        }
+        let accumulated = accumulate_stream(response, response_sender).await?;
 
-        tracing::info!("[github_backend] GitHub streaming response completed, total length: {}", accumulated_content.len());
-        Ok(accumulated_content)
+        tracing::info!("[github_backend] GitHub streaming response completed, total length: {}", accumulated.content.len());
+        Ok(accumulated)
     }
 
-    /// 尝试非流式请求
-    async fn try_non_streaming_request(
-        &self,
-        messages: Vec<ChatMessage>,
-    ) -> Result<String, Error> {
+    /// 尝试非流式请求，返回回答和思考过程
+    async fn try_non_streaming_request(&self, body: &Value) -> Result<StreamDelta, Error> {
         tracing::info!("[github_backend] Attempting non-streaming request to GitHub Models...");
-        
-        // 临时设置环境变量
-        self.setup_environment();
-        
-        let request = ChatRequest::new(&self.model, messages);
-        
-        let response = request.send().await?;
+
+        let mut body = body.clone();
+        body["stream"] = json!(false);
+        let response = self.chat()?.complete(&body).await?;
         tracing::info!("[github_backend] Non-streaming request successful");
+
+        let message = StreamDelta::from_message(&response["choices"][0]["message"]);
+
+        tracing::info!("[github_backend] GitHub non-streaming response completed, length: {}", message.content.len());
+        Ok(message)
+    }
+
+    /// 发送测试请求检查可用性（不含超时处理）
+    ///
+    /// 与发送题目走同一条流式/非流式回退路径，只是不显示增量内容。
+    async fn check_availability(&self) -> Result<String, Error> {
+        tracing::info!("[github_backend] Testing GitHub Models API availability...");
         
-        let content = if let Some(choice) = response.choices.first() {
-            if let Some(message) = &choice.message {
-                message.content.clone().unwrap_or_default()
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
-        };
+        if self.api_token.is_none() {
+            let error_msg = "GitHub token not available. Please set GITHUB_TOKEN environment variable.";
+            tracing::error!("[github_backend] {}", error_msg);
+            return Err(Error::Stream(error_msg.into()));
+        }
         
-        tracing::info!("[github_backend] GitHub non-streaming response completed, length: {}", content.len());
+        let body = self.chat_body(
+            "You are GitHub Copilot, a helpful AI assistant.",
+            "Please respond with 'Hello from GitHub Copilot!' to confirm you are available.",
+            &[],
+        );
+
+        let (sender, _receiver) = mpsc::channel();
+        let content = self.request_with_fallback(&body, &sender).await?.content;
+        if content.is_empty() {
+            tracing::error!("[github_backend] GitHub availability test failed: No response content");
+            return Err(Error::Stream("No response content from GitHub Models".into()));
+        }
+        tracing::info!("[github_backend] GitHub availability test successful: {}", content);
         Ok(content)
     }
+
+    /// 先尝试流式请求（流式的增量内容通过 `response_sender` 发送），失败时回退到非流式请求，返回完整的回答和思考过程
+    async fn request_with_fallback(
+        &self,
+        body: &Value,
+        response_sender: &mpsc::Sender<LLMResponse>,
+    ) -> Result<StreamDelta, Error> {
+        let streaming = self
+            .streaming
+            .then(|| self.try_streaming_request(body, response_sender));
+        stream_or_fallback(streaming, || self.try_non_streaming_request(body)).await
+    }
+
+    /// 先尝试流式请求，失败时回退到非流式请求，并发送最终响应
+    async fn send_with_fallback(
+        &self,
+        body: &Value,
+        response_sender: &mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        let result = self.request_with_fallback(body, response_sender).await;
+        let (content, reasoning) = match &result {
+            Ok(message) => (message.content.clone(), message.reasoning_text()),
+            Err(e) if llm_backend::RateLimited::from_error(e).is_some() => (format!("Error: {}", e), None),
+            Err(e) => (format!("Error: Both streaming and non-streaming requests failed. Last error: {}", e), None),
+        };
+        let _ = response_sender.send(LLMResponse {
+            content,
+            is_complete: true,
+            reasoning,
+        });
+        result.map(|_| ())
+    }
+}
+
+/// 等待流式请求（`None` 表示关闭了流式），失败时调用 `non_streaming` 回退到非流式请求
+///
+/// 被限流时立即再发非流式请求也会失败，直接返回带等待时间的错误。
+async fn stream_or_fallback<T, S, N, F>(streaming: Option<S>, non_streaming: N) -> Result<T, Error>
+where
+    S: Future<Output = Result<T, Error>>,
+    N: FnOnce() -> F,
+    F: Future<Output = Result<T, Error>>,
+{
+    let streaming_result = match streaming {
+        Some(request) => request.await,
+        None => Err(Error::Stream("Streaming disabled".into())),
+    };
+    match streaming_result {
+        Ok(content) => Ok(content),
+        Err(e) if llm_backend::RateLimited::from_error(&e).is_some() => {
+            let e = llm_backend::with_rate_limit(e);
+            tracing::warn!("[github_backend] Rate limited: {}", e);
+            Err(e)
+        }
+        Err(e) => {
+            tracing::warn!("[github_backend] Streaming request failed: {}, trying non-streaming request...", e);
+            non_streaming().await.map_err(|e2| {
+                let e2 = llm_backend::with_rate_limit(e2);
+                tracing::error!("[github_backend] Both streaming and non-streaming requests failed. Streaming error: {}, Non-streaming error: {}", e, e2);
+                e2
+            })
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -192,6 +321,15 @@ impl LLMBackend for GitHubBackend {
         text: String,
         image_path: Option<&Path>,
         response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        self.send_message_with_images(text, image_path.as_slice(), response_sender).await
+    }
+
+    async fn send_message_with_images(
+        &self,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
     ) -> Result<(), Error> {
         tracing::info!("[github_backend] Sending message to GitHub Models API...");
         
@@ -201,132 +339,67 @@ impl LLMBackend for GitHubBackend {
             let _ = response_sender.send(LLMResponse {
                 content: format!("Error: {}", error_msg),
                 is_complete: true,
+                reasoning: None,
             });
             return Err(Error::Stream(error_msg.into()));
         }
 
-        let messages = self.build_messages(&text, image_path);
-
-        // 首先尝试流式请求
-        match self.try_streaming_request(messages.clone(), &response_sender).await {
-            Ok(content) => {
-                // 流式请求成功完成
-                let _ = response_sender.send(LLMResponse {
-                    content,
-                    is_complete: true,
-                });
-                Ok(())
-            }
-            Err(e) => {
-                // 流式请求失败，尝试非流式请求
-                tracing::warn!("[github_backend] Streaming request failed: {}, trying non-streaming request...", e);
-                
-                match self.try_non_streaming_request(messages).await {
-                    Ok(content) => {
-                        // 发送完整响应
-                        let _ = response_sender.send(LLMResponse {
-                            content,
-                            is_complete: true,
-                        });
-                        Ok(())
-                    }
-                    Err(e2) => {
-                        tracing::error!("[github_backend] Both streaming and non-streaming requests failed. Streaming error: {}, Non-streaming error: {}", e, e2);
-                        let _ = response_sender.send(LLMResponse {
-                            content: format!("Error: Both streaming and non-streaming requests failed. Last error: {}", e2),
-                            is_complete: true,
-                        });
-                        Err(e2)
-                    }
-                }
-            }
-        }
+        let body = self.build_body(&text, image_paths);
+        llm_backend::send_with_timeout(
+            self.timeout,
+            &response_sender,
+            self.send_with_fallback(&body, &response_sender),
+        )
+        .await
     }
 
     async fn test_availability(&self) -> Result<String, Error> {
-        tracing::info!("[github_backend] Testing GitHub Models API availability...");
-        
+        llm_backend::with_timeout(self.timeout, self.check_availability()).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, Error> {
         if self.api_token.is_none() {
-            let error_msg = "GitHub token not available. Please set GITHUB_TOKEN environment variable.";
-            tracing::error!("[github_backend] {}", error_msg);
-            return Err(Error::Stream(error_msg.into()));
+            return Err(Error::Stream(
+                "GitHub token not available. Please set GITHUB_TOKEN environment variable.".into(),
+            ));
         }
-        
-        let messages = vec![
-            ChatMessage::system("You are GitHub Copilot, a helpful AI assistant."),
-            ChatMessage::user("Please respond with 'Hello from GitHub Copilot!' to confirm you are available."),
-        ];
-
-        // 临时设置环境变量
-        self.setup_environment();
-
-        // 首先尝试流式请求
-        tracing::info!("[github_backend] Attempting streaming test request...");
-        let stream_request = ChatRequest::new(&self.model, messages.clone()).with_stream();
-
-        match stream_request.send_stream().await {
-            Ok(mut response) => {
-                let mut accumulated_content = String::new();
-
-                while let Some(result) = response.next().await {
-                    match result {
-                        Ok(response) => {
-                            if let Some(choice) = response.choices.first() {
-                                if let Some(delta) = &choice.delta {
-                                    if let Some(content) = &delta.content {
-                                        accumulated_content.push_str(content);
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("[github_backend] GitHub streaming test error: {}", e);
-                            return Err(e);
-                        }
-                    }
-                }
-
-                if !accumulated_content.is_empty() {
-                    tracing::info!("[github_backend] GitHub streaming test successful: {}", accumulated_content);
-                    Ok(accumulated_content)
-                } else {
-                    tracing::error!("[github_backend] GitHub streaming test failed: No response content");
-                    Err(Error::Stream("No response content from GitHub Models".into()))
-                }
-            }
-            Err(e) => {
-                // 流式请求失败，尝试非流式请求
-                tracing::warn!("[github_backend] Streaming test failed: {}, trying non-streaming test...", e);
-                
-                let non_stream_request = ChatRequest::new(&self.model, messages);
-                
-                match non_stream_request.send().await {
-                    Ok(response) => {
-                        let content = if let Some(choice) = response.choices.first() {
-                            if let Some(message) = &choice.message {
-                                message.content.clone().unwrap_or_default()
-                            } else {
-                                String::new()
-                            }
-                        } else {
-                            String::new()
-                        };
-                        
-                        if !content.is_empty() {
-                            tracing::info!("[github_backend] GitHub non-streaming test successful: {}", content);
-                            Ok(content)
-                        } else {
-                            tracing::error!("[github_backend] GitHub non-streaming test failed: No response content");
-                            Err(Error::Stream("No response content from GitHub Models".into()))
-                        }
-                    }
-                    Err(e2) => {
-                        tracing::error!("[github_backend] Both streaming and non-streaming tests failed. Streaming error: {}, Non-streaming error: {}", e, e2);
-                        Err(e2)
-                    }
-                }
-            }
+        let url = format!("{}/models", self.base_url.trim_end_matches('/'));
+        tracing::info!("[github_backend] Listing models from {}", url);
+
+        let response = self
+            .build_client()?
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| llm_backend::request_error("Models", e))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Stream(format!("Models request failed: {}", e)))?;
+        if !status.is_success() {
+            return Err(Error::Stream(format!("Models endpoint returned {}: {}", status, body)));
         }
+        llm_backend::parse_models_response(&body)
+    }
+
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> serde_json::Value {
+        let body = self.build_body(text, image_path.as_slice());
+
+        json!({
+            "provider": self.provider().to_string(),
+            "base_url": self.base_url,
+            "api_token": self.api_token,
+            "body": body,
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn supports_vision(&self) -> bool {
+        llm_backend::is_vision_model(&self.model)
     }
 }
 
@@ -546,4 +619,86 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_stream_or_fallback() {
+        use std::cell::Cell;
+
+        // 发送题目和测试可用性都通过 request_with_fallback 走这里，用模拟请求检查回退规则
+        let fallback_calls = Cell::new(0);
+        let non_streaming = |result: Result<&'static str, &'static str>| {
+            let fallback_calls = &fallback_calls;
+            move || async move {
+                fallback_calls.set(fallback_calls.get() + 1);
+                result.map(str::to_string).map_err(|e| Error::Stream(e.into()))
+            }
+        };
+        let streaming = |result: Result<&'static str, String>| {
+            Some(async move { result.map(str::to_string).map_err(|e| Error::Stream(e.into())) })
+        };
+
+        // 流式成功时不发非流式请求
+        let content = stream_or_fallback(streaming(Ok("stream")), non_streaming(Ok("fallback"))).await;
+        assert_eq!(content.unwrap(), "stream");
+        assert_eq!(fallback_calls.get(), 0);
+
+        // 流式失败或关闭时回退到非流式
+        let content = stream_or_fallback(streaming(Err("connection reset".into())), non_streaming(Ok("fallback"))).await;
+        assert_eq!(content.unwrap(), "fallback");
+        let disabled = None::<std::future::Ready<Result<String, Error>>>;
+        let content = stream_or_fallback(disabled, non_streaming(Ok("fallback"))).await;
+        assert_eq!(content.unwrap(), "fallback");
+        assert_eq!(fallback_calls.get(), 2);
+
+        // 两者都失败时返回非流式的错误
+        let error = stream_or_fallback(streaming(Err("stream down".into())), non_streaming(Err("fallback down")))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("fallback down"));
+        assert_eq!(fallback_calls.get(), 3);
+
+        // 被限流时不再回退，错误带等待时间
+        let limited = "ApiError: 429 Too Many Requests: Please wait 42 seconds before retrying.".to_string();
+        let error = stream_or_fallback(streaming(Err(limited)), non_streaming(Ok("fallback")))
+            .await
+            .unwrap_err();
+        assert_eq!(llm_backend::RateLimited::from_error(&error).map(|r| r.retry_secs()), Some(42));
+        assert_eq!(fallback_calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_chat_request_goes_through_proxy() {
+        let (proxy, request) = llm_backend::serve_once(r#"{"choices":[{"message":{"content":"var Questions = [];"}}]}"#);
+        let backend = GitHubBackend::new("gpt-4o".to_string())
+            .with_base_url("http://models.invalid".to_string())
+            .with_api_key("ghp-test".to_string())
+            .with_proxy_url(format!("http://{}", proxy))
+            .with_streaming(false);
+
+        let (sender, receiver) = mpsc::channel();
+        backend.send_message("hi".to_string(), None, sender).await.unwrap();
+        assert_eq!(receiver.iter().last().unwrap().content, "var Questions = [];");
+
+        // 代理和 Token 都作用于发送题目的客户端，不写入进程环境变量
+        let request = request.join().unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post http://models.invalid/chat/completions "), "{}", request);
+        assert!(request.contains("authorization: bearer ghp-test"));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_sent_with_reply() {
+        let (addr, _request) = llm_backend::serve_once(
+            r#"{"choices":[{"message":{"content":"var Questions = [];","reasoning_content":"先看选项"}}]}"#,
+        );
+        let backend = GitHubBackend::new("deepseek-r1".to_string())
+            .with_base_url(format!("http://{}", addr))
+            .with_api_key("ghp-test".to_string())
+            .with_streaming(false);
+
+        let (sender, receiver) = mpsc::channel();
+        backend.send_message("hi".to_string(), None, sender).await.unwrap();
+        let last = receiver.iter().last().unwrap();
+        assert_eq!(last.content, "var Questions = [];");
+        assert_eq!(last.reasoning.as_deref(), Some("先看选项"));
+    }
+}
@@ -1,12 +1,22 @@
 use std::path::Path;
 use std::sync::mpsc;
+use std::time::Duration;
 
-use async_llm::{ChatMessage, ChatRequest, Error};
-use base64::{Engine, engine::general_purpose};
-use image::ImageFormat;
-use tokio_stream::StreamExt;
+use async_llm::Error;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::{Value, json};
 
-use super::llm_backend::{LLMBackend, LLMProvider, LLMResponse};
+use super::chat_completions::{ChatCompletions, StreamDelta};
+use super::llm_backend::{self, DEFAULT_MAX_TOKENS, DEFAULT_TEMPERATURE, DEFAULT_TIMEOUT_SECS, LLMBackend, LLMProvider, LLMResponse};
+use super::request_logger;
+use super::stream_util::accumulate_stream;
+use super::utility::{self, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat};
+
+/// 未配置 base URL 时使用的 OpenAI 官方接口地址
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// 组织级 OpenAI 账号需要携带的请求头
+pub const ORGANIZATION_HEADER: &str = "OpenAI-Organization";
 
 /// GPT 后端实现
 #[derive(Clone, Debug)]
@@ -14,6 +24,22 @@ pub struct GPTBackend {
     pub model: String,
     pub api_key: Option<String>,
     pub base_url: Option<String>,
+    /// 图片最长边像素上限
+    pub max_image_dim: u32,
+    /// 图片编码格式
+    pub image_format: ImageEncodeFormat,
+    /// 请求超时时间
+    pub timeout: Duration,
+    /// OpenAI 组织 ID，配置后通过 `OpenAI-Organization` 请求头发送
+    pub organization: Option<String>,
+    /// 是否使用流式请求，关闭时一次性返回完整回复
+    pub streaming: bool,
+    /// HTTP/SOCKS 代理地址，未配置时直连
+    pub proxy_url: Option<String>,
+    /// 单次回复的最大 token 数
+    pub max_tokens: u32,
+    /// 采样温度，越低输出格式越稳定
+    pub temperature: f32,
 }
 
 impl Default for GPTBackend {
@@ -22,6 +48,14 @@ impl Default for GPTBackend {
             model: "gpt-4o".to_string(),
             api_key: None,
             base_url: Some("https://api.tu-zi.com/v1".to_string()),
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            organization: None,
+            streaming: true,
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
         }
     }
 }
@@ -33,6 +67,14 @@ impl GPTBackend {
             model,
             api_key: None,
             base_url: None,
+            max_image_dim: DEFAULT_MAX_IMAGE_DIM,
+            image_format: ImageEncodeFormat::default(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            organization: None,
+            streaming: true,
+            proxy_url: None,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: DEFAULT_TEMPERATURE,
         }
     }
 
@@ -46,151 +88,254 @@ impl GPTBackend {
         self
     }
 
-    /// 从图片路径生成 base64 编码
-    fn image_to_base64(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
-        // 打开并解码图片，然后编码为 PNG 格式字节流，最后转为 base64 字符串
-        let image = image::ImageReader::open(path)?.decode()?;
-        let mut buf = Vec::new();
-        image.write_to(&mut std::io::Cursor::new(&mut buf), ImageFormat::Png)?;
-        Ok(general_purpose::STANDARD.encode(&buf))
+    /// 设置图片最长边像素上限
+    pub fn with_max_image_dim(mut self, max_image_dim: u32) -> Self {
+        self.max_image_dim = max_image_dim;
+        self
     }
 
-    /// 构建消息列表
-    fn build_messages(&self, text: &str, image_path: Option<&Path>) -> Vec<ChatMessage> {
-        if let Some(path) = image_path {
-            // 如果有图片，转换为 base64
-            tracing::info!(
-                "[gpt_backend] Converting image to base64: {}",
-                path.display()
-            );
-            match self.image_to_base64(path) {
-                Ok(base64) => {
-                    tracing::info!("[gpt_backend] Image converted to base64 successfully");
-                    let data_url = format!("data:image/png;base64,{}", base64);
-                    vec![
-                        ChatMessage::system(""),
-                        ChatMessage::user_image_with_text(text, data_url.as_str()),
-                    ]
-                }
-                Err(e) => {
-                    tracing::error!("[gpt_backend] Failed to convert image to base64: {}", e);
-                    vec![
-                        ChatMessage::system(
-                            "You are a helpful assistant for analyzing questions and images.",
-                        ),
-                        ChatMessage::user(text),
-                    ]
+    /// 设置图片编码格式
+    pub fn with_image_format(mut self, image_format: ImageEncodeFormat) -> Self {
+        self.image_format = image_format;
+        self
+    }
+
+    /// 设置请求超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 设置是否使用流式请求
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+        self
+    }
+
+    /// 设置单次回复的最大 token 数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// 设置采样温度
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// 设置 OpenAI 组织 ID
+    pub fn with_organization(mut self, organization: String) -> Self {
+        self.organization = Some(organization);
+        self
+    }
+
+    /// 设置 HTTP/SOCKS 代理地址
+    pub fn with_proxy_url(mut self, proxy_url: String) -> Self {
+        self.proxy_url = Some(proxy_url);
+        self
+    }
+
+    /// 请求 API 时使用的默认请求头（鉴权和组织 ID），未配置 API Key 时读取 `OPENAI_API_KEY`
+    fn default_headers(&self) -> HeaderMap {
+        let api_key = self.api_key.clone().or_else(|| std::env::var("OPENAI_API_KEY").ok());
+        let mut headers = llm_backend::bearer_headers(api_key.as_deref());
+        if let Some(organization) = &self.organization {
+            match HeaderValue::from_str(organization) {
+                Ok(value) => {
+                    headers.insert(ORGANIZATION_HEADER, value);
                 }
+                Err(e) => tracing::warn!("[gpt_backend] Invalid organization header: {}", e),
             }
-        } else {
-            // 只有文本
-            tracing::info!("[gpt_backend] Text-only request");
-            tracing::info!("messages: {:?}", text);
-            vec![ChatMessage::system(""), ChatMessage::user(text)]
         }
+        headers
+    }
 
+    /// 构建带默认请求头、超时和代理的 HTTP 客户端，聊天请求和模型列表都使用它
+    fn build_client(&self) -> Result<reqwest::Client, Error> {
+        llm_backend::build_http_client(
+            self.timeout,
+            self.proxy_url.as_deref(),
+            self.default_headers(),
+        )
     }
 
-    /// 设置环境变量以使用自定义的 API key 和 base URL
-    fn setup_environment(&self) {
-        if let Some(api_key) = &self.api_key {
-            unsafe {
-                std::env::set_var("OPENAI_API_KEY", api_key);
-                tracing::debug!("[gpt_backend] Set OPENAI_API_KEY environment variable");
-            }
-        }
+    /// 从图片路径生成 data URL（过大的图片会先缩小，MIME 与编码格式一致）
+    fn image_to_data_url(&self, path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        utility::img_to_data_url(path, self.max_image_dim, self.image_format)
+    }
 
-        if let Some(base_url) = &self.base_url {
-            unsafe {
-                std::env::set_var("OPENAI_BASE_URL", base_url);
-                tracing::debug!("[gpt_backend] Set OPENAI_BASE_URL to: {}", base_url);
-            }
+    /// 未配置 base URL 时读取 `OPENAI_BASE_URL`，都没有时使用 OpenAI 官方接口
+    fn api_base_url(&self) -> String {
+        self.base_url
+            .clone()
+            .or_else(|| std::env::var("OPENAI_BASE_URL").ok())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+    }
+
+    /// 使用带鉴权、组织 ID 请求头和代理的客户端请求 chat completions 接口
+    fn chat(&self) -> Result<ChatCompletions, Error> {
+        Ok(ChatCompletions::new(self.build_client()?, &self.api_base_url(), "GPT"))
+    }
+
+    /// 构建带最大 token 数和温度的流式请求体
+    fn chat_body(&self, system: &str, text: &str, image_data_urls: &[&str]) -> Value {
+        let mut body = request_logger::chat_body_with_images(&self.model, system, text, image_data_urls, true);
+        body["max_tokens"] = json!(self.max_tokens);
+        body["temperature"] = json!(self.temperature);
+        body
+    }
+
+    /// 构建题目的请求体，每张图片以 data URL 形式作为用户消息中单独的内容部分附加
+    fn build_body(&self, text: &str, image_paths: &[&Path]) -> Value {
+        if !image_paths.is_empty() && !self.supports_vision() {
+            tracing::warn!(
+                "[gpt_backend] Model {} is not known to accept images, the attached image may be ignored",
+                self.model
+            );
         }
+        let data_urls: Vec<String> = image_paths
+            .iter()
+            .filter_map(|path| {
+                tracing::info!("[gpt_backend] Converting image to base64: {}", path.display());
+                self.image_to_data_url(path)
+                    .map_err(|e| tracing::error!("[gpt_backend] Failed to convert image to base64: {}", e))
+                    .ok()
+            })
+            .collect();
+        // 图片都转换失败时只发送文本
+        let system = if !image_paths.is_empty() && data_urls.is_empty() {
+            "You are a helpful assistant for analyzing questions and images."
+        } else {
+            ""
+        };
+        let data_urls: Vec<&str> = data_urls.iter().map(String::as_str).collect();
+        self.chat_body(system, text, &data_urls)
     }
 
-    /// 尝试流式请求
+    /// 尝试流式请求，每收到一段内容就发送一次未完成的响应
     async fn try_streaming_request(
         &self,
-        messages: Vec<ChatMessage>,
+        body: &Value,
         response_sender: &mpsc::Sender<LLMResponse>,
-    ) -> Result<String, Error> {
+    ) -> Result<StreamDelta, Error> {
         tracing::info!("[gpt_backend] Attempting streaming request to GPT...");
 
-        // 设置环境变量
-        self.setup_environment();
-
-        let stream_request = ChatRequest::new(&self.model, messages).with_stream();
-
-        let mut response = stream_request.send_stream().await?;
+        let response = self.chat()?.stream(body).await?;
         tracing::info!("[gpt_backend] Send streaming request successful, processing response...");
 
-        let mut accumulated_content = String::new();
-
-        while let Some(result) = response.next().await {
-            match result {
-                Ok(response) => {
-                    if let Some(choice) = response.choices.first() {
-                        if let Some(delta) = &choice.delta {
-                            if let Some(content) = &delta.content {
-                                accumulated_content.push_str(content);
-
-                                // 发送流式更新
-                                tracing::trace!(
-                                    "[gpt_backend] Streaming response chunk, total length: {}",
-                                    accumulated_content.len()
-                                );
-                                let _ = response_sender.send(LLMResponse {
-                                    content: accumulated_content.clone(),
-                                    is_complete: false,
-                                });
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("[gpt_backend] GPT streaming error during processing: {}", e);
-                    return Err(e);
-                }
-            }
-        }
+        let accumulated = accumulate_stream(response, response_sender).await?;
 
         tracing::info!(
             "[gpt_backend] GPT streaming response completed, total length: {}",
-            accumulated_content.len()
+            accumulated.content.len()
         );
-        Ok(accumulated_content)
+        Ok(accumulated)
     }
 
-    /// 尝试非流式请求
-    async fn try_non_streaming_request(&self, messages: Vec<ChatMessage>) -> Result<String, Error> {
+    /// 尝试非流式请求，返回回答和思考过程
+    async fn try_non_streaming_request(&self, body: &Value) -> Result<StreamDelta, Error> {
         tracing::info!("[gpt_backend] Attempting non-streaming request to GPT...");
 
-        // 设置环境变量
-        self.setup_environment();
-
-        let request = ChatRequest::new(&self.model, messages);
-
-        let response = request.send().await?;
+        let mut body = body.clone();
+        body["stream"] = json!(false);
+        let response = self.chat()?.complete(&body).await?;
         tracing::info!("[gpt_backend] Non-streaming request successful");
 
-        let content = if let Some(choice) = response.choices.first() {
-            if let Some(message) = &choice.message {
-                message.content.clone().unwrap_or_default()
-            } else {
-                String::new()
-            }
-        } else {
-            String::new()
-        };
+        let message = StreamDelta::from_message(&response["choices"][0]["message"]);
 
         tracing::info!(
             "[gpt_backend] GPT non-streaming response completed, length: {}",
-            content.len()
+            message.content.len()
         );
+        Ok(message)
+    }
+
+    /// 发送测试请求检查可用性（不含超时处理）
+    async fn check_availability(&self) -> Result<String, Error> {
+        tracing::info!("[gpt_backend] Testing GPT availability...");
+
+        let body = self.chat_body(
+            "You are a helpful assistant.",
+                "先说下你的模型名称\n//请 
+直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不 
+用代码块包裹\nvar Questions = [\n    {\n        stem: `Which of the following is a <span class=\"underline fillblank\" data-blank-id=\"593417796829762300\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span> language?`, //这里不要带题号.这里的data-blank-id每次不要相同\n    
+    题型类型: \"语音题\",\n        answer: [\"programming\"],\n        analysis: \"考点：编程语言识别。 
+分析：Python是一种高级编程语言，广泛用于数据科学、人工智能等领域。故答案为：programming\", //解析要用中 
+文。格式要分为：考点，分析，故答案为：\n    },\n    {\n        stem: `The capital of France is <span class=\"underline fillblank\" data-blank-id=\"593417796829762301\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span>.`,\n        题型类型: \"填空题\",\n        answer: [\"Paris\"],\n        analysis: \"考点：世界地理常识。分析：巴黎是法国的首都和最大城市，也是法国的政治、经 
+济、文化中心。故答案为：Paris\"\n    },\n    {//如果检测到是一个文章。且一个题目里面有多个空的，用下面这种格式。段落两端对齐，首行缩 
+进，字体字号不变\n            stem:`Good morning my name is (1) <span class=\"underline fillblank\" data-blank-id=\"593417796829762302\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span> (这里可能会有提示的单词，你也要写上) I am from (2) <span class=\"underline fillblank\" data-blank-id=\"593417796829762303\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span>`,\n            //序号从(1)开始。data-blank-id每次不要相同。不用管原题目的题号\n            //序号从(1)开始。data-blank-id每次不要相同不用管原题目的题
+号\n            //序号从(1)开始。data-blank-id每次不要相同不用管原题目的题号\n            题型类型: \"填空题\",\n            answer: 
+[\"John\", \"Canada\"],\n            analysis: \"1. 考点：.....。分析：根据常见的自我介绍格式，名字是John. 故答案为：John,<br>2. 分析
+：.......。国家是Canada。故答案为： Canada\"\n    },\n];\n",
+            &[],
+        );
+
+        // 首先尝试流式请求，失败时回退到非流式请求
+        tracing::info!("[gpt_backend] Attempting streaming test request...");
+        let (sender, _receiver) = mpsc::channel();
+        let content = match self.try_streaming_request(&body, &sender).await {
+            Ok(message) => message.content,
+            Err(e) => {
+                tracing::warn!(
+                    "[gpt_backend] Streaming test failed: {}, trying non-streaming test...",
+                    e
+                );
+                self.try_non_streaming_request(&body).await.map_err(|e2| {
+                    tracing::error!(
+                        "[gpt_backend] Both streaming and non-streaming tests failed. Streaming error: {}, Non-streaming error: {}",
+                        e,
+                        e2
+                    );
+                    e2
+                })?.content
+            }
+        };
+
+        if content.is_empty() {
+            tracing::error!("[gpt_backend] GPT test failed: No response content");
+            return Err(Error::Stream("No response content from GPT".into()));
+        }
+        tracing::info!("[gpt_backend] GPT test successful: {}", content);
         Ok(content)
     }
 }
 
+/// 发送最终的完成响应（带上思考过程），失败或内容为空时发送以 "Error" 开头的响应
+fn send_final_response(
+    result: Result<StreamDelta, Error>,
+    response_sender: &mpsc::Sender<LLMResponse>,
+) -> Result<(), Error> {
+    match result {
+        Ok(message) if !message.content.is_empty() => {
+            let _ = response_sender.send(LLMResponse {
+                reasoning: message.reasoning_text(),
+                content: message.content,
+                is_complete: true,
+            });
+            Ok(())
+        }
+        Ok(_) => {
+            tracing::error!("[gpt_backend] GPT request failed: No response content");
+            let _ = response_sender.send(LLMResponse {
+                content: "Error: No response content from GPT".to_string(),
+                is_complete: true,
+                reasoning: None,
+            });
+            Err(Error::Stream("No response content from GPT".into()))
+        }
+        Err(e) => {
+            tracing::error!("[gpt_backend] GPT request failed: {}", e);
+            let _ = response_sender.send(LLMResponse {
+                content: format!("Error: {}", e),
+                is_complete: true,
+                reasoning: None,
+            });
+            Err(e)
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl LLMBackend for GPTBackend {
     fn provider(&self) -> LLMProvider {
@@ -207,243 +352,101 @@ impl LLMBackend for GPTBackend {
         image_path: Option<&Path>,
         response_sender: mpsc::Sender<LLMResponse>,
     ) -> Result<(), Error> {
-        let messages = self.build_messages(&text, image_path);
-        tracing::info!("[gpt_backend] current model: {}", self.model);
-
-        // 设置环境变量
-        self.setup_environment();
-
-        // 首先尝试流式请求
-        tracing::info!("[gpt_backend] Attempting streaming request...");
-        let stream_request = ChatRequest::new(&self.model, messages.clone()).with_stream();
-
-        match stream_request.send_stream().await {
-            Ok(mut response) => {
-                let mut accumulated_content = String::new();
-
-                while let Some(result) = response.next().await {
-                    match result {
-                        Ok(response) => {
-                            if let Some(content) = response
-                                .choices
-                                .first()
-                                .and_then(|c| c.delta.as_ref())
-                                .and_then(|d| d.content.as_ref())
-                            {
-                                accumulated_content.push_str(content);
-
-                                // 发送流式更新
-                                tracing::trace!(
-                                    "[gpt_backend] Streaming response chunk, total length: {}",
-                                    accumulated_content.len()
-                                );
-                                let _ = response_sender.send(LLMResponse {
-                                    content: accumulated_content.clone(),
-                                    is_complete: false,
-                                });
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("[gpt_backend] GPT streaming error during processing: {}", e);
-                            let _ = response_sender.send(LLMResponse {
-                                content: format!("Error during streaming: {}", e),
-                                is_complete: true,
-                            });
-                            return Err(e);
-                        }
-                    }
-                }
+        self.send_message_with_images(text, image_path.as_slice(), response_sender).await
+    }
 
-                if !accumulated_content.is_empty() {
-                    tracing::info!(
-                        "[gpt_backend] GPT streaming response completed, total length: {}",
-                        accumulated_content.len()
-                    );
-                    // 发送最终完成响应
-                    let _ = response_sender.send(LLMResponse {
-                        content: accumulated_content,
-                        is_complete: true,
-                    });
-                    Ok(())
-                } else {
-                    tracing::error!("[gpt_backend] GPT streaming failed: No response content");
-                    let _ = response_sender.send(LLMResponse {
-                        content: "Error: No response content from GPT streaming".to_string(),
-                        is_complete: true,
-                    });
-                    Err(Error::Stream("No response content from GPT".into()))
-                }
-            }
-            Err(e) => {
-                // 流式请求失败，尝试非流式请求
-                tracing::warn!(
-                    "[gpt_backend] Streaming request failed: {}, trying non-streaming request...",
-                    e
-                );
+    async fn send_message_with_images(
+        &self,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        let body = self.build_body(&text, image_paths);
+        tracing::info!("[gpt_backend] current model: {}", self.model);
 
-                let non_stream_request = ChatRequest::new(&self.model, messages);
-
-                match non_stream_request.send().await {
-                    Ok(response) => {
-                        if let Some(content) = response
-                            .choices
-                            .first()
-                            .and_then(|choice| choice.message.as_ref())
-                            .and_then(|message| message.content.as_ref())
-                            .filter(|content| !content.is_empty())
-                        {
-                            tracing::info!(
-                                "[gpt_backend] GPT non-streaming response successful, length: {}",
-                                content.len()
-                            );
-                            // 发送完整响应
-                            let _ = response_sender.send(LLMResponse {
-                                content: content.clone(),
-                                is_complete: true,
-                            });
-                            Ok(())
-                        } else {
-                            tracing::error!("[gpt_backend] GPT non-streaming failed: No response content");
-                            let _ = response_sender.send(LLMResponse {
-                                content: "Error: No response content from GPT non-streaming".to_string(),
-                                is_complete: true,
-                            });
-                            Err(Error::Stream("No response content from GPT".into()))
-                        }
-                    }
-                    Err(e2) => {
-                        tracing::error!(
-                            "[gpt_backend] Both streaming and non-streaming requests failed. Streaming error: {}, Non-streaming error: {}",
-                            e,
-                            e2
+        let request = async {
+            if self.streaming {
+                // 首先尝试流式请求，失败时回退到非流式请求
+                match self.try_streaming_request(&body, &response_sender).await {
+                    Ok(message) => Ok(message),
+                    Err(e) => {
+                        tracing::warn!(
+                            "[gpt_backend] Streaming request failed: {}, trying non-streaming request...",
+                            e
                         );
-                        let _ = response_sender.send(LLMResponse {
-                            content: format!("Error: Both streaming and non-streaming requests failed. Last error: {}", e2),
-                            is_complete: true,
-                        });
-                        Err(e2)
+                        self.try_non_streaming_request(&body).await
                     }
                 }
+            } else {
+                self.try_non_streaming_request(&body).await
             }
-        }
+        };
+        // 429 响应带有等待时间时转换为限流错误，便于重试逻辑按建议时间等待
+        let result = llm_backend::with_timeout(self.timeout, request)
+            .await
+            .map_err(llm_backend::with_rate_limit);
+
+        send_final_response(result, &response_sender)
     }
 
     async fn test_availability(&self) -> Result<String, Error> {
-        tracing::info!("[gpt_backend] Testing GPT availability...");
+        llm_backend::with_timeout(self.timeout, self.check_availability()).await
+    }
 
-        // 设置环境变量
-        self.setup_environment();
+    async fn list_models(&self) -> Result<Vec<String>, Error> {
+        let url = format!("{}/models", self.api_base_url().trim_end_matches('/'));
+        tracing::info!("[gpt_backend] Listing models from {}", url);
+
+        let response = self
+            .build_client()?
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| llm_backend::request_error("Models", e))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Stream(format!("Models request failed: {}", e)))?;
+        if !status.is_success() {
+            return Err(Error::Stream(format!("Models endpoint returned {}: {}", status, body)));
+        }
+        llm_backend::parse_models_response(&body)
+    }
 
-        let messages = vec![
-            ChatMessage::system("You are a helpful assistant."),
-            ChatMessage::user(
-                "先说下你的模型名称\n//请 
-直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不 
-用代码块包裹\nvar Questions = [\n    {\n        stem: `Which of the following is a <span class=\"underline fillblank\" data-blank-id=\"593417796829762300\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span> language?`, //这里不要带题号.这里的data-blank-id每次不要相同\n    
-    题型类型: \"语音题\",\n        answer: [\"programming\"],\n        analysis: \"考点：编程语言识别。 
-分析：Python是一种高级编程语言，广泛用于数据科学、人工智能等领域。故答案为：programming\", //解析要用中 
-文。格式要分为：考点，分析，故答案为：\n    },\n    {\n        stem: `The capital of France is <span class=\"underline fillblank\" data-blank-id=\"593417796829762301\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span>.`,\n        题型类型: \"填空题\",\n        answer: [\"Paris\"],\n        analysis: \"考点：世界地理常识。分析：巴黎是法国的首都和最大城市，也是法国的政治、经 
-济、文化中心。故答案为：Paris\"\n    },\n    {//如果检测到是一个文章。且一个题目里面有多个空的，用下面这种格式。段落两端对齐，首行缩 
-进，字体字号不变\n            stem:`Good morning my name is (1) <span class=\"underline fillblank\" data-blank-id=\"593417796829762302\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span> (这里可能会有提示的单词，你也要写上) I am from (2) <span class=\"underline fillblank\" data-blank-id=\"593417796829762303\" contenteditable=\"false\" style=\"text-indent: 0; border-bottom: 1px solid #f6c908;display:inline-block;min-width: 40px;max-width: 80px;\"><input type=\"text\" style=\"display:none\">   </span>`,\n            //序号从(1)开始。data-blank-id每次不要相同。不用管原题目的题号\n            //序号从(1)开始。data-blank-id每次不要相同不用管原题目的题
-号\n            //序号从(1)开始。data-blank-id每次不要相同不用管原题目的题号\n            题型类型: \"填空题\",\n            answer: 
-[\"John\", \"Canada\"],\n            analysis: \"1. 考点：.....。分析：根据常见的自我介绍格式，名字是John. 故答案为：John,<br>2. 分析
-：.......。国家是Canada。故答案为： Canada\"\n    },\n];\n",
-            ),
-        ];
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> serde_json::Value {
+        let body = self.build_body(text, image_path.as_slice());
 
-        // 首先尝试流式请求
-        tracing::info!("[gpt_backend] Attempting streaming test request...");
-        let stream_request = ChatRequest::new(&self.model, messages.clone()).with_stream();
-
-        match stream_request.send_stream().await {
-            Ok(mut response) => {
-                let mut accumulated_content = String::new();
-
-                while let Some(result) = response.next().await {
-                    match result {
-                        Ok(response) => {
-                            response
-                                .choices
-                                .first()
-                                .and_then(|c| c.delta.as_ref())
-                                .and_then(|d| d.content.as_ref())
-                                .map(|content| accumulated_content.push_str(content));
-                        }
-                        Err(e) => {
-                            tracing::error!("[gpt_backend] GPT streaming test error: {}", e);
-                            return Err(e);
-                        }
-                    }
-                }
+        serde_json::json!({
+            "provider": self.provider().to_string(),
+            "base_url": self.base_url,
+            "api_key": self.api_key,
+            "organization": self.organization,
+            "body": body,
+        })
+    }
 
-                if !accumulated_content.is_empty() {
-                    tracing::info!(
-                        "[gpt_backend] GPT streaming test successful: {}",
-                        accumulated_content
-                    );
-                    Ok(accumulated_content)
-                } else {
-                    tracing::error!("[gpt_backend] GPT streaming test failed: No response content");
-                    Err(Error::Stream("No response content from GPT".into()))
-                }
-            }
-            Err(e) => {
-                // 流式请求失败，尝试非流式请求
-                tracing::warn!(
-                    "[gpt_backend] Streaming test failed: {}, trying non-streaming test...",
-                    e
-                );
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
 
-                let non_stream_request = ChatRequest::new(&self.model, messages);
-
-                match non_stream_request.send().await {
-                    Ok(response) => response
-                        .choices
-                        .first()
-                        .and_then(|choice| choice.message.as_ref())
-                        .and_then(|message| message.content.as_ref())
-                        .filter(|content| !content.is_empty())
-                        .map(|content| {
-                            tracing::info!(
-                                "[gpt_backend] GPT non-streaming test successful: {}",
-                                content
-                            );
-                            content.clone()
-                        })
-                        .ok_or_else(|| {
-                            tracing::error!(
-                                "[gpt_backend] GPT non-streaming test failed: No response content"
-                            );
-                            Error::Stream("No response content from GPT".into())
-                        }),
-                    Err(e2) => {
-                        tracing::error!(
-                            "[gpt_backend] Both streaming and non-streaming tests failed. Streaming error: {}, Non-streaming error: {}",
-                            e,
-                            e2
-                        );
-                        Err(e2)
-                    }
-                }
-            }
-        }
+    fn supports_vision(&self) -> bool {
+        llm_backend::is_vision_model(&self.model)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use reqwest::header::AUTHORIZATION;
 
     #[tokio::test]
     async fn test_gpt_connection() {
         let _ = tracing_subscriber::fmt::try_init();
         let key = "01110011,01101011,00101101,01101111,01101011,01000110,01110111,01100101,01010100,01000110,01011001,01010101,01111010,00110000,00110001,01100001,01000010,01010000,00110111,01011001,01110110,01010100,01101011,01110110,00111000,01001001,00110100,01111010,01101000,01100101,01110110,01110100,01100011,01001000,00110111,01100111,01011000,01101001,01011001,01100010,01100100,01100010,01000111,00110011,01001010,01100010,01101011,01110100,00110001,01001110,01100100";
-        let bytes: Vec<u8> = key
-            .split(',')
-            .filter_map(|b| u8::from_str_radix(b.trim(), 2).ok())
-            .collect();
+        let api_key = utility::decode_binary_to_str(key).expect("test key should be valid binary");
         let backend = GPTBackend::new("gemini-2.5-pro".to_string())
-            .with_api_key(String::from_utf8(bytes).unwrap())
+            .with_api_key(api_key)
             .with_base_url(String::from("http://27.106.110.32:2052/v1"));
         println!("{:?}", backend);
         match backend.test_availability().await {
@@ -524,4 +527,167 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_organization_header() {
+        let backend = GPTBackend::new("gpt-4o".to_string()).with_api_key("sk-test".to_string());
+        let headers = backend.default_headers();
+        assert_eq!(headers.get(AUTHORIZATION).unwrap(), "Bearer sk-test");
+        assert!(headers.get(ORGANIZATION_HEADER).is_none());
+
+        let backend = backend.with_organization("org-123".to_string());
+        assert_eq!(backend.default_headers().get(ORGANIZATION_HEADER).unwrap(), "org-123");
+        assert!(backend.build_client().is_ok());
+        assert_eq!(backend.request_snapshot("hi", None)["organization"], "org-123");
+    }
+
+    #[tokio::test]
+    async fn test_chat_request_sends_organization_header() {
+        let (addr, request) = llm_backend::serve_once(r#"{"choices":[{"message":{"content":"var Questions = [];"}}]}"#);
+        let backend = GPTBackend::new("gpt-4o".to_string())
+            .with_base_url(format!("http://{}/v1", addr))
+            .with_api_key("sk-test".to_string())
+            .with_organization("org-123".to_string())
+            .with_streaming(false);
+
+        let (sender, receiver) = mpsc::channel();
+        backend.send_message("hi".to_string(), None, sender).await.unwrap();
+        assert_eq!(receiver.iter().last().unwrap().content, "var Questions = [];");
+
+        // 组织 ID 作为聊天请求的请求头发送，不经过环境变量
+        let request = request.join().unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /v1/chat/completions "), "{}", request);
+        assert!(request.contains("openai-organization: org-123"));
+        assert!(request.contains("authorization: bearer sk-test"));
+    }
+
+    #[test]
+    fn test_build_body_with_both_images() {
+        let dir = std::env::temp_dir().join(format!("question_tool_gpt_images_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("original.png");
+        let processed = dir.join("processed.png");
+        image::RgbImage::from_pixel(4, 4, image::Rgb([200, 200, 200])).save(&original).unwrap();
+        image::GrayImage::from_pixel(4, 4, image::Luma([255])).save(&processed).unwrap();
+
+        let backend = GPTBackend::new("gpt-4o".to_string());
+        // 题目、原图和处理后的图片各是用户消息中的一个内容部分
+        let parts = |body: Value| body["messages"][1]["content"].as_array().map_or(0, Vec::len);
+        assert_eq!(parts(backend.build_body("题目", &[original.as_path(), processed.as_path()])), 3);
+        assert_eq!(parts(backend.build_body("题目", &[original.as_path()])), 2);
+        assert_eq!(backend.build_body("题目", &[])["messages"][1]["content"], "题目");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_proxy_url_applied_to_client() {
+        let backend = GPTBackend::new("gpt-4o".to_string());
+        let client = backend.build_client().unwrap();
+        assert!(!format!("{:?}", client).contains("127.0.0.1:7890"));
+
+        let backend = backend.with_proxy_url("http://127.0.0.1:7890".to_string());
+        let client = backend.build_client().unwrap();
+        assert!(format!("{:?}", client).contains("127.0.0.1:7890"));
+
+        let backend = backend.with_proxy_url("socks5://127.0.0.1:1080".to_string());
+        let client = backend.build_client().unwrap();
+        assert!(format!("{:?}", client).contains("127.0.0.1:1080"));
+
+        let backend = backend.with_proxy_url("not a proxy".to_string());
+        assert!(backend.build_client().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_request_goes_through_proxy() {
+        let (proxy, request) = llm_backend::serve_once(r#"{"choices":[{"message":{"content":"var Questions = [];"}}]}"#);
+        let backend = GPTBackend::new("gpt-4o".to_string())
+            .with_base_url("http://api.invalid/v1".to_string())
+            .with_proxy_url(format!("http://{}", proxy))
+            .with_streaming(false);
+
+        let (sender, receiver) = mpsc::channel();
+        backend.send_message("hi".to_string(), None, sender).await.unwrap();
+        assert_eq!(receiver.iter().last().unwrap().content, "var Questions = [];");
+
+        // 代理收到的是完整地址的请求行，说明聊天请求使用了配置的代理
+        let request = request.join().unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post http://api.invalid/v1/chat/completions "), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_sends_partial_responses_before_final() {
+        let (sender, receiver) = mpsc::channel();
+        let deltas = tokio_stream::iter(vec![
+            Ok(StreamDelta::default()),
+            text("var "),
+            text(""),
+            text("Questions"),
+            text(" = [];"),
+        ]);
+
+        let result = accumulate_stream(deltas, &sender).await;
+        send_final_response(result, &sender).unwrap();
+        drop(sender);
+
+        let responses: Vec<LLMResponse> = receiver.iter().collect();
+        let contents: Vec<(&str, bool)> = responses
+            .iter()
+            .map(|response| (response.content.as_str(), response.is_complete))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                ("var ", false),
+                ("var Questions", false),
+                ("var Questions = [];", false),
+                ("var Questions = [];", true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streaming_error_sends_error_response() {
+        let (sender, receiver) = mpsc::channel();
+        let deltas = tokio_stream::iter(vec![text("partial"), Err(Error::Stream("connection reset".into()))]);
+
+        let result = accumulate_stream(deltas, &sender).await;
+        assert!(send_final_response(result, &sender).is_err());
+        drop(sender);
+
+        let last = receiver.iter().last().unwrap();
+        assert!(last.is_complete);
+        assert!(last.is_error());
+    }
+
+    fn text(content: &str) -> Result<StreamDelta, Error> {
+        Ok(StreamDelta { content: content.to_string(), ..Default::default() })
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_sent_with_reply() {
+        // 流式：思考过程随中间结果和完成响应一起发出
+        let (sender, receiver) = mpsc::channel();
+        let thinking = Ok(StreamDelta { reasoning: "先看题干".to_string(), ..Default::default() });
+        let result = accumulate_stream(tokio_stream::iter(vec![thinking, text("var Questions = [];")]), &sender).await;
+        send_final_response(result, &sender).unwrap();
+        drop(sender);
+        let last = receiver.iter().last().unwrap();
+        assert!(last.is_complete);
+        assert_eq!(last.reasoning.as_deref(), Some("先看题干"));
+
+        // 非流式：读取 message 中的 reasoning_content
+        let (addr, _request) = llm_backend::serve_once(
+            r#"{"choices":[{"message":{"content":"var Questions = [];","reasoning_content":"先看选项"}}]}"#,
+        );
+        let backend = GPTBackend::new("o1".to_string())
+            .with_base_url(format!("http://{}/v1", addr))
+            .with_api_key("sk-test".to_string())
+            .with_streaming(false);
+        let (sender, receiver) = mpsc::channel();
+        backend.send_message("hi".to_string(), None, sender).await.unwrap();
+        let last = receiver.iter().last().unwrap();
+        assert_eq!(last.content, "var Questions = [];");
+        assert_eq!(last.reasoning.as_deref(), Some("先看选项"));
+    }
 }
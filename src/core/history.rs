@@ -0,0 +1,140 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::question_type::Question;
+use super::utility;
+
+/// 题目历史记录，每行一个 JSON 追加写入 `<config_dir>/history.jsonl`
+#[derive(Clone, Debug)]
+pub struct QuestionHistory {
+    path: PathBuf,
+}
+
+impl QuestionHistory {
+    /// 使用指定文件创建历史记录
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 使用默认位置 `<config_dir>/history.jsonl` 创建历史记录
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(utility::app_config_dir()?.join("history.jsonl")))
+    }
+
+    /// 追加保存一道题目
+    pub fn save(&self, question: &Question) -> Result<(), Box<dyn std::error::Error>> {
+        let line = serde_json::to_string(question)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        tracing::debug!("[history] Saved question {} to {}", question.id, self.path.display());
+        Ok(())
+    }
+
+    /// 清空历史记录：保留文件只清空内容，之后仍可继续追加保存
+    pub fn clear(&self) -> Result<(), Box<dyn std::error::Error>> {
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        tracing::info!("[history] Cleared history file {}", self.path.display());
+        Ok(())
+    }
+
+    /// 加载所有历史题目，附加代码按 `scripts` 重新生成，无法解析的行会被跳过
+    #[cfg(test)]
+    pub fn load_all(&self, scripts: &super::question_type::ScriptOptions) -> Vec<Question> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::debug!("[history] No history loaded from {}: {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str::<Question>(line) {
+                Ok(mut question) => {
+                    // 附加代码不落盘，加载时按题目类型重新生成
+                    question.additional_code = scripts.generator(question.question_type).get_code();
+                    Some(question)
+                }
+                Err(e) => {
+                    tracing::warn!("[history] Skipping malformed history line: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::question_type::{QuestionType, ScriptOptions};
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_save_and_load_history() {
+        let path = std::env::temp_dir().join(format!("question_tool_history_{}.jsonl", Uuid::new_v4()));
+        let history = QuestionHistory::new(&path);
+        assert!(history.load_all(&ScriptOptions::default()).is_empty());
+
+        let mut first = Question::new(
+            QuestionType::SingleChoice,
+            "第一题".to_string(),
+            Some(PathBuf::from("/tmp/slint_paste_1.png")),
+        );
+        first.set_model_reply("var Questions = [];".to_string());
+        let mut second = Question::new(QuestionType::ClozeTest, "第二题".to_string(), None);
+        second.set_model_reply("var newContent = ``;".to_string());
+
+        history.save(&first).unwrap();
+        history.save(&second).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "not json"))
+            .unwrap();
+
+        let loaded = history.load_all(&ScriptOptions::default());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].id, first.id);
+        assert_eq!(loaded[0].get_type(), QuestionType::SingleChoice);
+        assert_eq!(loaded[0].get_img_path(), first.get_img_path());
+        assert_eq!(loaded[0].get_output(), Some("var Questions = [];"));
+        assert_eq!(loaded[0].additional_code, first.additional_code);
+        assert_eq!(loaded[1].id, second.id);
+        assert_eq!(loaded[1].get_img_path(), None);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_clear_history_keeps_file() {
+        let path = std::env::temp_dir().join(format!("question_tool_history_{}.jsonl", Uuid::new_v4()));
+        let history = QuestionHistory::new(&path);
+        let question = Question::new(QuestionType::SingleChoice, "第一题".to_string(), None);
+        history.save(&question).unwrap();
+        history.save(&question).unwrap();
+        assert_eq!(history.load_all(&ScriptOptions::default()).len(), 2);
+
+        history.clear().unwrap();
+        assert!(path.exists());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+        assert!(history.load_all(&ScriptOptions::default()).is_empty());
+
+        // 清空后仍可继续保存
+        history.save(&question).unwrap();
+        assert_eq!(history.load_all(&ScriptOptions::default()).len(), 1);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,14 +1,45 @@
 use std::fmt::Display;
 use std::path::{ Path};
 use std::sync::mpsc;
+use std::time::Duration;
 
 use async_llm::Error;
+use reqwest::header::HeaderMap;
+
+use super::question_type::QuestionType;
+use super::request_logger::FailedRequestLogger;
+
+/// 默认请求超时时间（秒）
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// 回复过短或请求返回可重试状态码时默认的最大重试次数
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// 默认视为可重试的 HTTP 状态码（限流和网关错误）
+pub const DEFAULT_RETRYABLE_STATUS: [u16; 5] = [429, 500, 502, 503, 504];
+
+/// 请求返回可重试状态码时，第 n 次重试前等待 n 倍的该时长
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 /// LLM 响应结构
 #[derive(Clone, Debug)]
 pub struct LLMResponse {
     pub content: String,
     pub is_complete: bool,
+    /// 推理模型（如 o1、DeepSeek-R1）单独返回的思考过程，目前为止的全部文本；其他模型为 None
+    pub reasoning: Option<String>,
+}
+
+impl LLMResponse {
+    /// 后端在失败时会发送以 "Error" 开头的完成响应
+    pub fn is_error(&self) -> bool {
+        self.content.starts_with("Error")
+    }
+
+    /// 清空已显示的中间结果，重试前发出，被放弃的那次回复不会残留在界面上
+    pub fn reset() -> Self {
+        Self { content: String::new(), is_complete: false, reasoning: Some(String::new()) }
+    }
 }
 
 /// LLM 提供商枚举
@@ -16,15 +47,42 @@ pub struct LLMResponse {
 pub enum LLMProvider {
     GPT,
     GitHub,
+    Gemini,
+    /// 任意 OpenAI 兼容接口
+    Custom,
 }
 impl Display for LLMProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LLMProvider::GPT => write!(f, "GPT"),
             LLMProvider::GitHub => write!(f, "GitHub"),
+            LLMProvider::Gemini => write!(f, "Gemini"),
+            LLMProvider::Custom => write!(f, "Custom"),
         }
     }
 }
+
+impl LLMProvider {
+    /// 按配置中的名称解析提供商，未知名称与 `LLMManager::from_config` 一样按 GPT 处理
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "GitHub" => LLMProvider::GitHub,
+            "Gemini" => LLMProvider::Gemini,
+            "Custom" => LLMProvider::Custom,
+            _ => LLMProvider::GPT,
+        }
+    }
+
+    /// 发送前提示题干过长的默认字符数，GitHub Models 免费额度的上下文窗口较小
+    pub fn default_stem_warning_chars(&self) -> usize {
+        match self {
+            LLMProvider::GitHub => 3000,
+            LLMProvider::GPT | LLMProvider::Custom => 6000,
+            LLMProvider::Gemini => 20000,
+        }
+    }
+}
+
 /// 通用 LLM 后端 trait
 #[async_trait::async_trait]
 pub trait LLMBackend: Send + Sync {
@@ -39,17 +97,355 @@ pub trait LLMBackend: Send + Sync {
         response_sender: mpsc::Sender<LLMResponse>,
     ) -> Result<(), Error>;
     
+    /// 发送消息并附带多张图片（如原图和预处理后的图片），不支持多图的后端只发送第一张
+    async fn send_message_with_images(
+        &self,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        self.send_message(text, image_paths.first().copied(), response_sender).await
+    }
+
     /// 测试 LLM 是否可用
     async fn test_availability(&self) -> Result<String, Error>;
+
+    /// 测试 LLM 是否可用，提供商返回了用量时一并返回 token 数
+    async fn test_availability_with_usage(&self) -> Result<(String, Option<TokenUsage>), Error> {
+        self.test_availability().await.map(|response| (response, None))
+    }
+
+    /// 生成请求的 JSON 快照，用于记录失败请求（写入前会脱敏）
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> serde_json::Value;
+
+    /// 查询提供商 `/models` 接口返回的可用模型列表，不支持时返回空列表
+    async fn list_models(&self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    /// 单次请求（包括流式接收）的超时时间
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(DEFAULT_TIMEOUT_SECS)
+    }
+
+    /// 模型是否接受图片输入，默认认为支持
+    fn supports_vision(&self) -> bool {
+        true
+    }
+}
+
+/// 一次请求的 token 用量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// 从响应 JSON 中读取 token 用量，兼容 OpenAI 的 `usage` 和 Gemini 的 `usageMetadata`
+pub fn parse_usage(response: &serde_json::Value) -> Option<TokenUsage> {
+    let (usage, prompt_key, completion_key) = match (response.get("usage"), response.get("usageMetadata")) {
+        (Some(usage), _) if usage.is_object() => (usage, "prompt_tokens", "completion_tokens"),
+        (_, Some(usage)) if usage.is_object() => (usage, "promptTokenCount", "candidatesTokenCount"),
+        _ => return None,
+    };
+    Some(TokenUsage {
+        prompt_tokens: usage.get(prompt_key)?.as_u64()?,
+        completion_tokens: usage.get(completion_key).and_then(|count| count.as_u64()).unwrap_or(0),
+    })
 }
 
+/// 默认单次回复的最大 token 数，长篇阅读的 JSON 不会被截断
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// 默认采样温度，较低的温度让输出格式更稳定
+pub const DEFAULT_TEMPERATURE: f32 = 0.2;
+
+/// 已知支持图片输入的模型名前缀（忽略大小写，GitHub Models 的 `publisher/` 前缀会被去掉）
+const VISION_MODEL_PREFIXES: [&str; 20] = [
+    "gpt-4o",
+    "chatgpt-4o",
+    "gpt-4.1",
+    "gpt-4.5",
+    "gpt-4-turbo",
+    "gpt-4-vision",
+    "gpt-5",
+    "o3",
+    "o4-mini",
+    "claude-3",
+    "claude-sonnet-4",
+    "claude-opus-4",
+    "gemini",
+    "llama-3.2-11b-vision",
+    "llama-3.2-90b-vision",
+    "llama-4",
+    "phi-3.5-vision",
+    "phi-4-multimodal",
+    "pixtral",
+    "qwen2.5-vl",
+];
+
+/// 已知支持图片输入、但同名前缀的其他型号不支持的模型（完全匹配）
+const VISION_MODEL_EXACT: [&str; 2] = ["o1", "o1-2024-12-17"];
+
+/// 根据已知模型列表判断模型是否支持图片输入
+pub fn is_vision_model(model: &str) -> bool {
+    let name = model.rsplit('/').next().unwrap_or(model).trim().to_ascii_lowercase();
+    VISION_MODEL_EXACT.contains(&name.as_str())
+        || VISION_MODEL_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// 超时错误：`request timed out after <timeout>`
+pub fn timeout_error(timeout: Duration) -> Error {
+    Error::Stream(format!("{} {:?}", TIMEOUT_ERROR_PREFIX, timeout))
+}
+
+/// [`timeout_error`] 的错误信息前缀
+pub const TIMEOUT_ERROR_PREFIX: &str = "request timed out after";
+
+/// 连接失败或请求超时时错误信息的前缀，离线队列据此判断是否为网络错误
+pub const NETWORK_ERROR_PREFIX: &str = "network unavailable:";
+
+/// 把发送请求时的 reqwest 错误转换为 [`Error`]，连接失败和超时会带上 [`NETWORK_ERROR_PREFIX`]
+pub fn request_error(label: &str, error: reqwest::Error) -> Error {
+    if error.is_connect() || error.is_timeout() {
+        Error::Stream(format!("{} {} request failed: {}", NETWORK_ERROR_PREFIX, label, error))
+    } else {
+        Error::Stream(format!("{} request failed: {}", label, error))
+    }
+}
+
+/// 为请求加上超时，超时后返回 [`timeout_error`]
+pub async fn with_timeout<T>(
+    timeout: Duration,
+    request: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    tokio::time::timeout(timeout, request)
+        .await
+        .unwrap_or_else(|_| Err(timeout_error(timeout)))
+}
+
+/// 为发送请求加上超时，超时后向 UI 发送一条错误的完成响应
+pub async fn send_with_timeout(
+    timeout: Duration,
+    response_sender: &mpsc::Sender<LLMResponse>,
+    request: impl Future<Output = Result<(), Error>>,
+) -> Result<(), Error> {
+    match tokio::time::timeout(timeout, request).await {
+        Ok(result) => result,
+        Err(_) => {
+            let error = timeout_error(timeout);
+            tracing::error!("LLM {}", error);
+            let _ = response_sender.send(LLMResponse {
+                content: format!("Error: {}", error),
+                is_complete: true,
+                reasoning: None,
+            });
+            Err(error)
+        }
+    }
+}
+
+/// 从错误信息中提取 HTTP 状态码（独立出现的 400–599 之间的三位数）
+pub fn error_status(error: &Error) -> Option<u16> {
+    message_status(&error.to_string())
+}
+
+fn message_status(message: &str) -> Option<u16> {
+    let bytes = message.as_bytes();
+    (0..bytes.len().saturating_sub(2)).find_map(|start| {
+        let end = start + 3;
+        let bounded = (start == 0 || !bytes[start - 1].is_ascii_alphanumeric())
+            && bytes.get(end).is_none_or(|b| !b.is_ascii_alphanumeric());
+        let status = message.get(start..end)?.parse::<u16>().ok()?;
+        (bounded && bytes[start..end].iter().all(u8::is_ascii_digit) && (400..600).contains(&status))
+            .then_some(status)
+    })
+}
+
+/// 限流时最多等待多久后自动重试，服务端要求等待更久时直接把错误返回给用户
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// 请求被限流（HTTP 429），`retry_after` 为服务端建议的等待时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rate limited (429), retry after {}s", self.retry_secs())
+    }
+}
+
+impl From<RateLimited> for Error {
+    fn from(rate_limited: RateLimited) -> Self {
+        Error::Stream(rate_limited.to_string())
+    }
+}
+
+impl RateLimited {
+    /// 从 429 响应的 `Retry-After` 请求头和响应体中读取等待时间
+    pub fn from_response(retry_after_header: Option<&str>, body: &str) -> Option<Self> {
+        parse_retry_after(retry_after_header, body).map(|retry_after| Self { retry_after })
+    }
+
+    /// 从错误信息中识别限流，状态码不是 429 或没有给出等待时间时返回 None
+    pub fn from_message(message: &str) -> Option<Self> {
+        (message_status(message) == Some(429))
+            .then(|| Self::from_response(None, message))
+            .flatten()
+    }
+
+    pub fn from_error(error: &Error) -> Option<Self> {
+        Self::from_message(&error.to_string())
+    }
+
+    /// 等待的整秒数（向上取整）
+    pub fn retry_secs(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+
+    /// 显示给用户的提示
+    pub fn hint(&self) -> String {
+        format!("请求过于频繁，请 {} 秒后重试", self.retry_secs())
+    }
+}
+
+/// 把带有等待时间的 429 错误转换为 [`RateLimited`] 错误，其他错误原样返回
+pub fn with_rate_limit(error: Error) -> Error {
+    match RateLimited::from_error(&error) {
+        Some(rate_limited) => rate_limited.into(),
+        None => error,
+    }
+}
+
+/// 解析建议的等待时间：优先使用 `Retry-After` 请求头（秒数），其次是响应体 JSON 中的
+/// `retryAfter` / `retry_after` 字段，最后是 "Please wait 42 seconds" 之类的文字说明
+pub fn parse_retry_after(retry_after_header: Option<&str>, body: &str) -> Option<Duration> {
+    if let Some(duration) = retry_after_header.and_then(parse_seconds) {
+        return Some(duration);
+    }
+    let json = body
+        .find('{')
+        .zip(body.rfind('}'))
+        .and_then(|(start, end)| serde_json::from_str::<serde_json::Value>(body.get(start..=end)?).ok());
+    if let Some(duration) = json.as_ref().and_then(json_retry_after) {
+        return Some(duration);
+    }
+
+    let lower = body.to_ascii_lowercase();
+    ["retry after ", "retry-after:", "retry-after ", "retry in ", "wait "]
+        .iter()
+        .flat_map(|marker| lower.match_indices(marker).map(move |(index, _)| index + marker.len()))
+        .find_map(|start| {
+            let rest = lower[start..].trim_start();
+            let number_len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            parse_seconds(&rest[..number_len])
+        })
+}
+
+/// 在 JSON（包括嵌套的 `error` 对象）中查找等待时间字段
+fn json_retry_after(value: &serde_json::Value) -> Option<Duration> {
+    let object = value.as_object()?;
+    ["retryAfter", "retry_after", "Retry-After"]
+        .iter()
+        .filter_map(|key| object.get(*key))
+        .find_map(|value| match value {
+            serde_json::Value::Number(n) => n.as_f64().and_then(|secs| Duration::try_from_secs_f64(secs).ok()),
+            serde_json::Value::String(s) => parse_seconds(s),
+            _ => None,
+        })
+        .or_else(|| object.values().find_map(json_retry_after))
+}
+
+fn parse_seconds(text: &str) -> Option<Duration> {
+    let secs: f64 = text.trim().trim_end_matches('.').parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// 构建直接请求 API 使用的 HTTP 客户端，配置了代理时所有请求都经过该代理
+///
+/// `proxy_url` 支持 `http://`、`https://` 和 `socks5://`，未配置时沿用系统代理设置。
+pub fn build_http_client(
+    timeout: Duration,
+    proxy_url: Option<&str>,
+    headers: HeaderMap,
+) -> Result<reqwest::Client, Error> {
+    let mut builder = reqwest::Client::builder()
+        .default_headers(headers)
+        .timeout(timeout);
+    if let Some(proxy_url) = proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| Error::Stream(format!("Invalid proxy URL {}: {}", proxy_url, e)))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::Stream(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// 带 `Authorization: Bearer <token>` 的默认请求头，未配置 token 时为空
+pub fn bearer_headers(token: Option<&str>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = token {
+        match reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)) {
+            Ok(value) => {
+                headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            Err(e) => tracing::warn!("[llm_backend] Invalid API key header: {}", e),
+        }
+    }
+    headers
+}
+
+/// 解析 `/models` 接口的响应，兼容 OpenAI 的 `{"data": [{"id": ...}]}` 和 GitHub Models 的 `[{"name": ...}]`
+///
+/// GitHub Models 的 `id` 是 azureml 注册路径，因此优先使用 `name`。
+pub fn parse_models_response(body: &str) -> Result<Vec<String>, Error> {
+    let value: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| Error::Stream(format!("Invalid models response: {}", e)))?;
+    let entries = value
+        .get("data")
+        .unwrap_or(&value)
+        .as_array()
+        .ok_or_else(|| Error::Stream("Models response is not a list".into()))?;
+
+    let mut models: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .get("name")
+                .or_else(|| entry.get("id"))
+                .and_then(|id| id.as_str())
+                .or_else(|| entry.as_str())
+        })
+        .map(str::to_string)
+        .collect();
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+use super::custom_backend::CustomBackend;
 use super::gpt_backend::GPTBackend;
+use super::gemini_backend::GeminiBackend;
 use super::github_backend::GitHubBackend;
 
 /// LLM 管理器，负责管理不同的 LLM 后端
 pub struct LLMManager {
     backends: Vec<Box<dyn LLMBackend>>,
     current_backend: Option<usize>,
+    failed_request_logger: Option<FailedRequestLogger>,
+    /// 回复少于该字符数时视为失败并重试，0 表示不检查
+    min_reply_chars: usize,
+    /// 回复过短或返回可重试状态码时的最大重试次数
+    max_retries: u32,
+    /// 除默认状态码外额外视为可重试的 HTTP 状态码
+    extra_retryable_status: Vec<u16>,
+    /// 可重试错误的重试间隔基数
+    retry_delay: Duration,
 }
 
 impl LLMManager {
@@ -57,11 +453,56 @@ impl LLMManager {
         Self {
             backends: Vec::new(),
             current_backend: None,
+            failed_request_logger: None,
+            min_reply_chars: 0,
+            max_retries: DEFAULT_MAX_RETRIES,
+            extra_retryable_status: Vec::new(),
+            retry_delay: DEFAULT_RETRY_DELAY,
         }
     }
 
+    /// 设置失败请求记录器
+    pub fn with_failed_request_logger(mut self, logger: FailedRequestLogger) -> Self {
+        self.failed_request_logger = Some(logger);
+        self
+    }
+
+    /// 设置回复的最小字符数，过短的回复会触发重试
+    pub fn with_min_reply_chars(mut self, min_reply_chars: usize) -> Self {
+        self.min_reply_chars = min_reply_chars;
+        self
+    }
+
+    /// 设置回复过短或返回可重试状态码时的最大重试次数
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 设置额外的可重试 HTTP 状态码，与 [`DEFAULT_RETRYABLE_STATUS`] 合并
+    pub fn with_retryable_status(mut self, status: Vec<u16>) -> Self {
+        self.extra_retryable_status = status;
+        self
+    }
+
+    /// 错误是否带有可重试的 HTTP 状态码
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        error_status(error).is_some_and(|status| {
+            DEFAULT_RETRYABLE_STATUS.contains(&status) || self.extra_retryable_status.contains(&status)
+        })
+    }
+
      pub fn from_config(config: &crate::app::llm_settings::LLMConfig) -> Self {
-        let mut manager = Self::new();
+        let mut manager = Self::new()
+            .with_min_reply_chars(config.min_reply_chars)
+            .with_max_retries(config.max_retries)
+            .with_retryable_status(config.retryable_status.clone());
+        if config.log_failed_requests {
+            match FailedRequestLogger::open_default() {
+                Ok(logger) => manager = manager.with_failed_request_logger(logger),
+                Err(e) => tracing::warn!("Failed to open failed request log directory: {}", e),
+            }
+        }
 
         // 添加 GPT 后端
         let mut gpt_backend = GPTBackend::default();
@@ -73,18 +514,77 @@ impl LLMManager {
                 gpt_backend = gpt_backend.with_base_url(base_url.clone());
             }
         }
+        if let Some(organization) = &config.organization {
+            gpt_backend = gpt_backend.with_organization(organization.clone());
+        }
+        if let Some(proxy_url) = config.proxy_url() {
+            gpt_backend = gpt_backend.with_proxy_url(proxy_url.to_string());
+        }
         gpt_backend.model = config.model.clone();
+        gpt_backend = gpt_backend
+            .with_max_image_dim(config.max_image_dim)
+            .with_image_format(config.image_format)
+            .with_timeout(config.timeout_for(&LLMProvider::GPT))
+            .with_streaming(config.enable_streaming)
+            .with_max_tokens(config.max_tokens)
+            .with_temperature(config.temperature);
 
         let gpt_index = manager.add_backend(Box::new(gpt_backend));
 
         // 添加 GitHub 后端
-        let mut github_backend = GitHubBackend::new(config.model.clone());
+        let mut github_backend = GitHubBackend::new(config.model.clone())
+            .with_max_image_dim(config.max_image_dim)
+            .with_image_format(config.image_format)
+            .with_timeout(config.timeout_for(&LLMProvider::GitHub))
+            .with_streaming(config.enable_streaming)
+            .with_max_tokens(config.max_tokens)
+            .with_temperature(config.temperature);
         if let Some(token) = &config.github_token {
             github_backend = github_backend.with_api_key(token.clone());
         }
+        if let Some(proxy_url) = config.proxy_url() {
+            github_backend = github_backend.with_proxy_url(proxy_url.to_string());
+        }
 
         let github_index = manager.add_backend(Box::new(github_backend));
 
+        // 添加 Gemini 后端
+        let mut gemini_backend = GeminiBackend::new(config.model.clone())
+            .with_max_image_dim(config.max_image_dim)
+            .with_image_format(config.image_format)
+            .with_timeout(config.timeout_for(&LLMProvider::Gemini))
+            .with_max_tokens(config.max_tokens)
+            .with_temperature(config.temperature);
+        if let Some(api_key) = &config.gemini_api_key {
+            gemini_backend = gemini_backend.with_api_key(api_key.clone());
+        }
+        if let Some(proxy_url) = config.proxy_url() {
+            gemini_backend = gemini_backend.with_proxy_url(proxy_url.to_string());
+        }
+
+        let gemini_index = manager.add_backend(Box::new(gemini_backend));
+
+        // 选择自定义提供商时添加 OpenAI 兼容后端，使用 api_key / base_url / model 配置
+        let custom_index = (config.provider == LLMProvider::Custom.to_string()).then(|| {
+            let mut custom_backend = CustomBackend::new(
+                config.base_url.clone().unwrap_or_default(),
+                config.model.clone(),
+            )
+            .with_max_image_dim(config.max_image_dim)
+            .with_image_format(config.image_format)
+            .with_timeout(config.timeout_for(&LLMProvider::Custom))
+            .with_streaming(config.enable_streaming)
+            .with_max_tokens(config.max_tokens)
+            .with_temperature(config.temperature);
+            if let Some(api_key) = &config.api_key {
+                custom_backend = custom_backend.with_api_key(api_key.clone());
+            }
+            if let Some(proxy_url) = config.proxy_url() {
+                custom_backend = custom_backend.with_proxy_url(proxy_url.to_string());
+            }
+            manager.add_backend(Box::new(custom_backend))
+        });
+
         // 设置当前后端
         match config.provider.as_str() {
             "GPT" => {
@@ -93,13 +593,42 @@ impl LLMManager {
             "GitHub" => {
                 let _ = manager.set_current_backend(github_index);
             }
+            "Gemini" => {
+                let _ = manager.set_current_backend(gemini_index);
+            }
+            "Custom" => {
+                let _ = manager.set_current_backend(custom_index.unwrap_or(gpt_index));
+            }
             _ => {
                 let _ = manager.set_current_backend(gpt_index);
             }
         }
+        tracing::debug!(
+            "Configured backends: {:?}, current: {:?}",
+            manager.list_backends(),
+            manager.current_backend
+        );
 
         manager
     }
+    /// 根据题型创建管理器，题型配置了单独的模型时使用该模型
+    pub fn manager_for_question_type(
+        config: &crate::app::llm_settings::LLMConfig,
+        question_type: QuestionType,
+    ) -> Self {
+        let model = config.model_for(question_type);
+        if model == config.model {
+            return Self::from_config(config);
+        }
+
+        tracing::info!("Using model override for {}: {}", question_type.as_str(), model);
+        let config = crate::app::llm_settings::LLMConfig {
+            model: model.to_string(),
+            ..config.clone()
+        };
+        Self::from_config(&config)
+    }
+
     /// 添加后端
     pub fn add_backend(&mut self, backend: Box<dyn LLMBackend>) -> usize {
         let index = self.backends.len();
@@ -131,7 +660,6 @@ impl LLMManager {
     }
 
     /// 列出所有后端
-    #[allow(dead_code)]
     pub fn list_backends(&self) -> Vec<(usize, LLMProvider, &str)> {
         self.backends
             .iter()
@@ -140,31 +668,218 @@ impl LLMManager {
             .collect()
     }
 
-    /// 发送消息到当前后端
+    /// 发送消息到当前后端，请求返回可重试状态码时重新请求，回复过短时附加提示重新请求（不超过重试上限）
     pub async fn send_message(
         &self,
         text: String,
         image_path: Option<&Path>,
         response_sender: mpsc::Sender<LLMResponse>,
     ) -> Result<(), Error> {
+        self.send_message_with_images(text, image_path.as_slice(), response_sender).await
+    }
+
+    /// 与 [`LLMManager::send_message`] 相同，但附带多张图片
+    pub async fn send_message_with_images(
+        &self,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        let Some(backend) = self.current_backend() else {
+            return Err(Error::Stream("No backend available".into()));
+        };
+        if self.max_retries == 0 {
+            return self.send_once(backend, text, image_paths, response_sender).await;
+        }
+
+        let mut prompt = text.clone();
+        let mut attempt = 0;
+        loop {
+            // 中间结果直接转发给调用方，完成响应先截留下来，确认不需要重试后再发出
+            let (attempt_sender, attempt_receiver) = mpsc::channel::<LLMResponse>();
+            let partial_sender = response_sender.clone();
+            let forwarder = tokio::task::spawn_blocking(move || {
+                let mut complete = None;
+                let mut forwarded = false;
+                for response in attempt_receiver {
+                    if response.is_complete {
+                        complete = Some(response);
+                    } else {
+                        forwarded = true;
+                        let _ = partial_sender.send(response);
+                    }
+                }
+                (complete, forwarded)
+            });
+
+            let result = self
+                .send_once(backend, prompt.clone(), image_paths, attempt_sender)
+                .await;
+            let (complete, forwarded) = forwarder.await.unwrap_or_default();
+            // 重试前清空这次尝试已转发的中间结果
+            let discard_partials = || {
+                if forwarded {
+                    let _ = response_sender.send(LLMResponse::reset());
+                }
+            };
+
+            if attempt < self.max_retries {
+                if let Err(e) = &result {
+                    // 限流时按服务端建议的时间等待，要求等待太久时不再重试
+                    let wait = match RateLimited::from_error(e) {
+                        Some(rate_limited) => {
+                            (rate_limited.retry_after <= MAX_RATE_LIMIT_WAIT).then_some(rate_limited.retry_after)
+                        }
+                        None => Some(self.retry_delay * (attempt + 1)),
+                    };
+                    if let Some(wait) = wait.filter(|_| self.is_retryable(e)) {
+                        attempt += 1;
+                        tracing::warn!(
+                            "Request failed with retryable status: {}, retrying in {:?} ({}/{})",
+                            e,
+                            wait,
+                            attempt,
+                            self.max_retries
+                        );
+                        discard_partials();
+                        tokio::time::sleep(wait).await;
+                        continue;
+                    }
+                }
+            }
+
+            let reply_chars = complete
+                .as_ref()
+                .filter(|response| result.is_ok() && !response.is_error())
+                .map(|response| response.content.trim().chars().count());
+            match reply_chars {
+                Some(chars) if chars < self.min_reply_chars && attempt < self.max_retries => {
+                    attempt += 1;
+                    tracing::warn!(
+                        "Reply too short ({} < {} chars), retrying ({}/{})",
+                        chars,
+                        self.min_reply_chars,
+                        attempt,
+                        self.max_retries
+                    );
+                    discard_partials();
+                    prompt = format!(
+                        "{}\n\n注意：上一次回复只有 {} 个字符，内容不完整。请严格按照模板输出完整的代码。",
+                        text, chars
+                    );
+                }
+                _ => {
+                    if let Some(response) = complete {
+                        let _ = response_sender.send(response);
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// 向指定后端发送一次请求，处理超时并记录失败请求
+    async fn send_once(
+        &self,
+        backend: &dyn LLMBackend,
+        text: String,
+        image_paths: &[&Path],
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        tracing::info!("Sending message to LLM backend: {}", backend.provider());
+        let text_for_log = self.failed_request_logger.is_some().then(|| text.clone());
+        let timeout_sender = response_sender.clone();
+        let result = send_with_timeout(
+            backend.timeout(),
+            &timeout_sender,
+            backend.send_message_with_images(text, image_paths, response_sender),
+        )
+        .await;
+
+        // 请求失败时记录请求体
+        if let (Err(e), Some(logger), Some(text)) =
+            (&result, &self.failed_request_logger, text_for_log)
+        {
+            let mut snapshot = backend.request_snapshot(&text, image_paths.first().copied());
+            if let Some(object) = snapshot.as_object_mut() {
+                object.insert("error".to_string(), e.to_string().into());
+            }
+            if let Err(err) = logger.dump(snapshot) {
+                tracing::warn!("Failed to dump failed request: {}", err);
+            }
+        }
+        result
+    }
+
+    /// 获取当前后端的可用模型列表
+    pub async fn list_current_backend_models(&self) -> Result<Vec<String>, Error> {
         if let Some(backend) = self.current_backend() {
-            tracing::info!("Sending message to LLM backend: {}", backend.provider());
-            backend.send_message(text, image_path, response_sender).await
+            backend.list_models().await
         } else {
             Err(Error::Stream("No backend available".into()))
         }
     }
 
     /// 测试当前后端可用性
-    pub async fn test_current_backend(&self) -> Result<String, Error> {
+    pub async fn test_current_backend(&self) -> Result<(String, Option<TokenUsage>), Error> {
         if let Some(backend) = self.current_backend() {
-            backend.test_availability().await
+            with_timeout(backend.timeout(), backend.test_availability_with_usage()).await
         } else {
             Err(Error::Stream("No backend available".into()))
         }
     }
 }
 
+/// 后端健康检查的结果，启动和设置变更后在后台刷新
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BackendHealth {
+    /// 还没有检查
+    #[default]
+    Unknown,
+    /// 正在检查
+    Checking,
+    /// 后端可用，`latency_ms` 为测试请求的往返耗时
+    Healthy { latency_ms: u128 },
+    /// 后端不可用，附带错误信息
+    Unreachable(String),
+}
+
+impl BackendHealth {
+    /// 用当前后端发送一次测试请求
+    pub async fn check(manager: &LLMManager) -> Self {
+        let started = std::time::Instant::now();
+        match manager.test_current_backend().await {
+            Ok(_) => BackendHealth::Healthy {
+                latency_ms: started.elapsed().as_millis(),
+            },
+            Err(e) => {
+                tracing::warn!("Backend health check failed: {}", e);
+                BackendHealth::Unreachable(e.to_string())
+            }
+        }
+    }
+
+    /// 界面指示灯使用的状态：`unknown`、`checking`、`ok` 或 `error`
+    pub fn status(&self) -> &'static str {
+        match self {
+            BackendHealth::Unknown => "unknown",
+            BackendHealth::Checking => "checking",
+            BackendHealth::Healthy { .. } => "ok",
+            BackendHealth::Unreachable(_) => "error",
+        }
+    }
+
+    /// 指示灯旁显示的说明
+    pub fn summary(&self) -> String {
+        match self {
+            BackendHealth::Unknown => "后端未检查".to_string(),
+            BackendHealth::Checking => "正在检查后端…".to_string(),
+            BackendHealth::Healthy { latency_ms } => format!("后端可用（{}ms）", latency_ms),
+            BackendHealth::Unreachable(e) => format!("后端不可用: {}", e),
+        }
+    }
+}
+
 impl Default for LLMManager {
     fn default() -> Self {
         // 使用默认配置创建管理器
@@ -174,9 +889,192 @@ impl Default for LLMManager {
 }
 
 
+/// 在本地端口上接收一个 HTTP 请求并以 `body`（JSON）回复，返回监听地址和读取收到的原始请求的线程
+///
+/// 用于检查后端实际发出的请求（请求行、请求头），也可以作为 HTTP 代理的地址使用。
+#[cfg(test)]
+pub(crate) fn serve_once(body: &str) -> (std::net::SocketAddr, std::thread::JoinHandle<String>) {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // 读完请求头和 content-length 指定长度的请求体后再回复
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let content_length = text[..end]
+                    .lines()
+                    .find_map(|line| {
+                        line.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .and_then(|value| value.trim().parse::<usize>().ok())
+                    })
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8_lossy(&request).into_owned()
+    });
+    (addr, handle)
+}
+
+/// 模拟后端的回复函数：参数为提示词和图片路径，返回完整回复或错误
+#[cfg(test)]
+type MockReply = Box<
+    dyn Fn(String, Option<std::path::PathBuf>) -> std::pin::Pin<Box<dyn Future<Output = Result<String, Error>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// 模拟后端收到的请求：提示词和图片路径
+#[cfg(test)]
+pub(crate) type MockRequests = std::sync::Arc<std::sync::Mutex<Vec<(String, Option<std::path::PathBuf>)>>>;
+
+/// 测试用的模拟后端，每次请求调用 `reply` 得到回复，收到的提示词和图片都会记录下来
+///
+/// 回复作为一条完成响应发出；`reply` 返回错误时和真实后端一样发出 `Error: ...` 的完成响应并返回错误。
+/// 测试可用性时以 `"ping"` 调用 `reply`，不计入请求记录。
+#[cfg(test)]
+pub(crate) struct MockBackend {
+    provider: LLMProvider,
+    timeout: Duration,
+    api_key: Option<String>,
+    reply: MockReply,
+    requests: MockRequests,
+    stream_partial: bool,
+}
+
+#[cfg(test)]
+impl MockBackend {
+    /// 按 `reply(提示词, 图片)` 同步返回回复的模拟后端
+    pub(crate) fn new(reply: impl Fn(&str, Option<&Path>) -> Result<String, Error> + Send + Sync + 'static) -> Self {
+        Self::new_async(move |text, image_path| std::future::ready(reply(&text, image_path.as_deref())))
+    }
+
+    /// 回复需要等待（延迟或永不返回）的模拟后端
+    pub(crate) fn new_async<F>(reply: impl Fn(String, Option<std::path::PathBuf>) -> F + Send + Sync + 'static) -> Self
+    where
+        F: Future<Output = Result<String, Error>> + Send + 'static,
+    {
+        Self {
+            provider: LLMProvider::GPT,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            api_key: None,
+            reply: Box::new(move |text, image_path| Box::pin(reply(text, image_path))),
+            requests: Default::default(),
+            stream_partial: false,
+        }
+    }
+
+    /// 总是返回同一回复的模拟后端
+    pub(crate) fn fixed(reply: &str) -> Self {
+        let reply = reply.to_string();
+        Self::new(move |_, _| Ok(reply.clone()))
+    }
+
+    pub(crate) fn with_provider(mut self, provider: LLMProvider) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    pub(crate) fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 请求快照中带上的密钥，用于检查脱敏
+    pub(crate) fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// 成功的回复先作为一条中间结果发出，再发出完成响应
+    pub(crate) fn with_stream_partial(mut self) -> Self {
+        self.stream_partial = true;
+        self
+    }
+
+    /// 收到的请求（提示词和图片），加入管理器前取出
+    pub(crate) fn requests(&self) -> MockRequests {
+        self.requests.clone()
+    }
+}
+
+#[cfg(test)]
+#[async_trait::async_trait]
+impl LLMBackend for MockBackend {
+    fn provider(&self) -> LLMProvider {
+        self.provider.clone()
+    }
+
+    fn model_name(&self) -> &str {
+        "mock-model"
+    }
+
+    async fn send_message(
+        &self,
+        text: String,
+        image_path: Option<&Path>,
+        response_sender: mpsc::Sender<LLMResponse>,
+    ) -> Result<(), Error> {
+        let image_path = image_path.map(Path::to_path_buf);
+        self.requests.lock().unwrap().push((text.clone(), image_path.clone()));
+        let result = (self.reply)(text, image_path).await;
+        let content = match &result {
+            Ok(reply) => reply.clone(),
+            Err(e) => format!("Error: {}", e),
+        };
+        if self.stream_partial && result.is_ok() {
+            let _ = response_sender.send(LLMResponse { content: content.clone(), is_complete: false, reasoning: None });
+        }
+        let _ = response_sender.send(LLMResponse { content, is_complete: true, reasoning: None });
+        result.map(|_| ())
+    }
+
+    async fn test_availability(&self) -> Result<String, Error> {
+        (self.reply)("ping".to_string(), None).await
+    }
+
+    fn request_snapshot(&self, text: &str, image_path: Option<&Path>) -> serde_json::Value {
+        serde_json::json!({
+            "provider": self.provider.to_string(),
+            "api_key": self.api_key,
+            "body": super::request_logger::chat_body_with_images(
+                "mock-model",
+                "",
+                text,
+                image_path.map(|_| "data:image/png;base64,AAAA").as_slice(),
+                true,
+            ),
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
 
     #[tokio::test]
     async fn test_llm_manager() {
@@ -202,5 +1100,381 @@ mod tests {
         
         println!("✅ LLM Manager tests passed!");
     }
-}
 
+    #[test]
+    fn test_parse_usage() {
+        let openai = serde_json::json!({
+            "choices": [],
+            "usage": { "prompt_tokens": 20, "completion_tokens": 7, "total_tokens": 27 }
+        });
+        assert_eq!(
+            parse_usage(&openai),
+            Some(TokenUsage { prompt_tokens: 20, completion_tokens: 7 })
+        );
+
+        let gemini = serde_json::json!({
+            "usageMetadata": { "promptTokenCount": 9, "candidatesTokenCount": 3 }
+        });
+        assert_eq!(
+            parse_usage(&gemini),
+            Some(TokenUsage { prompt_tokens: 9, completion_tokens: 3 })
+        );
+
+        assert_eq!(parse_usage(&serde_json::json!({ "usage": null })), None);
+        assert_eq!(parse_usage(&serde_json::json!({ "choices": [] })), None);
+    }
+
+    #[test]
+    fn test_vision_model_allowlist() {
+        assert!(is_vision_model("gpt-4o"));
+        assert!(is_vision_model("openai/gpt-4o-mini"));
+        assert!(is_vision_model("GPT-4.1"));
+        assert!(is_vision_model("o1"));
+        assert!(!is_vision_model("o1-mini"));
+        assert!(!is_vision_model("gpt-3.5-turbo"));
+        assert!(!is_vision_model("deepseek/DeepSeek-R1"));
+
+        assert!(crate::core::github_backend::GitHubBackend::new("gpt-4o".to_string()).supports_vision());
+        assert!(!crate::core::gpt_backend::GPTBackend::new("Meta-Llama-3.1-8B-Instruct".to_string()).supports_vision());
+    }
+
+    #[test]
+    fn test_provider_timeout_overrides_global_timeout() {
+        use crate::app::llm_settings::LLMConfig;
+
+        let mut config = LLMConfig {
+            provider: "GitHub".to_string(),
+            timeout_secs: 45,
+            ..LLMConfig::default()
+        };
+        config.provider_timeouts.insert("GitHub".to_string(), 300);
+
+        let manager = LLMManager::from_config(&config);
+        let backend = manager.current_backend().unwrap();
+        assert_eq!(backend.provider(), LLMProvider::GitHub);
+        assert_eq!(backend.timeout(), Duration::from_secs(300));
+
+        // 未单独配置的提供商使用全局超时
+        config.provider = "GPT".to_string();
+        let manager = LLMManager::from_config(&config);
+        assert_eq!(manager.current_backend().unwrap().timeout(), Duration::from_secs(45));
+    }
+
+    #[test]
+    fn test_max_tokens_and_temperature_from_config() {
+        use crate::app::llm_settings::LLMConfig;
+
+        let default_body = LLMManager::from_config(&LLMConfig::default())
+            .current_backend()
+            .unwrap()
+            .request_snapshot("hi", None)["body"]
+            .clone();
+        assert_eq!(default_body["max_tokens"], DEFAULT_MAX_TOKENS);
+
+        for provider in ["GPT", "GitHub", "Gemini", "Custom"] {
+            let config = LLMConfig {
+                provider: provider.to_string(),
+                max_tokens: 8000,
+                temperature: 0.7,
+                ..LLMConfig::default()
+            };
+            let manager = LLMManager::from_config(&config);
+            let backend = manager.current_backend().unwrap();
+            assert_eq!(backend.provider().to_string(), provider);
+            let body = backend.request_snapshot("hi", None)["body"].clone();
+            // Gemini 的参数放在 generationConfig 中
+            let (max_tokens, temperature) = match provider {
+                "Gemini" => (
+                    &body["generationConfig"]["maxOutputTokens"],
+                    &body["generationConfig"]["temperature"],
+                ),
+                _ => (&body["max_tokens"], &body["temperature"]),
+            };
+            assert_eq!(*max_tokens, 8000, "{}", provider);
+            assert_eq!(temperature.as_f64().map(|t| t as f32), Some(0.7), "{}", provider);
+        }
+    }
+
+    #[test]
+    fn test_custom_provider_registered_from_config() {
+        use crate::app::llm_settings::LLMConfig;
+
+        let config = LLMConfig {
+            provider: "Custom".to_string(),
+            model: "llama3".to_string(),
+            base_url: Some("http://fake.local:11434/v1".to_string()),
+            ..LLMConfig::default()
+        };
+        let manager = LLMManager::from_config(&config);
+        let backend = manager.current_backend().unwrap();
+        assert_eq!(backend.provider(), LLMProvider::Custom);
+        assert_eq!(backend.model_name(), "llama3");
+        assert_eq!(
+            backend.request_snapshot("hi", None)["base_url"],
+            "http://fake.local:11434/v1/chat/completions"
+        );
+
+        // 未选择自定义提供商时不注册
+        let config = LLMConfig { provider: "GPT".to_string(), ..config };
+        let manager = LLMManager::from_config(&config);
+        assert!(manager.list_backends().iter().all(|(_, provider, _)| *provider != LLMProvider::Custom));
+    }
+
+    #[test]
+    fn test_parse_models_response() {
+        let openai = r#"{
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o-mini", "object": "model", "owned_by": "openai"},
+                {"id": "gpt-4o", "object": "model", "owned_by": "openai"}
+            ]
+        }"#;
+        assert_eq!(parse_models_response(openai).unwrap(), vec!["gpt-4o", "gpt-4o-mini"]);
+
+        let github = r#"[
+            {"id": "azureml://registries/azure-openai/models/gpt-4o/versions/2", "name": "gpt-4o", "friendly_name": "OpenAI GPT-4o"},
+            {"name": "Meta-Llama-3.1-8B-Instruct"}
+        ]"#;
+        assert_eq!(
+            parse_models_response(github).unwrap(),
+            vec!["Meta-Llama-3.1-8B-Instruct", "gpt-4o"]
+        );
+
+        assert!(parse_models_response(r#"{"error": "unauthorized"}"#).is_err());
+        assert!(parse_models_response("not json").is_err());
+    }
+
+    #[test]
+    fn test_model_override_takes_precedence() {
+        use crate::app::llm_settings::LLMConfig;
+
+        let mut config = LLMConfig {
+            model: "gpt-4o-mini".to_string(),
+            ..LLMConfig::default()
+        };
+        config
+            .model_overrides
+            .insert(QuestionType::Reading.as_str().to_string(), "gpt-4o".to_string());
+
+        let manager = LLMManager::manager_for_question_type(&config, QuestionType::Reading);
+        assert_eq!(manager.current_backend().unwrap().model_name(), "gpt-4o");
+
+        let manager = LLMManager::manager_for_question_type(&config, QuestionType::SingleChoice);
+        assert_eq!(manager.current_backend().unwrap().model_name(), "gpt-4o-mini");
+    }
+
+    #[tokio::test]
+    async fn test_hanging_backend_times_out() {
+        let mut manager = LLMManager::new();
+        let backend = MockBackend::new_async(|_, _| std::future::pending())
+            .with_provider(LLMProvider::GitHub)
+            .with_timeout(Duration::from_millis(200));
+        manager.add_backend(Box::new(backend));
+
+        let (sender, receiver) = mpsc::channel();
+        let error = manager
+            .send_message("题干".to_string(), None, sender)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("request timed out after 200ms"));
+
+        // UI 会收到一条错误的完成响应，从而结束“正在生成”状态
+        let response = receiver.try_recv().unwrap();
+        assert!(response.is_complete);
+        assert!(response.is_error());
+        assert!(response.content.contains("request timed out after 200ms"));
+
+        let error = manager.test_current_backend().await.unwrap_err();
+        assert!(error.to_string().contains("request timed out"));
+    }
+
+    #[test]
+    fn test_parse_rate_limit_response() {
+        // GitHub Models 的 429 响应体
+        let body = r#"{"error":{"code":"RateLimitReached","message":"Rate limit of 15 per 60s exceeded for UserByModelByMinute. Please wait 42 seconds before retrying.","details":"Rate limit of 15 per 60s exceeded for UserByModelByMinute. Please wait 42 seconds before retrying."}}"#;
+        assert_eq!(parse_retry_after(None, body), Some(Duration::from_secs(42)));
+        // Retry-After 请求头优先
+        assert_eq!(parse_retry_after(Some(" 7 "), body), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after(None, r#"{"error":{"message":"Too many requests","retryAfter":"12.5"}}"#),
+            Some(Duration::from_millis(12500))
+        );
+        assert_eq!(parse_retry_after(Some("Wed, 21 Oct 2015 07:28:00 GMT"), "{}"), None);
+        assert_eq!(parse_retry_after(None, "Too many requests"), None);
+
+        // async-llm 把状态码和响应体放在错误信息里
+        let error = Error::Stream(format!("ApiError: 429 Too Many Requests: {}", body));
+        let rate_limited = RateLimited::from_error(&error).unwrap();
+        assert_eq!(rate_limited.retry_after, Duration::from_secs(42));
+        assert_eq!(rate_limited.hint(), "请求过于频繁，请 42 秒后重试");
+
+        // 转换后的错误仍带 429 状态码，可以再次识别
+        let converted = with_rate_limit(error);
+        assert_eq!(converted.to_string(), Error::Stream("Rate limited (429), retry after 42s".into()).to_string());
+        assert_eq!(error_status(&converted), Some(429));
+        assert_eq!(RateLimited::from_error(&converted), Some(rate_limited));
+        assert_eq!(
+            RateLimited::from_message(&format!("Error: {}", converted)).map(|r| r.retry_secs()),
+            Some(42)
+        );
+        assert_eq!(RateLimited { retry_after: Duration::from_millis(12500) }.retry_secs(), 13);
+
+        // 其他状态码不视为限流
+        assert_eq!(RateLimited::from_message("Custom API returned 503: retry after 5 seconds"), None);
+    }
+
+    /// 发送一次消息，返回后端收到的请求次数和最终的完成响应
+    async fn send_with_status_failures(manager: LLMManager, failures: Vec<u16>) -> (u32, Vec<String>) {
+        let mut manager = manager;
+        manager.retry_delay = Duration::ZERO;
+        // 先按顺序返回指定状态码的错误，之后成功
+        let failures = Mutex::new(failures);
+        let backend = MockBackend::new(move |_, _| {
+            let mut failures = failures.lock().unwrap();
+            match (!failures.is_empty()).then(|| failures.remove(0)) {
+                Some(status) => Err(Error::Stream(format!(
+                    "Custom API returned {} <unknown status code>: origin error",
+                    status
+                ))),
+                None => Ok("var Questions = [];".to_string()),
+            }
+        })
+        .with_provider(LLMProvider::Custom);
+        let requests = backend.requests();
+        manager.add_backend(Box::new(backend));
+
+        let (sender, receiver) = mpsc::channel();
+        let _ = manager.send_message("题干".to_string(), None, sender).await;
+        let completed = receiver
+            .iter()
+            .filter(|response| response.is_complete)
+            .map(|response| response.content)
+            .collect();
+        let attempts = requests.lock().unwrap().len() as u32;
+        (attempts, completed)
+    }
+
+    #[tokio::test]
+    async fn test_configured_status_is_retried() {
+        assert_eq!(
+            error_status(&Error::Stream("Custom API returned 520 <unknown status code>: x".into())),
+            Some(520)
+        );
+        assert_eq!(error_status(&Error::Stream("request timed out after 500ms".into())), None);
+
+        // 默认不重试 520，错误直接返回给调用方
+        let (attempts, completed) = send_with_status_failures(LLMManager::new(), vec![520]).await;
+        assert_eq!(attempts, 1);
+        assert_eq!(completed.len(), 1);
+        assert!(completed[0].starts_with("Error"));
+
+        // 配置后 520 会被重试，调用方只收到成功的完成响应
+        let config = crate::app::llm_settings::LLMConfig {
+            retryable_status: vec![520],
+            ..Default::default()
+        };
+        let manager = LLMManager::new().with_retryable_status(config.retryable_status.clone());
+        let (attempts, completed) = send_with_status_failures(manager, vec![520]).await;
+        assert_eq!(attempts, 2);
+        assert_eq!(completed, vec!["var Questions = [];".to_string()]);
+
+        // 默认状态码仍然可重试
+        let manager = LLMManager::new().with_retryable_status(vec![520]);
+        let (attempts, _) = send_with_status_failures(manager, vec![503, 520]).await;
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_short_reply_is_retried() {
+        let full_reply = "var Questions = [{ stem: \"What is AI?\", answer: 0 }];";
+        let mut manager = LLMManager::new().with_min_reply_chars(20).with_max_retries(2);
+        let replies = Mutex::new(vec!["OK", full_reply]);
+        let backend = MockBackend::new(move |_, _| Ok(replies.lock().unwrap().remove(0).to_string()));
+        let requests = backend.requests();
+        manager.add_backend(Box::new(backend));
+
+        let (sender, receiver) = mpsc::channel();
+        manager
+            .send_message("阅读理解题干".to_string(), None, sender)
+            .await
+            .unwrap();
+
+        let prompts: Vec<String> = requests.lock().unwrap().iter().map(|(text, _)| text.clone()).collect();
+        assert_eq!(prompts.len(), 2);
+        assert_eq!(prompts[0], "阅读理解题干");
+        assert!(prompts[1].starts_with("阅读理解题干"));
+        assert!(prompts[1].contains("上一次回复只有 2 个字符"));
+
+        // 过短的回复不会作为完成响应发出
+        let completed: Vec<String> = receiver
+            .iter()
+            .filter(|response| response.is_complete)
+            .map(|response| response.content)
+            .collect();
+        assert_eq!(completed, vec![full_reply.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_partials_of_retried_attempt_are_reset() {
+        let full_reply = "var Questions = [{ stem: \"What is AI?\", answer: 0 }];";
+        let mut manager = LLMManager::new().with_min_reply_chars(20).with_max_retries(2);
+        let replies = Mutex::new(vec!["OK", full_reply]);
+        let backend = MockBackend::new(move |_, _| Ok(replies.lock().unwrap().remove(0).to_string())).with_stream_partial();
+        manager.add_backend(Box::new(backend));
+
+        let (sender, receiver) = mpsc::channel();
+        manager.send_message("题干".to_string(), None, sender).await.unwrap();
+
+        // 过短回复的中间结果之后紧跟清空事件，再显示重试的回复
+        let responses: Vec<(String, bool)> =
+            receiver.iter().map(|response| (response.content, response.is_complete)).collect();
+        assert_eq!(
+            responses,
+            vec![
+                ("OK".to_string(), false),
+                (String::new(), false),
+                (full_reply.to_string(), false),
+                (full_reply.to_string(), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_short_reply_is_kept_after_retry_cap() {
+        let mut manager = LLMManager::new().with_min_reply_chars(20).with_max_retries(1);
+        let replies = Mutex::new(vec!["OK", "Still short"]);
+        let backend = MockBackend::new(move |_, _| Ok(replies.lock().unwrap().remove(0).to_string()));
+        let requests = backend.requests();
+        manager.add_backend(Box::new(backend));
+
+        let (sender, receiver) = mpsc::channel();
+        manager.send_message("题干".to_string(), None, sender).await.unwrap();
+
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        let last = receiver.iter().last().unwrap();
+        assert!(last.is_complete);
+        assert_eq!(last.content, "Still short");
+    }
+
+    #[tokio::test]
+    async fn test_backend_health_reflects_backend() {
+        let mut manager = LLMManager::new();
+        manager.add_backend(Box::new(MockBackend::fixed("ok").with_provider(LLMProvider::Custom)));
+        let health = BackendHealth::check(&manager).await;
+        assert!(matches!(health, BackendHealth::Healthy { .. }));
+        assert_eq!(health.status(), "ok");
+
+        let mut manager = LLMManager::new();
+        let backend = MockBackend::new(|_, _| {
+            Err(Error::Stream("Custom API returned 401 Unauthorized: invalid api key".into()))
+        });
+        manager.add_backend(Box::new(backend.with_provider(LLMProvider::Custom)));
+        let health = BackendHealth::check(&manager).await;
+        assert_eq!(health.status(), "error");
+        assert!(health.summary().contains("401 Unauthorized"));
+
+        let health = BackendHealth::check(&LLMManager::new()).await;
+        assert_eq!(health.status(), "error");
+        assert_eq!(BackendHealth::default().status(), "unknown");
+    }
+}
@@ -1,19 +1,167 @@
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::OnceCell;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
-    EnvFilter,
+    EnvFilter, Registry,
     fmt::layer,
     layer::{SubscriberExt},
+    reload,
     util::SubscriberInitExt,
 };
 
+use super::utility;
+
+/// 未设置 `RUST_LOG` 且配置中没有指定时使用的日志级别
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// 日志文件按天滚动，最多保留的文件数
+const MAX_LOG_FILES: usize = 7;
+
+/// 初始化后用于按配置调整日志级别
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+/// 日志文件目录 `<配置目录>/logs`
+pub fn log_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(utility::app_config_dir()?.join("logs"))
+}
+
 pub fn init() {
-    tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+    init_with_writer(std::io::stdout);
+}
+
+/// 日志写到标准错误，命令行模式下标准输出只保留转换结果
+pub fn init_stderr() {
+    init_with_writer(std::io::stderr);
+}
+
+/// 按配置设置日志级别（如 `debug` 或 `question_tool=trace`），设置了 `RUST_LOG` 时以环境变量为准
+pub fn set_level(level: &str) {
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_some() {
+        return;
+    }
+    let Some(handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+    if let Err(e) = reload_level(handle, level) {
+        tracing::warn!("[logger] Failed to change log level to {:?}: {}", level, e);
+    }
+}
+
+/// 解析日志级别并替换 `handle` 对应的过滤器，之后的日志立即按新级别过滤
+fn reload_level<S>(handle: &reload::Handle<EnvFilter, S>, level: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(level).map_err(|e| e.to_string())?;
+    handle.reload(filter).map_err(|e| e.to_string())
+}
+
+fn init_with_writer<W>(writer: W)
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    match log_dir() {
+        Ok(dir) => init_in(writer, Some(&dir)),
+        Err(e) => {
+            init_in(writer, None);
+            tracing::warn!("[logger] Failed to locate log directory: {}", e);
+        }
+    }
+}
+
+/// 同时输出到控制台和 `log_dir` 中按天滚动的日志文件，日志目录不可用时只输出到控制台
+///
+/// Windows 发布版没有控制台窗口，日志文件是唯一能看到日志的地方。
+fn init_in<W>(writer: W, log_dir: Option<&Path>)
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    let (appender, error) = match log_dir.map(file_appender) {
+        Some(Ok(appender)) => (Some(appender), None),
+        Some(Err(e)) => (None, Some(e.to_string())),
+        None => (None, None),
+    };
+    let (filter, handle) = reload::Layer::new(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL)),
+    );
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
         .with(
             layer()
                 .with_file(true)
                 .with_line_number(true)
                 .with_thread_ids(true)
-                .with_target(false),
+                .with_target(false)
+                .with_writer(writer),
         )
-        .init();
+        .with(appender.map(|appender| {
+            layer()
+                .with_ansi(false)
+                .with_file(true)
+                .with_line_number(true)
+                .with_thread_ids(true)
+                .with_target(false)
+                .with_writer(appender)
+        }))
+        .try_init()
+        .is_ok();
+    if installed {
+        let _ = FILTER_HANDLE.set(handle);
+    }
+
+    match (error, log_dir) {
+        (Some(e), _) => tracing::warn!("[logger] File logging disabled: {}", e),
+        (None, Some(dir)) => tracing::info!("[logger] Writing logs to {}", dir.display()),
+        (None, None) => {}
+    }
+}
+
+/// 在 `dir` 中创建按天滚动的日志文件（question_tool.<日期>.log）
+fn file_appender(dir: &Path) -> Result<RollingFileAppender, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("question_tool")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_creates_log_dir() {
+        let dir = std::env::temp_dir()
+            .join(format!("question_tool_logs_{}", uuid::Uuid::new_v4()))
+            .join("logs");
+        assert!(!dir.exists());
+
+        init_in(std::io::sink, Some(&dir));
+        assert!(dir.is_dir());
+
+        let _ = std::fs::remove_dir_all(dir.parent().unwrap());
+    }
+
+    #[test]
+    fn test_reload_level_updates_active_filter() {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry().with(filter);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(tracing::enabled!(tracing::Level::INFO));
+            assert!(!tracing::enabled!(tracing::Level::DEBUG));
+
+            reload_level(&handle, "debug").unwrap();
+            assert!(tracing::enabled!(tracing::Level::DEBUG));
+
+            reload_level(&handle, "warn").unwrap();
+            assert!(!tracing::enabled!(tracing::Level::INFO));
+
+            // 无法解析的级别不改变当前过滤器
+            assert!(reload_level(&handle, "question_tool=loud").is_err());
+            assert!(tracing::enabled!(tracing::Level::WARN));
+            assert!(!tracing::enabled!(tracing::Level::INFO));
+        });
+    }
 }
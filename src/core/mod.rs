@@ -1,6 +1,23 @@
+pub mod batch;
+pub mod chat_completions;
 pub mod clipboard_monitor;
+pub mod custom_backend;
+pub mod export;
+pub mod fixture;
+pub mod gemini_backend;
 pub mod github_backend;
 pub mod gpt_backend;
+pub mod history;
 pub mod llm_backend;
 pub mod logger;
-pub mod question_type;
\ No newline at end of file
+pub mod parser;
+pub mod question_type;
+pub mod request_log;
+pub mod request_logger;
+pub mod request_queue;
+pub mod response_cache;
+pub mod selectors;
+pub mod stats;
+pub mod stream_util;
+pub mod translate;
+pub mod utility;
\ No newline at end of file
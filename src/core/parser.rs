@@ -0,0 +1,371 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::question_type::QuestionType;
+use super::utility::{self, ObjectProperty};
+
+/// 从模型输出中解析出的一道题目
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ParsedQuestion {
+    /// 题干，模板字符串会去掉首尾空白
+    pub stem: String,
+    pub options: Vec<String>,
+    /// 答案原值：选项索引、索引数组或文本，缺少时为 None（兼容补全题的 `answers` 字段）
+    pub answer: Option<serde_json::Value>,
+    pub analysis: String,
+}
+
+/// 解析模型输出失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// 题目还没有模型输出
+    NoOutput,
+    /// 既没有 `Questions` 数组，也没有 `newContent` 对象
+    MissingQuestions,
+    /// 第 `index` 个（从 0 开始）题目对象无法识别任何属性
+    InvalidObject { index: usize },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::NoOutput => write!(f, "还没有模型输出"),
+            ParseError::MissingQuestions => write!(f, "输出中缺少 Questions 数组"),
+            ParseError::InvalidObject { index } => write!(f, "第 {} 道题目的格式无法识别", index + 1),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// 题目字段应有的取值类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// 字符串（引号或模板字符串）
+    Text,
+    /// 从 0 开始的选项索引
+    Index,
+    /// 字符串数组
+    Texts,
+    /// 选项索引数组
+    Indexes,
+}
+
+impl FieldKind {
+    fn matches(self, js: &str, value: Range<usize>) -> bool {
+        let raw = js[value.clone()].trim();
+        let is_text = |raw: &str| raw.starts_with(['"', '\'', '`']);
+        let is_index = |raw: &str| raw.trim().parse::<u64>().is_ok();
+        let items = || {
+            let start = value.start + (js[value.clone()].len() - js[value.clone()].trim_start().len());
+            utility::array_items(js, start..value.end)
+        };
+        match self {
+            FieldKind::Text => is_text(raw),
+            FieldKind::Index => is_index(raw),
+            FieldKind::Texts => raw.starts_with('[') && items().into_iter().all(|item| is_text(&js[item])),
+            FieldKind::Indexes => raw.starts_with('[') && items().into_iter().all(|item| is_index(&js[item])),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldKind::Text => write!(f, "字符串"),
+            FieldKind::Index => write!(f, "选项索引（从 0 开始的整数）"),
+            FieldKind::Texts => write!(f, "字符串数组"),
+            FieldKind::Indexes => write!(f, "选项索引数组"),
+        }
+    }
+}
+
+/// 按题型校验输出结构时发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// 输出无法解析
+    Parse(ParseError),
+    /// 第 `index` 道题（从 0 开始）缺少必需字段
+    MissingField { index: usize, field: &'static str, expected: FieldKind },
+    /// 第 `index` 道题的字段类型不对
+    WrongType { index: usize, field: &'static str, expected: FieldKind },
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Parse(e) => write!(f, "{}", e),
+            SchemaError::MissingField { index, field, expected } => {
+                write!(f, "第 {} 道题缺少 {} 字段，应为{}", index + 1, field, expected)
+            }
+            SchemaError::WrongType { index, field, expected } => {
+                write!(f, "第 {} 道题的 {} 字段应为{}", index + 1, field, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// 各题型每道题必需的字段及其类型，没有固定结构的题型返回空
+pub fn required_fields(question_type: QuestionType) -> &'static [(&'static str, FieldKind)] {
+    use FieldKind::*;
+    match question_type {
+        // 完形填空的小题题干就是文章中的空，只有选项
+        QuestionType::ClozeTest => &[("options", Texts), ("answer", Index), ("analysis", Text)],
+        QuestionType::SingleChoice
+        | QuestionType::Reading
+        | QuestionType::ListeningSingle
+        | QuestionType::ListeningCompound => {
+            &[("stem", Text), ("options", Texts), ("answer", Index), ("analysis", Text)]
+        }
+        QuestionType::MutiTiankong | QuestionType::GeneralFill => {
+            &[("stem", Text), ("answer", Texts), ("analysis", Text)]
+        }
+        QuestionType::Matching => &[
+            ("stem", Text),
+            ("items", Texts),
+            ("options", Texts),
+            ("matches", Indexes),
+            ("analysis", Text),
+        ],
+        QuestionType::Ordering => &[("stem", Text), ("options", Texts), ("sequence", Indexes), ("analysis", Text)],
+        QuestionType::ShortAnswer => &[("stem", Text), ("answer", Text), ("points", Texts), ("analysis", Text)],
+        QuestionType::CompleteQuestion => &[("stem", Text), ("options", Texts), ("answers", Texts), ("analysis", Text)],
+        QuestionType::ClozeTestNote => &[],
+    }
+}
+
+/// 按题型校验模型输出中每道题的必需字段是否存在且类型正确
+///
+/// 返回校验通过的题目数量，有问题时返回全部错误。没有固定结构的题型直接通过。
+pub fn validate_schema(question_type: QuestionType, js: &str) -> Result<usize, Vec<SchemaError>> {
+    let fields = required_fields(question_type);
+    if fields.is_empty() {
+        return Ok(0);
+    }
+    let objects = utility::array_object_ranges(js, "Questions")
+        .or_else(|| declared_object(js, "newContent").map(|object| vec![object]))
+        .ok_or_else(|| vec![SchemaError::Parse(ParseError::MissingQuestions)])?;
+
+    let mut errors = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        let properties = utility::object_properties(js, object.clone());
+        if properties.is_empty() {
+            errors.push(SchemaError::Parse(ParseError::InvalidObject { index }));
+            continue;
+        }
+        for &(field, expected) in fields {
+            match properties.iter().find(|property| property.key == field) {
+                None => errors.push(SchemaError::MissingField { index, field, expected }),
+                Some(property) if !expected.matches(js, property.value_range.clone()) => {
+                    errors.push(SchemaError::WrongType { index, field, expected })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    if errors.is_empty() { Ok(objects.len()) } else { Err(errors) }
+}
+
+/// 把模型输出的 JS 解析为结构化的题目
+///
+/// 优先解析 `Questions` 数组，没有时把补全题的 `newContent = {...}` 对象当作一道题目。
+/// 兼容引号字符串和模板字符串题干、对象中的注释以及结尾多余的逗号。
+pub fn parse_questions(js: &str) -> Result<Vec<ParsedQuestion>, ParseError> {
+    let objects = utility::array_object_ranges(js, "Questions")
+        .or_else(|| declared_object(js, "newContent").map(|object| vec![object]))
+        .ok_or(ParseError::MissingQuestions)?;
+
+    objects
+        .into_iter()
+        .enumerate()
+        .map(|(index, object)| {
+            let properties = utility::object_properties(js, object);
+            if properties.is_empty() {
+                return Err(ParseError::InvalidObject { index });
+            }
+            Ok(parse_object(js, &properties))
+        })
+        .collect()
+}
+
+/// 查找 `name = {...}` 形式的对象声明，值不是对象时返回 None
+pub(crate) fn declared_object(js: &str, name: &str) -> Option<Range<usize>> {
+    let range = utility::find_declaration(js, name)?;
+    let declaration = &js[range.clone()];
+    let after_eq = &declaration[declaration.find('=')? + 1..];
+    let start = range.start + declaration.len() - after_eq.trim_start().len();
+    (js.as_bytes().get(start) == Some(&b'{')).then(|| start..utility::skip_value(js.as_bytes(), start))
+}
+
+fn parse_object(js: &str, properties: &[ObjectProperty<'_>]) -> ParsedQuestion {
+    let value = |key: &str| {
+        properties
+            .iter()
+            .find(|property| property.key == key)
+            .map(|property| &js[property.value_range.clone()])
+    };
+    let string = |key: &str| value(key).map(|raw| utility::js_string_value(raw).trim().to_string());
+
+    ParsedQuestion {
+        stem: string("stem").unwrap_or_default(),
+        options: properties
+            .iter()
+            .find(|property| property.key == "options")
+            .map(|property| string_items(js, property.value_range.clone()))
+            .unwrap_or_default(),
+        answer: value("answer").or_else(|| value("answers")).map(|raw| parse_answer(raw.trim())),
+        analysis: string("analysis").unwrap_or_default(),
+    }
+}
+
+fn string_items(js: &str, array: Range<usize>) -> Vec<String> {
+    utility::array_string_items(js, array)
+        .into_iter()
+        .map(|item| utility::js_string_value(&js[item]))
+        .collect()
+}
+
+/// 解析答案：JSON 值原样保留，单引号或模板字符串数组转换为字符串数组
+fn parse_answer(raw: &str) -> serde_json::Value {
+    if let Ok(value) = serde_json::from_str(raw) {
+        return value;
+    }
+    if raw.starts_with('[') {
+        return serde_json::Value::Array(
+            string_items(raw, 0..raw.len())
+                .into_iter()
+                .map(serde_json::Value::String)
+                .collect(),
+        );
+    }
+    serde_json::Value::String(utility::js_string_value(raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::question_type::{PromptTemplate, Question, QuestionType};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_prompt_samples() {
+        let parse = |question_type| parse_questions(&PromptTemplate::new(question_type).get_prompt());
+
+        let questions = parse(QuestionType::SingleChoice).unwrap();
+        assert_eq!(questions.len(), 1);
+        assert!(questions[0].stem.starts_with("Which of the following is a <span"));
+        assert_eq!(questions[0].options, vec!["Python", "HTML", "CSS", "HTTP"]);
+        assert_eq!(questions[0].answer, Some(json!(0)));
+        assert!(questions[0].analysis.starts_with("考点：编程语言识别"));
+
+        let questions = parse(QuestionType::Reading).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].stem, "");
+        assert_eq!(questions[1].options[0], "Artificial Intelligence");
+        assert_eq!(questions[1].options.len(), 4);
+
+        let questions = parse(QuestionType::ClozeTest).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[1].answer, Some(json!(0)));
+
+        let questions = parse(QuestionType::ListeningSingle).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].answer, Some(json!(1)));
+
+        // 对象之间缺少逗号时也能识别
+        assert_eq!(parse(QuestionType::ListeningCompound).unwrap().len(), 2);
+
+        let questions = parse(QuestionType::MutiTiankong).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[1].answer, Some(json!(["Paris"])));
+        assert!(questions[1].analysis.contains("故答案为：Paris"));
+
+        let questions = parse(QuestionType::GeneralFill).unwrap();
+        assert_eq!(questions[0].answer, Some(json!(["John", "Canada"])));
+        assert!(questions[0].stem.starts_with("Good morning"));
+
+        let questions = parse(QuestionType::Matching).unwrap();
+        assert_eq!(questions[0].options.len(), 4);
+
+        let questions = parse(QuestionType::CompleteQuestion).unwrap();
+        assert_eq!(questions.len(), 1);
+        assert!(questions[0].stem.starts_with("<p style="));
+        assert_eq!(questions[0].answer, Some(json!(["A", "E"])));
+        assert_eq!(questions[0].options.len(), 5);
+
+        assert_eq!(parse(QuestionType::ClozeTestNote), Err(ParseError::MissingQuestions));
+    }
+
+    #[test]
+    fn test_parse_quotes_and_trailing_commas() {
+        let js = r#"var Questions = [
+    {
+        stem: `  Template stem  `,
+        'options': ['It\'s A', `B`, "C",],
+        answer: ['x', 'y',],
+        analysis: '考点：...',
+    },
+    { "stem": "Second", "answer": 2, },
+];"#;
+        let questions = parse_questions(js).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].stem, "Template stem");
+        assert_eq!(questions[0].options.len(), 3);
+        assert_eq!(questions[0].answer, Some(json!(["x", "y"])));
+        assert_eq!(questions[0].analysis, "考点：...");
+        assert_eq!(questions[1].answer, Some(json!(2)));
+        assert!(questions[1].options.is_empty());
+
+        assert_eq!(
+            parse_questions("var Questions = [{ 1 + 1 }];"),
+            Err(ParseError::InvalidObject { index: 0 })
+        );
+
+        let mut question = Question::new(QuestionType::SingleChoice, String::new(), None);
+        assert_eq!(question.parsed_output(), Err(ParseError::NoOutput));
+        question.set_model_reply(js.to_string());
+        assert_eq!(question.parsed_output().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let single_choice = r#"var Questions = [{
+    "stem": "Pick one",
+    "options": ["A", 'B'],
+    "answer": 1, // B
+    "analysis": `故答案为：B`
+}];"#;
+        assert_eq!(validate_schema(QuestionType::SingleChoice, single_choice), Ok(1));
+
+        let mut question = Question::new(QuestionType::SingleChoice, String::new(), None);
+        assert_eq!(question.validate_output(), Err(vec![SchemaError::Parse(ParseError::NoOutput)]));
+        question.set_model_reply(single_choice.to_string());
+        assert_eq!(question.validate_output(), Ok(1));
+
+        // 多空题缺少 answer 数组，答案写成了字符串的也要指出
+        let multi_blank = r#"var Questions = [
+    { "stem": "The capital of France is ____.", "analysis": "故答案为：Paris" },
+    { "stem": "1 + 1 = ____", "answer": "2", "analysis": "故答案为：2" },
+];"#;
+        let errors = validate_schema(QuestionType::MutiTiankong, multi_blank).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                SchemaError::MissingField { index: 0, field: "answer", expected: FieldKind::Texts },
+                SchemaError::WrongType { index: 1, field: "answer", expected: FieldKind::Texts },
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "第 1 道题缺少 answer 字段，应为字符串数组");
+
+        // 单选题的答案是选项索引，不能是数组
+        assert!(validate_schema(QuestionType::SingleChoice, multi_blank).is_err());
+        assert_eq!(
+            validate_schema(QuestionType::Matching, "var x = 1;"),
+            Err(vec![SchemaError::Parse(ParseError::MissingQuestions)])
+        );
+        assert_eq!(validate_schema(QuestionType::ClozeTestNote, ""), Ok(0));
+    }
+}
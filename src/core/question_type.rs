@@ -1,8 +1,370 @@
-use std::{path::PathBuf, str::FromStr};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use uuid::Uuid;
 
+use super::parser::{self, ParseError, ParsedQuestion, SchemaError};
+use super::selectors::SelectorConfig;
+use super::utility;
+
+/// 题干默认最大字符数
+pub const DEFAULT_MAX_STEM_CHARS: usize = 8000;
+
+/// 同时发送原图和预处理后的图片时加在提示词前的说明
+pub const BOTH_IMAGES_NOTE: &str = "我附上了同一页题目的两个版本：第一张是原图，第二张是经过灰度和对比度增强的图片。\
+请对照两张图片识别题目内容，以文字更清晰的一张为准，不要把它们当作两道不同的题目。\n";
+
+/// 生成题目时使用的设置，由当前配置构建后传给 [`Question::new_with_params`] 和 [`PromptTemplate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptOptions {
+    /// 题干最大字符数，0 表示不限制
+    pub max_stem_chars: usize,
+    /// 是否合并 OCR 在行尾用连字符断开的单词
+    pub dehyphenate_stem: bool,
+    /// 提示词要求的解析顺序
+    pub analysis_order: AnalysisOrder,
+    /// 文章段落 `<p>` 的样式，为空表示使用默认样式
+    pub paragraph_style: String,
+    /// 每个提示词预先生成的 data-blank-id 数量，0 表示由模型自行编写
+    pub blank_id_pool_size: usize,
+    /// 提示词说明文字的语言
+    pub language: PromptLanguage,
+    /// 生成附加代码使用的设置
+    pub scripts: ScriptOptions,
+}
+
+impl PromptOptions {
+    /// 实际使用的文章段落样式，未设置时为默认样式
+    pub fn paragraph_style(&self) -> &str {
+        match self.paragraph_style.trim() {
+            "" => DEFAULT_PARAGRAPH_STYLE,
+            style => style,
+        }
+    }
+}
+
+impl Default for PromptOptions {
+    fn default() -> Self {
+        Self {
+            max_stem_chars: DEFAULT_MAX_STEM_CHARS,
+            dehyphenate_stem: false,
+            analysis_order: AnalysisOrder::default(),
+            paragraph_style: DEFAULT_PARAGRAPH_STYLE.to_string(),
+            blank_id_pool_size: 0,
+            language: PromptLanguage::default(),
+            scripts: ScriptOptions::default(),
+        }
+    }
+}
+
+/// 解析各部分的排列顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AnalysisOrder {
+    /// 考点，分析，故答案为
+    #[default]
+    ConceptFirst,
+    /// 故答案为，考点，分析
+    AnswerFirst,
+}
+
+/// 先给答案时模板里解析格式说明的改写
+const ANSWER_FIRST_FORMATS: &[(&str, &str)] = &[
+    ("格式：考点，分析，故答案为", "格式：故答案为，考点，分析"),
+    ("格式要分为：考点，分析，故答案为：", "格式要分为：故答案为，考点，分析："),
+    ("格式要分为：原文，分析，故答案为：", "格式要分为：故答案为，原文，分析："),
+];
+
+impl AnalysisOrder {
+    /// 按解析顺序改写模板里的格式说明和示例解析，默认顺序原样返回
+    pub fn apply(&self, prompt: String) -> String {
+        match self {
+            AnalysisOrder::ConceptFirst => prompt,
+            AnalysisOrder::AnswerFirst => {
+                let prompt = ANSWER_FIRST_FORMATS
+                    .iter()
+                    .fold(prompt, |prompt, (concept_first, answer_first)| prompt.replace(concept_first, answer_first));
+                prompt.lines().map(answer_first_example).collect::<Vec<_>>().join("\n")
+            }
+        }
+    }
+}
+
+/// 把一行里 analysis 示例的 “故答案为” 挪到最前面
+fn answer_first_example(line: &str) -> String {
+    // 兼容 analysis: "…" 和 "analysis": "…" 两种写法
+    let value = line
+        .find("analysis")
+        .map(|key| &line[key + "analysis".len()..])
+        .map(|rest| rest.strip_prefix('"').unwrap_or(rest))
+        .and_then(|rest| rest.strip_prefix(':'))
+        .and_then(|rest| rest.trim_start().strip_prefix('"'));
+    let Some(value) = value else {
+        return line.to_string();
+    };
+    let open = line.len() - value.len();
+    let Some(close) = value.find('"').map(|i| open + i) else {
+        return line.to_string();
+    };
+    let analysis = &line[open..close];
+    if !analysis.contains("故答案为：") {
+        return line.to_string();
+    }
+    let reordered = analysis.split("<br>").map(answer_first_item).collect::<Vec<_>>().join("<br>");
+    format!("{}{}{}", &line[..open], reordered, &line[close..])
+}
+
+/// 改写单个小题的解析，保留序号和小题间的逗号
+fn answer_first_item(item: &str) -> String {
+    let Some(pos) = item.find("故答案为：") else {
+        return item.to_string();
+    };
+    let number_len = item
+        .find(". ")
+        .filter(|&i| i > 0 && item[..i].chars().all(|c| c.is_ascii_digit()))
+        .map_or(0, |i| i + 2);
+    let (number, body) = (&item[..number_len], item[number_len..pos].trim_end());
+    let answer = item[pos..].trim_end();
+    let (answer, separator) = match answer.strip_suffix(',') {
+        Some(answer) => (answer, ","),
+        None => (answer, ""),
+    };
+    format!("{}{}。{}{}", number, answer.trim_end_matches('。'), body, separator)
+}
+
+/// 文章段落的默认样式：两端对齐，首行缩进
+pub const DEFAULT_PARAGRAPH_STYLE: &str = "text-align: justify; text-indent: 2em;";
+
+/// 保存按钮默认最多点击的次数
+pub const DEFAULT_SAVE_RETRY_ATTEMPTS: usize = 3;
+
+/// 两次点击保存按钮之间默认的等待时间（毫秒）
+pub const DEFAULT_SAVE_RETRY_DELAY_MS: u64 = 1000;
+
+/// 提示词中说明文字使用的语言，JS 代码骨架和字段名不受影响
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PromptLanguage {
+    /// 中文说明
+    #[default]
+    Chinese,
+    /// 英文说明，适合对英文指令更敏感的模型
+    English,
+}
+
+/// 提示词模板中的中文说明及其英文译文
+///
+/// 按顺序替换，较长的说明要排在它包含的较短说明之前。
+const ENGLISH_INSTRUCTIONS: &[(&str, &str)] = &[
+    (
+        "请你把我给你的题目转换成如下格式的 JavaScript，格式如下：",
+        "Convert the question I give you into JavaScript in the following format:",
+    ),
+    ("输出模式如下：", "Output format:"),
+    (
+        "//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，不要带有```javascript ```。只输出代码就可以了。我不用代码块包裹",
+        "//Output only JavaScript code in the following format and nothing else. Do not wrap it in ```javascript ```; output the bare code only",
+    ),
+    (
+        "//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹.你要分成多个{}去写",
+        "//Output only JavaScript code in the following format and nothing else. Do not wrap it in ```javascript ```; output the bare code only. Write each question as a separate {}",
+    ),
+    (
+        "//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹",
+        "//Output only JavaScript code in the following format and nothing else. Do not wrap it in ```javascript ```; output the bare code only",
+    ),
+    (
+        "//输出下面的js格式的代码。你不用给我答案和其它，只给我js代码就行。我也不用 ```javascript ```代码块包裹。只要js代码就行",
+        "//Output JavaScript code in the format below. Do not give answers or anything else, only the code. Do not wrap it in ```javascript ```",
+    ),
+    (
+        "//正文中 中英文之间请保持空格。如grammars (语法) and",
+        "//In the passage, keep a space between English words and Chinese text",
+    ),
+    (
+        "// 完形填空模板，段落两端对齐，首行缩进，字体字号不变",
+        "// Cloze template: justify paragraphs, indent the first line, keep the font and font size",
+    ),
+    (
+        "// 模板，段落两端对齐，首行缩进，字体字号不变",
+        "// Template: justify paragraphs, indent the first line, keep the font and font size",
+    ),
+    (
+        "//段落两端对齐，首行缩进，字体字号不变。",
+        "//Justify paragraphs, indent the first line, keep the font and font size.",
+    ),
+    (
+        "// 在OCR时，注意把试卷中的不相关内容去掉，避免干扰",
+        "// When doing OCR, remove anything on the paper that is not part of the question",
+    ),
+    (
+        "// 字体和字大小要和此模板一致，不要改变",
+        "// Keep the font and font size exactly as in this template",
+    ),
+    (
+        "//如果原来的题目有表格。请用html的table标签来表示表格。但是要正式",
+        "//If the original question has a table, write it with an HTML table tag, neatly formatted",
+    ),
+    (
+        "//如果有表格，那就用 Html 的表格语法来写。如果有个框，这个框要用html的一个行一个列的表格来画。",
+        "//Write tables with HTML table syntax. Draw a box as an HTML table with one row and one column.",
+    ),
+    (
+        "//如果有表格，那就用 Html 的表格语法来写。如果有框，这个框要用html的一个行一个列的表格来画。",
+        "//Write tables with HTML table syntax. Draw a box as an HTML table with one row and one column.",
+    ),
+    ("//do not 带有```javascript ```", "//do not add ```javascript ```"),
+    ("//注意段落和换行", "//Keep the paragraphs and line breaks"),
+    (
+        "// !!! 注意：'answer' 字段是 从0开始的数字索引 (0=A, 1=B, 2=C) !!!",
+        "// !!! NOTE: the 'answer' field is a zero-based index (0=A, 1=B, 2=C) !!!",
+    ),
+    (
+        "// 通用示例题目数据，适用于各类阅读理解或单选题",
+        "// Generic example data, suitable for reading comprehension and single choice questions",
+    ),
+    (
+        "// 完形填空题目数据，每个对象包含选项和答案索引",
+        "// Cloze question data, each object holds the options and the answer index",
+    ),
+    (
+        "// 注意：'answer' 字段是从0开始的数字索引 (0=A, 1=B, 2=C, 3=D)",
+        "// NOTE: the 'answer' field is a zero-based index (0=A, 1=B, 2=C, 3=D)",
+    ),
+    (
+        "// 匹配题：多个小题共用同一组选项，每个小题从选项中选出一个与之匹配的选项",
+        "// Matching: several items share one set of options, each item is matched with one option",
+    ),
+    (
+        "//匹配题的总要求，不要带题号",
+        "//The overall instruction of the matching question, without the question number",
+    ),
+    ("//小题内容，不要带题号", "//Item text, without the item number"),
+    (
+        "//选项中不要带有A、B、C等前缀，和原文保持一致",
+        "//No A/B/C prefixes in the options, otherwise keep the original text",
+    ),
+    (
+        "// 匹配关系：第 i 个小题对应的选项索引 (0=A, 1=B, 2=C ...)，长度必须和 items 一致，选项可以多于小题",
+        "// Matches: the option index for the i-th item (0=A, 1=B, 2=C ...); must have the same length as items, there may be more options than items",
+    ),
+    (
+        "// 简答题：根据题目写出参考答案和评分要点",
+        "// Short answer questions: write a reference answer and the grading points",
+    ),
+    ("//简答题的题目，不要带题号", "//The short answer question, without the question number"),
+    (
+        "//参考答案，用一个字符串写出完整的答案",
+        "//Reference answer, the complete answer as a single string",
+    ),
+    (
+        "//评分要点，每条一个要点并注明分值",
+        "//Grading points, one point per entry with its score",
+    ),
+    (
+        "// 排序题：把打乱的句子按正确的顺序排列",
+        "// Ordering questions: arrange the shuffled sentences in the correct order",
+    ),
+    ("//排序题的总要求，不要带题号", "//The overall instruction of the ordering question, without the question number"),
+    (
+        "//待排序的句子，按原题中给出的顺序列出，不要带有A、B、C等前缀",
+        "//Sentences to arrange, listed in the order given by the original question, without A/B/C prefixes",
+    ),
+    (
+        "// 正确顺序：按先后列出句子在 options 中的索引 (0=A, 1=B, 2=C ...)，每个索引恰好出现一次",
+        "// Correct order: the indexes of the sentences in options (0=A, 1=B, 2=C ...) from first to last, each index exactly once",
+    ),
+    (
+        "//这里不要带题号.这里的data-blank-id每次不要相同",
+        "//No question number here. Use a different data-blank-id every time",
+    ),
+    ("//这里不要带题号", "//No question number here"),
+    ("//答案中不要带有A.", "//No \"A.\" prefix in the options"),
+    (
+        "//原文保持一致~如果原文中每个选项有.那就在选项后面加英文句号，没有就算了。总之和原文保持一致",
+        "//Keep the original text: end an option with a period only if the original option has one",
+    ),
+    (
+        "//解析要用中文。格式要分为：考点，分析，故答案为：",
+        "//Write the analysis in Chinese, split into the same sections as this example",
+    ),
+    (
+        "//解析要用中文。格式要分为：原文，分析，故答案为：（每个题目 的解析要有原文）。格式要用html的语法来写。",
+        "//Write the analysis in Chinese, split into the same sections as this example (every analysis must quote the transcript). Format it with HTML.",
+    ),
+    (
+        "//你要好好看看一个题目有多少个空，然后每个空的data-blank-id都不一样。你要注意我给你的题目有几个空，你也打几个空",
+        "//Check how many blanks each question has and output exactly that many, each with a different data-blank-id",
+    ),
+    (
+        "//如果原题目中横线后面有一个括号，括号里面有提示词，你也要把括号和提示词写上去",
+        "//If a blank is followed by a hint in brackets, keep the brackets and the hint",
+    ),
+    (
+        "//不用加题号 不用加题号 不用加题号 不用加题号 不用加题号 不用加题号",
+        "//Do not add question numbers. Do not add question numbers. Do not add question numbers",
+    ),
+    (
+        "(这里可能会有提示的单词，你也要写上)",
+        "(there may be a hint word here, keep it)",
+    ),
+    (
+        "//序号从(1)开始。data-blank-id每次不要相同。不用管原题目的题号",
+        "//Number the blanks from (1). Use a different data-blank-id every time. Ignore the original question numbers",
+    ),
+    (
+        "// 好。现在我会给你一个题目 。请给出每个题目的tag。你要重点看这个空的考点。而不是把整个句子的考点都总结上。输出格式",
+        "// I will give you a question. Give a tag for every blank, based on what that blank tests rather than the whole sentence. Output format",
+    ),
+    (
+        "//最后给我的时候要把第一个题目的tag放在最后一行。如原来的数组是 [[1],[2],[3]]，你要输出 [[2],[3],[1]]。明白吗？",
+        "//Move the tag of the first blank to the last line. For example, if the array is [[1],[2],[3]], output [[2],[3],[1]].",
+    ),
+    (
+        "【完形：名词",
+        "Choose tags from this list:\n 【完形：名词",
+    ),
+    ("注意事项：", "Notes:"),
+    ("1. 题目不要带题号", "1. Do not include the question number"),
+    ("2. data-blank-id每次要不同", "2. Use a different data-blank-id every time"),
+    (
+        "3. 答案选项不要带有A、B、C、D前缀",
+        "3. No A, B, C, D prefixes in the options",
+    ),
+    (
+        "4. 解析要用中文，格式：考点，分析，故答案为",
+        "4. Write the analysis in Chinese, split into the same sections as the example",
+    ),
+    (
+        "5. 不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹",
+        "5. Do not wrap the code in ```javascript ```; output the bare code only",
+    ),
+    ("// 答案索引：A", "// answer index: A"),
+];
+
+/// 截断过长的题干，尽量在句子边界处截断，返回截断后的题干以及是否发生了截断
+pub fn truncate_stem(stem: &str, max_chars: usize) -> (String, bool) {
+    if max_chars == 0 || stem.chars().count() <= max_chars {
+        return (stem.to_string(), false);
+    }
+
+    let head: String = stem.chars().take(max_chars).collect();
+    // 只有边界不太靠前时才按句子截断，避免丢掉过多内容
+    let boundary = head
+        .char_indices()
+        .rev()
+        .filter(|(_, c)| matches!(c, '.' | '!' | '?' | '。' | '！' | '？' | '\n'))
+        .map(|(i, c)| i + c.len_utf8())
+        .find(|end| head[..*end].chars().count() >= max_chars / 2);
+
+    let truncated = match boundary {
+        Some(end) => head[..end].trim_end().to_string(),
+        None => head,
+    };
+    (truncated, true)
+}
+
 /// 题目类型枚举
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum QuestionType {
     /// 单选题
     SingleChoice,
@@ -22,6 +384,12 @@ pub enum QuestionType {
     ClozeTestNote,
     /// 补全题
     CompleteQuestion,
+    /// 匹配题
+    Matching,
+    /// 简答题
+    ShortAnswer,
+    /// 排序题
+    Ordering,
 }
 
 impl QuestionType {
@@ -37,6 +405,51 @@ impl QuestionType {
             QuestionType::ClozeTestNote => "完形填空note",
             QuestionType::GeneralFill => "语篇填空",
             QuestionType::CompleteQuestion => "补全题",
+            QuestionType::Matching => "匹配题",
+            QuestionType::ShortAnswer => "简答题",
+            QuestionType::Ordering => "排序题",
+        }
+    }
+
+    /// 供新用户参考的示例题干，展示该题型应粘贴的内容
+    pub fn example_stem(&self) -> &'static str {
+        match self {
+            QuestionType::SingleChoice => {
+                "1. —Is this your pen? 这是你的钢笔吗？\n—Yes, it's ______.\nA. my  B. mine  C. me  D. I"
+            }
+            QuestionType::Reading => {
+                "阅读短文，选择正确答案。\nTom gets up at six every day. He goes to school by bike.\n1. How does Tom go to school?\nA. By bus.  B. By bike.  C. On foot.  D. By car."
+            }
+            QuestionType::ClozeTest => {
+                "完形填空：\nI have a dog. It is very __1__. I often play with __2__.\n1. A. cute  B. cuter  C. cutest  D. cutely\n2. A. it  B. its  C. they  D. them"
+            }
+            QuestionType::ListeningSingle => {
+                "听录音，选择正确答案。\n原文：W: What time is it? M: It's half past seven.\n1. What time is it now?\nA. 7:00  B. 7:30  C. 8:30"
+            }
+            QuestionType::ListeningCompound => {
+                "听对话，回答问题。\n原文：M: Where are you going? W: To the library. I want to borrow some books.\n1. Where is the woman going?\nA. To the park.  B. To the library.  C. To the shop.\n2. What does she want to do?\nA. Read books.  B. Borrow books.  C. Buy books."
+            }
+            QuestionType::MutiTiankong => {
+                "根据句意填空。\n1. The capital of France is ______. (Paris)\n2. There are ______ days in a week. (seven)"
+            }
+            QuestionType::ClozeTestNote => {
+                "完形填空（仅生成注释）：\nI have a dog. It is very __1__.\n1. A. cute  B. cuter  C. cutest  D. cutely\n答案：A"
+            }
+            QuestionType::GeneralFill => {
+                "语篇填空：阅读短文，在空白处填入一个适当的词或括号内单词的正确形式。\nGood morning! My name ______ (be) John. I come from ______ (加拿大)."
+            }
+            QuestionType::CompleteQuestion => {
+                "补全对话，从选项中选出能填入空白处的最佳选项。\nA: Hello! __1__\nB: I'm fine, thanks.\nA. How are you?  B. What's your name?  C. Where are you from?"
+            }
+            QuestionType::Matching => {
+                "根据人物描述匹配职业。\n1. Tom likes helping sick people.\n2. Lucy is good at drawing.\nA. A painter.  B. A doctor.  C. A cook."
+            }
+            QuestionType::ShortAnswer => {
+                "回答问题：What can students do to protect the environment? 请至少写出两条建议。"
+            }
+            QuestionType::Ordering => {
+                "将下列句子排成一段通顺的对话。\nA. Nice to meet you, too.\nB. Hello, I'm Tom.\nC. Hi, Tom. I'm Lucy. Nice to meet you."
+            }
         }
     }
 }
@@ -47,31 +460,143 @@ impl FromStr for QuestionType {
         match input {
             "单选题" => Ok(QuestionType::SingleChoice),
             "阅读理解" => Ok(QuestionType::Reading),
-            "完型填空" => Ok(QuestionType::ClozeTest),
+            "完型填空" | "完形填空" => Ok(QuestionType::ClozeTest),
             "单项听力理解" => Ok(QuestionType::ListeningSingle),
             "听力复合题" => Ok(QuestionType::ListeningCompound),
             "多个填空题" => Ok(QuestionType::MutiTiankong),
             "语篇填空" => Ok(QuestionType::GeneralFill),
             "完形填空note" => Ok(QuestionType::ClozeTestNote),
             "补全题" => Ok(QuestionType::CompleteQuestion),
+            "匹配题" => Ok(QuestionType::Matching),
+            "简答题" => Ok(QuestionType::ShortAnswer),
+            "排序题" => Ok(QuestionType::Ordering),
             _ => Err(()),
         }
     }
 }
 
+/// 提示词的附加参数，用于按年级和难度调整生成的解析
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptParams {
+    /// 年级，如 "初中"、"高中"
+    pub grade: Option<String>,
+    /// 难度，如 "基础"、"较难"
+    pub difficulty: Option<String>,
+}
+
+impl PromptParams {
+    /// 创建参数，空字符串视为未指定
+    pub fn new(grade: &str, difficulty: &str) -> Self {
+        let non_empty = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        Self {
+            grade: non_empty(grade),
+            difficulty: non_empty(difficulty),
+        }
+    }
+
+    /// 追加到提示词末尾的说明，没有指定任何参数时返回 None
+    pub fn instruction(&self, language: PromptLanguage) -> Option<String> {
+        let lines: Vec<String> = [
+            self.grade.as_ref().map(|grade| match language {
+                PromptLanguage::Chinese => format!("请针对{}难度生成解析", grade),
+                PromptLanguage::English => format!("Target the analysis at {} students", grade),
+            }),
+            self.difficulty.as_ref().map(|difficulty| match language {
+                PromptLanguage::Chinese => format!("题目难度为{}，解析的深度和用词要与该难度相符", difficulty),
+                PromptLanguage::English => format!(
+                    "The question difficulty is {}; match the depth and wording of the analysis to it",
+                    difficulty
+                ),
+            }),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
 /// 题目模板提示词
 pub struct PromptTemplate {
     question_type: QuestionType,
+    analysis_order: AnalysisOrder,
+    paragraph_style: String,
+    params: PromptParams,
+    language: PromptLanguage,
+    /// 预先生成的 data-blank-id，非空时要求模型依次使用
+    blank_ids: Vec<String>,
 }
 
 impl PromptTemplate {
-    /// 创建新的提示模板
+    /// 使用默认设置创建新的提示模板
+    #[cfg(test)]
     pub fn new(question_type: QuestionType) -> Self {
-        Self { question_type }
+        Self::new_with_params(question_type, PromptParams::default(), &PromptOptions::default())
+    }
+
+    /// 按指定设置创建带年级、难度参数的提示模板
+    pub fn new_with_params(question_type: QuestionType, params: PromptParams, options: &PromptOptions) -> Self {
+        Self {
+            question_type,
+            analysis_order: options.analysis_order,
+            paragraph_style: options.paragraph_style().to_string(),
+            params,
+            language: options.language,
+            blank_ids: utility::generate_blank_ids(options.blank_id_pool_size),
+        }
     }
 
-    /// 获取对应类型的提示词
+    /// 指定预先生成的 data-blank-id，为空时由模型自行编写
+    #[cfg(test)]
+    pub fn with_blank_ids(mut self, blank_ids: Vec<String>) -> Self {
+        self.blank_ids = blank_ids;
+        self
+    }
+
+    /// 要求模型依次使用预先生成的 data-blank-id 的说明，模板中没有空或没有预生成的 id 时返回 None
+    fn blank_id_instruction(&self, prompt: &str) -> Option<String> {
+        if self.blank_ids.is_empty() || !prompt.contains("data-blank-id") {
+            return None;
+        }
+        Some(match self.language {
+            PromptLanguage::Chinese => format!(
+                "data-blank-id 请按顺序使用以下编号，每个空一个，不要自己编写：{}",
+                self.blank_ids.join("、")
+            ),
+            PromptLanguage::English => format!(
+                "Use the following data-blank-id values in order, one per blank, instead of making up your own: {}",
+                self.blank_ids.join(", ")
+            ),
+        })
+    }
+
+    /// 获取对应类型的提示词，带解析的题型会按解析顺序改写格式说明
     pub fn get_prompt(&self) -> String {
+        let mut prompt = self.get_template();
+        if self.language == PromptLanguage::English {
+            prompt = ENGLISH_INSTRUCTIONS
+                .iter()
+                .fold(prompt, |prompt, (chinese, english)| prompt.replace(chinese, english));
+        }
+        prompt = self.analysis_order.apply(prompt);
+        if self.paragraph_style != DEFAULT_PARAGRAPH_STYLE {
+            prompt = prompt.replace(DEFAULT_PARAGRAPH_STYLE, &self.paragraph_style);
+        }
+        if let Some(instruction) = self.blank_id_instruction(&prompt) {
+            prompt = format!("{}\n{}", prompt, instruction);
+        }
+        match self.question_type {
+            // 打标工具只输出标签，没有解析
+            QuestionType::ClozeTestNote => prompt,
+            _ => match self.params.instruction(self.language) {
+                Some(instruction) => format!("{}\n{}", prompt, instruction),
+                None => prompt,
+            },
+        }
+    }
+
+    /// 获取对应类型的提示词模板
+    fn get_template(&self) -> String {
         match self.question_type {
             QuestionType::SingleChoice => Self::get_single_choice_prompt(),
             QuestionType::Reading => Self::get_reading_prompt(),
@@ -82,6 +607,9 @@ impl PromptTemplate {
             QuestionType::ClozeTestNote => Self::get_cloze_test_note_prompt(),
             QuestionType::GeneralFill => Self::get_general_fill_prompt(),
             QuestionType::CompleteQuestion => Self::get_complete_question_prompt(),
+            QuestionType::Matching => Self::get_matching_prompt(),
+            QuestionType::ShortAnswer => Self::get_short_answer_prompt(),
+            QuestionType::Ordering => Self::get_ordering_prompt(),
         }
     }
 
@@ -218,6 +746,82 @@ var Questions = [
         )
     }
 
+    /// 匹配题提示词
+    fn get_matching_prompt() -> String {
+        String::from(
+            r#"
+//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹
+// 匹配题：多个小题共用同一组选项，每个小题从选项中选出一个与之匹配的选项
+// 在OCR时，注意把试卷中的不相关内容去掉，避免干扰
+var Questions = [
+    {
+        "stem": "Match the people with the jobs they want to do in the future.",//匹配题的总要求，不要带题号
+        "items": [
+            "Tom likes helping sick people.",//小题内容，不要带题号
+            "Lucy is good at drawing pictures.",
+            "Jack enjoys cooking for his family."
+        ],
+        "options": [
+            "A painter.",//选项中不要带有A、B、C等前缀，和原文保持一致
+            "A doctor.",
+            "A cook.",
+            "A teacher."
+        ],
+        // 匹配关系：第 i 个小题对应的选项索引 (0=A, 1=B, 2=C ...)，长度必须和 items 一致，选项可以多于小题
+        "matches": [1, 0, 2],
+        "analysis": "考点：信息匹配。分析：Tom 喜欢帮助病人，适合当医生；Lucy 擅长画画，适合当画家；Jack 喜欢做饭，适合当厨师。故答案为：1-B，2-A，3-C"//解析要用中文。格式要分为：考点，分析，故答案为：
+    }
+];
+"#,
+        )
+    }
+
+    /// 排序题提示词
+    fn get_ordering_prompt() -> String {
+        String::from(
+            r#"
+//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹
+// 排序题：把打乱的句子按正确的顺序排列
+// 在OCR时，注意把试卷中的不相关内容去掉，避免干扰
+var Questions = [
+    {
+        "stem": "Put the following sentences in the correct order to make a dialogue.",//排序题的总要求，不要带题号
+        "options": [
+            "Nice to meet you, too.",//待排序的句子，按原题中给出的顺序列出，不要带有A、B、C等前缀
+            "Hello, I'm Tom.",
+            "Hi, Tom. I'm Lucy. Nice to meet you."
+        ],
+        // 正确顺序：按先后列出句子在 options 中的索引 (0=A, 1=B, 2=C ...)，每个索引恰好出现一次
+        "sequence": [1, 2, 0],
+        "analysis": "考点：对话排序。分析：对话以 Tom 的自我介绍开始，Lucy 回应并说很高兴认识他，最后 Tom 回答 Nice to meet you, too。故答案为：BCA"//解析要用中文。格式要分为：考点，分析，故答案为：
+    }
+];
+"#,
+        )
+    }
+
+    /// 简答题提示词
+    fn get_short_answer_prompt() -> String {
+        String::from(
+            r#"
+//请直接输出如下格式的JavaScript代码，不要回复其他内容。不要带有```javascript ```，只输出代码就可以了。我不用代码块包裹
+// 简答题：根据题目写出参考答案和评分要点
+// 在OCR时，注意把试卷中的不相关内容去掉，避免干扰
+var Questions = [
+    {
+        "stem": "What can students do to protect the environment? Give at least two suggestions.",//简答题的题目，不要带题号
+        "answer": "Students can plant more trees and take the bus or ride a bike to school instead of going by car.",//参考答案，用一个字符串写出完整的答案
+        "points": [
+            "Plant more trees. (1分)",//评分要点，每条一个要点并注明分值
+            "Take the bus or ride a bike instead of going by car. (1分)"
+        ],
+        "analysis": "考点：环境保护。分析：题目要求至少给出两条保护环境的建议，植树和绿色出行都是常见的做法，言之有理即可。故答案为：Students can plant more trees and take the bus or ride a bike to school."//解析要用中文。格式要分为：考点，分析，故答案为：
+    }
+];
+"#,
+        )
+    }
+
     fn get_listening_single_prompt() -> String {
         String::from(
             r#"
@@ -405,19 +1009,242 @@ var newContent = {
     }
 }
 
+/// 生成代码中直接点击保存按钮的语句
+const SAVE_CLICK: &str = "saveButton.click();";
+
+/// 生成代码中查找保存按钮的调用，存在时附加带重试的查找函数
+const FIND_SAVE_BUTTON: &str = "await findSaveButton()";
+
+/// 配置目录下存放附加代码覆盖文件的子目录，文件名为 `<题型>.js`（如 `单选题.js`）
+pub const SCRIPTS_DIR_NAME: &str = "scripts";
+
+/// 生成附加代码使用的设置，启动时从配置目录读取一次，生成题目时不再读取磁盘
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptOptions {
+    /// 替换脚本中选择器占位符的配置（`<config_dir>/selectors.json`）
+    pub selectors: SelectorConfig,
+    /// 各题型的覆盖脚本（`<config_dir>/scripts/<题型>.js`），只包含非空的文件
+    pub overrides: HashMap<QuestionType, String>,
+    /// 保存按钮最多点击的次数
+    pub save_retry_attempts: usize,
+    /// 两次点击保存按钮之间的等待时间（毫秒）
+    pub save_retry_delay_ms: u64,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            selectors: SelectorConfig::default(),
+            overrides: HashMap::new(),
+            save_retry_attempts: DEFAULT_SAVE_RETRY_ATTEMPTS,
+            save_retry_delay_ms: DEFAULT_SAVE_RETRY_DELAY_MS,
+        }
+    }
+}
+
+impl ScriptOptions {
+    /// 从默认的配置目录读取，配置目录不可用时使用默认值
+    pub fn load() -> Self {
+        match utility::app_config_dir() {
+            Ok(dir) => Self::load_from(&dir),
+            Err(e) => {
+                tracing::warn!("[question_type] No config dir, using default script options: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 从指定的配置目录读取，文件不存在或无法解析时使用默认值
+    pub fn load_from(config_dir: &Path) -> Self {
+        Self {
+            selectors: SelectorConfig::load_from(&config_dir.join("selectors.json")),
+            overrides: load_overrides(&config_dir.join(SCRIPTS_DIR_NAME)),
+            ..Self::default()
+        }
+    }
+
+    /// 按当前设置创建指定题型的附加代码生成器
+    pub fn generator(&self, question_type: QuestionType) -> AdditionalCodeGenerator {
+        let generator = AdditionalCodeGenerator::new(question_type)
+            .with_selectors(self.selectors.clone())
+            .with_save_retry(self.save_retry_attempts, self.save_retry_delay_ms);
+        match self.overrides.get(&question_type) {
+            Some(code) => generator.with_override_code(code.clone()),
+            None => generator,
+        }
+    }
+}
+
+/// 读取 `scripts_dir` 中以题型命名的覆盖脚本，跳过空文件和无法识别的文件名
+fn load_overrides(scripts_dir: &Path) -> HashMap<QuestionType, String> {
+    let Ok(entries) = std::fs::read_dir(scripts_dir) else {
+        return HashMap::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?;
+            let question_type = QuestionType::from_str(name).ok().filter(|t| t.as_str() == name)?;
+            let code = std::fs::read_to_string(&path).ok()?;
+            if code.trim().is_empty() {
+                tracing::warn!("[question_type] Ignoring empty additional code override {}", path.display());
+                return None;
+            }
+            tracing::info!("[question_type] Using additional code override {}", path.display());
+            Some((question_type, code))
+        })
+        .collect()
+}
+
 /// 附加代码生成器
 pub struct AdditionalCodeGenerator {
     question_type: QuestionType,
+    save_retry_attempts: usize,
+    save_retry_delay_ms: u64,
+    /// 替代内置脚本的覆盖脚本
+    override_code: Option<String>,
+    /// 替换脚本中选择器占位符的配置
+    selectors: SelectorConfig,
 }
 
 impl AdditionalCodeGenerator {
-    /// 创建新的附加代码生成器
+    /// 创建新的附加代码生成器，保存重试参数使用默认值
     pub fn new(question_type: QuestionType) -> Self {
-        Self { question_type }
+        Self {
+            question_type,
+            save_retry_attempts: DEFAULT_SAVE_RETRY_ATTEMPTS,
+            save_retry_delay_ms: DEFAULT_SAVE_RETRY_DELAY_MS,
+            override_code: None,
+            selectors: SelectorConfig::default(),
+        }
+    }
+
+    /// 指定替换选择器占位符使用的配置，未指定时使用默认选择器
+    pub fn with_selectors(mut self, selectors: SelectorConfig) -> Self {
+        self.selectors = selectors;
+        self
+    }
+
+    /// 指定替代内置脚本的覆盖脚本
+    pub fn with_override_code(mut self, code: String) -> Self {
+        self.override_code = Some(code);
+        self
+    }
+
+    /// 指定保存按钮的点击次数上限和重试间隔（毫秒）
+    pub fn with_save_retry(mut self, attempts: usize, delay_ms: u64) -> Self {
+        self.save_retry_attempts = attempts.max(1);
+        self.save_retry_delay_ms = delay_ms;
+        self
     }
 
-    /// 获取附加代码
+    /// 获取附加代码，找不到保存按钮时按间隔重试查找，点击保存按钮的地方会改为等待按钮可用并在保存未生效时重试
+    ///
+    /// 目标网站改版后可以在 `scripts/<题型>.js` 中放入修改过选择器的脚本，
+    /// 存在且非空时优先使用，不需要重新编译。脚本中的 `{{xxx_selector}}` 占位符
+    /// 会替换为 [`SelectorConfig`] 中配置的选择器。
     pub fn get_code(&self) -> String {
+        let mut code = self.override_code.clone().unwrap_or_else(|| self.get_template_code());
+        if code.contains(FIND_SAVE_BUTTON) {
+            code.push_str(&self.get_find_save_button_code());
+        }
+        let code = self.selectors.apply(&code);
+        if code.contains(SAVE_CLICK) {
+            code.replace(SAVE_CLICK, "await clickSaveWithRetry(saveButton);") + &self.get_save_retry_code()
+        } else {
+            code
+        }
+    }
+
+    /// 按配置的选择器查找保存按钮，找不到时依次尝试备用选择器，页面还未渲染出按钮时按间隔重试
+    ///
+    /// 目标网站改版后选择器失效时在控制台给出明确的错误，而不是静默跳过保存。
+    fn get_find_save_button_code(&self) -> String {
+        format!(
+            r#"
+
+/**
+ * 查找保存按钮，找不到时按间隔重试
+ * @returns {{Promise<HTMLElement|null>}} 保存按钮，重试后仍找不到时为 null
+ */
+async function findSaveButton() {{
+    const maxAttempts = {attempts};
+    const retryDelay = {delay};
+    const locate = () => document.querySelector('{selector}')
+        // 备用选择器
+        || document.querySelector('button.ant-btn.ant-btn-primary')
+        || Array.from(document.querySelectorAll('button')).find(btn =>
+            btn.textContent.includes('保存') || btn.textContent.includes('保 存')
+        );
+
+    for (let attempt = 1; attempt <= maxAttempts; attempt++) {{
+        const button = locate();
+        if (button) {{
+            return button;
+        }}
+        console.warn(`⚠️ 未找到保存按钮，第 ${{attempt}} 次尝试`);
+        await new Promise(resolve => setTimeout(resolve, retryDelay));
+    }}
+    console.error(`❌ 尝试 ${{maxAttempts}} 次后仍未找到保存按钮，请检查 selectors.json 中的 save_button_selector 是否与当前页面一致`);
+    return null;
+}}
+"#,
+            attempts = self.save_retry_attempts,
+            delay = self.save_retry_delay_ms,
+            selector = "{{save_button_selector}}",
+        )
+    }
+
+    /// 等待保存按钮可用后点击，表单仍未保存时按间隔重试
+    ///
+    /// 表单视为已保存：出现保存成功提示，或保存按钮所在的表单已被关闭。
+    fn get_save_retry_code(&self) -> String {
+        format!(
+            r#"
+
+/**
+ * 等待保存按钮可用后点击，表单仍未保存时重试
+ * @param {{HTMLElement}} saveButton - 保存按钮
+ * @returns {{Promise<boolean>}} 是否保存成功
+ */
+async function clickSaveWithRetry(saveButton) {{
+    const maxAttempts = {attempts};
+    const retryDelay = {delay};
+    const isEnabled = () => !saveButton.disabled
+        && saveButton.getAttribute('aria-disabled') !== 'true'
+        && !saveButton.classList.contains('ant-btn-loading');
+    const isFormDirty = () => document.body.contains(saveButton)
+        && !document.querySelector('.ant-message-success');
+
+    for (let attempt = 1; attempt <= maxAttempts; attempt++) {{
+        // 等待按钮可用，最多等待一个重试间隔
+        for (let waited = 0; !isEnabled() && waited < retryDelay; waited += 100) {{
+            await new Promise(resolve => setTimeout(resolve, 100));
+        }}
+        if (!isEnabled()) {{
+            console.warn(`⚠️ 保存按钮不可用，第 ${{attempt}} 次尝试`);
+            continue;
+        }}
+        saveButton.click();
+        await new Promise(resolve => setTimeout(resolve, retryDelay));
+        if (!isFormDirty()) {{
+            console.log(`✅ 保存成功（第 ${{attempt}} 次点击）`);
+            return true;
+        }}
+        console.warn(`⚠️ 表单仍未保存，第 ${{attempt}} 次点击后重试`);
+    }}
+    console.error(`❌ 点击保存 ${{maxAttempts}} 次后表单仍未保存`);
+    return false;
+}}
+"#,
+            attempts = self.save_retry_attempts,
+            delay = self.save_retry_delay_ms,
+        )
+    }
+
+    /// 获取对应类型的附加代码模板
+    fn get_template_code(&self) -> String {
         match self.question_type {
             QuestionType::SingleChoice => self.get_single_choice_code(),
             QuestionType::Reading => self.get_reading_code(),
@@ -428,6 +1255,9 @@ impl AdditionalCodeGenerator {
             QuestionType::ClozeTestNote => self.get_cloze_test_note_code(),
             QuestionType::GeneralFill => self.get_muti_tiankong_code(),
             QuestionType::CompleteQuestion => self.get_complete_question_code(),
+            QuestionType::Matching => self.get_matching_code(),
+            QuestionType::ShortAnswer => self.get_short_answer_code(),
+            QuestionType::Ordering => self.get_ordering_code(),
         }
     }
     fn get_complete_question_code(&self)-> String {
@@ -436,7 +1266,7 @@ impl AdditionalCodeGenerator {
 async function main(newContent) {
     console.log("主函数开始执行...");
     var stemEditor = document.querySelector(
-        '.ql-editor[data-placeholder="请录入题干"]',
+        '{{stem_selector}}',
     );
     if (!stemEditor) {
         // 备用选择器 - 查找题干内容编辑器
@@ -598,7 +1428,7 @@ function clickBlankFillingElement(type) {
 // 定位并点击最后一题的函数
 async function locateAndClickLastQuestion() {
     // 查找所有题目容器
-    var allQuestions = document.querySelectorAll('.question-item');
+    var allQuestions = document.querySelectorAll('{{question_item_selector}}');
 
     if (allQuestions.length > 0) {
         // 获取最后一个题目
@@ -883,10 +1713,10 @@ async function fillQuestionContent(questionData) {
     await delay(800);
 
     // 找到当前活动的题目表单容器
-    let currentForm = document.querySelector('.question-item.active');
+    let currentForm = document.querySelector('{{question_item_selector}}.active');
     if (!currentForm) {
         // 备用选择器：查找最后一个题目容器或当前编辑的题目
-        const allQuestions = document.querySelectorAll('.question-item');
+        const allQuestions = document.querySelectorAll('{{question_item_selector}}');
         if (allQuestions.length > 0) {
             currentForm = allQuestions[allQuestions.length - 1];
         }
@@ -913,10 +1743,10 @@ async function fillQuestionContent(questionData) {
     await delay(300);
 
     // 步骤 4: 填充选项
-    var optionInputs = currentForm.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+    var optionInputs = currentForm.querySelectorAll('{{option_selector}}');
     if (optionInputs.length === 0) {
         // 备用选择器
-        optionInputs = document.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+        optionInputs = document.querySelectorAll('{{option_selector}}');
     }
 
     for (let i = 0; i < questionData.options.length; i++) {
@@ -949,16 +1779,7 @@ async function fillQuestionContent(questionData) {
     await fillEditableDiv(currentForm, '请录入解析', questionData.analysis);
 
     // 点击保存按钮
-    var saveButton = document.querySelector('button.ant-btn.ant-btn-primary[data-v-4c71fb2d]');
-    if (!saveButton) {
-        // 备用选择器
-        saveButton = document.querySelector('button.ant-btn.ant-btn-primary');
-        if (!saveButton) {
-            saveButton = Array.from(document.querySelectorAll('button')).find(btn =>
-                btn.textContent.includes('保存') || btn.textContent.includes('保 存')
-            );
-        }
-    }
+    var saveButton = await findSaveButton();
 
     if (saveButton) {
         saveButton.click();
@@ -1036,7 +1857,7 @@ function triggerInputEvents(element) {
 // 定位并点击最后一题的函数
 async function locateAndClickLastQuestion() {
     // 查找所有题目容器
-    var allQuestions = document.querySelectorAll('.question-item');
+    var allQuestions = document.querySelectorAll('{{question_item_selector}}');
 
     if (allQuestions.length > 0) {
         // 获取最后一个题目
@@ -1355,7 +2176,7 @@ async function processAllQuestions() {
             await fillEditableDiv(currentForm, '请录入小题题干', questionData.stem);
 
             // 步骤 4: 填充选项
-            var optionInputs = currentForm.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+            var optionInputs = currentForm.querySelectorAll('{{option_selector}}');
             for (let i = 0; i < questionData.options.length; i++) {
                 if (optionInputs[i]) {
                     optionInputs[i].classList.remove('placeholder');
@@ -1513,7 +2334,7 @@ async function configureQuestion(questionIndex, questionData) {
 
         // 步骤 3: 填充选项A、B、C、D
         console.log(`正在配置第${questionIndex + 1}题的选项...`);
-        const optionInputs = activeConfig.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+        const optionInputs = activeConfig.querySelectorAll('{{option_selector}}');
 
         for (let i = 0; i < questionData.options.length && i < optionInputs.length; i++) {
             if (optionInputs[i]) {
@@ -1605,56 +2426,554 @@ window.setInitialContent = setInitialContent;"#,
         )
     }
 
-    fn get_listening_compound_code(&self) -> String {
+    /// 匹配题附加代码
+    fn get_matching_code(&self) -> String {
         String::from(
-            r#"
-//MARK： 使用XPath查找包含"阅读理解"文本的元素
-function clickReadingElement() {
-    // XPath表达式：查找class包含"tag"且包含"阅读理解"文本的元素
-    var xpath = "//*[contains(@class,'tag') and contains(text(),'阅读理解')]";
+            r#"/**
+ * 等待指定毫秒数
+ * @param {number} ms - 等待的时间（毫秒）
+ */
+var delay = (ms) => new Promise(resolve => setTimeout(resolve, ms));
 
-    // 执行XPath查询
-    var result = document.evaluate(
-        xpath,
-        document,
-        null,
-        XPathResult.FIRST_ORDERED_NODE_TYPE,
-        null
-    );
+/**
+ * 触发一个元素上的多个事件，以模拟真实用户操作，确保框架能接收到变更
+ * @param {HTMLElement} element - 目标元素
+ */
+function triggerEvents(element) {
+    element.focus();
+    // 触发一系列事件，确保兼容各种前端框架
+    ['input', 'change', 'keyup', 'blur'].forEach(eventType => {
+        element.dispatchEvent(new Event(eventType, { bubbles: true, cancelable: true }));
+    });
+}
 
-    // 如果找到元素，点击它
-    if (result.singleNodeValue) {
-        result.singleNodeValue.click();
-        console.log("成功点击阅读理解元素");
-        return true;
-    } else {
-        console.log("未找到包含'阅读理解'文本的元素");
-        return false;
+/**
+ * 向可编辑的 div 填入内容
+ * @param {HTMLElement} element - 可编辑元素
+ * @param {string} content - 要填充的 HTML 内容
+ */
+async function fillEditable(element, content) {
+    element.classList.remove('placeholder'); // 移除占位符样式
+    element.innerHTML = content;
+    triggerEvents(element);
+    await delay(100); // 每个填充操作后短暂延时，增加稳定性
+}
+
+/**
+ * 点击文字为 text 的按钮，直到 selector 匹配的元素数量达到 count
+ * @param {HTMLElement} container - 查找范围
+ * @param {string} selector - 需要补足数量的元素选择器
+ * @param {number} count - 目标数量
+ * @param {string} text - 按钮文字
+ */
+async function ensureCount(container, selector, count, text) {
+    for (let attempt = 0; attempt < 20 && container.querySelectorAll(selector).length < count; attempt++) {
+        const addButton = Array.from(container.querySelectorAll('button, .ant-btn, span'))
+            .find(element => element.textContent.trim() === text);
+        if (!addButton) {
+            console.warn(`⚠️ 找不到"${text}"按钮，无法补足到 ${count} 个`);
+            return;
+        }
+        addButton.click();
+        await delay(300);
     }
 }
 
-// 完整的操作流程
-async function operateElements() {
-    // 1. 点击下拉框
-    var selectDiv = document.querySelector('div[title="单选题"]');
-    if (selectDiv) {
-        selectDiv.click();
+// ----------- 匹配题配置功能 -----------
 
-        // 2. 选择复合题 - 使用 Promise 替代 setTimeout
-        await new Promise(resolve => {
-            setTimeout(function () {
-                var options = document.querySelectorAll('li.ant-select-dropdown-menu-item');
-                for (var i = 0; i < options.length; i++) {
-                    if (options[i].textContent.trim() === '复合题') {
-                        options[i].click();
-                        break;
-                    }
-                }
-                resolve();
-            }, 100);
-        });
+/**
+ * 配置单个匹配题：题干、选项池、小题和匹配关系、解析
+ * @param {number} questionIndex - 题目索引
+ * @param {Object} questionData - 题目数据
+ */
+async function configureMatchingQuestion(questionIndex, questionData) {
+    console.log(`\n--- [ ${questionIndex + 1} / ${Questions.length} ] --- 开始配置匹配题...`);
 
-        // 3. 使用XPath点击阅读理解标签 - 使用 Promise 替代 setTimeout
+    try {
+        if (questionData.matches.length !== questionData.items.length) {
+            console.warn(`⚠️ 匹配关系数量 (${questionData.matches.length}) 与小题数量 (${questionData.items.length}) 不一致`);
+        }
+
+        // 步骤 1: 填充题干
+        const stemInput = document.querySelector('.ckeditor_div[placeholder="请录入题干"][contenteditable="true"]')
+            || document.querySelector('.ckeditor_div.cke_editable');
+        if (stemInput) {
+            await fillEditable(stemInput, `<p>${questionData.stem}</p>`);
+            console.log("✅ 成功填充题干");
+        } else {
+            console.warn("⚠️ 题干输入框未找到");
+        }
+
+        // 步骤 2: 填充共用的选项池
+        console.log(`正在配置 ${questionData.options.length} 个选项...`);
+        const optionSelector = '{{option_selector}}';
+        await ensureCount(document, optionSelector, questionData.options.length, "添加选项");
+        const optionInputs = document.querySelectorAll(optionSelector);
+        for (let i = 0; i < questionData.options.length && i < optionInputs.length; i++) {
+            await fillEditable(optionInputs[i], questionData.options[i]);
+            console.log(`✅ 成功设置选项 ${String.fromCharCode(65 + i)}: ${questionData.options[i]}`);
+        }
+
+        // 步骤 3: 逐个填充小题并选择匹配的选项
+        const itemSelector = '.match-item';
+        await ensureCount(document, itemSelector, questionData.items.length, "添加小题");
+        const itemRows = document.querySelectorAll(itemSelector);
+        for (let i = 0; i < questionData.items.length; i++) {
+            const row = itemRows[i];
+            if (!row) {
+                console.warn(`⚠️ 找不到第${i + 1}个小题的配置区域`);
+                continue;
+            }
+
+            const itemInput = row.querySelector('.ckeditor_div[contenteditable="true"]');
+            if (itemInput) {
+                await fillEditable(itemInput, questionData.items[i]);
+            }
+
+            const match = questionData.matches[i];
+            const letter = String.fromCharCode(65 + match);
+            const matchButton = Array.from(row.querySelectorAll('.ant-radio-wrapper, .ant-checkbox-wrapper, label'))
+                .find(element => element.textContent.trim() === letter);
+            if (matchButton) {
+                matchButton.click();
+                console.log(`✅ 第${i + 1}个小题匹配选项 ${letter}`);
+            } else {
+                console.warn(`⚠️ 第${i + 1}个小题找不到选项 ${letter}`);
+            }
+            await delay(100);
+        }
+
+        // 步骤 4: 填充解析
+        console.log(`开始输入解析...`);
+        const explanationInput = document.querySelector('[placeholder="请录入解析"][contenteditable="true"]')
+            || document.querySelector('.ckeditor_div[placeholder="请录入解析"]');
+        if (explanationInput) {
+            await fillEditable(explanationInput, questionData.analysis);
+            console.log(`✅ 成功填充解析`);
+        } else {
+            console.warn(`⚠️ 解析输入框未找到`);
+        }
+
+        console.log(`👍 第 ${questionIndex + 1} 题配置完成！`);
+
+    } catch (error) {
+        console.error(`💥 配置第${questionIndex + 1}题时发生错误:`, error);
+    }
+}
+
+// ----------- 主执行函数 -----------
+
+/**
+ * 处理所有匹配题
+ */
+async function processAllQuestions() {
+    console.log(`🚀 匹配题配置脚本启动，准备处理 ${Questions.length} 道题目...`);
+
+    try {
+        for (const [index, questionData] of Questions.entries()) {
+            await configureMatchingQuestion(index, questionData);
+            await delay(500); // 题目间延时
+        }
+
+        console.log("\n🎉🎉🎉 所有题目均已成功配置！");
+
+    } catch (error) {
+        console.error("💥 脚本执行过程中发生严重错误:", error);
+        console.error("请检查页面结构或刷新页面后重试。");
+    }
+}
+
+// 启动脚本
+processAllQuestions();
+
+// 导出函数供手动调用
+console.log("🎉 匹配题一键配置脚本已加载！");
+console.log("脚本功能：1. 题干设置 -> 2. 选项池配置 -> 3. 小题和匹配关系设置 -> 4. 解析输入");
+console.log("可用函数：");
+console.log("- processAllQuestions()：重新执行完整配置");
+console.log("- configureMatchingQuestion(index, data)：配置单个题目");
+
+// 挂载到window对象
+window.processAllQuestions = processAllQuestions;
+window.configureMatchingQuestion = configureMatchingQuestion;"#,
+        )
+    }
+
+    /// 排序题附加代码
+    fn get_ordering_code(&self) -> String {
+        String::from(
+            r#"/**
+ * 等待指定毫秒数
+ * @param {number} ms - 等待的时间（毫秒）
+ */
+var delay = (ms) => new Promise(resolve => setTimeout(resolve, ms));
+
+/**
+ * 触发一个元素上的多个事件，以模拟真实用户操作，确保框架能接收到变更
+ * @param {HTMLElement} element - 目标元素
+ */
+function triggerEvents(element) {
+    element.focus();
+    // 触发一系列事件，确保兼容各种前端框架
+    ['input', 'change', 'keyup', 'blur'].forEach(eventType => {
+        element.dispatchEvent(new Event(eventType, { bubbles: true, cancelable: true }));
+    });
+}
+
+/**
+ * 向可编辑的 div 填入内容
+ * @param {HTMLElement} element - 可编辑元素
+ * @param {string} content - 要填充的 HTML 内容
+ */
+async function fillEditable(element, content) {
+    element.classList.remove('placeholder'); // 移除占位符样式
+    element.innerHTML = content;
+    triggerEvents(element);
+    await delay(100); // 每个填充操作后短暂延时，增加稳定性
+}
+
+/**
+ * 点击文字为 text 的按钮，直到 selector 匹配的元素数量达到 count
+ * @param {HTMLElement} container - 查找范围
+ * @param {string} selector - 需要补足数量的元素选择器
+ * @param {number} count - 目标数量
+ * @param {string} text - 按钮文字
+ */
+async function ensureCount(container, selector, count, text) {
+    for (let attempt = 0; attempt < 20 && container.querySelectorAll(selector).length < count; attempt++) {
+        const addButton = Array.from(container.querySelectorAll('button, .ant-btn, span'))
+            .find(element => element.textContent.trim() === text);
+        if (!addButton) {
+            console.warn(`⚠️ 找不到"${text}"按钮，无法补足到 ${count} 个`);
+            return;
+        }
+        addButton.click();
+        await delay(300);
+    }
+}
+
+/**
+ * 检查 sequence 是否恰好包含每个选项索引一次
+ * @param {number[]} sequence - 正确顺序
+ * @param {number} optionCount - 选项数量
+ */
+function isValidSequence(sequence, optionCount) {
+    const sorted = [...sequence].sort((a, b) => a - b);
+    return sorted.length === optionCount && sorted.every((value, index) => value === index);
+}
+
+/**
+ * 设置排序答案：优先填入答案输入框（如 "BCA"），没有输入框时按顺序点击排序区中的句子
+ * @param {number[]} sequence - 正确顺序
+ */
+async function setOrderAnswer(sequence) {
+    const letters = sequence.map(index => String.fromCharCode(65 + index));
+
+    const answerInput = document.querySelector('input[placeholder*="答案"]');
+    if (answerInput) {
+        // 通过原生 setter 赋值，框架才能感知到变更
+        const setter = Object.getOwnPropertyDescriptor(HTMLInputElement.prototype, 'value').set;
+        setter.call(answerInput, letters.join(''));
+        triggerEvents(answerInput);
+        console.log(`✅ 成功填写排序答案: ${letters.join('')}`);
+        return;
+    }
+
+    const answerEditor = document.querySelector('[placeholder="请录入答案"][contenteditable="true"]');
+    if (answerEditor) {
+        await fillEditable(answerEditor, letters.join(''));
+        console.log(`✅ 成功填写排序答案: ${letters.join('')}`);
+        return;
+    }
+
+    // 点选式排序：依次点击每个位置应放的句子
+    const sortItems = Array.from(document.querySelectorAll('.sort-item, .ant-tag, .ant-checkable-tag'));
+    for (const letter of letters) {
+        const item = sortItems.find(element => element.textContent.trim().startsWith(letter));
+        if (!item) {
+            console.warn(`⚠️ 找不到选项 ${letter} 的排序项，请手动设置答案顺序 ${letters.join('')}`);
+            return;
+        }
+        item.click();
+        await delay(200);
+    }
+    console.log(`✅ 已按顺序点击排序项: ${letters.join('')}`);
+}
+
+// ----------- 排序题配置功能 -----------
+
+/**
+ * 配置单个排序题：题干、待排序的句子、正确顺序和解析
+ * @param {number} questionIndex - 题目索引
+ * @param {Object} questionData - 题目数据
+ */
+async function configureOrderingQuestion(questionIndex, questionData) {
+    console.log(`
+--- [ ${questionIndex + 1} / ${Questions.length} ] --- 开始配置排序题...`);
+
+    try {
+        if (!isValidSequence(questionData.sequence, questionData.options.length)) {
+            console.warn(`⚠️ 正确顺序 [${questionData.sequence}] 与 ${questionData.options.length} 个句子不对应，请检查模型输出`);
+        }
+
+        // 步骤 1: 填充题干
+        const stemInput = document.querySelector('.ckeditor_div[placeholder="请录入题干"][contenteditable="true"]')
+            || document.querySelector('.ckeditor_div.cke_editable');
+        if (stemInput) {
+            await fillEditable(stemInput, `<p>${questionData.stem}</p>`);
+            console.log("✅ 成功填充题干");
+        } else {
+            console.warn("⚠️ 题干输入框未找到");
+        }
+
+        // 步骤 2: 填充待排序的句子
+        console.log(`正在配置 ${questionData.options.length} 个句子...`);
+        const optionSelector = '{{option_selector}}';
+        await ensureCount(document, optionSelector, questionData.options.length, "添加选项");
+        const optionInputs = document.querySelectorAll(optionSelector);
+        for (let i = 0; i < questionData.options.length && i < optionInputs.length; i++) {
+            await fillEditable(optionInputs[i], questionData.options[i]);
+            console.log(`✅ 成功设置句子 ${String.fromCharCode(65 + i)}: ${questionData.options[i]}`);
+        }
+
+        // 步骤 3: 设置正确顺序
+        await setOrderAnswer(questionData.sequence);
+
+        // 步骤 4: 填充解析
+        console.log(`开始输入解析...`);
+        const explanationInput = document.querySelector('[placeholder="请录入解析"][contenteditable="true"]')
+            || document.querySelector('.ckeditor_div[placeholder="请录入解析"]');
+        if (explanationInput) {
+            await fillEditable(explanationInput, questionData.analysis);
+            console.log(`✅ 成功填充解析`);
+        } else {
+            console.warn(`⚠️ 解析输入框未找到`);
+        }
+
+        console.log(`👍 第 ${questionIndex + 1} 题配置完成！`);
+
+    } catch (error) {
+        console.error(`💥 配置第${questionIndex + 1}题时发生错误:`, error);
+    }
+}
+
+// ----------- 主执行函数 -----------
+
+/**
+ * 处理所有排序题
+ */
+async function processAllQuestions() {
+    console.log(`🚀 排序题配置脚本启动，准备处理 ${Questions.length} 道题目...`);
+
+    try {
+        for (const [index, questionData] of Questions.entries()) {
+            await configureOrderingQuestion(index, questionData);
+            await delay(500); // 题目间延时
+        }
+
+        console.log("\n🎉🎉🎉 所有题目均已成功配置！");
+
+    } catch (error) {
+        console.error("💥 脚本执行过程中发生严重错误:", error);
+        console.error("请检查页面结构或刷新页面后重试。");
+    }
+}
+
+// 启动脚本
+processAllQuestions();
+
+// 导出函数供手动调用
+console.log("🎉 排序题一键配置脚本已加载！");
+console.log("脚本功能：1. 题干设置 -> 2. 句子配置 -> 3. 正确顺序设置 -> 4. 解析输入");
+console.log("可用函数：");
+console.log("- processAllQuestions()：重新执行完整配置");
+console.log("- configureOrderingQuestion(index, data)：配置单个题目");
+
+// 挂载到window对象
+window.processAllQuestions = processAllQuestions;
+window.configureOrderingQuestion = configureOrderingQuestion;"#,
+        )
+    }
+
+    /// 简答题附加代码
+    fn get_short_answer_code(&self) -> String {
+        String::from(
+            r#"/**
+ * 等待指定毫秒数
+ * @param {number} ms - 等待的时间（毫秒）
+ */
+var delay = (ms) => new Promise(resolve => setTimeout(resolve, ms));
+
+/**
+ * 触发一个元素上的多个事件，以模拟真实用户操作，确保框架能接收到变更
+ * @param {HTMLElement} element - 目标元素
+ */
+function triggerEvents(element) {
+    element.focus();
+    // 触发一系列事件，确保兼容各种前端框架
+    ['input', 'change', 'keyup', 'blur'].forEach(eventType => {
+        element.dispatchEvent(new Event(eventType, { bubbles: true, cancelable: true }));
+    });
+}
+
+/**
+ * 向可编辑的 div 填入内容
+ * @param {HTMLElement} element - 可编辑元素
+ * @param {string} content - 要填充的 HTML 内容
+ */
+async function fillEditable(element, content) {
+    element.classList.remove('placeholder'); // 移除占位符样式
+    element.innerHTML = content;
+    triggerEvents(element);
+    await delay(100); // 每个填充操作后短暂延时，增加稳定性
+}
+
+/**
+ * 按 placeholder 查找简答题编辑器中的可编辑区域
+ * @param {string} placeholder - 输入框的占位文字
+ */
+function findEditor(placeholder) {
+    return document.querySelector(`.ckeditor_div[placeholder="${placeholder}"][contenteditable="true"]`)
+        || document.querySelector(`[placeholder="${placeholder}"][contenteditable="true"]`);
+}
+
+// ----------- 简答题配置功能 -----------
+
+/**
+ * 配置单个简答题：题干、参考答案、评分要点和解析
+ * @param {number} questionIndex - 题目索引
+ * @param {Object} questionData - 题目数据
+ */
+async function configureShortAnswerQuestion(questionIndex, questionData) {
+    console.log(`\n--- [ ${questionIndex + 1} / ${Questions.length} ] --- 开始配置简答题...`);
+
+    try {
+        // 步骤 1: 填充题干
+        const stemInput = findEditor("请录入题干") || document.querySelector('.ckeditor_div.cke_editable');
+        if (stemInput) {
+            await fillEditable(stemInput, `<p>${questionData.stem}</p>`);
+            console.log("✅ 成功填充题干");
+        } else {
+            console.warn("⚠️ 题干输入框未找到");
+        }
+
+        // 步骤 2: 填充参考答案，评分要点逐条附在答案后面
+        const points = questionData.points || [];
+        const answerHtml = `<p>${questionData.answer}</p>`
+            + (points.length > 0 ? `<p>评分要点：</p>${points.map(point => `<p>${point}</p>`).join('')}` : '');
+        const answerInput = findEditor("请录入答案") || findEditor("请录入参考答案");
+        if (answerInput) {
+            await fillEditable(answerInput, answerHtml);
+            console.log(`✅ 成功填充参考答案和 ${points.length} 条评分要点`);
+        } else {
+            console.warn("⚠️ 答案输入框未找到");
+        }
+
+        // 步骤 3: 填充解析
+        console.log(`开始输入解析...`);
+        const explanationInput = findEditor("请录入解析");
+        if (explanationInput) {
+            await fillEditable(explanationInput, questionData.analysis);
+            console.log(`✅ 成功填充解析`);
+        } else {
+            console.warn(`⚠️ 解析输入框未找到`);
+        }
+
+        console.log(`👍 第 ${questionIndex + 1} 题配置完成！`);
+
+    } catch (error) {
+        console.error(`💥 配置第${questionIndex + 1}题时发生错误:`, error);
+    }
+}
+
+// ----------- 主执行函数 -----------
+
+/**
+ * 处理所有简答题
+ */
+async function processAllQuestions() {
+    console.log(`🚀 简答题配置脚本启动，准备处理 ${Questions.length} 道题目...`);
+
+    try {
+        for (const [index, questionData] of Questions.entries()) {
+            await configureShortAnswerQuestion(index, questionData);
+            await delay(500); // 题目间延时
+        }
+
+        console.log("\n🎉🎉🎉 所有题目均已成功配置！");
+
+    } catch (error) {
+        console.error("💥 脚本执行过程中发生严重错误:", error);
+        console.error("请检查页面结构或刷新页面后重试。");
+    }
+}
+
+// 启动脚本
+processAllQuestions();
+
+// 导出函数供手动调用
+console.log("🎉 简答题一键配置脚本已加载！");
+console.log("脚本功能：1. 题干设置 -> 2. 参考答案和评分要点 -> 3. 解析输入");
+console.log("可用函数：");
+console.log("- processAllQuestions()：重新执行完整配置");
+console.log("- configureShortAnswerQuestion(index, data)：配置单个题目");
+
+// 挂载到window对象
+window.processAllQuestions = processAllQuestions;
+window.configureShortAnswerQuestion = configureShortAnswerQuestion;"#,
+        )
+    }
+
+    fn get_listening_compound_code(&self) -> String {
+        String::from(
+            r#"
+//MARK： 使用XPath查找包含"阅读理解"文本的元素
+function clickReadingElement() {
+    // XPath表达式：查找class包含"tag"且包含"阅读理解"文本的元素
+    var xpath = "//*[contains(@class,'tag') and contains(text(),'阅读理解')]";
+
+    // 执行XPath查询
+    var result = document.evaluate(
+        xpath,
+        document,
+        null,
+        XPathResult.FIRST_ORDERED_NODE_TYPE,
+        null
+    );
+
+    // 如果找到元素，点击它
+    if (result.singleNodeValue) {
+        result.singleNodeValue.click();
+        console.log("成功点击阅读理解元素");
+        return true;
+    } else {
+        console.log("未找到包含'阅读理解'文本的元素");
+        return false;
+    }
+}
+
+// 完整的操作流程
+async function operateElements() {
+    // 1. 点击下拉框
+    var selectDiv = document.querySelector('div[title="单选题"]');
+    if (selectDiv) {
+        selectDiv.click();
+
+        // 2. 选择复合题 - 使用 Promise 替代 setTimeout
+        await new Promise(resolve => {
+            setTimeout(function () {
+                var options = document.querySelectorAll('li.ant-select-dropdown-menu-item');
+                for (var i = 0; i < options.length; i++) {
+                    if (options[i].textContent.trim() === '复合题') {
+                        options[i].click();
+                        break;
+                    }
+                }
+                resolve();
+            }, 100);
+        });
+
+        // 3. 使用XPath点击阅读理解标签 - 使用 Promise 替代 setTimeout
         await new Promise(resolve => {
             setTimeout(function () {
                 clickReadingElement();
@@ -1796,7 +3115,7 @@ async function processAllQuestions() {
             await fillEditableDiv(currentForm, '请录入小题题干', questionData.stem);
 
             // 步骤 4: 填充选项
-            var optionInputs = currentForm.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+            var optionInputs = currentForm.querySelectorAll('{{option_selector}}');
             for (let i = 0; i < questionData.options.length; i++) {
                 if (optionInputs[i]) {
                     optionInputs[i].classList.remove('placeholder');
@@ -1986,10 +3305,10 @@ async function fillQuestionContent(questionData) {
     await delay(800);
 
     // 找到当前活动的题目表单容器
-    let currentForm = document.querySelector('.question-item.active');
+    let currentForm = document.querySelector('{{question_item_selector}}.active');
     if (!currentForm) {
         // 备用选择器：查找最后一个题目容器或当前编辑的题目
-        const allQuestions = document.querySelectorAll('.question-item');
+        const allQuestions = document.querySelectorAll('{{question_item_selector}}');
         if (allQuestions.length > 0) {
             currentForm = allQuestions[allQuestions.length - 1];
         }
@@ -2016,10 +3335,10 @@ async function fillQuestionContent(questionData) {
     await delay(300);
 
     // 步骤 4: 填充选项
-    var optionInputs = currentForm.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+    var optionInputs = currentForm.querySelectorAll('{{option_selector}}');
     if (optionInputs.length === 0) {
         // 备用选择器
-        optionInputs = document.querySelectorAll('.options .ckeditor_div[contenteditable="true"]');
+        optionInputs = document.querySelectorAll('{{option_selector}}');
     }
 
     for (let i = 0; i < questionData.options.length; i++) {
@@ -2052,16 +3371,7 @@ async function fillQuestionContent(questionData) {
     await fillEditableDiv(currentForm, '请录入解析', questionData.analysis);
 
     // 点击保存按钮
-    var saveButton = document.querySelector('button.ant-btn.ant-btn-primary[data-v-4c71fb2d]');
-    if (!saveButton) {
-        // 备用选择器
-        saveButton = document.querySelector('button.ant-btn.ant-btn-primary');
-        if (!saveButton) {
-            saveButton = Array.from(document.querySelectorAll('button')).find(btn =>
-                btn.textContent.includes('保存') || btn.textContent.includes('保 存')
-            );
-        }
-    }
+    var saveButton = await findSaveButton();
 
     if (saveButton) {
         saveButton.click();
@@ -2139,7 +3449,7 @@ function triggerInputEvents(element) {
 // 定位并点击最后一题的函数
 async function locateAndClickLastQuestion() {
     // 查找所有题目容器
-    var allQuestions = document.querySelectorAll('.question-item');
+    var allQuestions = document.querySelectorAll('{{question_item_selector}}');
 
     if (allQuestions.length > 0) {
         // 获取最后一个题目
@@ -2356,7 +3666,7 @@ async function fillQuestionContent(questionData) {
     await new Promise(resolve => setTimeout(resolve, 800));
 
     // 填充题干内容 - 针对填空题的编辑器
-    var stemEditor = document.querySelector('.ql-editor[data-placeholder="请录入题干"]');
+    var stemEditor = document.querySelector('{{stem_selector}}');
     if (!stemEditor) {
         // 备用选择器 - 查找题干内容编辑器
         stemEditor = document.querySelector('div[contenteditable="true"][placeholder*="题干"]');
@@ -2405,8 +3715,8 @@ async function fillQuestionContent(questionData) {
         console.error('❌ 未找到解析编辑器');
     }
     // 点击保存按钮
-    var saveButton = document.querySelector('button.ant-btn.ant-btn-primary[data-v-4c71fb2d]');
-    if (saveButton && saveButton.textContent.includes('保 存')) {
+    var saveButton = await findSaveButton();
+    if (saveButton) {
         saveButton.click();
         console.log('✅ 已点击保存按钮');
         await new Promise(resolve => setTimeout(resolve, 1000));
@@ -2641,7 +3951,7 @@ async function simulateTypingInput(element, text) {
 // 定位并点击最后一题的函数
 async function locateAndClickLastQuestion() {
     // 查找所有题目容器
-    var allQuestions = document.querySelectorAll('.question-item');
+    var allQuestions = document.querySelectorAll('{{question_item_selector}}');
 
     if (allQuestions.length > 0) {
         // 获取最后一个题目
@@ -2899,7 +4209,7 @@ main(questionTags);
     }
 }
 /// 题目结构体
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Question {
     /// 题目类型
     pub question_type: QuestionType,
@@ -2913,7 +4223,17 @@ pub struct Question {
     pub img_path: Option<PathBuf>,
     /// 输出结果（可选）
     pub output: Option<String>,
-    /// 附加代码（可选）
+    /// 年级、难度等提示词参数
+    #[serde(default)]
+    pub params: PromptParams,
+    /// 科目标签，用于题库归类
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// 年级标签，用于题库归类
+    #[serde(default)]
+    pub grade: Option<String>,
+    /// 附加代码（可选），可由题目类型重新生成，因此不序列化
+    #[serde(skip)]
     pub additional_code: String,
 }
 #[allow(dead_code)]
@@ -2930,12 +4250,47 @@ impl Question {
     pub fn set_img_path(&mut self, path: Option<PathBuf>) {
         self.img_path = path;
     }
+    /// 设置科目标签，空字符串视为未设置
+    pub fn set_subject(&mut self, subject: Option<String>) {
+        self.subject = subject.filter(|subject| !subject.trim().is_empty());
+    }
+    /// 设置年级标签，空字符串视为未设置
+    pub fn set_grade(&mut self, grade: Option<String>) {
+        self.grade = grade.filter(|grade| !grade.trim().is_empty());
+    }
 
-    /// 创建新的题目
+    /// 使用默认设置创建新的题目
     pub fn new(question_type: QuestionType, stem: String, img_path: Option<PathBuf>) -> Self {
-        let prompt_template = PromptTemplate::new(question_type);
+        Self::new_with_params(question_type, stem, img_path, PromptParams::default(), &PromptOptions::default())
+    }
+
+    /// 按指定设置创建带年级、难度参数的题目
+    pub fn new_with_params(
+        question_type: QuestionType,
+        stem: String,
+        img_path: Option<PathBuf>,
+        params: PromptParams,
+        options: &PromptOptions,
+    ) -> Self {
+        let prompt_template = PromptTemplate::new_with_params(question_type, params.clone(), options);
         let prompt = prompt_template.get_prompt();
 
+        let stem = if options.dehyphenate_stem {
+            utility::dehyphenate(&stem)
+        } else {
+            stem
+        };
+
+        let max_chars = options.max_stem_chars;
+        let (stem, truncated) = truncate_stem(&stem, max_chars);
+        if truncated {
+            tracing::warn!(
+                "[question_type] Stem exceeds {} chars, truncated to {} chars",
+                max_chars,
+                stem.chars().count()
+            );
+        }
+
         Self {
             question_type,
             id: Uuid::new_v4(),
@@ -2943,12 +4298,45 @@ impl Question {
             stem,
             img_path,
             output: None,
-            additional_code: AdditionalCodeGenerator::new(question_type).get_code(),
+            params,
+            subject: None,
+            grade: None,
+            additional_code: options.scripts.generator(question_type).get_code(),
+        }
+    }
+    /// 使用相同的题干、图片、提示词参数和标签生成另一种题型的新题目
+    pub fn regenerate_as(&self, question_type: QuestionType, options: &PromptOptions) -> Self {
+        Self {
+            subject: self.subject.clone(),
+            grade: self.grade.clone(),
+            ..Self::new_with_params(
+                question_type,
+                self.stem.clone(),
+                self.img_path.clone(),
+                self.params.clone(),
+                options,
+            )
         }
     }
     pub fn prompt_stem(&self) -> String {
         self.stem.clone() + &self.prompt.clone()
     }
+    /// 题干字符数超过 `limit` 时返回提示信息，按字符而不是字节计数
+    pub fn stem_length_warning(&self, limit: usize) -> Option<String> {
+        let chars = self.stem.chars().count();
+        (chars > limit).then(|| {
+            format!(
+                "⚠️ 题干有 {} 字，超过 {} 字的建议上限，回复可能被截断，建议拆分后分别发送",
+                chars, limit
+            )
+        })
+    }
+
+    /// 估算发送给模型的提示词 token 数（不含图片）
+    pub fn estimated_prompt_tokens(&self) -> usize {
+        utility::estimate_tokens(&self.prompt_stem())
+    }
+
     /// 获取题目ID
     pub fn get_id(&self) -> &Uuid {
         &self.id
@@ -2974,6 +4362,16 @@ impl Question {
         self.img_path.as_ref()
     }
 
+    /// 获取科目标签
+    pub fn get_subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// 获取年级标签
+    pub fn get_grade(&self) -> Option<&str> {
+        self.grade.as_deref()
+    }
+
     /// 获取输出结果
     pub fn get_output(&self) -> Option<&str> {
         self.output.as_deref()
@@ -2989,6 +4387,27 @@ impl Question {
         }
         final_output
     }
+
+    /// 把最终输出（模型输出和附加代码）写入文件，内容与复制到剪贴板的一致
+    pub fn write_output_to(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.get_final_output())
+    }
+    /// 把模型输出解析为结构化的题目
+    pub fn parsed_output(&self) -> Result<Vec<ParsedQuestion>, ParseError> {
+        parser::parse_questions(self.output.as_deref().ok_or(ParseError::NoOutput)?)
+    }
+
+    /// 按题型校验模型输出的结构，返回校验通过的题目数量
+    pub fn validate_output(&self) -> Result<usize, Vec<SchemaError>> {
+        let output = self.output.as_deref().ok_or_else(|| vec![SchemaError::Parse(ParseError::NoOutput)])?;
+        parser::validate_schema(self.question_type, output)
+    }
+
+    /// 统计模型输出中 `Questions` 数组生成的题目数量
+    pub fn count_generated(&self) -> Option<usize> {
+        utility::count_array_objects(self.output.as_deref()?, "Questions")
+    }
+
     /// 检查题目是否完整
     pub fn is_complete(&self) -> bool {
         !self.stem.is_empty() && self.output.is_some()
@@ -3022,6 +4441,31 @@ impl Question {
 mod tests {
     use super::*;
 
+    const ALL_TYPES: [QuestionType; 12] = [
+        QuestionType::SingleChoice,
+        QuestionType::Reading,
+        QuestionType::ClozeTest,
+        QuestionType::ListeningSingle,
+        QuestionType::ListeningCompound,
+        QuestionType::MutiTiankong,
+        QuestionType::ClozeTestNote,
+        QuestionType::GeneralFill,
+        QuestionType::CompleteQuestion,
+        QuestionType::Matching,
+        QuestionType::ShortAnswer,
+        QuestionType::Ordering,
+    ];
+
+    /// 按指定设置创建不带年级、难度参数的提示模板
+    fn template_with(question_type: QuestionType, options: &PromptOptions) -> PromptTemplate {
+        PromptTemplate::new_with_params(question_type, PromptParams::default(), options)
+    }
+
+    /// 使用默认设置创建带年级、难度参数的提示模板
+    fn template_with_params(question_type: QuestionType, params: PromptParams) -> PromptTemplate {
+        PromptTemplate::new_with_params(question_type, params, &PromptOptions::default())
+    }
+
     #[test]
     fn test_question_type_as_str() {
         assert_eq!(QuestionType::SingleChoice.as_str(), "单选题");
@@ -3042,6 +4486,32 @@ mod tests {
         assert!(!question.is_complete()); // 没有输出结果，所以不完整
     }
 
+    #[test]
+    fn test_prompt_stem_is_stem_plus_template() {
+        for question_type in ALL_TYPES {
+            let question = Question::new(question_type, "预览题干".to_string(), None);
+            let template = PromptTemplate::new(question_type).get_prompt();
+            assert_eq!(question.prompt_stem(), format!("预览题干{}", template));
+        }
+    }
+
+    #[test]
+    fn test_every_type_has_example_stem() {
+        for question_type in ALL_TYPES {
+            assert!(!question_type.example_stem().trim().is_empty(), "{} 缺少示例", question_type.as_str());
+        }
+    }
+
+    #[test]
+    fn test_prompt_samples_match_schema() {
+        for question_type in ALL_TYPES {
+            let template = PromptTemplate::new(question_type).get_prompt();
+            if let Err(errors) = parser::validate_schema(question_type, &template) {
+                panic!("{} 示例不符合结构：{:?}", question_type.as_str(), errors);
+            }
+        }
+    }
+
     #[test]
     fn test_prompt_template() {
         let template = PromptTemplate::new(QuestionType::SingleChoice);
@@ -3051,6 +4521,165 @@ mod tests {
         assert!(prompt.contains("Questions"));
     }
 
+    #[test]
+    fn test_prompt_analysis_order() {
+        // 默认顺序不改动模板
+        let prompt = PromptTemplate::new(QuestionType::SingleChoice).get_prompt();
+        assert_eq!(prompt, PromptTemplate::get_single_choice_prompt());
+
+        let answer_first = PromptOptions { analysis_order: AnalysisOrder::AnswerFirst, ..PromptOptions::default() };
+        let prompt = template_with(QuestionType::SingleChoice, &answer_first).get_prompt();
+        assert!(prompt.contains("格式：故答案为，考点，分析"));
+        assert!(prompt.contains(
+            "analysis: \"故答案为：programming。考点：编程语言识别。分析：Python是一种高级编程语言，广泛用于数据科学、人工智能等领域。\""
+        ));
+
+        // 任何题型都不再残留先写考点的格式说明或示例
+        for question_type in ALL_TYPES {
+            let prompt = template_with(question_type, &answer_first).get_prompt();
+            assert!(!prompt.contains("考点，分析，故答案为"), "{:?}", question_type);
+            assert!(!prompt.contains("原文，分析，故答案为"), "{:?}", question_type);
+            for line in prompt.lines().filter(|line| line.contains("analysis") && line.contains("故答案为：")) {
+                let value = line.split_once("analysis").unwrap().1;
+                assert!(value.find("故答案为：") < value.find("分析："), "{:?}: {}", question_type, line);
+            }
+        }
+
+        let prompt = template_with(QuestionType::GeneralFill, &answer_first).get_prompt();
+        assert!(prompt.contains("analysis: \"1. 故答案为：John。考点：.....。分析：根据常见的自我介绍格式，名字是John.,<br>2. 故答案为： Canada。分析：.......。国家是Canada。\""));
+
+        let question = Question::new_with_params(
+            QuestionType::SingleChoice,
+            "题干".to_string(),
+            None,
+            PromptParams::default(),
+            &answer_first,
+        );
+        assert!(question.get_prompt().contains("格式：故答案为，考点，分析"));
+    }
+
+    #[test]
+    fn test_prompt_params_injected() {
+        let params = PromptParams::new("高中", " ");
+        assert_eq!(params.difficulty, None);
+        let prompt = PromptTemplate::new_with_params(QuestionType::Reading, params.clone(), &PromptOptions::default()).get_prompt();
+        assert!(prompt.ends_with("请针对高中难度生成解析"));
+
+        let prompt = template_with_params(QuestionType::SingleChoice, PromptParams::new("初中", "较难")).get_prompt();
+        assert!(prompt.contains("请针对初中难度生成解析"));
+        assert!(prompt.contains("题目难度为较难"));
+
+        // 不指定参数时与 new 完全一致
+        assert_eq!(
+            template_with_params(QuestionType::Reading, PromptParams::default()).get_prompt(),
+            PromptTemplate::new(QuestionType::Reading).get_prompt()
+        );
+
+        let options = PromptOptions::default();
+        let question = Question::new_with_params(QuestionType::Reading, "题干".to_string(), None, params, &options);
+        assert!(question.get_prompt().contains("请针对高中难度生成解析"));
+        assert!(question
+            .regenerate_as(QuestionType::SingleChoice, &options)
+            .get_prompt()
+            .contains("请针对高中难度生成解析"));
+    }
+
+    #[test]
+    fn test_prompt_embeds_pregenerated_blank_ids() {
+        let ids = utility::generate_blank_ids(3);
+        let prompt = PromptTemplate::new(QuestionType::MutiTiankong)
+            .with_blank_ids(ids.clone())
+            .get_prompt();
+        assert!(prompt.contains(&format!("不要自己编写：{}", ids.join("、"))));
+
+        let english = PromptOptions { language: PromptLanguage::English, ..PromptOptions::default() };
+        let prompt = template_with(QuestionType::SingleChoice, &english)
+            .with_blank_ids(ids.clone())
+            .get_prompt();
+        assert!(prompt.contains(&ids.join(", ")));
+
+        // 模板中没有空时不追加
+        let prompt = PromptTemplate::new(QuestionType::Matching)
+            .with_blank_ids(ids.clone())
+            .get_prompt();
+        assert!(!prompt.contains(&ids[0]));
+        assert_eq!(
+            PromptTemplate::new(QuestionType::Reading).with_blank_ids(Vec::new()).get_prompt(),
+            PromptTemplate::new(QuestionType::Reading).get_prompt()
+        );
+
+        // 按设置的数量生成
+        let options = PromptOptions { blank_id_pool_size: 3, ..PromptOptions::default() };
+        let prompt = template_with(QuestionType::MutiTiankong, &options).get_prompt();
+        let (_, ids) = prompt.rsplit_once("不要自己编写：").unwrap();
+        assert_eq!(ids.lines().next().unwrap().split('、').count(), 3);
+        assert!(!PromptTemplate::new(QuestionType::MutiTiankong).get_prompt().contains("不要自己编写"));
+    }
+
+    #[test]
+    fn test_english_prompt_keeps_js_skeleton() {
+        let is_cjk = |c: char| ('\u{4e00}'..='\u{9fff}').contains(&c);
+        let english_options = PromptOptions { language: PromptLanguage::English, ..PromptOptions::default() };
+        for question_type in ALL_TYPES {
+            let chinese = PromptTemplate::new(question_type).get_prompt();
+            let english = template_with(question_type, &english_options).get_prompt();
+            assert_ne!(chinese, english, "{:?}", question_type);
+
+            let declaration = match question_type {
+                QuestionType::ClozeTestNote => "var questionTags",
+                QuestionType::CompleteQuestion => "var newContent",
+                _ => "var Questions",
+            };
+            assert!(english.contains(declaration), "{:?}", question_type);
+            // 字段名（`"stem"` 或 `stem:` 形式）出现的次数不变
+            for field in ["stem", "options", "answer", "answers", "analysis", "items", "matches", "points", "sequence", "题型类型"] {
+                let count = |prompt: &str| {
+                    prompt.matches(&format!("\"{}\"", field)).count() + prompt.matches(&format!("{}:", field)).count()
+                };
+                assert_eq!(count(&chinese), count(&english), "{:?} {}", question_type, field);
+            }
+            // 所有注释说明都已译成英文
+            for line in english.lines() {
+                if let Some((_, comment)) = line.split_once("//") {
+                    assert!(!comment.chars().any(is_cjk), "{:?}: {}", question_type, line);
+                }
+            }
+        }
+
+        let options = PromptOptions { analysis_order: AnalysisOrder::AnswerFirst, ..english_options };
+        let prompt = template_with(QuestionType::SingleChoice, &options).get_prompt();
+        assert!(prompt.starts_with("Convert the question I give you into JavaScript"));
+        assert!(prompt.contains("analysis: \"故答案为：programming。考点：编程语言识别。"));
+        assert!(!prompt.contains("注意事项"));
+    }
+
+    #[test]
+    fn test_custom_paragraph_style() {
+        let style = "text-align: left; text-indent: 0;";
+        let options = PromptOptions { paragraph_style: format!(" {} ", style), ..PromptOptions::default() };
+        let prompt = template_with(QuestionType::Reading, &options).get_prompt();
+        assert!(prompt.contains(&format!("<p style=\"{}\">", style)));
+        assert!(!prompt.contains(DEFAULT_PARAGRAPH_STYLE));
+
+        // 留空时使用默认样式
+        let options = PromptOptions { paragraph_style: String::new(), ..PromptOptions::default() };
+        assert_eq!(options.paragraph_style(), DEFAULT_PARAGRAPH_STYLE);
+        assert_eq!(
+            template_with(QuestionType::Reading, &options).get_prompt(),
+            PromptTemplate::new(QuestionType::Reading).get_prompt()
+        );
+
+        let reply = r#"var newContent = `
+    <p style="text-align: justify; text-indent: 2em;">First paragraph.</p>
+    <p>Second paragraph.</p>
+`;
+var Questions = [{ stem: `<p style="color: red;">Question</p>`, answer: 0 }];"#;
+        let styled = utility::apply_paragraph_style(reply, style);
+        let passage = &styled[utility::find_declaration(&styled, "newContent").unwrap()];
+        assert_eq!(passage.matches(&format!("<p style=\"{}\">", style)).count(), 2);
+        assert!(styled.contains(r#"<p style="color: red;">Question</p>"#));
+    }
+
     #[test]
     fn test_additional_code_generator() {
         let generator = AdditionalCodeGenerator::new(QuestionType::ClozeTest);
@@ -3059,4 +4688,361 @@ mod tests {
         assert!(code.contains("完形填空"));
         assert!(code.contains("function"));
     }
+
+    #[test]
+    fn test_additional_code_override_file() {
+        let dir = std::env::temp_dir().join(format!("question_tool_scripts_{}", Uuid::new_v4()));
+        let scripts_dir = dir.join(SCRIPTS_DIR_NAME);
+        std::fs::create_dir_all(&scripts_dir).unwrap();
+        let script = "document.querySelector('.ant-select-dropdown-menu-item').click();\nsaveButton.click();\n";
+        std::fs::write(scripts_dir.join("单选题.js"), script).unwrap();
+        std::fs::write(scripts_dir.join("阅读理解.js"), "  \n").unwrap();
+        std::fs::write(scripts_dir.join("完型填空.js"), script).unwrap();
+
+        // 覆盖脚本只在加载设置时读取一次，之后删除目录不影响生成
+        let scripts = ScriptOptions::load_from(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(scripts.overrides.len(), 1);
+
+        let code = scripts.generator(QuestionType::SingleChoice).get_code();
+        assert!(code.starts_with("document.querySelector('.ant-select-dropdown-menu-item').click();"));
+        // 覆盖脚本中的保存点击同样会加上重试
+        assert!(code.contains("await clickSaveWithRetry(saveButton);"));
+        assert!(code.contains("async function clickSaveWithRetry(saveButton)"));
+
+        // 没有覆盖文件、文件为空或文件名不是题型名称时使用内置脚本
+        let code = scripts.generator(QuestionType::Matching).get_code();
+        assert!(code.contains("configureMatchingQuestion"));
+        let generator = scripts.generator(QuestionType::Reading);
+        assert!(generator.override_code.is_none());
+        let template = SelectorConfig::default().apply(&generator.get_template_code());
+        assert!(generator.get_code().starts_with(&template.replace(SAVE_CLICK, "await clickSaveWithRetry(saveButton);")));
+        assert!(scripts.generator(QuestionType::ClozeTest).override_code.is_none());
+    }
+
+    #[test]
+    fn test_additional_code_selectors() {
+        let selectors = SelectorConfig {
+            question_item_selector: ".topic-card".to_string(),
+            save_button_selector: "button.save-question".to_string(),
+            ..SelectorConfig::default()
+        };
+        for question_type in ALL_TYPES {
+            let code = AdditionalCodeGenerator::new(question_type)
+                .with_selectors(selectors.clone())
+                .get_code();
+            assert!(!code.contains("{{"), "{:?} 的附加代码中仍有未替换的占位符", question_type);
+        }
+
+        let code = AdditionalCodeGenerator::new(QuestionType::SingleChoice)
+            .with_selectors(selectors)
+            .get_code();
+        assert!(code.contains("'.topic-card.active'"));
+        assert!(!code.contains("data-v-4c71fb2d"));
+
+        // 默认配置与原来写死的选择器一致
+        let code = AdditionalCodeGenerator::new(QuestionType::SingleChoice)
+            .with_selectors(SelectorConfig::default())
+            .get_code();
+        assert!(code.contains("'.question-item.active'"));
+        assert!(code.contains("'button.ant-btn.ant-btn-primary[data-v-4c71fb2d]'"));
+    }
+
+    #[test]
+    fn test_script_options_load_selectors_once() {
+        let dir = std::env::temp_dir().join(format!("question_tool_script_options_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(ScriptOptions::load_from(&dir), ScriptOptions::default());
+
+        std::fs::write(dir.join("selectors.json"), r#"{"question_item_selector": ".topic-card"}"#).unwrap();
+        let scripts = ScriptOptions::load_from(&dir);
+        // 读取后删除配置目录，生成题目时使用已读取的选择器
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let options = PromptOptions { scripts, ..PromptOptions::default() };
+        let question =
+            Question::new_with_params(QuestionType::SingleChoice, String::new(), None, PromptParams::default(), &options);
+        assert!(question.additional_code.contains("'.topic-card.active'"));
+        assert!(!Question::new(QuestionType::SingleChoice, String::new(), None).additional_code.contains(".topic-card"));
+    }
+
+    #[test]
+    fn test_save_click_retries() {
+        let code = AdditionalCodeGenerator::new(QuestionType::SingleChoice)
+            .with_save_retry(5, 1500)
+            .get_code();
+        // 只剩下重试函数内部的点击
+        assert_eq!(code.matches(SAVE_CLICK).count(), 1);
+        assert!(code.contains("await clickSaveWithRetry(saveButton);"));
+        assert!(code.contains("async function clickSaveWithRetry(saveButton)"));
+        assert!(code.contains("const maxAttempts = 5;"));
+        assert!(code.contains("const retryDelay = 1500;"));
+        // 点击前检查按钮可用，点击后检查表单是否已保存
+        assert!(code.contains("!saveButton.disabled"));
+        assert!(code.contains("for (let attempt = 1; attempt <= maxAttempts; attempt++)"));
+        assert!(code.contains("if (!isFormDirty())"));
+
+        // 没有保存按钮的题型不附加重试代码
+        let code = AdditionalCodeGenerator::new(QuestionType::Matching).get_code();
+        assert!(!code.contains("clickSaveWithRetry"));
+    }
+
+    #[test]
+    fn test_find_save_button_retries() {
+        let selectors = SelectorConfig {
+            save_button_selector: "button.save-question".to_string(),
+            ..SelectorConfig::default()
+        };
+        for question_type in [QuestionType::SingleChoice, QuestionType::ListeningSingle, QuestionType::MutiTiankong] {
+            let code = AdditionalCodeGenerator::new(question_type)
+                .with_selectors(selectors.clone())
+                .with_save_retry(4, 800)
+                .get_code();
+            assert!(code.contains("var saveButton = await findSaveButton();"), "{:?}", question_type);
+            assert_eq!(code.matches("async function findSaveButton()").count(), 1, "{:?}", question_type);
+            // 查找使用配置的选择器，找不到时按间隔重试并明确报错
+            assert!(code.contains("document.querySelector('button.save-question')"));
+            assert!(code.contains("const maxAttempts = 4;"));
+            assert!(code.contains("const retryDelay = 800;"));
+            assert!(code.contains("const button = locate();"));
+            assert!(code.contains("仍未找到保存按钮"));
+        }
+
+        let code = AdditionalCodeGenerator::new(QuestionType::Matching).get_code();
+        assert!(!code.contains("findSaveButton"));
+    }
+
+    #[test]
+    fn test_matching_question_type() {
+        assert_eq!(QuestionType::Matching.as_str(), "匹配题");
+        assert_eq!(QuestionType::from_str("匹配题"), Ok(QuestionType::Matching));
+
+        let prompt = PromptTemplate::new(QuestionType::Matching).get_prompt();
+        assert!(prompt.contains("匹配"));
+        assert!(prompt.contains("Questions"));
+        assert!(prompt.contains("\"matches\""));
+
+        let code = AdditionalCodeGenerator::new(QuestionType::Matching).get_code();
+        assert!(code.contains("匹配题"));
+        assert!(code.contains("questionData.matches"));
+    }
+
+    #[test]
+    fn test_ordering_question_type() {
+        assert_eq!(QuestionType::Ordering.as_str(), "排序题");
+        assert_eq!(QuestionType::from_str("排序题"), Ok(QuestionType::Ordering));
+
+        let prompt = PromptTemplate::new(QuestionType::Ordering).get_prompt();
+        assert!(prompt.contains("排序题"));
+        assert!(prompt.contains("var Questions"));
+        assert!(prompt.contains("\"sequence\": [1, 2, 0]"));
+        assert!(prompt.contains("每个索引恰好出现一次"));
+
+        let questions = parser::parse_questions(&prompt).unwrap();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].options.len(), 3);
+
+        let code = AdditionalCodeGenerator::new(QuestionType::Ordering).get_code();
+        assert!(code.contains("configureOrderingQuestion"));
+        assert!(code.contains("questionData.sequence"));
+        assert!(code.contains("isValidSequence"));
+    }
+
+    #[test]
+    fn test_short_answer_question_type() {
+        assert_eq!(QuestionType::ShortAnswer.as_str(), "简答题");
+        assert_eq!(QuestionType::from_str("简答题"), Ok(QuestionType::ShortAnswer));
+
+        let prompt = PromptTemplate::new(QuestionType::ShortAnswer).get_prompt();
+        assert!(prompt.contains("简答"));
+        assert!(prompt.contains("var Questions"));
+        assert!(prompt.contains("\"points\""));
+
+        let code = AdditionalCodeGenerator::new(QuestionType::ShortAnswer).get_code();
+        assert!(code.contains("简答题"));
+        assert!(code.contains("请录入答案"));
+        assert!(code.contains("questionData.points"));
+    }
+
+    #[test]
+    fn test_stem_length_warning_counts_chars() {
+        // 30 个汉字共 90 字节，按字符计数不超过上限
+        let stem = "阅读下面的短文，然后回答问题。".repeat(2);
+        let question = Question::new(QuestionType::Reading, stem, None);
+        assert_eq!(question.get_stem().chars().count(), 30);
+        assert_eq!(question.stem_length_warning(30), None);
+        assert_eq!(question.stem_length_warning(100), None);
+
+        let warning = question.stem_length_warning(29).unwrap();
+        assert!(warning.contains("题干有 30 字"));
+        assert!(warning.contains("超过 29 字"));
+
+        let question = Question::new(QuestionType::Reading, "Read the text 阅读".to_string(), None);
+        assert_eq!(question.stem_length_warning(16), None);
+        assert!(question.stem_length_warning(15).is_some());
+    }
+
+    #[test]
+    fn test_truncate_long_stem_at_sentence_boundary() {
+        let sentence = "This is a long OCR sentence. ";
+        let stem = sentence.repeat(DEFAULT_MAX_STEM_CHARS / sentence.len() + 10);
+
+        let question = Question::new(QuestionType::Reading, stem.clone(), None);
+        let truncated = question.get_stem();
+        assert!(truncated.chars().count() <= DEFAULT_MAX_STEM_CHARS);
+        assert!(truncated.ends_with("sentence."));
+        assert!(stem.starts_with(truncated));
+
+        // 按传入的设置截断，0 表示不限制
+        let options = PromptOptions { max_stem_chars: 12, ..PromptOptions::default() };
+        let question = Question::new_with_params(QuestionType::Reading, stem.clone(), None, PromptParams::default(), &options);
+        assert_eq!(question.get_stem(), "This is a lo");
+        let options = PromptOptions { max_stem_chars: 0, ..PromptOptions::default() };
+        let question = Question::new_with_params(QuestionType::Reading, stem.clone(), None, PromptParams::default(), &options);
+        assert_eq!(question.get_stem(), stem);
+
+        let (truncated, was_truncated) = truncate_stem("第一句。第二句很长很长", 8);
+        assert!(was_truncated);
+        assert_eq!(truncated, "第一句。");
+    }
+
+    #[test]
+    fn test_dehyphenate_stem_option() {
+        let stem = "a long program-\nming task".to_string();
+        assert_eq!(Question::new(QuestionType::Reading, stem.clone(), None).get_stem(), stem);
+
+        let options = PromptOptions { dehyphenate_stem: true, ..PromptOptions::default() };
+        let question = Question::new_with_params(QuestionType::Reading, stem, None, PromptParams::default(), &options);
+        assert_eq!(question.get_stem(), "a long programming task");
+    }
+
+    #[test]
+    fn test_short_stem_unchanged() {
+        let question = Question::new(QuestionType::SingleChoice, "短题干。".to_string(), None);
+        assert_eq!(question.get_stem(), "短题干。");
+
+        let (stem, truncated) = truncate_stem("no boundary here at all", 5);
+        assert!(truncated);
+        assert_eq!(stem, "no bo");
+
+        assert_eq!(truncate_stem("任意长度", 0), ("任意长度".to_string(), false));
+    }
+
+    #[test]
+    fn test_estimated_prompt_tokens() {
+        let question = Question::new(QuestionType::SingleChoice, "这是题目 stem text".to_string(), None);
+        let expected = utility::estimate_tokens(&question.prompt_stem());
+        assert_eq!(question.estimated_prompt_tokens(), expected);
+        assert!(question.estimated_prompt_tokens() > utility::estimate_tokens("这是题目 stem text"));
+    }
+
+    #[test]
+    fn test_count_generated_single_choice() {
+        let mut question = Question::new(QuestionType::SingleChoice, "题目".to_string(), None);
+        assert_eq!(question.count_generated(), None);
+
+        question.set_model_reply(
+            r#"var Questions = [
+    {
+        stem: `Which of the following is a <span data-blank-id="1"></span> language?`,
+        "options": ["Python", "HTML", "CSS", "HTTP"],
+        "answer": 0, // 答案索引：A
+        analysis: "考点：编程语言识别。{不是对象}"
+    },
+    {
+        stem: `The capital of France is ____.`,
+        "options": ["Paris", "London", "Berlin", "Rome"],
+        "answer": 0,
+        analysis: "考点：世界地理常识。"
+    }
+];"#
+            .to_string(),
+        );
+        assert_eq!(question.count_generated(), Some(2));
+    }
+
+    #[test]
+    fn test_regenerate_as_new_type() {
+        let mut question = Question::new(
+            QuestionType::SingleChoice,
+            "同一张截图".to_string(),
+            Some(PathBuf::from("/tmp/slint_paste_1.png")),
+        );
+        question.set_model_reply("var Questions = [];".to_string());
+
+        let regenerated = question.regenerate_as(QuestionType::Reading, &PromptOptions::default());
+        assert_eq!(regenerated.get_type(), QuestionType::Reading);
+        assert_eq!(regenerated.get_stem(), question.get_stem());
+        assert_eq!(regenerated.get_img_path(), question.get_img_path());
+        assert_ne!(regenerated.id, question.id);
+        assert_eq!(regenerated.get_output(), None);
+        assert_eq!(regenerated.get_prompt(), PromptTemplate::new(QuestionType::Reading).get_prompt());
+    }
+
+    #[test]
+    fn test_count_generated_error_reply() {
+        let mut question = Question::new(QuestionType::SingleChoice, "题目".to_string(), None);
+        question.set_model_reply("Error: request failed".to_string());
+        assert_eq!(question.count_generated(), None);
+    }
+
+    #[test]
+    fn test_count_generated_cloze() {
+        let mut question = Question::new(QuestionType::ClozeTest, "题目".to_string(), None);
+        question.set_model_reply(
+            r#"var newContent = `
+    <p>Last week, we asked { students } this <span data-blank-id="31">31</span>.</p>
+`;
+
+// 完形填空题目数据
+var Questions = [
+    { "options": ["reason", "question", "word", "way"], "answer": 1, "analysis": "故选B。" },
+    { "options": ["answers", "problems", "questions", "changes"], "answer": 0, "analysis": "故选A。" },
+    { "options": ["a", "b", "c", "d"], "answer": 2, "analysis": "故选C。" },
+];"#
+            .to_string(),
+        );
+        assert_eq!(question.count_generated(), Some(3));
+    }
+
+    #[test]
+    fn test_count_generated_multi_blank() {
+        let mut question = Question::new(QuestionType::MutiTiankong, "题目".to_string(), None);
+        question.set_model_reply(
+            r#"var Questions = [
+    {
+        stem: `I am from (1) <span style="${ {a: 1}.a }px">   </span> {hint} and (2) <span>   </span>`,
+        题型类型: "填空题",
+        answer: ["China", "Beijing"],
+        analysis: "1. 考点：.....<br>2. 分析：......."
+    }
+    {
+        stem: `Good morning }} my name is <span>   </span>`,
+        题型类型: "填空题",
+        answer: ["John"],
+        analysis: "故答案为：John"
+    },
+];"#
+            .to_string(),
+        );
+        assert_eq!(question.count_generated(), Some(2));
+
+        question.set_model_reply("var questionTags = [[\"完形：代词\"]];".to_string());
+        assert_eq!(question.count_generated(), None);
+    }
+
+    #[test]
+    fn test_write_output_to_file() {
+        let mut question = Question::new(QuestionType::SingleChoice, "题干".to_string(), None);
+        question.set_model_reply("var Questions = [];".to_string());
+        let path = std::env::temp_dir().join(format!("question_tool_output_{}.js", Uuid::new_v4()));
+
+        question.write_output_to(&path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, question.get_final_output());
+        assert!(written.starts_with("var Questions = [];\n\n"));
+        assert!(written.contains("clickSaveWithRetry"));
+
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,189 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::question_type::QuestionType;
+use super::request_logger;
+use super::stats;
+use super::utility;
+
+/// 日志中保留的回复字符数
+pub const RESPONSE_PREVIEW_CHARS: usize = 500;
+
+/// 请求日志最多保留的条数，启动时删除超出部分中最早的记录
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// 一次请求的日志记录，不包含提示词原文和密钥
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestLogEntry {
+    /// 请求完成时间（Unix 秒）
+    pub timestamp: u64,
+    /// 题型名称，与 `QuestionType::as_str()` 一致
+    pub question_type: String,
+    pub model: String,
+    /// 提示词的字符数
+    pub prompt_chars: usize,
+    /// 从发送到完成的耗时（毫秒）
+    pub latency_ms: u64,
+    pub success: bool,
+    /// 回复或错误信息，超过 [`RESPONSE_PREVIEW_CHARS`] 个字符时截断
+    pub response: String,
+}
+
+impl RequestLogEntry {
+    /// 创建一条当前时间的记录，回复会被截断
+    pub fn now(
+        question_type: QuestionType,
+        model: &str,
+        prompt_chars: usize,
+        latency: Duration,
+        success: bool,
+        response: &str,
+    ) -> Self {
+        let mut preview: String = response.chars().take(RESPONSE_PREVIEW_CHARS).collect();
+        if preview.len() < response.len() {
+            preview.push('…');
+        }
+        Self {
+            timestamp: stats::unix_now(),
+            question_type: question_type.as_str().to_string(),
+            model: model.to_string(),
+            prompt_chars,
+            latency_ms: latency.as_millis() as u64,
+            success,
+            response: preview,
+        }
+    }
+}
+
+/// 请求日志，每行一个 JSON 追加写入 `<config_dir>/request_log.jsonl`，用于审计和调整提示词
+#[derive(Clone, Debug)]
+pub struct RequestLog {
+    path: PathBuf,
+    max_entries: usize,
+}
+
+impl RequestLog {
+    /// 使用指定文件创建日志
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// 使用默认位置 `<config_dir>/request_log.jsonl` 创建日志
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(utility::app_config_dir()?.join("request_log.jsonl")))
+    }
+
+    /// 脱敏后追加一条记录，只追加一行，不读取已有的记录
+    pub fn append(&self, entry: &RequestLogEntry) -> Result<(), Box<dyn std::error::Error>> {
+        let mut value = serde_json::to_value(entry)?;
+        request_logger::redact(&mut value);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&value)?)?;
+        Ok(())
+    }
+
+    /// 超出条数上限时只保留最新的记录，返回删除的条数；启动时调用一次
+    pub fn trim(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+        if lines.len() <= self.max_entries {
+            return Ok(0);
+        }
+        let removed = lines.len() - self.max_entries;
+        fs::write(&self.path, lines[removed..].join("\n") + "\n")?;
+        tracing::info!("[request_log] Trimmed {} old entries from request log", removed);
+        Ok(removed)
+    }
+
+    /// 最近的 `n` 条记录，按时间从旧到新排列，无法解析的行会被跳过
+    #[cfg(test)]
+    pub fn last(&self, n: usize) -> Vec<RequestLogEntry> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        let entries: Vec<RequestLogEntry> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("[request_log] Skipping malformed log line: {}", e);
+                    None
+                }
+            })
+            .collect();
+        entries[entries.len().saturating_sub(n)..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_request_log_appends_one_line() {
+        let path = std::env::temp_dir().join(format!("question_tool_request_log_{}.jsonl", Uuid::new_v4()));
+        let mut log = RequestLog::new(&path);
+        log.max_entries = 2;
+
+        let reply = format!("var Questions = [{}];", "x".repeat(600));
+        let entry = RequestLogEntry::now(
+            QuestionType::SingleChoice,
+            "gpt-4o",
+            1234,
+            Duration::from_millis(850),
+            true,
+            &reply,
+        );
+        log.append(&entry).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 1);
+        let line: serde_json::Value = serde_json::from_str(content.trim()).unwrap();
+        assert_eq!(line["question_type"], "单选题");
+        assert_eq!(line["model"], "gpt-4o");
+        assert_eq!(line["prompt_chars"], 1234);
+        assert_eq!(line["latency_ms"], 850);
+        assert_eq!(line["success"], true);
+        assert_eq!(line["response"].as_str().unwrap().chars().count(), RESPONSE_PREVIEW_CHARS + 1);
+        assert!(line.get("prompt").is_none());
+
+        // 错误信息中的密钥会被脱敏，追加时不删除记录，裁剪后只保留最新的记录
+        let error = RequestLogEntry::now(
+            QuestionType::Reading,
+            "gpt-4o",
+            10,
+            Duration::from_millis(20),
+            false,
+            "Error: invalid key sk-abcdefghijklmnop",
+        );
+        log.append(&error).unwrap();
+        log.append(&error).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        assert_eq!(log.trim().unwrap(), 1);
+        assert_eq!(log.trim().unwrap(), 0);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        let last = log.last(5);
+        assert_eq!(last.len(), 2);
+        assert!(!last[1].response.contains("sk-abcdefghijklmnop"));
+        assert_eq!(log.last(1).len(), 1);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::logger;
+
+/// 需要整体脱敏的字段名（不区分大小写）
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "api_token",
+    "github_token",
+    "gemini_api_key",
+    "authorization",
+    "token",
+];
+
+/// 文本中常见的密钥前缀，出现在正文里时同样需要脱敏
+const SECRET_PREFIXES: &[&str] = &["sk-", "ghp_", "github_pat_"];
+
+/// 失败请求记录器
+/// 在请求失败时把请求体（已脱敏，图片只保留长度）写入 `<config_dir>/logs/failed_requests/<id>.json`，方便复现问题
+#[derive(Clone, Debug)]
+pub struct FailedRequestLogger {
+    dir: PathBuf,
+}
+
+impl FailedRequestLogger {
+    /// 创建写入指定目录的记录器
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// 使用默认位置 `<config_dir>/logs/failed_requests` 创建记录器
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(logger::log_dir()?.join("failed_requests")))
+    }
+
+    /// 脱敏后写入请求快照，返回写入的文件路径
+    pub fn dump(&self, mut snapshot: Value) -> std::io::Result<PathBuf> {
+        redact(&mut snapshot);
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.dir.join(format!("{}.json", Uuid::new_v4()));
+        let content = serde_json::to_string_pretty(&snapshot)?;
+        fs::write(&path, content)?;
+        tracing::info!("[request_logger] Failed request dumped to: {}", path.display());
+        Ok(path)
+    }
+}
+
+/// 构建 OpenAI 兼容的 chat 请求体，每张图片是用户消息中单独的一个内容部分
+pub fn chat_body_with_images(
+    model: &str,
+    system: &str,
+    text: &str,
+    image_data_urls: &[&str],
+    stream: bool,
+) -> Value {
+    let user_content = if image_data_urls.is_empty() {
+        json!(text)
+    } else {
+        let mut parts = vec![json!({ "type": "text", "text": text })];
+        parts.extend(
+            image_data_urls
+                .iter()
+                .map(|url| json!({ "type": "image_url", "image_url": { "url": url } })),
+        );
+        Value::Array(parts)
+    };
+
+    json!({
+        "model": model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": user_content },
+        ],
+        "stream": stream,
+    })
+}
+
+/// 递归脱敏 JSON 中的密钥，图片数据替换为长度占位符
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            // Gemini 的图片以 { mime_type, data } 的 inline_data 形式发送
+            let inline_image = map.contains_key("mime_type");
+            for (key, item) in map.iter_mut() {
+                let is_secret = SECRET_KEYS.iter().any(|k| key.eq_ignore_ascii_case(k));
+                match item {
+                    Value::String(s) if is_secret => *s = mask_secret(s),
+                    Value::String(s) if inline_image && key == "data" => *s = image_placeholder(s.len()),
+                    _ => redact(item),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) => *s = mask_data_url(s).unwrap_or_else(|| mask_inline_secrets(s)),
+        _ => {}
+    }
+}
+
+/// base64 图片数据的占位符
+fn image_placeholder(len: usize) -> String {
+    format!("<image data: {} chars>", len)
+}
+
+/// 把 `data:<mime>;base64,...` 形式的图片替换为只保留类型和长度的占位符
+fn mask_data_url(text: &str) -> Option<String> {
+    let (mime_type, data) = text.strip_prefix("data:")?.split_once(";base64,")?;
+    Some(format!("data:{};base64,{}", mime_type, image_placeholder(data.len())))
+}
+
+/// 只保留密钥的前 4 个字符
+fn mask_secret(secret: &str) -> String {
+    if secret.chars().count() <= 8 {
+        "****".to_string()
+    } else {
+        format!("{}****", secret.chars().take(4).collect::<String>())
+    }
+}
+
+/// 脱敏文本中以常见前缀开头的密钥
+fn mask_inline_secrets(text: &str) -> String {
+    let mut result = text.to_string();
+    for prefix in SECRET_PREFIXES {
+        let mut search_from = 0;
+        while let Some(offset) = result[search_from..].find(prefix) {
+            let start = search_from + offset;
+            // 跳过单词内部的匹配，例如 "task-based"
+            if result[..start].chars().next_back().is_some_and(|c| c.is_alphanumeric()) {
+                search_from = start + prefix.len();
+                continue;
+            }
+            let end = result[start..]
+                .find(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+                .map_or(result.len(), |i| start + i);
+            let masked = mask_secret(&result[start..end]);
+            result.replace_range(start..end, &masked);
+            search_from = start + masked.len();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::llm_backend::{LLMManager, MockBackend};
+    use async_llm::Error;
+    use std::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_failed_request_writes_redacted_body() {
+        let dir = std::env::temp_dir().join(format!("question_tool_failed_{}", Uuid::new_v4()));
+
+        let mut manager = LLMManager::new().with_failed_request_logger(FailedRequestLogger::new(&dir));
+        let backend = MockBackend::new(|_, _| Err(Error::Stream("mock failure".into())));
+        manager.add_backend(Box::new(backend.with_api_key("sk-test-secret-key-123456")));
+
+        let (sender, _receiver) = mpsc::channel();
+        let result = manager
+            .send_message("题目内容 ghp_abcdefghijklmnop".to_string(), None, sender)
+            .await;
+        assert!(result.is_err());
+
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().flatten().collect();
+        assert_eq!(files.len(), 1);
+        let content = fs::read_to_string(files[0].path()).unwrap();
+
+        assert!(content.contains("题目内容"));
+        assert!(content.contains("mock failure"));
+        assert!(content.contains("sk-t****"));
+        assert!(!content.contains("sk-test-secret-key-123456"));
+        assert!(!content.contains("ghp_abcdefghijklmnop"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_redact_replaces_image_data() {
+        let data_url = format!("data:image/png;base64,{}", "A".repeat(4096));
+        let mut snapshot = json!({
+            "body": chat_body_with_images("gpt-4o", "", "题目", &[&data_url], true),
+            "gemini": { "inline_data": { "mime_type": "image/jpeg", "data": "B".repeat(2048) } },
+        });
+        redact(&mut snapshot);
+
+        let content = snapshot.to_string();
+        assert!(!content.contains("AAAA"));
+        assert!(!content.contains("BBBB"));
+        assert_eq!(
+            snapshot["body"]["messages"][1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,<image data: 4096 chars>"
+        );
+        assert_eq!(snapshot["gemini"]["inline_data"]["data"], "<image data: 2048 chars>");
+        assert_eq!(snapshot["gemini"]["inline_data"]["mime_type"], "image/jpeg");
+        assert_eq!(snapshot["body"]["messages"][1]["content"][0]["text"], "题目");
+    }
+}
@@ -0,0 +1,332 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, mpsc};
+
+use async_llm::Error;
+
+use super::llm_backend::{LLMManager, LLMResponse, NETWORK_ERROR_PREFIX, TIMEOUT_ERROR_PREFIX};
+use super::question_type::{Question, QuestionType};
+use super::utility;
+
+/// 串行化队列文件的读写，避免发送线程和重试线程互相覆盖
+static QUEUE_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 判断错误是否由网络不可用引起（连接失败、DNS 解析失败、超时等）
+///
+/// 后端发送请求失败时按 reqwest 的 `is_connect()`/`is_timeout()` 加上 [`NETWORK_ERROR_PREFIX`]，
+/// 这里只匹配该前缀和 [`llm_backend::timeout_error`](super::llm_backend::timeout_error) 的前缀，
+/// 不会把错误详情中偶然出现的 "connect"、"network"、"connection refused" 等字样当作网络错误。
+pub fn is_network_error(error: &Error) -> bool {
+    let message = error.to_string();
+    [NETWORK_ERROR_PREFIX, TIMEOUT_ERROR_PREFIX]
+        .iter()
+        .any(|prefix| message.contains(prefix))
+}
+
+/// 离线请求队列，以 JSON 数组形式保存在 `<config_dir>/pending_requests.json`
+///
+/// 题目附带的图片会复制到队列文件旁的 `pending_images` 目录，退出时清理剪贴板临时图片不会影响重试
+#[derive(Clone, Debug)]
+pub struct RequestQueue {
+    path: PathBuf,
+}
+
+impl RequestQueue {
+    /// 使用指定文件创建队列
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 使用默认位置 `<config_dir>/pending_requests.json` 创建队列
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(utility::app_config_dir()?.join("pending_requests.json")))
+    }
+
+    /// 队列中图片的保存目录
+    fn images_dir(&self) -> PathBuf {
+        self.path.with_file_name("pending_images")
+    }
+
+    /// 将请求加入队列末尾，图片复制到队列目录下
+    pub fn enqueue(&self, question: &Question) -> Result<(), Box<dyn std::error::Error>> {
+        let mut question = question.clone();
+        if let Some(image) = question.get_img_path() {
+            let dir = self.images_dir();
+            fs::create_dir_all(&dir)?;
+            let extension = image.extension().and_then(|ext| ext.to_str()).unwrap_or("png");
+            let copy = dir.join(format!("{}.{}", question.id, extension));
+            fs::copy(image, &copy)?;
+            question.set_img_path(Some(copy));
+        }
+
+        let _guard = QUEUE_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pending = self.read();
+        pending.push(question.clone());
+        self.write(&pending)?;
+        tracing::info!(
+            "[request_queue] Queued question {} for retry ({} pending)",
+            question.id,
+            pending.len()
+        );
+        Ok(())
+    }
+
+    /// 获取所有待发送的请求
+    pub fn pending(&self) -> Vec<Question> {
+        let _guard = QUEUE_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.read()
+    }
+
+    /// 依次重新发送队列中的请求，返回成功完成的题目，失败的请求保留在队列中
+    ///
+    /// 每道题用 `manager_for(题型)` 创建的管理器发送，题型配置了单独的模型时按该模型重试。
+    pub async fn drain(&self, manager_for: impl Fn(QuestionType) -> LLMManager) -> Vec<Question> {
+        let pending = self.pending();
+        if pending.is_empty() {
+            return Vec::new();
+        }
+        tracing::info!("[request_queue] Draining {} pending requests", pending.len());
+
+        let mut completed = Vec::new();
+        for mut question in pending {
+            let (sender, receiver) = mpsc::channel::<LLMResponse>();
+            let result = manager_for(question.get_type())
+                .send_message(question.prompt_stem(), question.get_img_path().map(|p| p.as_path()), sender)
+                .await;
+
+            let reply = receiver
+                .try_iter()
+                .filter(|response| response.is_complete)
+                .last()
+                .filter(|response| !response.is_error());
+            match (result, reply) {
+                (Ok(()), Some(response)) => {
+                    question.set_model_reply(response.content);
+                    completed.push(question);
+                }
+                (Err(e), _) => {
+                    tracing::warn!("[request_queue] Retry of question {} failed: {}", question.id, e);
+                }
+                (Ok(()), None) => {
+                    tracing::warn!("[request_queue] Retry of question {} got no usable reply", question.id);
+                }
+            }
+        }
+
+        // 只移除已完成的请求，保留重试期间新加入的请求
+        let _guard = QUEUE_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let remaining: Vec<Question> = self
+            .read()
+            .into_iter()
+            .filter(|question| !completed.iter().any(|done| done.id == question.id))
+            .collect();
+        if let Err(e) = self.write(&remaining) {
+            tracing::error!("[request_queue] Failed to update queue file: {}", e);
+        }
+        let images_dir = self.images_dir();
+        for image in completed.iter().filter_map(|question| question.get_img_path()) {
+            if image.starts_with(&images_dir) {
+                let _ = fs::remove_file(image);
+            }
+        }
+        tracing::info!(
+            "[request_queue] Drained {} requests, {} still pending",
+            completed.len(),
+            remaining.len()
+        );
+        completed
+    }
+
+    fn read(&self) -> Vec<Question> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        match serde_json::from_str::<Vec<Question>>(&content) {
+            // 附加代码不落盘，重新发送只需要提示词和图片，不在这里生成
+            Ok(questions) => questions,
+            Err(e) => {
+                tracing::warn!("[request_queue] Ignoring malformed queue file: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn write(&self, questions: &[Question]) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(&self.path, serde_json::to_string_pretty(questions)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::llm_backend::{self, MockBackend};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_is_network_error_matches_connection_failures_only() {
+        // 连接一个已关闭的端口，reqwest 报告 is_connect()
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let error = reqwest::Client::new()
+            .get(format!("http://127.0.0.1:{}/models", port))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(is_network_error(&llm_backend::request_error("Models", error)));
+        assert!(is_network_error(&llm_backend::timeout_error(std::time::Duration::from_secs(5))));
+
+        // 服务端返回的错误详情中出现 connect、network、timeout 等单词不算网络错误
+        for message in [
+            "Custom API returned 400 Bad Request: unknown parameter connect_timeout",
+            "GitHub API returned 403 Forbidden: network policy denies this model",
+            "Models endpoint returned 500: upstream dns misconfigured",
+            "Custom API returned 502 Bad Gateway: upstream connection refused",
+        ] {
+            assert!(!is_network_error(&Error::Stream(message.into())), "{}", message);
+        }
+    }
+
+    /// 只有一个模拟后端的管理器
+    fn mock_manager(backend: MockBackend) -> LLMManager {
+        let mut manager = LLMManager::new();
+        manager.add_backend(Box::new(backend));
+        manager
+    }
+
+    #[tokio::test]
+    async fn test_offline_request_is_drained_when_backend_returns() {
+        let online = Arc::new(AtomicBool::new(false));
+        // 离线时和真实后端一样返回带网络错误前缀的错误
+        let manager_for = |_| {
+            let online = online.clone();
+            mock_manager(MockBackend::new(move |_, _| {
+                if online.load(Ordering::Relaxed) {
+                    Ok("var Questions = [];".to_string())
+                } else {
+                    Err(Error::Stream(format!("{} error sending request: connection refused", NETWORK_ERROR_PREFIX)))
+                }
+            }))
+        };
+
+        let path = std::env::temp_dir().join(format!("question_tool_queue_{}.json", Uuid::new_v4()));
+        let queue = RequestQueue::new(&path);
+        let question = Question::new(QuestionType::SingleChoice, "离线题目".to_string(), None);
+
+        let (sender, _receiver) = mpsc::channel();
+        let error = manager_for(question.get_type())
+            .send_message(question.prompt_stem(), None, sender)
+            .await
+            .unwrap_err();
+        assert!(is_network_error(&error));
+        queue.enqueue(&question).unwrap();
+
+        assert!(queue.drain(&manager_for).await.is_empty());
+        assert_eq!(queue.pending().len(), 1);
+
+        online.store(true, Ordering::Relaxed);
+        let drained = queue.drain(&manager_for).await;
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].id, question.id);
+        assert_eq!(drained[0].get_output(), Some("var Questions = [];"));
+        assert!(queue.pending().is_empty());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_queued_image_survives_paste_file_cleanup() {
+        use crate::core::clipboard_monitor::PasteFileGuard;
+
+        let dir = std::env::temp_dir().join(format!("question_tool_queue_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let queue = RequestQueue::new(dir.join("pending_requests.json"));
+
+        // 剪贴板临时图片由 PasteFileGuard 跟踪，退出时会被删除
+        let paste_files = PasteFileGuard::default();
+        let image = std::env::temp_dir().join(format!("paste_{}.png", Uuid::new_v4()));
+        fs::write(&image, b"png").unwrap();
+        paste_files.tracker().lock().unwrap().push(image.clone());
+
+        let question = Question::new(QuestionType::SingleChoice, "带图的离线题目".to_string(), Some(image.clone()));
+        queue.enqueue(&question).unwrap();
+        paste_files.cleanup();
+        assert!(!image.exists());
+
+        let queued = queue.pending().remove(0);
+        let queued_image = queued.get_img_path().unwrap().clone();
+        assert!(queued_image.starts_with(&dir));
+        assert_eq!(fs::read(&queued_image).unwrap(), b"png");
+
+        // 重试成功后删除队列中的图片副本
+        let drained = queue.drain(|_| mock_manager(MockBackend::fixed("var Questions = [];"))).await;
+        assert_eq!(drained.len(), 1);
+        assert!(!queued_image.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_each_queued_question_sends_its_own_image() {
+        let dir = std::env::temp_dir().join(format!("question_tool_queue_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let queue = RequestQueue::new(dir.join("pending_requests.json"));
+
+        let mut questions = Vec::new();
+        for (index, stem) in ["第一题", "第二题"].into_iter().enumerate() {
+            let image = dir.join(format!("source_{}.png", index));
+            fs::write(&image, stem).unwrap();
+            let question = Question::new(QuestionType::SingleChoice, stem.to_string(), Some(image));
+            queue.enqueue(&question).unwrap();
+            questions.push(question);
+        }
+
+        // 回复中带上收到的图片文件名
+        let drained = queue
+            .drain(|_| {
+                mock_manager(MockBackend::new(|_, image| {
+                    let name = image.and_then(|image| image.file_name()).unwrap().to_string_lossy();
+                    Ok(format!("var Questions = [/* {} */];", name))
+                }))
+            })
+            .await;
+
+        // 每道题发送的是自己那张图片的副本
+        assert_eq!(drained.len(), 2);
+        for (drained, question) in drained.iter().zip(&questions) {
+            assert_eq!(drained.id, question.id);
+            assert!(drained.get_output().unwrap().contains(&question.id.to_string()));
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_drain_uses_manager_for_each_question_type() {
+        let path = std::env::temp_dir().join(format!("question_tool_queue_{}.json", Uuid::new_v4()));
+        let queue = RequestQueue::new(&path);
+        for question_type in [QuestionType::SingleChoice, QuestionType::Reading] {
+            let question = Question::new(question_type, "离线题目".to_string(), None);
+            queue.enqueue(&question).unwrap();
+        }
+
+        // 按题型创建的管理器在回复中带上题型，模拟题型配置了单独的模型
+        let drained = queue
+            .drain(|question_type| {
+                let reply = format!("var Questions = [/* {} */];", question_type.as_str());
+                mock_manager(MockBackend::fixed(&reply))
+            })
+            .await;
+        let outputs: Vec<&str> = drained.iter().filter_map(|question| question.get_output()).collect();
+        assert_eq!(
+            outputs,
+            vec![
+                format!("var Questions = [/* {} */];", QuestionType::SingleChoice.as_str()),
+                format!("var Questions = [/* {} */];", QuestionType::Reading.as_str()),
+            ]
+        );
+
+        let _ = fs::remove_file(&path);
+    }
+}
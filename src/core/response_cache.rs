@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::utility;
+
+/// 默认最多缓存的回复数
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+/// 一条缓存的回复
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    output: String,
+}
+
+/// 模型回复缓存，以提示词和图片内容的哈希为键，按最近使用顺序淘汰
+///
+/// 配置了磁盘文件时，每次写入后同步保存到 `<config_dir>/response_cache.json`，下次启动时加载。
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    capacity: usize,
+    /// 最近使用的条目在前
+    entries: VecDeque<CacheEntry>,
+    path: Option<PathBuf>,
+}
+
+impl ResponseCache {
+    /// 创建只保存在内存中的缓存
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+            path: None,
+        }
+    }
+
+    /// 创建同时保存到指定文件的缓存，文件中已有的条目会被加载
+    pub fn with_disk(capacity: usize, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut cache = Self::new(capacity);
+        match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<VecDeque<CacheEntry>>(&content) {
+                Ok(entries) => cache.entries = entries,
+                Err(e) => tracing::warn!("[response_cache] Ignoring malformed cache file: {}", e),
+            },
+            Err(e) => tracing::debug!("[response_cache] No cache loaded from {}: {}", path.display(), e),
+        }
+        cache.entries.truncate(cache.capacity);
+        cache.path = Some(path);
+        cache
+    }
+
+    /// 使用默认位置 `<config_dir>/response_cache.json` 创建磁盘缓存
+    pub fn open_default(capacity: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::with_disk(capacity, utility::app_config_dir()?.join("response_cache.json")))
+    }
+
+    /// 计算缓存键：提供商、模型、规范化空白后的提示词和图片字节的 FNV-1a 哈希
+    ///
+    /// 只有空白不同的题干共用缓存，题干内容有任何修改或换了提供商、模型都不会命中。
+    /// 标准库的哈希算法不保证跨版本稳定，磁盘缓存需要固定的算法。
+    pub fn key(provider: &str, model: &str, prompt: &str, image_path: Option<&Path>) -> String {
+        let mut hash = FNV_OFFSET;
+        for part in [provider, model] {
+            hash = fnv1a(fnv1a(hash, part.as_bytes()), &[0]);
+        }
+        hash = fnv1a(hash, utility::normalize_whitespace(prompt).as_bytes());
+        if let Some(path) = image_path {
+            match fs::read(path) {
+                Ok(bytes) => hash = fnv1a(fnv1a(hash, &[0xff]), &bytes),
+                Err(e) => {
+                    // 图片读取失败时按路径区分，避免和无图片的请求共用缓存
+                    tracing::warn!("[response_cache] Failed to read image {}: {}", path.display(), e);
+                    hash = fnv1a(fnv1a(hash, &[0xfe]), path.to_string_lossy().as_bytes());
+                }
+            }
+        }
+        format!("{:016x}", hash)
+    }
+
+    /// 查找缓存的回复，命中时标记为最近使用
+    pub fn get(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|entry| entry.key == key)?;
+        let entry = self.entries.remove(index)?;
+        let output = entry.output.clone();
+        self.entries.push_front(entry);
+        Some(output)
+    }
+
+    /// 写入回复，超出容量时淘汰最久未使用的条目
+    pub fn insert(&mut self, key: String, output: String) {
+        self.entries.retain(|entry| entry.key != key);
+        self.entries.push_front(CacheEntry { key, output });
+        self.entries.truncate(self.capacity);
+        self.persist();
+    }
+
+    /// 清空缓存，配置了磁盘文件时同时删除文件
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if let Some(path) = &self.path {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    tracing::error!("[response_cache] Failed to remove cache file: {}", e);
+                }
+                _ => {}
+            }
+        }
+        tracing::info!("[response_cache] Cache cleared");
+    }
+
+    /// 缓存的条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let result = serde_json::to_string(&self.entries)
+            .map_err(|e| e.to_string())
+            .and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::error!("[response_cache] Failed to save cache file {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let image = std::env::temp_dir().join(format!("question_tool_cache_{}.png", Uuid::new_v4()));
+        fs::write(&image, b"image bytes").unwrap();
+
+        let key = ResponseCache::key("GPT", "gpt-4o", "题干", Some(&image));
+        assert_eq!(key, ResponseCache::key("GPT", "gpt-4o", "题干", Some(&image)));
+        assert_ne!(key, ResponseCache::key("GPT", "gpt-4o", "题干", None));
+        assert_ne!(key, ResponseCache::key("GPT", "gpt-4o", "另一个题干", Some(&image)));
+
+        let mut cache = ResponseCache::default();
+        assert_eq!(cache.get(&key), None);
+        cache.insert(key.clone(), "var Questions = [];".to_string());
+        assert_eq!(cache.get(&key), Some("var Questions = [];".to_string()));
+
+        // 图片内容变化后不再命中
+        fs::write(&image, b"other bytes").unwrap();
+        assert_eq!(cache.get(&ResponseCache::key("GPT", "gpt-4o", "题干", Some(&image))), None);
+
+        cache.clear();
+        assert_eq!(cache.get(&key), None);
+        let _ = fs::remove_file(&image);
+    }
+
+    #[test]
+    fn test_cache_misses_after_model_change() {
+        let key = ResponseCache::key("GPT", "gpt-4o", "题干", None);
+        let mut cache = ResponseCache::default();
+        cache.insert(key.clone(), "var Questions = [];".to_string());
+        assert_eq!(cache.get(&ResponseCache::key("GPT", "gpt-4o", "题干", None)), Some("var Questions = [];".to_string()));
+
+        // 换了模型或提供商后不再使用之前的回复
+        assert_eq!(cache.get(&ResponseCache::key("GPT", "gpt-4o-mini", "题干", None)), None);
+        assert_eq!(cache.get(&ResponseCache::key("Custom", "gpt-4o", "题干", None)), None);
+        // 分隔符避免提供商和模型拼接后相同
+        assert_ne!(ResponseCache::key("GP", "Tgpt-4o", "题干", None), key);
+    }
+
+    #[test]
+    fn test_cache_key_ignores_whitespace_only_edits() {
+        let key = ResponseCache::key("GPT", "gpt-4o", "Which of the following is a language?", None);
+        let mut cache = ResponseCache::default();
+        cache.insert(key.clone(), "var Questions = [];".to_string());
+
+        // 只有空白不同时命中
+        for stem in ["  Which of the following is a language?\n", "Which  of the\tfollowing\r\nis a language?"] {
+            assert_eq!(ResponseCache::key("GPT", "gpt-4o", stem, None), key);
+            assert_eq!(cache.get(&ResponseCache::key("GPT", "gpt-4o", stem, None)), Some("var Questions = [];".to_string()));
+        }
+
+        // 修改内容后不再命中
+        for stem in ["Which of the following is a programming language?", "Which of the following is a language.", "Whichof the following is a language?"] {
+            assert_ne!(ResponseCache::key("GPT", "gpt-4o", stem, None), key);
+            assert_eq!(cache.get(&ResponseCache::key("GPT", "gpt-4o", stem, None)), None);
+        }
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = ResponseCache::new(2);
+        cache.insert("a".to_string(), "A".to_string());
+        cache.insert("b".to_string(), "B".to_string());
+        // 访问 a 后 b 成为最久未使用的条目
+        assert_eq!(cache.get("a"), Some("A".to_string()));
+        cache.insert("c".to_string(), "C".to_string());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some("A".to_string()));
+        assert_eq!(cache.get("c"), Some("C".to_string()));
+    }
+
+    #[test]
+    fn test_disk_cache_persists() {
+        let path = std::env::temp_dir().join(format!("question_tool_cache_{}.json", Uuid::new_v4()));
+        let mut cache = ResponseCache::with_disk(2, &path);
+        cache.insert("a".to_string(), "A".to_string());
+
+        let mut reloaded = ResponseCache::with_disk(2, &path);
+        assert_eq!(reloaded.get("a"), Some("A".to_string()));
+
+        reloaded.clear();
+        assert!(!path.exists());
+    }
+}
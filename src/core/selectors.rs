@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// 附加代码中使用的 CSS 选择器，启动时从 `<config_dir>/selectors.json` 读取
+///
+/// 目标网站改版后只需修改配置文件，内置脚本中的 `{{xxx_selector}}` 占位符会替换为配置的值。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectorConfig {
+    /// 题干编辑器
+    pub stem_selector: String,
+    /// 题目列表中的每道题目，当前题目会再加上 `.active`
+    pub question_item_selector: String,
+    /// 选项输入框
+    pub option_selector: String,
+    /// 保存按钮，找不到时脚本仍会按按钮样式和文字查找
+    pub save_button_selector: String,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self {
+            stem_selector: r#".ql-editor[data-placeholder="请录入题干"]"#.to_string(),
+            question_item_selector: ".question-item".to_string(),
+            option_selector: r#".options .ckeditor_div[contenteditable="true"]"#.to_string(),
+            save_button_selector: "button.ant-btn.ant-btn-primary[data-v-4c71fb2d]".to_string(),
+        }
+    }
+}
+
+impl SelectorConfig {
+    /// 从指定文件加载配置，文件不存在或无法解析时使用默认值
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("[selectors] Invalid selector config {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// 把脚本中的 `{{xxx_selector}}` 占位符替换为配置的选择器
+    ///
+    /// 占位符都位于单引号字符串中，替换时转义反斜杠和单引号；空的配置项使用默认值。
+    pub fn apply(&self, script: &str) -> String {
+        let defaults = Self::default();
+        [
+            ("stem_selector", &self.stem_selector, &defaults.stem_selector),
+            ("question_item_selector", &self.question_item_selector, &defaults.question_item_selector),
+            ("option_selector", &self.option_selector, &defaults.option_selector),
+            ("save_button_selector", &self.save_button_selector, &defaults.save_button_selector),
+        ]
+        .into_iter()
+        .fold(script.to_string(), |script, (name, value, default)| {
+            let value = if value.trim().is_empty() { default } else { value.trim() };
+            let escaped = value.replace('\\', "\\\\").replace('\'', "\\'");
+            script.replace(&format!("{{{{{}}}}}", name), &escaped)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_selectors() {
+        let script = "document.querySelector('{{stem_selector}}');\n\
+            document.querySelectorAll('{{question_item_selector}}.active');\n\
+            form.querySelectorAll('{{option_selector}}');\n\
+            document.querySelector('{{save_button_selector}}');";
+
+        let defaults = SelectorConfig::default().apply(script);
+        assert_eq!(
+            defaults,
+            "document.querySelector('.ql-editor[data-placeholder=\"请录入题干\"]');\n\
+            document.querySelectorAll('.question-item.active');\n\
+            form.querySelectorAll('.options .ckeditor_div[contenteditable=\"true\"]');\n\
+            document.querySelector('button.ant-btn.ant-btn-primary[data-v-4c71fb2d]');"
+        );
+
+        let config = SelectorConfig {
+            question_item_selector: ".topic-card".to_string(),
+            save_button_selector: "button[title='保存']".to_string(),
+            option_selector: "  ".to_string(),
+            ..SelectorConfig::default()
+        };
+        let applied = config.apply(script);
+        assert!(applied.contains("querySelectorAll('.topic-card.active')"));
+        assert!(applied.contains(r"querySelector('button[title=\'保存\']')"));
+        // 空的配置项使用默认值
+        assert!(applied.contains(r#"querySelectorAll('.options .ckeditor_div[contenteditable="true"]')"#));
+        assert!(!applied.contains("{{"));
+    }
+
+    #[test]
+    fn test_load_selectors_falls_back_to_defaults() {
+        let path = std::env::temp_dir().join(format!("question_tool_selectors_{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(SelectorConfig::load_from(&path), SelectorConfig::default());
+
+        std::fs::write(&path, r#"{"question_item_selector": ".topic-card"}"#).unwrap();
+        let config = SelectorConfig::load_from(&path);
+        assert_eq!(config.question_item_selector, ".topic-card");
+        assert_eq!(config.option_selector, SelectorConfig::default().option_selector);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(SelectorConfig::load_from(&path), SelectorConfig::default());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::question_type::QuestionType;
+use super::utility;
+
+/// 每周统计覆盖的时长
+pub const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 一次请求的使用记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageRecord {
+    /// 请求完成时间（Unix 秒）
+    pub timestamp: u64,
+    pub question_type: QuestionType,
+    /// 估算的提示词 token 数
+    pub prompt_tokens: usize,
+    /// 估算的回复 token 数，失败时为 0
+    pub reply_tokens: usize,
+    pub success: bool,
+}
+
+impl UsageRecord {
+    /// 创建一条当前时间的记录
+    pub fn now(question_type: QuestionType, prompt_tokens: usize, reply_tokens: usize, success: bool) -> Self {
+        Self {
+            timestamp: unix_now(),
+            question_type,
+            prompt_tokens,
+            reply_tokens,
+            success,
+        }
+    }
+
+    /// 转为 CSV 行：`timestamp,question_type,prompt_tokens,reply_tokens,success`
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.timestamp,
+            self.question_type.as_str(),
+            self.prompt_tokens,
+            self.reply_tokens,
+            u8::from(self.success)
+        )
+    }
+
+    /// 解析一行 CSV，格式不对时返回 None
+    fn from_csv(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        let [timestamp, question_type, prompt_tokens, reply_tokens, success] = fields[..] else {
+            return None;
+        };
+        Some(Self {
+            timestamp: timestamp.parse().ok()?,
+            question_type: QuestionType::from_str(question_type).ok()?,
+            prompt_tokens: prompt_tokens.parse().ok()?,
+            reply_tokens: reply_tokens.parse().ok()?,
+            success: success == "1",
+        })
+    }
+}
+
+/// 本地使用统计，每行一条记录追加写入 `<config_dir>/usage_stats.csv`，只保存在本机
+#[derive(Clone, Debug)]
+pub struct UsageStats {
+    path: PathBuf,
+}
+
+impl UsageStats {
+    /// 使用指定文件创建统计
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 使用默认位置 `<config_dir>/usage_stats.csv` 创建统计
+    pub fn open_default() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::new(utility::app_config_dir()?.join("usage_stats.csv")))
+    }
+
+    /// 追加一条记录
+    pub fn record(&self, record: &UsageRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", record.to_csv())?;
+        Ok(())
+    }
+
+    /// 加载所有记录，无法解析的行会被跳过
+    pub fn load_all(&self) -> Vec<UsageRecord> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let record = UsageRecord::from_csv(line);
+                if record.is_none() {
+                    tracing::warn!("[stats] Skipping malformed usage line: {}", line);
+                }
+                record
+            })
+            .collect()
+    }
+}
+
+/// 一段时间内的使用汇总
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// 统计起始时间（Unix 秒）
+    pub since: u64,
+    pub total: usize,
+    pub succeeded: usize,
+    /// 每种题型的请求数，键为 `QuestionType::as_str()`
+    pub per_type: BTreeMap<String, usize>,
+    pub prompt_tokens: usize,
+    pub reply_tokens: usize,
+}
+
+impl Summary {
+    /// 成功率，没有请求时返回 None
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.succeeded as f64 / self.total as f64)
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.total == 0 {
+            return write!(f, "最近 7 天没有请求记录");
+        }
+        writeln!(
+            f,
+            "最近 7 天共 {} 次请求，成功 {} 次（{:.0}%）",
+            self.total,
+            self.succeeded,
+            self.success_rate().unwrap_or_default() * 100.0
+        )?;
+        writeln!(
+            f,
+            "估算 token：提示词 {}，回复 {}",
+            self.prompt_tokens, self.reply_tokens
+        )?;
+        let per_type = self
+            .per_type
+            .iter()
+            .map(|(question_type, count)| format!("{} {}", question_type, count))
+            .collect::<Vec<_>>()
+            .join("，");
+        write!(f, "按题型：{}", per_type)
+    }
+}
+
+/// 汇总 `since` 之后（含）的记录
+pub fn summarize(records: &[UsageRecord], since: u64) -> Summary {
+    let mut summary = Summary {
+        since,
+        ..Default::default()
+    };
+    for record in records.iter().filter(|record| record.timestamp >= since) {
+        summary.total += 1;
+        summary.succeeded += usize::from(record.success);
+        *summary
+            .per_type
+            .entry(record.question_type.as_str().to_string())
+            .or_default() += 1;
+        summary.prompt_tokens += record.prompt_tokens;
+        summary.reply_tokens += record.reply_tokens;
+    }
+    summary
+}
+
+/// 从默认统计文件汇总最近 7 天的使用情况
+pub fn weekly_summary() -> Summary {
+    let since = unix_now().saturating_sub(WEEK.as_secs());
+    match UsageStats::open_default() {
+        Ok(stats) => summarize(&stats.load_all(), since),
+        Err(e) => {
+            tracing::error!("[stats] Failed to open usage stats: {}", e);
+            Summary {
+                since,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_summary_from_fixture() {
+        let path = std::env::temp_dir().join(format!("question_tool_stats_{}.csv", Uuid::new_v4()));
+        fs::write(
+            &path,
+            "100,单选题,10,20,1\n\
+             900,阅读理解,100,0,0\n\
+             1000,单选题,30,40,1\n\
+             not,a,valid,line\n\
+             1100,阅读理解,50,60,1\n\
+             1150,完形填空,0,0,0\n",
+        )
+        .unwrap();
+        let stats = UsageStats::new(&path);
+        stats
+            .record(&UsageRecord {
+                timestamp: 1200,
+                question_type: QuestionType::Matching,
+                prompt_tokens: 5,
+                reply_tokens: 5,
+                success: true,
+            })
+            .unwrap();
+
+        let records = stats.load_all();
+        assert_eq!(records.len(), 6);
+
+        // 早于起始时间的第一条记录不计入
+        let summary = summarize(&records, 500);
+        assert_eq!(summary.total, 5);
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.success_rate(), Some(0.6));
+        assert_eq!(summary.prompt_tokens, 185);
+        assert_eq!(summary.reply_tokens, 105);
+        assert_eq!(summary.per_type.get("阅读理解"), Some(&2));
+        assert_eq!(summary.per_type.get("单选题"), Some(&1));
+        assert_eq!(summary.per_type.get("匹配题"), Some(&1));
+        assert_eq!(summary.per_type.get("完形填空"), Some(&1));
+        assert!(summary.to_string().starts_with("最近 7 天共 5 次请求，成功 3 次（60%）"));
+
+        assert_eq!(summarize(&records, 2000).success_rate(), None);
+        let _ = fs::remove_file(&path);
+    }
+}
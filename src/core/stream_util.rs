@@ -0,0 +1,107 @@
+use std::sync::mpsc;
+
+use async_llm::Error;
+use tokio_stream::StreamExt;
+
+use super::chat_completions::StreamDelta;
+use super::llm_backend::LLMResponse;
+
+/// 累积流式增量，每次追加后发送一次未完成的响应（回答和思考过程都是目前为止的全部文本）
+///
+/// 只有 role 的块或空增量直接跳过；流结束时返回累积的回答和思考过程，出错时立即返回错误。
+/// 完成响应由调用方在拿到完整内容后发送。
+pub async fn accumulate_stream<S>(
+    mut stream: S,
+    response_sender: &mpsc::Sender<LLMResponse>,
+) -> Result<StreamDelta, Error>
+where
+    S: tokio_stream::Stream<Item = Result<StreamDelta, Error>> + Unpin,
+{
+    let mut accumulated = StreamDelta::default();
+
+    while let Some(result) = stream.next().await {
+        match result {
+            Ok(delta) if !delta.is_empty() => {
+                accumulated.content.push_str(&delta.content);
+                accumulated.reasoning.push_str(&delta.reasoning);
+
+                tracing::trace!("[stream_util] Streaming response chunk, total length: {}", accumulated.content.len());
+                let _ = response_sender.send(LLMResponse {
+                    content: accumulated.content.clone(),
+                    is_complete: false,
+                    reasoning: accumulated.reasoning_text(),
+                });
+            }
+            Ok(_) => {
+                tracing::trace!("[stream_util] Skipping role-only or empty delta");
+            }
+            Err(e) => {
+                tracing::warn!("[stream_util] Streaming error during processing: {}", e);
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(accumulated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(content: &str) -> Result<StreamDelta, Error> {
+        Ok(StreamDelta { content: content.to_string(), ..Default::default() })
+    }
+
+    #[tokio::test]
+    async fn test_accumulate_stream_synthetic_chunks() {
+        let (sender, receiver) = mpsc::channel();
+        let chunks = tokio_stream::iter(vec![
+            Ok(StreamDelta::default()),
+            text("var "),
+            text(""),
+            text("Questions"),
+            text(" = [];"),
+        ]);
+
+        let content = accumulate_stream(chunks, &sender).await.unwrap().content;
+        assert_eq!(content, "var Questions = [];");
+
+        drop(sender);
+        let updates: Vec<(String, bool)> = receiver
+            .iter()
+            .map(|response| (response.content, response.is_complete))
+            .collect();
+        assert_eq!(
+            updates,
+            vec![
+                ("var ".to_string(), false),
+                ("var Questions".to_string(), false),
+                ("var Questions = [];".to_string(), false),
+            ]
+        );
+
+        // 流读到末尾时结束，出错时返回错误
+        let (sender, _receiver) = mpsc::channel();
+        let deltas = tokio_stream::iter(vec![text("partial"), Ok(StreamDelta::default())]);
+        assert_eq!(accumulate_stream(deltas, &sender).await.unwrap().content, "partial");
+        let failing = tokio_stream::iter(vec![text("partial"), Err(Error::Stream("connection reset".into()))]);
+        assert!(accumulate_stream(failing, &sender).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accumulate_stream_keeps_reasoning() {
+        let (sender, receiver) = mpsc::channel();
+        let thinking = |reasoning: &str| Ok(StreamDelta { reasoning: reasoning.to_string(), ..Default::default() });
+        let deltas = tokio_stream::iter(vec![thinking("先看"), thinking("题干"), text("var Questions = [];")]);
+
+        let accumulated = accumulate_stream(deltas, &sender).await.unwrap();
+        assert_eq!(accumulated.content, "var Questions = [];");
+        assert_eq!(accumulated.reasoning, "先看题干");
+
+        // 中间结果带上目前为止的思考过程
+        drop(sender);
+        let reasoning: Vec<Option<String>> = receiver.iter().map(|response| response.reasoning).collect();
+        assert_eq!(reasoning, vec![Some("先看".to_string()), Some("先看题干".to_string()), Some("先看题干".to_string())]);
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+use std::sync::mpsc;
+
+use async_llm::Error;
+
+use super::llm_backend::{LLMManager, LLMResponse};
+use super::utility;
+
+/// 翻译解析时发送给模型的指令，后面附上待翻译解析的 JSON 数组
+pub const TRANSLATE_ANALYSIS_INSTRUCTION: &str = "请把下面 JSON 数组中的每一条题目解析翻译为英文，\
+保留其中的 HTML 标签、选项字母和英文原文，“考点/分析/故答案为”分别译为 \"Key point\" / \"Analysis\" / \"So the answer is\"。\
+只输出一个长度相同、顺序相同的 JSON 字符串数组，不要输出其他内容：\n";
+
+/// 把模型输出中每道题的 `analysis` 翻译为英文后替换回原输出，其他内容保持不变
+///
+/// 只把解析文本发送给模型，不发送题干和选项。没有解析时直接返回原输出；
+/// 模型回复无法解析或条数不一致时返回错误，调用方应保留原输出。
+pub async fn translate_analysis(manager: &LLMManager, js: &str) -> Result<String, Error> {
+    let ranges = utility::analysis_value_ranges(js);
+    if ranges.is_empty() {
+        return Ok(js.to_string());
+    }
+    let analyses: Vec<String> = ranges.iter().map(|range| utility::js_string_value(&js[range.clone()])).collect();
+    let prompt = format!(
+        "{}{}",
+        TRANSLATE_ANALYSIS_INSTRUCTION,
+        serde_json::to_string_pretty(&analyses).map_err(|e| Error::Stream(e.to_string()))?
+    );
+
+    tracing::info!("[translate] Translating {} analyses to English", analyses.len());
+    let (sender, receiver) = mpsc::channel::<LLMResponse>();
+    manager.send_message(prompt, None, sender).await?;
+    let reply = receiver
+        .try_iter()
+        .filter(|response| response.is_complete)
+        .last()
+        .filter(|response| !response.is_error())
+        .ok_or_else(|| Error::Stream("翻译解析没有得到回复".into()))?;
+
+    let translated = parse_translations(&reply.content, analyses.len())?;
+    Ok(replace_ranges(js, &ranges, &translated))
+}
+
+/// 解析模型回复中的字符串数组，兼容代码块包裹和前后多余的文字
+fn parse_translations(reply: &str, expected: usize) -> Result<Vec<String>, Error> {
+    let start = reply.find('[');
+    let end = reply.rfind(']');
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err(Error::Stream("翻译结果中没有 JSON 数组".into())),
+    };
+    let translated: Vec<String> =
+        serde_json::from_str(json).map_err(|e| Error::Stream(format!("翻译结果无法解析: {}", e)))?;
+    if translated.len() != expected {
+        return Err(Error::Stream(format!("翻译结果有 {} 条，应为 {} 条", translated.len(), expected)));
+    }
+    Ok(translated)
+}
+
+/// 把每个范围替换为对应译文的 JSON 字符串字面量
+fn replace_ranges(js: &str, ranges: &[Range<usize>], values: &[String]) -> String {
+    let mut result = String::with_capacity(js.len());
+    let mut last = 0;
+    for (range, value) in ranges.iter().zip(values) {
+        result.push_str(&js[last..range.start]);
+        result.push_str(&serde_json::to_string(value).unwrap_or_default());
+        last = range.end;
+    }
+    result.push_str(&js[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::llm_backend::MockBackend;
+    use crate::core::parser;
+
+    #[tokio::test]
+    async fn test_translate_analysis_only_changes_analysis() {
+        let mut manager = LLMManager::new();
+        // 把收到的解析数组逐条加上 "EN:" 前缀
+        let backend = MockBackend::new(|text, _| {
+            let analyses: Vec<String> = serde_json::from_str(&text[text.find('[').unwrap()..]).unwrap();
+            let translated: Vec<String> = analyses.iter().map(|analysis| format!("EN: {}", analysis)).collect();
+            Ok(format!("```json\n{}\n```", serde_json::to_string(&translated).unwrap()))
+        });
+        let requests = backend.requests();
+        manager.add_backend(Box::new(backend));
+
+        let js = r#"var Questions = [
+    {
+        "stem": "题干一",
+        "options": ["甲", "乙"],
+        "answer": 0,
+        "analysis": "考点：名词。故答案为：A"
+    },
+    { stem: `题干二`, answer: ["Paris"], analysis: '考点：首都' },
+];"#;
+        let translated = translate_analysis(&manager, js).await.unwrap();
+
+        let before = parser::parse_questions(js).unwrap();
+        let after = parser::parse_questions(&translated).unwrap();
+        assert_eq!(after.len(), before.len());
+        for (before, after) in before.iter().zip(&after) {
+            assert_eq!(after.stem, before.stem);
+            assert_eq!(after.options, before.options);
+            assert_eq!(after.answer, before.answer);
+            assert_eq!(after.analysis, format!("EN: {}", before.analysis));
+        }
+
+        // 只发送解析，不发送题干和选项
+        let sent: Vec<String> = requests.lock().unwrap().iter().map(|(text, _)| text.clone()).collect();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].contains("考点：首都"));
+        assert!(!sent[0].contains("题干") && !sent[0].contains("Paris"));
+
+        // 没有解析时不请求模型
+        assert_eq!(translate_analysis(&manager, "var x = 1;").await.unwrap(), "var x = 1;");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_translations_checks_count() {
+        assert_eq!(parse_translations("[\"a\", \"b\"]", 2).unwrap(), vec!["a", "b"]);
+        assert!(parse_translations("[\"a\"]", 2).is_err());
+        assert!(parse_translations("no json", 1).is_err());
+    }
+}
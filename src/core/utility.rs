@@ -0,0 +1,1821 @@
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use base64::{Engine, engine::general_purpose};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, ImageReader, imageops};
+use serde::{Deserialize, Serialize};
+
+/// 发送前图片最长边的默认像素上限
+pub const DEFAULT_MAX_IMAGE_DIM: u32 = 2048;
+
+/// 发送给模型的图片编码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageEncodeFormat {
+    /// 无损 PNG，适合文字截图
+    #[default]
+    Png,
+    /// 有损 JPEG，`quality` 取值 1-100，适合照片类图片
+    Jpeg { quality: u8 },
+}
+
+impl ImageEncodeFormat {
+    /// 对应的 MIME 类型
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageEncodeFormat::Png => "image/png",
+            ImageEncodeFormat::Jpeg { .. } => "image/jpeg",
+        }
+    }
+}
+
+/// 空格 id 属性名
+const BLANK_ID_ATTR: &str = "data-blank-id=";
+
+/// 获取应用配置目录（`<config_dir>/question_tool`），不存在时自动创建
+pub fn app_config_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut config_dir = dirs::config_dir()
+        .or_else(dirs::home_dir)
+        .ok_or("无法找到配置目录")?;
+
+    config_dir.push("question_tool");
+
+    // 确保目录存在
+    if !config_dir.exists() {
+        std::fs::create_dir_all(&config_dir)?;
+    }
+
+    Ok(config_dir)
+}
+
+/// 读取图片、按需缩小后以指定格式编码为 base64
+///
+/// JPEG 不支持透明通道，编码前会丢弃 alpha。
+pub fn img_to_base64_with_format(
+    path: &Path,
+    max_dim: u32,
+    format: ImageEncodeFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let image = image::ImageReader::open(path)?.decode()?;
+    let image = downscale_image(image, max_dim);
+
+    let mut buf = Vec::new();
+    match format {
+        ImageEncodeFormat::Png => {
+            image.write_to(&mut std::io::Cursor::new(&mut buf), ImageFormat::Png)?;
+        }
+        ImageEncodeFormat::Jpeg { quality } => {
+            let encoder = JpegEncoder::new_with_quality(&mut buf, quality.clamp(1, 100));
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+        }
+    }
+    Ok(general_purpose::STANDARD.encode(&buf))
+}
+
+/// 读取图片并生成 `data:<mime>;base64,<data>` 格式的 data URL
+pub fn img_to_data_url(
+    path: &Path,
+    max_dim: u32,
+    format: ImageEncodeFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let base64 = img_to_base64_with_format(path, max_dim, format)?;
+    Ok(format!("data:{};base64,{}", format.mime_type(), base64))
+}
+
+/// 手动选择图片时允许的文件扩展名（与 image 启用的解码格式一致）
+pub const SUPPORTED_IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// 完整解码一次图片，确认文件是支持的格式且内容可用，返回图片尺寸
+pub fn decode_image_file(path: &Path) -> Result<(u32, u32), Box<dyn std::error::Error>> {
+    let image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    Ok((image.width(), image.height()))
+}
+
+/// 发送前检查图片是否完整：先读取文件头中的尺寸，再完整解码一次，截断或损坏的截图返回 false
+pub fn is_valid_image(path: &Path) -> bool {
+    let dimensions = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(image::ImageError::IoError)
+        .and_then(|reader| reader.into_dimensions());
+    match dimensions {
+        Ok((width, height)) if width > 0 && height > 0 => match decode_image_file(path) {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::warn!("[utility] Image {} failed to decode: {}", path.display(), e);
+                false
+            }
+        },
+        Ok(_) => false,
+        Err(e) => {
+            tracing::warn!("[utility] Image {} has an invalid header: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// 等比缩小图片，使最长边不超过 `max_dim`
+fn downscale_image(image: DynamicImage, max_dim: u32) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let longest = width.max(height);
+    if max_dim == 0 || longest <= max_dim {
+        return image;
+    }
+
+    let scale = max_dim as f64 / longest as f64;
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+    tracing::debug!(
+        "[utility] Downscaling image from {}x{} to {}x{}",
+        width,
+        height,
+        new_width,
+        new_height
+    );
+
+    DynamicImage::ImageRgba8(imageops::resize(
+        &image,
+        new_width,
+        new_height,
+        imageops::FilterType::Lanczos3,
+    ))
+}
+
+/// 界面预览图最长边的像素上限
+pub const THUMBNAIL_MAX_DIM: u32 = 800;
+
+/// 预览缩略图临时文件名的前缀
+pub const THUMBNAIL_FILE_PREFIX: &str = "question_tool_thumb_";
+
+/// 生成用于界面显示的缩略图，最长边不超过 `max_dim`，发送给模型的仍是原图
+///
+/// 缩略图按原图路径、大小、修改时间和 `max_dim` 缓存在临时目录，同一张图片再次显示时直接复用；
+/// 原图不超过 `max_dim`（或 `max_dim` 为 0）时直接返回原图路径。
+pub fn make_thumbnail(path: &Path, max_dim: u32) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let metadata = std::fs::metadata(path)?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+    max_dim.hash(&mut hasher);
+    let thumbnail = std::env::temp_dir().join(format!("{}{:016x}.png", THUMBNAIL_FILE_PREFIX, hasher.finish()));
+    if thumbnail.exists() {
+        return Ok(thumbnail);
+    }
+
+    let (width, height) = ImageReader::open(path)?.with_guessed_format()?.into_dimensions()?;
+    if max_dim == 0 || width.max(height) <= max_dim {
+        return Ok(path.to_path_buf());
+    }
+    let image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    // 先写入临时名再改名，避免另一个线程读到写了一半的缩略图
+    let partial = thumbnail.with_extension("partial");
+    image.thumbnail(max_dim, max_dim).save_with_format(&partial, ImageFormat::Png)?;
+    std::fs::rename(&partial, &thumbnail)?;
+    tracing::debug!(
+        "[utility] Created {}px thumbnail for {}x{} image {}",
+        max_dim,
+        width,
+        height,
+        path.display()
+    );
+    Ok(thumbnail)
+}
+
+/// 界面显示用的图片路径：缩略图生成失败时退回原图
+pub fn thumbnail_for_display(path: &Path) -> PathBuf {
+    make_thumbnail(path, THUMBNAIL_MAX_DIM).unwrap_or_else(|e| {
+        tracing::warn!("[utility] Failed to create thumbnail for {}: {}", path.display(), e);
+        path.to_path_buf()
+    })
+}
+
+/// 发送前为提高 OCR 识别率对图片做的预处理
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOpts {
+    /// 转为灰度图
+    pub grayscale: bool,
+    /// 对比度调整的百分比，正数增强、负数减弱，0 表示不调整
+    pub contrast: f32,
+    /// 二值化阈值（0-255），亮度不低于阈值的像素变为白色，其余变为黑色；None 表示不二值化
+    pub threshold: Option<u8>,
+}
+
+impl Default for PreprocessOpts {
+    fn default() -> Self {
+        Self {
+            grayscale: true,
+            contrast: 30.0,
+            threshold: None,
+        }
+    }
+}
+
+/// 按选项对图片做灰度、对比度和二值化处理，结果写入临时目录的 PNG 文件并返回其路径
+///
+/// 原图不会被修改，尺寸保持不变；调用方用完后负责删除返回的临时文件。
+pub fn preprocess_for_ocr(path: &Path, opts: PreprocessOpts) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let mut image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    if opts.grayscale || opts.threshold.is_some() {
+        image = DynamicImage::ImageLuma8(image.to_luma8());
+    }
+    if opts.contrast != 0.0 {
+        image = image.adjust_contrast(opts.contrast);
+    }
+    if let Some(threshold) = opts.threshold {
+        let mut luma = image.to_luma8();
+        for pixel in luma.pixels_mut() {
+            pixel.0[0] = if pixel.0[0] >= threshold { 255 } else { 0 };
+        }
+        image = DynamicImage::ImageLuma8(luma);
+    }
+
+    let output = std::env::temp_dir().join(format!("question_tool_ocr_{}.png", uuid::Uuid::new_v4()));
+    image.save_with_format(&output, ImageFormat::Png)?;
+    tracing::debug!(
+        "[utility] Preprocessed image {} -> {} ({:?})",
+        path.display(),
+        output.display(),
+        opts
+    );
+    Ok(output)
+}
+
+/// 粗略估算文本的 token 数：CJK 字符约 1 个 token，ASCII 单词约 0.75 个 token，其他字符按 1 个计算
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut char_tokens = 0usize;
+    let mut ascii_words = 0usize;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if c.is_ascii() {
+            let is_word_char = !c.is_ascii_whitespace();
+            if is_word_char && !in_word {
+                ascii_words += 1;
+            }
+            in_word = is_word_char;
+        } else {
+            in_word = false;
+            if !c.is_whitespace() {
+                char_tokens += 1;
+            }
+        }
+    }
+
+    char_tokens + (ascii_words * 3).div_ceil(4)
+}
+
+/// 去掉首尾空白，并把连续的空白（空格、制表符、换行）合并为一个空格
+pub fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 合并 OCR 在行尾用连字符断开的单词，如 "program-\nming" → "programming"
+///
+/// 只有连字符紧跟字母、下一行以小写字母开头时才合并，其他连字符保持不变。
+pub fn dehyphenate(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(offset) = rest.find('-') {
+        let (head, tail) = rest.split_at(offset);
+        result.push_str(head);
+
+        let after_hyphen = tail[1..].trim_start_matches([' ', '\t']);
+        let next_line = after_hyphen
+            .strip_prefix("\r\n")
+            .or_else(|| after_hyphen.strip_prefix('\n'))
+            .map(|line| line.trim_start_matches([' ', '\t']));
+        let joins_word = head.chars().next_back().is_some_and(char::is_alphabetic)
+            && next_line.is_some_and(|line| line.starts_with(|c: char| c.is_lowercase()));
+
+        match next_line {
+            Some(line) if joins_word => rest = line,
+            _ => {
+                result.push('-');
+                rest = &tail[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 合并分开生成的文章 (`newContent`) 和题目 (`Questions`) 为一段脚本，并重新保证 data-blank-id 唯一
+///
+/// 找不到对应声明时，整段输入会原样保留。
+#[cfg(test)]
+pub fn merge_js_outputs(passage_js: &str, questions_js: &str) -> String {
+    let passage = find_declaration(passage_js, "newContent")
+        .map_or(passage_js.trim(), |range| &passage_js[range]);
+    let questions = find_declaration(questions_js, "Questions")
+        .map_or(questions_js.trim(), |range| &questions_js[range]);
+
+    let merged = format!("{}\n\n{}\n", passage, questions);
+    renumber_blank_ids(&merged)
+}
+
+/// 把 `newContent` 文章中所有 `<p>` 段落的 style 替换为 `style`，没有 style 的段落会补上
+///
+/// 找不到 `newContent` 声明时原样返回。
+pub fn apply_paragraph_style(script: &str, style: &str) -> String {
+    let Some(range) = find_declaration(script, "newContent") else {
+        return script.to_string();
+    };
+    let style = style.replace('"', "'");
+    let passage = &script[range.clone()];
+
+    let mut result = String::with_capacity(script.len());
+    result.push_str(&script[..range.start]);
+    let mut last = 0;
+    let mut search_from = 0;
+    while let Some(offset) = passage[search_from..].find("<p") {
+        let tag_start = search_from + offset;
+        search_from = tag_start + 2;
+        if !passage[search_from..].starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            continue;
+        }
+        let Some(tag_len) = passage[tag_start..].find('>') else {
+            break;
+        };
+        let tag = &passage[tag_start..tag_start + tag_len];
+
+        match tag.find("style=\"") {
+            Some(style_offset) => {
+                let value_start = tag_start + style_offset + "style=\"".len();
+                let Some(value_len) = passage[value_start..tag_start + tag_len].find('"') else {
+                    continue;
+                };
+                result.push_str(&passage[last..value_start]);
+                result.push_str(&style);
+                last = value_start + value_len;
+            }
+            None => {
+                result.push_str(&passage[last..tag_start + 2]);
+                result.push_str(&format!(" style=\"{}\"", style));
+                last = tag_start + 2;
+            }
+        }
+        search_from = tag_start + tag_len;
+    }
+    result.push_str(&passage[last..]);
+    result.push_str(&script[range.end..]);
+    result
+}
+
+/// 提取 `newContent` 文章的 HTML（模板字符串或字符串字面量的内容）
+///
+/// 找不到声明或 `newContent` 是对象（如单选题）时返回 None。
+pub fn extract_new_content(script: &str) -> Option<String> {
+    let range = find_declaration(script, "newContent")?;
+    let declaration = &script[range];
+    let value = declaration[declaration.find('=')? + 1..].trim_start();
+    if !value.starts_with(['`', '"', '\'']) {
+        return None;
+    }
+
+    let end = skip_value(value.as_bytes(), 0);
+    if end < 2 {
+        return None;
+    }
+    Some(value[1..end - 1].trim().to_string())
+}
+
+/// 把文章 HTML 转成便于预览的纯文本：段落之间空一行，空格位置显示为 `____`，表格单元格用制表符分隔
+pub fn html_to_preview_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    // 源码中的换行与空格等价，只有标签会产生换行
+    while let Some(tag_start) = rest.find('<') {
+        text.push_str(&rest[..tag_start].replace(['\r', '\n'], " "));
+        let Some(tag_len) = rest[tag_start..].find('>') else {
+            rest = &rest[tag_start..];
+            break;
+        };
+        let tag = &rest[tag_start + 1..tag_start + tag_len];
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match (tag.starts_with('/'), name.as_str()) {
+            (true, "p" | "div" | "table") => text.push_str("\n\n"),
+            (true, "tr") | (false, "br") => text.push('\n'),
+            (true, "td" | "th") => text.push('\t'),
+            (false, "span") if tag.contains("fillblank") => text.push_str(" ____"),
+            _ => {}
+        }
+        rest = &rest[tag_start + tag_len + 1..];
+    }
+    text.push_str(&rest.replace(['\r', '\n'], " "));
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&");
+
+    // 合并行内多余空白，保留段落和换行
+    text.split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(|line| {
+                    line.split('\t')
+                        .map(|cell| cell.split_whitespace().collect::<Vec<_>>().join(" "))
+                        .collect::<Vec<_>>()
+                        .join("\t")
+                        .trim()
+                        .to_string()
+                })
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 查找 `var/let/const <name> = ...;` 声明在脚本中的范围（包含结尾分号）
+pub fn find_declaration(script: &str, name: &str) -> Option<Range<usize>> {
+    let bytes = script.as_bytes();
+    let mut search_from = 0;
+
+    while let Some(offset) = script[search_from..].find(name) {
+        let name_start = search_from + offset;
+        let name_end = name_start + name.len();
+        search_from = name_end;
+
+        // 变量名前必须是 var/let/const 关键字
+        let before = script[..name_start].trim_end();
+        let Some(keyword_start) = ["var", "let", "const"]
+            .iter()
+            .find(|keyword| before.ends_with(*keyword))
+            .map(|keyword| before.len() - keyword.len())
+        else {
+            continue;
+        };
+        if keyword_start > 0 && is_ident_byte(bytes[keyword_start - 1]) {
+            continue;
+        }
+        if bytes.get(name_end).is_some_and(|b| is_ident_byte(*b)) {
+            continue;
+        }
+
+        // 变量名后必须是赋值号
+        let after = &script[name_end..];
+        let trimmed = after.trim_start();
+        if !trimmed.starts_with('=') || trimmed.starts_with("==") {
+            continue;
+        }
+        let after_eq = &trimmed[1..];
+        let value_start = script.len() - after_eq.trim_start().len();
+
+        let mut end = skip_value(bytes, value_start);
+        let rest = &script[end..];
+        if rest.trim_start().starts_with(';') {
+            end += rest.len() - rest.trim_start().len() + 1;
+        }
+        return Some(keyword_start..end);
+    }
+
+    None
+}
+
+/// 查找脚本中所有 `var/let/const <name> = ...;` 声明的范围，按出现顺序排列
+fn find_declarations(script: &str, name: &str) -> Vec<Range<usize>> {
+    let mut declarations = Vec::new();
+    let mut offset = 0;
+    while let Some(range) = find_declaration(&script[offset..], name) {
+        let range = offset + range.start..offset + range.end;
+        offset = range.end;
+        declarations.push(range);
+    }
+    declarations
+}
+
+/// 校验 `Questions` 输出时发现的问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuestionsJsIssue {
+    /// 没有 `Questions` 数组
+    Missing,
+    /// 出现了多个 `Questions` 声明，平台只读取第一个
+    Multiple(usize),
+}
+
+impl std::fmt::Display for QuestionsJsIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestionsJsIssue::Missing => write!(f, "输出中没有 Questions 数组"),
+            QuestionsJsIssue::Multiple(count) => {
+                write!(f, "输出中有 {} 个 Questions 数组，平台只会读取第一个", count)
+            }
+        }
+    }
+}
+
+/// 校验输出中的 `Questions` 数组，通过时返回题目数
+pub fn validate_questions_js(script: &str) -> Result<usize, QuestionsJsIssue> {
+    match find_declarations(script, "Questions").len() {
+        0 => Err(QuestionsJsIssue::Missing),
+        1 => count_array_objects(script, "Questions").ok_or(QuestionsJsIssue::Missing),
+        count => Err(QuestionsJsIssue::Multiple(count)),
+    }
+}
+
+/// 检查输出中的 `data-blank-id` 是否各不相同，有重复时按首次出现的顺序返回重复的 ID
+pub fn check_unique_blank_ids(js: &str) -> Result<(), Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for range in blank_id_ranges(js) {
+        let id = &js[range];
+        if !seen.insert(id) && !duplicates.iter().any(|duplicate| duplicate == id) {
+            duplicates.push(id.to_string());
+        }
+    }
+    if duplicates.is_empty() { Ok(()) } else { Err(duplicates) }
+}
+
+/// 把后续 `Questions` 数组中的题目合并到第一个数组末尾，并删除后续的声明
+///
+/// 只有一个数组或第一个声明不是数组时原样返回。
+pub fn merge_questions_arrays(script: &str) -> String {
+    let declarations = find_declarations(script, "Questions");
+    let Some((first, later)) = declarations.split_first() else {
+        return script.to_string();
+    };
+    if later.is_empty() {
+        return script.to_string();
+    }
+    let Some(first_objects) = array_object_ranges(script, "Questions") else {
+        return script.to_string();
+    };
+
+    let later_objects: Vec<&str> = later
+        .iter()
+        .flat_map(|range| {
+            let declaration = &script[range.clone()];
+            array_object_ranges(declaration, "Questions")
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |object| &declaration[object])
+        })
+        .collect();
+
+    // 插在第一个数组最后一个题目之后，保留其后的注释和逗号；数组为空时插在 `[` 之后
+    let (insert_at, separator) = match first_objects.last() {
+        Some(last) => (last.end, ","),
+        None => (first.start + script[first.clone()].find('[').map_or(0, |pos| pos + 1), ""),
+    };
+    let mut result = String::with_capacity(script.len());
+    result.push_str(&script[..insert_at]);
+    if !later_objects.is_empty() {
+        result.push_str(separator);
+        let inserted: Vec<String> = later_objects.iter().map(|object| format!("\n    {}", object)).collect();
+        result.push_str(&inserted.join(","));
+    }
+
+    // 删除后续声明及其前面的空白
+    let mut last = insert_at;
+    for range in later {
+        let cut = script[..range.start].trim_end().len().max(last);
+        result.push_str(&script[last..cut]);
+        last = range.end;
+    }
+    result.push_str(&script[last..]);
+    result
+}
+
+/// 统计 `name` 数组声明中顶层对象 `{...}` 的个数，找不到数组时返回 None
+pub fn count_array_objects(script: &str, name: &str) -> Option<usize> {
+    array_object_ranges(script, name).map(|ranges| ranges.len())
+}
+
+/// 查找 `name` 数组声明中每个顶层对象 `{...}` 的范围，找不到数组时返回 None
+pub(crate) fn array_object_ranges(script: &str, name: &str) -> Option<Vec<Range<usize>>> {
+    let range = find_declaration(script, name)?;
+    let bytes = script.as_bytes();
+    let start = range.start + script[range.clone()].find('=')? + 1;
+    let start = start + (script[start..].len() - script[start..].trim_start().len());
+    if bytes.get(start) != Some(&b'[') {
+        return None;
+    }
+
+    let mut objects = Vec::new();
+    let mut i = start + 1;
+    while i < range.end {
+        match bytes[i] {
+            b']' => return Some(objects),
+            b'{' => {
+                let end = skip_value(bytes, i);
+                objects.push(i..end);
+                i = end;
+            }
+            b'"' | b'\'' => i = skip_string(bytes, i),
+            b'`' => i = skip_template(bytes, i),
+            b'[' | b'(' => i = skip_value(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_line_comment(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            _ => i += 1,
+        }
+    }
+
+    Some(objects)
+}
+
+/// 对象字面量中的一个属性：属性名（已去掉引号）、属性名范围和属性值范围
+pub(crate) struct ObjectProperty<'a> {
+    pub(crate) key: &'a str,
+    pub(crate) key_range: Range<usize>,
+    pub(crate) value_range: Range<usize>,
+}
+
+/// 解析对象字面量 `{...}` 的顶层属性，遇到无法识别的语法时提前结束
+pub(crate) fn object_properties(script: &str, object: Range<usize>) -> Vec<ObjectProperty<'_>> {
+    let bytes = script.as_bytes();
+    let mut properties = Vec::new();
+    let mut i = object.start + 1;
+
+    loop {
+        i = skip_trivia(bytes, i);
+        while bytes.get(i) == Some(&b',') {
+            i = skip_trivia(bytes, i + 1);
+        }
+        if i >= object.end || bytes[i] == b'}' {
+            break;
+        }
+
+        let key_start = i;
+        let key = match bytes[i] {
+            b'"' | b'\'' => {
+                i = skip_string(bytes, i);
+                &script[key_start + 1..i.saturating_sub(1).max(key_start + 1)]
+            }
+            // 允许中文等非 ASCII 属性名，如 `题型类型`
+            b if is_ident_byte(b) || !b.is_ascii() => {
+                while i < object.end && (is_ident_byte(bytes[i]) || !bytes[i].is_ascii()) {
+                    i += 1;
+                }
+                &script[key_start..i]
+            }
+            _ => break,
+        };
+        let key_range = key_start..i;
+
+        i = skip_trivia(bytes, i);
+        if bytes.get(i) != Some(&b':') {
+            break;
+        }
+        let value_start = skip_trivia(bytes, i + 1);
+        let value_end = skip_property_value(bytes, value_start).min(object.end - 1);
+        properties.push(ObjectProperty {
+            key,
+            key_range,
+            value_range: value_start..value_end,
+        });
+        i = value_end;
+    }
+
+    properties
+}
+
+/// 返回从 `start` 开始的属性值的结束位置，标量值在分隔符、空白或注释处结束
+fn skip_property_value(bytes: &[u8], start: usize) -> usize {
+    match bytes.get(start) {
+        Some(b'"' | b'\'' | b'`' | b'[' | b'{' | b'(') => skip_value(bytes, start),
+        _ => bytes[start.min(bytes.len())..]
+            .iter()
+            .position(|b| matches!(b, b',' | b'}' | b']' | b'/') || b.is_ascii_whitespace())
+            .map_or(bytes.len(), |offset| start + offset),
+    }
+}
+
+/// 跳过空白和注释
+fn skip_trivia(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b if b.is_ascii_whitespace() => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_line_comment(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            _ => break,
+        }
+    }
+    i
+}
+
+/// 为 `Questions` 中缺少解析（没有 analysis 或为空字符串）的题目生成占位解析
+///
+/// 占位格式为 `考点：...；故答案为：<答案>`，数字答案索引会转换为选项字母。
+/// 没有 answer 字段的题目保持不变。
+pub fn fill_missing_analysis(script: &str) -> String {
+    let Some(objects) = array_object_ranges(script, "Questions") else {
+        return script.to_string();
+    };
+
+    // (替换范围, 替换内容)，按位置从前往后排列
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for object in objects {
+        let properties = object_properties(script, object);
+        let Some(answer) = properties.iter().find(|property| property.key == "answer") else {
+            continue;
+        };
+        let placeholder = serde_json::to_string(&format!(
+            "考点：...；故答案为：{}",
+            format_answer(&script[answer.value_range.clone()])
+        ))
+        .unwrap_or_default();
+
+        match properties.iter().find(|property| property.key == "analysis") {
+            Some(analysis) => {
+                let value = script[analysis.value_range.clone()].trim();
+                if matches!(value, "\"\"" | "''" | "``") {
+                    edits.push((analysis.value_range.clone(), placeholder));
+                }
+            }
+            None => {
+                let last = properties.last().unwrap_or(answer);
+                let line_start = script[..last.key_range.start].rfind('\n').map_or(0, |pos| pos + 1);
+                let prefix = &script[line_start..last.key_range.start];
+                // 多行对象沿用上一属性的缩进，单行对象直接追加
+                let (separator, indent) = if prefix.trim().is_empty() {
+                    (",\n", prefix)
+                } else {
+                    (", ", "")
+                };
+                let insert_at = last.value_range.end;
+                edits.push((
+                    insert_at..insert_at,
+                    format!("{}{}\"analysis\": {}", separator, indent, placeholder),
+                ));
+            }
+        }
+    }
+
+    let mut result = String::with_capacity(script.len());
+    let mut last = 0;
+    for (range, replacement) in edits {
+        result.push_str(&script[last..range.start]);
+        result.push_str(&replacement);
+        last = range.end;
+    }
+    result.push_str(&script[last..]);
+    result
+}
+
+/// 把 answer 字段的值转换为解析中展示的答案，数字索引转换为选项字母
+fn format_answer(raw: &str) -> String {
+    fn format_item(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::Number(n) => match n.as_u64() {
+                Some(index) if index < 26 => ((b'A' + index as u8) as char).to_string(),
+                _ => n.to_string(),
+            },
+            serde_json::Value::String(s) => s.trim().to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    match serde_json::from_str::<serde_json::Value>(raw.trim()) {
+        Ok(serde_json::Value::Array(items)) => items.iter().map(format_item).collect::<Vec<_>>().join(", "),
+        Ok(value) => format_item(&value),
+        Err(_) => raw.trim().trim_matches(|c| c == '\'' || c == '"' || c == '`').to_string(),
+    }
+}
+
+/// 复合题（阅读理解、听力组合等）中的一道小题
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SubQuestion {
+    pub stem: String,
+    pub options: Vec<String>,
+    /// 答案原值：选项索引、索引数组或文本，缺少时为 None
+    pub answer: Option<serde_json::Value>,
+    pub analysis: String,
+}
+
+/// 复合题的结构化表示：文章和其后的小题
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct CompoundQuestion {
+    /// `newContent` 文章的 HTML，没有文章时为空
+    pub passage: String,
+    pub subquestions: Vec<SubQuestion>,
+}
+
+/// 把复合题的输出解析为文章和小题
+///
+/// 缺少 `newContent` 时文章为空，缺少 `Questions` 时没有小题。
+#[cfg(test)]
+pub fn parse_compound(script: &str) -> CompoundQuestion {
+    let subquestions = array_object_ranges(script, "Questions")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|object| {
+            let properties = object_properties(script, object);
+            let value = |key: &str| {
+                properties
+                    .iter()
+                    .find(|property| property.key == key)
+                    .map(|property| property.value_range.clone())
+            };
+            SubQuestion {
+                stem: value("stem").map(|range| js_string_value(&script[range])).unwrap_or_default(),
+                options: value("options")
+                    .map(|range| {
+                        array_string_items(script, range)
+                            .into_iter()
+                            .map(|item| js_string_value(&script[item]))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                answer: value("answer").map(|range| {
+                    let raw = script[range].trim();
+                    serde_json::from_str(raw)
+                        .unwrap_or_else(|_| serde_json::Value::String(js_string_value(raw)))
+                }),
+                analysis: value("analysis").map(|range| js_string_value(&script[range])).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    CompoundQuestion {
+        passage: extract_new_content(script).unwrap_or_default(),
+        subquestions,
+    }
+}
+
+/// 取出 JS 字符串字面量的内容，双引号字符串按 JSON 转义解析，其他引号只去掉首尾引号
+pub(crate) fn js_string_value(raw: &str) -> String {
+    let raw = raw.trim();
+    serde_json::from_str::<String>(raw)
+        .unwrap_or_else(|_| raw.trim_matches(|c| c == '\'' || c == '"' || c == '`').to_string())
+}
+
+/// `Questions` 数组（或补全题的 `newContent` 对象）中每个字符串类型 `analysis` 值的范围
+pub(crate) fn analysis_value_ranges(script: &str) -> Vec<Range<usize>> {
+    let objects = array_object_ranges(script, "Questions")
+        .or_else(|| super::parser::declared_object(script, "newContent").map(|object| vec![object]))
+        .unwrap_or_default();
+    objects
+        .into_iter()
+        .filter_map(|object| {
+            object_properties(script, object)
+                .into_iter()
+                .find(|property| property.key == "analysis")
+                .map(|property| property.value_range)
+        })
+        .filter(|range| script[range.clone()].starts_with(['"', '\'', '`']))
+        .collect()
+}
+
+/// 把解析中常见的 Markdown 转换为 HTML：`**粗体**` 转为 `<strong>`，`*斜体*` 转为 `<em>`，换行转为 `<br>`
+///
+/// 只识别星号，下划线在题目中常用作填空横线，不做转换。
+/// 星号内侧紧挨空白（如 `3 * 4 * 5`）或没有配对时原样保留。
+pub fn markdown_to_html(text: &str) -> String {
+    let text = replace_delimited(text, "**", "strong");
+    let text = replace_delimited(&text, "*", "em");
+    text.replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+/// 把成对的 `delimiter` 包裹的内容替换为 `<tag>内容</tag>`，内容为空、跨行或首尾为空白时不替换
+fn replace_delimited(text: &str, delimiter: &str, tag: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(delimiter) {
+        let after = &rest[start + delimiter.len()..];
+        let inner = after.find(delimiter).map(|end| &after[..end]).filter(|inner| {
+            !inner.is_empty()
+                && !inner.contains('\n')
+                && !inner.starts_with(char::is_whitespace)
+                && !inner.ends_with(char::is_whitespace)
+        });
+        result.push_str(&rest[..start]);
+        match inner {
+            Some(inner) => {
+                result.push_str(&format!("<{tag}>{inner}</{tag}>"));
+                rest = &after[inner.len() + delimiter.len()..];
+            }
+            None => {
+                result.push_str(delimiter);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 把每道题 `analysis` 中的 Markdown 转换为 HTML，其他内容和没有 Markdown 的解析保持不变
+pub fn convert_analysis_markdown(script: &str) -> String {
+    let mut result = String::with_capacity(script.len());
+    let mut last = 0;
+    for range in analysis_value_ranges(script) {
+        let analysis = js_string_value(&script[range.clone()]);
+        let converted = markdown_to_html(&analysis);
+        if converted == analysis {
+            continue;
+        }
+        result.push_str(&script[last..range.start]);
+        result.push_str(&serde_json::to_string(&converted).unwrap_or_default());
+        last = range.end;
+    }
+    result.push_str(&script[last..]);
+    result
+}
+
+/// 同一题目中出现重复选项时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateOptionMode {
+    /// 不检查
+    Off,
+    /// 只提示重复的选项
+    #[default]
+    Flag,
+    /// 用占位干扰项替换重复的选项
+    Replace,
+}
+
+/// 替换重复选项时使用的占位干扰项
+pub const DUPLICATE_OPTION_PLACEHOLDER: &str = "【重复选项，请替换为新的干扰项】";
+
+/// 同一题目中与前面选项重复的选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateOption {
+    /// 题目序号，从 0 开始
+    pub question_index: usize,
+    /// 与之重复的较早选项序号
+    pub first_index: usize,
+    /// 重复的选项序号
+    pub option_index: usize,
+    /// 选项内容
+    pub text: String,
+}
+
+impl std::fmt::Display for DuplicateOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "第 {} 题选项 {} 与 {} 重复：{}",
+            self.question_index + 1,
+            option_letter(self.option_index),
+            option_letter(self.first_index),
+            self.text
+        )
+    }
+}
+
+pub(crate) fn option_letter(index: usize) -> char {
+    (b'A' + (index % 26) as u8) as char
+}
+
+/// 查找 `Questions` 中每道题内容重复的选项（忽略首尾空白、大小写和连续空白）
+pub fn find_duplicate_options(script: &str) -> Vec<DuplicateOption> {
+    duplicate_option_ranges(script)
+        .into_iter()
+        .map(|(duplicate, _)| duplicate)
+        .collect()
+}
+
+/// 把 `Questions` 中重复的选项替换为 [`DUPLICATE_OPTION_PLACEHOLDER`]
+///
+/// 默认替换后出现的选项；如果它恰好是答案，则替换较早的那个，保证答案不变。
+pub fn replace_duplicate_options(script: &str) -> String {
+    let placeholder = serde_json::to_string(DUPLICATE_OPTION_PLACEHOLDER).unwrap_or_default();
+    let mut edits: Vec<Range<usize>> = duplicate_option_ranges(script)
+        .into_iter()
+        .map(|(_, range)| range)
+        .collect();
+    edits.sort_by_key(|range| range.start);
+    edits.dedup();
+
+    let mut result = String::with_capacity(script.len());
+    let mut last = 0;
+    for range in edits {
+        result.push_str(&script[last..range.start]);
+        result.push_str(&placeholder);
+        last = range.end;
+    }
+    result.push_str(&script[last..]);
+    result
+}
+
+/// 查找重复选项，同时返回替换时应改写的选项范围
+fn duplicate_option_ranges(script: &str) -> Vec<(DuplicateOption, Range<usize>)> {
+    let Some(objects) = array_object_ranges(script, "Questions") else {
+        return Vec::new();
+    };
+
+    let mut duplicates = Vec::new();
+    for (question_index, object) in objects.into_iter().enumerate() {
+        let properties = object_properties(script, object);
+        let Some(options) = properties.iter().find(|property| property.key == "options") else {
+            continue;
+        };
+        let answer = properties
+            .iter()
+            .find(|property| property.key == "answer")
+            .and_then(|answer| script[answer.value_range.clone()].trim().parse::<usize>().ok());
+
+        let items = array_string_items(script, options.value_range.clone());
+        let mut seen: Vec<(String, usize)> = Vec::new();
+        for (option_index, item) in items.iter().enumerate() {
+            let text = script[item.start + 1..item.end.saturating_sub(1).max(item.start + 1)].trim();
+            let key = normalize_whitespace(text).to_lowercase();
+            if key.is_empty() {
+                continue;
+            }
+            match seen.iter().find(|(seen_key, _)| *seen_key == key) {
+                Some(&(_, first_index)) => {
+                    let replace = if answer == Some(option_index) {
+                        items[first_index].clone()
+                    } else {
+                        item.clone()
+                    };
+                    duplicates.push((
+                        DuplicateOption {
+                            question_index,
+                            first_index,
+                            option_index,
+                            text: text.to_string(),
+                        },
+                        replace,
+                    ));
+                }
+                None => seen.push((key, option_index)),
+            }
+        }
+    }
+    duplicates
+}
+
+/// 返回数组字面量 `[...]` 中每个顶层字符串元素（含引号）的范围，其他元素跳过
+pub(crate) fn array_string_items(script: &str, array: Range<usize>) -> Vec<Range<usize>> {
+    array_items(script, array)
+        .into_iter()
+        .filter(|item| matches!(script.as_bytes()[item.start], b'"' | b'\'' | b'`'))
+        .collect()
+}
+
+/// 返回数组字面量 `[...]` 中每个顶层元素的范围，不是数组时返回空
+pub(crate) fn array_items(script: &str, array: Range<usize>) -> Vec<Range<usize>> {
+    let bytes = script.as_bytes();
+    let mut items = Vec::new();
+    if bytes.get(array.start) != Some(&b'[') {
+        return items;
+    }
+
+    let mut i = array.start + 1;
+    loop {
+        i = skip_trivia(bytes, i);
+        while bytes.get(i) == Some(&b',') {
+            i = skip_trivia(bytes, i + 1);
+        }
+        if i >= array.end || bytes[i] == b']' {
+            break;
+        }
+        let end = skip_property_value(bytes, i).min(array.end);
+        items.push(i..end);
+        i = end.max(i + 1);
+    }
+    items
+}
+
+/// 返回从 `start` 开始的一个 JS 值的结束位置（字符串、模板字符串或括号表达式）
+pub fn skip_value(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = skip_string(bytes, i);
+                if depth == 0 {
+                    return i;
+                }
+            }
+            b'`' => {
+                i = skip_template(bytes, i);
+                if depth == 0 {
+                    return i;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_line_comment(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            b'[' | b'{' | b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b']' | b'}' | b')' => {
+                i += 1;
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return i;
+                }
+            }
+            b';' if depth == 0 => return i,
+            _ => i += 1,
+        }
+    }
+
+    bytes.len()
+}
+
+/// 跳过 `'...'` 或 `"..."` 字符串，返回结束引号之后的位置
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// 跳过模板字符串（支持 `${...}` 嵌套），返回结束反引号之后的位置
+fn skip_template(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'`' => return i + 1,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => i = skip_value(bytes, i + 1),
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn skip_line_comment(bytes: &[u8], start: usize) -> usize {
+    bytes[start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(bytes.len(), |offset| start + offset + 1)
+}
+
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    bytes[start + 2..]
+        .windows(2)
+        .position(|w| w == b"*/")
+        .map_or(bytes.len(), |offset| start + 2 + offset + 2)
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$'
+}
+
+/// 查找脚本中所有 data-blank-id 值的范围（兼容 `"..."`、`'...'` 以及 JS 字符串里转义的 `\"...\"`）
+pub fn blank_id_ranges(script: &str) -> Vec<Range<usize>> {
+    let bytes = script.as_bytes();
+    let mut ranges = Vec::new();
+    let mut search_from = 0;
+
+    while let Some(offset) = script[search_from..].find(BLANK_ID_ATTR) {
+        let mut i = search_from + offset + BLANK_ID_ATTR.len();
+        search_from = i;
+
+        if bytes.get(i) == Some(&b'\\') {
+            i += 1;
+        }
+        if !matches!(bytes.get(i), Some(b'"') | Some(b'\'')) {
+            continue;
+        }
+        let start = i + 1;
+        let end = bytes[start..]
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_'))
+            .map_or(bytes.len(), |len| start + len);
+        ranges.push(start..end);
+        search_from = end;
+    }
+
+    ranges
+}
+
+/// 把重复出现的 data-blank-id 替换为新的唯一 id，首次出现的保持不变
+///
+/// 新 id 从现有数字 id 的最大值加一开始递增；所有 id 已经各不相同时原样返回。
+pub fn renumber_blank_ids(script: &str) -> String {
+    let ranges = blank_id_ranges(script);
+    let mut next_id = ranges
+        .iter()
+        .filter_map(|range| script[range.clone()].parse::<u64>().ok())
+        .max()
+        .map_or(1, |max| max + 1);
+
+    let mut seen = HashSet::new();
+    let mut result = String::with_capacity(script.len());
+    let mut last = 0;
+
+    for range in ranges {
+        let id = &script[range.clone()];
+        if seen.insert(id.to_string()) {
+            continue;
+        }
+        let new_id = next_id.to_string();
+        next_id += 1;
+        seen.insert(new_id.clone());
+
+        result.push_str(&script[last..range.start]);
+        result.push_str(&new_id);
+        last = range.end;
+    }
+    result.push_str(&script[last..]);
+    result
+}
+
+/// 生成 blank id 的起始时间（2020-01-01，Unix 毫秒）
+const BLANK_ID_EPOCH_MS: u64 = 1_577_836_800_000;
+
+/// 上一次生成的 blank id，保证同一进程内生成的 id 递增
+static LAST_BLANK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// 生成 `count` 个各不相同的纯数字 data-blank-id
+///
+/// 与平台的 `593417796829762300` 形式一致：高位为起始时间以来的毫秒数，低 22 位为随机序号，
+/// 同一进程内多次调用生成的 id 严格递增，不会重复。
+pub fn generate_blank_ids(count: usize) -> Vec<String> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+        .saturating_sub(BLANK_ID_EPOCH_MS);
+    let random = (uuid::Uuid::new_v4().as_u128() as u64) & 0x3f_ffff;
+    let seed = (millis << 22) | random;
+
+    let count = count as u64;
+    let start = LAST_BLANK_ID
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+            Some(seed.max(last + 1) + count - 1)
+        })
+        .map_or(seed, |last| seed.max(last + 1));
+    (start..start + count).map(|id| id.to_string()).collect()
+}
+
+/// 解码二进制字符串失败的原因
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryDecodeError {
+    /// 第 `index` 个（从 0 开始）片段不是 8 位二进制数
+    InvalidToken { index: usize, token: String },
+    /// 解码出的字节不是合法的 UTF-8
+    InvalidUtf8,
+}
+
+#[cfg(test)]
+impl std::fmt::Display for BinaryDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryDecodeError::InvalidToken { index, token } => {
+                write!(f, "第 {} 个片段 {:?} 不是 8 位二进制数", index + 1, token)
+            }
+            BinaryDecodeError::InvalidUtf8 => write!(f, "解码结果不是合法的 UTF-8"),
+        }
+    }
+}
+
+#[cfg(test)]
+impl std::error::Error for BinaryDecodeError {}
+
+/// 把逗号分隔的 8 位二进制字节（如 `01101000,01101001`）解码为字符串
+///
+/// 片段首尾的空白和空片段（如结尾多余的逗号）会被忽略。
+#[cfg(test)]
+pub fn decode_binary_to_str(encoded: &str) -> Result<String, BinaryDecodeError> {
+    let bytes = encoded
+        .split(',')
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, token)| !token.is_empty())
+        .map(|(index, token)| {
+            let is_byte = token.len() == 8 && token.bytes().all(|b| b == b'0' || b == b'1');
+            is_byte
+                .then(|| u8::from_str_radix(token, 2).ok())
+                .flatten()
+                .ok_or_else(|| BinaryDecodeError::InvalidToken {
+                    index,
+                    token: token.to_string(),
+                })
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    String::from_utf8(bytes).map_err(|_| BinaryDecodeError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 写一张纯色测试图片到临时目录
+    fn write_test_image(width: u32, height: u32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("question_tool_img_{}.png", uuid::Uuid::new_v4()));
+        image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 30]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    fn decode_base64_image(base64: &str) -> DynamicImage {
+        let bytes = general_purpose::STANDARD.decode(base64).unwrap();
+        image::load_from_memory(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_img_to_base64_downscales_large_image() {
+        let path = write_test_image(400, 100);
+        let image = decode_base64_image(&img_to_base64_with_format(&path, 256, ImageEncodeFormat::Png).unwrap());
+        assert_eq!((image.width(), image.height()), (256, 64));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_img_to_base64_keeps_small_image() {
+        let path = write_test_image(300, 200);
+        let image = decode_base64_image(&img_to_base64_with_format(&path, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat::Png).unwrap());
+        assert_eq!((image.width(), image.height()), (300, 200));
+
+        let image = decode_base64_image(&img_to_base64_with_format(&path, 0, ImageEncodeFormat::Png).unwrap());
+        assert_eq!((image.width(), image.height()), (300, 200));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_jpeg_format_produces_decodable_image() {
+        let path = write_test_image(64, 48);
+        let format = ImageEncodeFormat::Jpeg { quality: 85 };
+        let base64 = img_to_base64_with_format(&path, DEFAULT_MAX_IMAGE_DIM, format).unwrap();
+        let bytes = general_purpose::STANDARD.decode(&base64).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 48));
+
+        let data_url = img_to_data_url(&path, DEFAULT_MAX_IMAGE_DIM, format).unwrap();
+        assert!(data_url.starts_with("data:image/jpeg;base64,"));
+        let data_url = img_to_data_url(&path, DEFAULT_MAX_IMAGE_DIM, ImageEncodeFormat::Png).unwrap();
+        assert!(data_url.starts_with("data:image/png;base64,"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("你好世界"), 4);
+        assert_eq!(estimate_tokens("one two three four"), 3);
+        // 4 个 CJK 字符 + 4 个 ASCII 单词
+        assert_eq!(estimate_tokens("请翻译 hello world, good day。"), 4 + 3);
+        // 不足一个 token 的单词向上取整
+        assert_eq!(estimate_tokens("中文 and"), 3);
+        assert_eq!(estimate_tokens("  \n\t  "), 0);
+    }
+
+    #[test]
+    fn test_merge_js_outputs() {
+        let passage_js = r#"// 文章部分
+var newContent = `
+    <p style="text-align: justify; text-indent: 2em;">
+        I like <span class="number fillblank" contenteditable="false" data-blank-id="1">1</span> and
+        <span class="number fillblank" contenteditable="false" data-blank-id="2">2</span>; really.
+    </p>
+`;
+var Questions = [];
+"#;
+        let questions_js = r#"var Questions = [
+    {
+        stem: `A <span class="underline fillblank" data-blank-id="1"></span> {b}`,
+        answer: ["x"],
+    },
+    {
+        "stem": "B <span class=\"underline fillblank\" data-blank-id=\"1\"></span>",
+        answer: ["y"],
+    },
+];
+console.log("done");
+"#;
+
+        let merged = merge_js_outputs(passage_js, questions_js);
+
+        assert!(merged.contains("var newContent = `"));
+        assert!(merged.contains("var Questions = [\n    {"));
+        assert!(!merged.contains("var Questions = [];"));
+        assert!(!merged.contains("console.log"));
+        assert!(merged.contains("really."));
+
+        let ids: Vec<&str> = blank_id_ranges(&merged)
+            .into_iter()
+            .map(|range| &merged[range])
+            .collect();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 4);
+        assert_eq!(&ids[..2], &["1", "2"]);
+    }
+
+    #[test]
+    fn test_extract_new_content_from_reading_output() {
+        let output = r#"var newContent = `
+    <p style="text-align: justify; text-indent: 2em;">
+        Tom likes   reading. He reads
+        <span class="number fillblank" contenteditable="false" data-blank-id="1"
+              style="display: inline-block;width:40px">1</span> every day.
+    </p>
+    <p style="text-align: justify; text-indent: 2em;">Books &amp; magazines are his friends.</p>
+    <table><tr><td>Name</td><td>Age</td></tr><tr><td>Tom</td><td>12</td></tr></table>
+`;
+
+var Questions = [
+    { "stem": "What does Tom like?", "options": ["Reading", "Running"], "answer": 0 }
+];
+"#;
+
+        let html = extract_new_content(output).unwrap();
+        assert!(html.starts_with("<p style=\"text-align: justify; text-indent: 2em;\">"));
+        assert!(html.ends_with("</table>"));
+        assert!(!html.contains("Questions"));
+
+        assert_eq!(
+            html_to_preview_text(&html),
+            "Tom likes reading. He reads ____1 every day.\n\n\
+             Books & magazines are his friends.\n\n\
+             Name\tAge\nTom\t12"
+        );
+
+        // 单选题的 newContent 是对象，没有文章
+        assert_eq!(extract_new_content("var newContent = { stem: \"a\" };"), None);
+        assert_eq!(extract_new_content("var Questions = [];"), None);
+        assert_eq!(extract_new_content("let newContent = \"<p>hi</p>\";").as_deref(), Some("<p>hi</p>"));
+    }
+
+    #[test]
+    fn test_parse_compound_reading_output() {
+        let output = r#"var newContent = `
+    <p style="text-align: justify; text-indent: 2em;">Tom likes reading. He reads every day.</p>
+`;
+
+var Questions = [
+    {
+        "stem": "What does Tom like?",
+        "options": [
+            "Reading.",//和原文保持一致
+            "Running."
+        ],
+        "answer": 0, // 答案索引：A
+        "analysis": "考点：细节理解。分析：原文提到 \"Tom likes reading\"。故答案为：A"
+    },
+    {
+        stem: `How often does Tom read?`,
+        options: ['Every day.', 'Every week.'],
+        answer: 0
+    }
+];
+"#;
+
+        let compound = parse_compound(output);
+        assert_eq!(
+            compound.passage,
+            "<p style=\"text-align: justify; text-indent: 2em;\">Tom likes reading. He reads every day.</p>"
+        );
+        assert_eq!(compound.subquestions.len(), 2);
+
+        let first = &compound.subquestions[0];
+        assert_eq!(first.stem, "What does Tom like?");
+        assert_eq!(first.options, vec!["Reading.", "Running."]);
+        assert_eq!(first.answer, Some(serde_json::json!(0)));
+        assert_eq!(first.analysis, "考点：细节理解。分析：原文提到 \"Tom likes reading\"。故答案为：A");
+
+        let second = &compound.subquestions[1];
+        assert_eq!(second.stem, "How often does Tom read?");
+        assert_eq!(second.options, vec!["Every day.", "Every week."]);
+        assert_eq!(second.analysis, "");
+
+        assert_eq!(parse_compound("var Questions = [];"), CompoundQuestion::default());
+    }
+
+    #[test]
+    fn test_multiple_questions_arrays_merged() {
+        let output = r#"var newContent = `<p>Passage</p>`;
+
+var Questions = [
+    { "stem": "Q1", "options": ["a", "b"], "answer": 0 }, // 第一题
+];
+
+var Questions = [
+    { "stem": "Q2", "options": ["a", "b"], "answer": 1 },
+    { "stem": "Q3", "options": ["a", "b"], "answer": 0 }
+];
+"#;
+        assert_eq!(validate_questions_js(output), Err(QuestionsJsIssue::Multiple(2)));
+
+        let merged = merge_questions_arrays(output);
+        assert_eq!(validate_questions_js(&merged), Ok(3));
+        assert_eq!(merged.matches("var Questions").count(), 1);
+        let stems: Vec<String> = parse_compound(&merged)
+            .subquestions
+            .into_iter()
+            .map(|question| question.stem)
+            .collect();
+        assert_eq!(stems, vec!["Q1", "Q2", "Q3"]);
+        assert!(merged.contains("// 第一题"));
+        assert!(merged.starts_with("var newContent = `<p>Passage</p>`;"));
+
+        assert_eq!(validate_questions_js("var Questions = [{ answer: 0 }];"), Ok(1));
+        assert_eq!(validate_questions_js("var newContent = {};"), Err(QuestionsJsIssue::Missing));
+        assert_eq!(merge_questions_arrays("var Questions = [];"), "var Questions = [];");
+        assert_eq!(
+            validate_questions_js(&merge_questions_arrays("var Questions = [];\nlet Questions = [{ a: 1 }];")),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_check_unique_blank_ids() {
+        let unique = r#"var Questions = [{ "stem": "A <input data-blank-id=\"b1\"/> and <input data-blank-id=\"b2\"/>" },
+    { stem: `<span data-blank-id="b3"></span>` }];"#;
+        assert_eq!(check_unique_blank_ids(unique), Ok(()));
+        assert_eq!(check_unique_blank_ids("var Questions = [];"), Ok(()));
+
+        let duplicated = r#"var Questions = [{ "stem": "<input data-blank-id=\"7\"/> <input data-blank-id=\"8\"/>" },
+    { stem: `<input data-blank-id="7"/> <input data-blank-id='8'/> <input data-blank-id="7"/>` }];"#;
+        assert_eq!(check_unique_blank_ids(duplicated), Err(vec!["7".to_string(), "8".to_string()]));
+    }
+
+    #[test]
+    fn test_renumber_blank_ids() {
+        let duplicated = r#"var Questions = [{ "stem": "<input data-blank-id=\"7\"/> <input data-blank-id=\"7-1\"/>" },
+    { stem: `<input data-blank-id="7"/> <input data-blank-id='8'/> <input data-blank-id="7"/>` }];"#;
+        let renumbered = renumber_blank_ids(duplicated);
+        assert_eq!(check_unique_blank_ids(&renumbered), Ok(()));
+        assert_eq!(
+            renumbered,
+            r#"var Questions = [{ "stem": "<input data-blank-id=\"7\"/> <input data-blank-id=\"7-1\"/>" },
+    { stem: `<input data-blank-id="9"/> <input data-blank-id='8'/> <input data-blank-id="10"/>` }];"#
+        );
+
+        // 已经各不相同时不做修改
+        assert_eq!(renumber_blank_ids(&renumbered), renumbered);
+        assert_eq!(renumber_blank_ids("var Questions = [];"), "var Questions = [];");
+    }
+
+    #[test]
+    fn test_generate_blank_ids_unique_and_numeric() {
+        assert!(generate_blank_ids(0).is_empty());
+
+        let mut ids = generate_blank_ids(500);
+        ids.extend(generate_blank_ids(500));
+        assert_eq!(ids.len(), 1000);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        for id in &ids {
+            assert!(id.chars().all(|c| c.is_ascii_digit()), "{id}");
+            assert_eq!(id.len(), "593417796829762300".len(), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        assert_eq!(normalize_whitespace("  Which   of\tthe\r\n following  "), "Which of the following");
+        assert_eq!(normalize_whitespace(" \n "), "");
+    }
+
+    #[test]
+    fn test_dehyphenate_joins_ocr_line_breaks() {
+        assert_eq!(dehyphenate("program-\nming"), "programming");
+        assert_eq!(dehyphenate("The program-  \r\n   ming language"), "The programming language");
+        assert_eq!(dehyphenate("inter-\nnational and com-\nputer"), "international and computer");
+    }
+
+    #[test]
+    fn test_dehyphenate_keeps_legitimate_hyphens() {
+        assert_eq!(dehyphenate("a well-known fact"), "a well-known fact");
+        assert_eq!(dehyphenate("1990-\n2000"), "1990-\n2000");
+        assert_eq!(dehyphenate("Part A-\nPart B"), "Part A-\nPart B");
+        assert_eq!(dehyphenate("- item\n- item"), "- item\n- item");
+        assert_eq!(dehyphenate("end-"), "end-");
+    }
+
+    #[test]
+    fn test_merge_without_declarations_keeps_input() {
+        let merged = merge_js_outputs("<p>passage</p>", "var Questions = [1, 2];");
+        assert!(merged.starts_with("<p>passage</p>"));
+        assert!(merged.contains("var Questions = [1, 2];"));
+    }
+
+    #[test]
+    fn test_make_thumbnail_limits_longest_side() {
+        let path = write_test_image(1200, 300);
+        let thumbnail = make_thumbnail(&path, 400).unwrap();
+        assert_ne!(thumbnail, path);
+        let (width, height) = decode_image_file(&thumbnail).unwrap();
+        assert!(width.max(height) <= 400, "{}x{}", width, height);
+        assert_eq!((width, height), (400, 100));
+        // 再次显示时复用缓存的缩略图，原图不变
+        assert_eq!(make_thumbnail(&path, 400).unwrap(), thumbnail);
+        assert_eq!(decode_image_file(&path).unwrap(), (1200, 300));
+
+        // 原图不超过上限时直接使用原图
+        assert_eq!(make_thumbnail(&path, 2000).unwrap(), path);
+        assert_eq!(make_thumbnail(&path, 0).unwrap(), path);
+
+        let _ = std::fs::remove_file(&thumbnail);
+        let _ = std::fs::remove_file(&path);
+        assert!(make_thumbnail(&path, 400).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_for_ocr_keeps_dimensions() {
+        let path = std::env::temp_dir().join(format!("question_tool_img_{}.png", uuid::Uuid::new_v4()));
+        image::RgbImage::from_fn(40, 30, |x, _| image::Rgb([(x * 6) as u8, 120, 200]))
+            .save(&path)
+            .unwrap();
+
+        for opts in [
+            PreprocessOpts::default(),
+            PreprocessOpts { grayscale: false, contrast: -20.0, threshold: None },
+            PreprocessOpts { grayscale: false, contrast: 0.0, threshold: Some(128) },
+        ] {
+            let processed = preprocess_for_ocr(&path, opts).unwrap();
+            assert_ne!(processed, path);
+            assert_eq!(decode_image_file(&processed).unwrap(), (40, 30), "{:?}", opts);
+            let image = image::open(&processed).unwrap();
+            if opts.grayscale || opts.threshold.is_some() {
+                assert_eq!(image.color(), image::ColorType::L8);
+            }
+            if opts.threshold.is_some() {
+                assert!(image.to_luma8().pixels().all(|pixel| pixel.0[0] == 0 || pixel.0[0] == 255));
+            }
+            let _ = std::fs::remove_file(&processed);
+        }
+
+        // 原图不变
+        assert_eq!(image::open(&path).unwrap().color(), image::ColorType::Rgb8);
+        let _ = std::fs::remove_file(&path);
+        assert!(preprocess_for_ocr(&path, PreprocessOpts::default()).is_err());
+    }
+
+    #[test]
+    fn test_decode_image_file() {
+        let path = write_test_image(30, 20);
+        assert_eq!(decode_image_file(&path).unwrap(), (30, 20));
+        let _ = std::fs::remove_file(&path);
+
+        let path = std::env::temp_dir().join(format!("question_tool_img_{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "not an image").unwrap();
+        assert!(decode_image_file(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+
+        assert!(decode_image_file(Path::new("/nonexistent/question_tool.png")).is_err());
+    }
+
+    #[test]
+    fn test_truncated_png_is_invalid() {
+        let path = std::env::temp_dir().join(format!("question_tool_img_{}.png", uuid::Uuid::new_v4()));
+        image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, (x ^ y) as u8]))
+            .save(&path)
+            .unwrap();
+        assert!(is_valid_image(&path));
+
+        // 保留文件头和尺寸信息，截掉后半部分的像素数据
+        let bytes = std::fs::read(&path).unwrap();
+        let truncated = std::env::temp_dir().join(format!("question_tool_img_{}.png", uuid::Uuid::new_v4()));
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(!is_valid_image(&truncated));
+
+        std::fs::write(&truncated, &bytes[..8]).unwrap();
+        assert!(!is_valid_image(&truncated));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&truncated);
+    }
+
+    #[test]
+    fn test_fill_missing_analysis() {
+        let script = r#"var Questions = [
+    {
+        "stem": "Which one is a programming language?",
+        "options": ["Python", "HTML", "CSS"],
+        "answer": 2, // 答案索引：C
+    },
+    {
+        stem: "The capital of France is ____.",
+        answer: ["Paris"],
+        analysis: ""
+    },
+    { stem: "Keep me", answer: 0, analysis: "考点：已有解析。故答案为：A" },
+    { stem: "No answer" }
+];"#;
+
+        let filled = fill_missing_analysis(script);
+        assert!(filled.contains("\"analysis\": \"考点：...；故答案为：C\""));
+        assert!(filled.contains("analysis: \"考点：...；故答案为：Paris\""));
+        assert!(filled.contains("analysis: \"考点：已有解析。故答案为：A\""));
+        assert_eq!(filled.matches("考点：...").count(), 2);
+        assert_eq!(count_array_objects(&filled, "Questions"), Some(4));
+
+        let no_questions = "var newContent = {};";
+        assert_eq!(fill_missing_analysis(no_questions), no_questions);
+    }
+
+    #[test]
+    fn test_markdown_to_html() {
+        assert_eq!(markdown_to_html("**x**"), "<strong>x</strong>");
+        assert_eq!(markdown_to_html("第一行\n第二行\r\n第三行"), "第一行<br>第二行<br>第三行");
+        assert_eq!(
+            markdown_to_html("**考点**：*be* 动词。故答案为：A"),
+            "<strong>考点</strong>：<em>be</em> 动词。故答案为：A"
+        );
+        // 填空横线、乘号和未配对的星号保持不变
+        assert_eq!(markdown_to_html("I ____ a student."), "I ____ a student.");
+        assert_eq!(markdown_to_html("3 * 4 * 5 = 60"), "3 * 4 * 5 = 60");
+        assert_eq!(markdown_to_html("**unclosed"), "**unclosed");
+    }
+
+    #[test]
+    fn test_convert_analysis_markdown() {
+        let script = r#"var Questions = [
+    { stem: "**keep** the stem", answer: 0, analysis: "**考点**：名词\n故答案为：A" },
+    { stem: "Plain", answer: 1, analysis: '考点：动词' }
+];"#;
+        let converted = convert_analysis_markdown(script);
+        assert!(converted.contains(r#"analysis: "<strong>考点</strong>：名词<br>故答案为：A""#));
+        assert!(converted.contains("stem: \"**keep** the stem\""));
+        assert!(converted.contains("analysis: '考点：动词'"));
+        assert_eq!(convert_analysis_markdown("var x = 1;"), "var x = 1;");
+    }
+
+    #[test]
+    fn test_duplicate_options_flagged() {
+        let script = r#"var Questions = [
+    {
+        "stem": "Pick one.",
+        "options": ["In winter.", "In  Winter. ", 'In spring.'],
+        "answer": 1,
+    },
+    { stem: "No duplicates", options: ["a", "b", "c"], answer: 0 }
+];"#;
+
+        let duplicates = find_duplicate_options(script);
+        assert_eq!(
+            duplicates,
+            vec![DuplicateOption {
+                question_index: 0,
+                first_index: 0,
+                option_index: 1,
+                text: "In  Winter.".to_string(),
+            }]
+        );
+        assert_eq!(duplicates[0].to_string(), "第 1 题选项 B 与 A 重复：In  Winter.");
+
+        // 重复的选项是答案时替换较早的那个
+        let replaced = replace_duplicate_options(script);
+        assert!(!replaced.contains("\"In winter.\""));
+        assert!(replaced.contains("\"In  Winter. \""));
+        assert_eq!(replaced.matches(DUPLICATE_OPTION_PLACEHOLDER).count(), 1);
+        assert!(find_duplicate_options(&replaced).is_empty());
+        assert_eq!(DuplicateOptionMode::default(), DuplicateOptionMode::Flag);
+    }
+
+    #[test]
+    fn test_decode_binary_to_str() {
+        assert_eq!(decode_binary_to_str("01101000,01101001").unwrap(), "hi");
+        assert_eq!(decode_binary_to_str(" 01101000 ,\n01101001, ").unwrap(), "hi");
+        assert_eq!(decode_binary_to_str("").unwrap(), "");
+        // 多字节 UTF-8 字符
+        assert_eq!(decode_binary_to_str("11100100,10111000,10101101").unwrap(), "中");
+
+        assert_eq!(decode_binary_to_str("11111111,11111110"), Err(BinaryDecodeError::InvalidUtf8));
+        assert_eq!(decode_binary_to_str("11100100,10111000"), Err(BinaryDecodeError::InvalidUtf8));
+    }
+
+    #[test]
+    fn test_decode_binary_to_str_rejects_stray_tokens() {
+        assert_eq!(
+            decode_binary_to_str("01101000,0110100x"),
+            Err(BinaryDecodeError::InvalidToken { index: 1, token: "0110100x".to_string() })
+        );
+        assert_eq!(
+            decode_binary_to_str("1101000,01101001"),
+            Err(BinaryDecodeError::InvalidToken { index: 0, token: "1101000".to_string() })
+        );
+        assert_eq!(
+            decode_binary_to_str("01101000;01101001"),
+            Err(BinaryDecodeError::InvalidToken { index: 0, token: "01101000;01101001".to_string() })
+        );
+        assert_eq!(
+            decode_binary_to_str("+0110100"),
+            Err(BinaryDecodeError::InvalidToken { index: 0, token: "+0110100".to_string() })
+        );
+    }
+}
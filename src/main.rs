@@ -13,13 +13,21 @@ use slint::ComponentHandle;
 mod app;
 mod core;
 
+use crate::app::cli::CliArgs;
 use crate::app::{AppState, ClipboardTimer, EventHandlers};
 use crate::core::logger;
 
 #[tokio::main]
 async fn main() {
+    // 带 --cli 参数时不启动界面，转换结果写到标准输出
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(cli_args) = CliArgs::parse(&args) {
+        setup_environment(true);
+        std::process::exit(app::cli::run(cli_args).await);
+    }
+
     // 初始化环境
-    setup_environment();
+    setup_environment(false);
     let app = App::new().unwrap();
 
     // 创建应用状态和组件
@@ -39,21 +47,30 @@ async fn main() {
 
     // 设置 LLM 相关回调和 UI 状态
     app_state.setup_llm_callbacks(&app).init_llm_ui_state(&app);
+    // 后台检查当前后端是否可用，结果显示在侧边栏
+    app_state.start_health_check(&app);
 
     let mut clipboard_timer = ClipboardTimer::new(app_state.clone());
-    clipboard_timer.start(app.as_weak());
+    clipboard_timer.start(app.as_weak(), app_state.clipboard_config.timer_interval());
     EventHandlers::new(app_state.clone()).setup_callbacks(&app);
 
     tracing::info!("[main] Application initialized, starting UI loop");
 
     // 运行 UI 主循环
     app.run().unwrap();
+
+    // 删除本次运行产生的剪贴板临时图片
+    app_state.paste_files.cleanup();
 }
 
-/// 设置应用环境
-fn setup_environment() {
+/// 设置应用环境，命令行模式下日志写到标准错误
+fn setup_environment(cli_mode: bool) {
     dotenvy::dotenv().ok();
-    logger::init();
+    if cli_mode {
+        logger::init_stderr();
+    } else {
+        logger::init();
+    }
     tracing::info!("[main] Application starting");
 
     // 如果环境变量存在，设置 OpenAI 相关变量